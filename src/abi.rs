@@ -0,0 +1,632 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::bind::Object;
+use crate::dwarf;
+use crate::dwarf::{AttrValue, Die, Dwarf, Unit};
+use crate::elf;
+use crate::elf::ElfFile;
+
+const MAX_TYPE_DEPTH: usize = 32;   // nesting followed when naming or sizing a type
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Compatible,     // existing consumers keep working
+    Breaking,       // consumers built against the old file may fail to load or misbehave
+}
+
+/// One difference between the interfaces of two versions of a shared library.
+pub struct AbiChange {
+    pub severity:   Severity,
+    pub kind:       &'static str,   // soname, symbol, version, function, variable or type
+    pub message:    String,
+}
+
+pub struct AbiReport {
+    pub changes:    Vec<AbiChange>,
+    pub debug_info: bool,           // both files had DWARF, so signatures and layouts were compared
+}
+
+impl AbiReport {
+    pub fn is_breaking(&self) -> bool {
+        self.changes.iter().any(|c| c.severity == Severity::Breaking)
+    }
+
+    fn push(&mut self, severity: Severity, kind: &'static str, message: String) {
+        self.changes.push(AbiChange{severity, kind, message});
+    }
+}
+
+/// A type as rendered in C and its byte size.
+type Described = (String, Option<u64>);
+
+/// Return and parameter types of an exported function as (name, byte size).
+#[derive(PartialEq)]
+struct Function {
+    ret:        Described,
+    params:     Vec<Described>,
+    variadic:   bool,
+}
+
+impl Function {
+    fn signature(&self, name: &str) -> String {
+        let mut params: Vec<&str> = self.params.iter().map(|p| p.0.as_str()).collect();
+        if self.variadic {
+            params.push("...");
+        }
+        format!("{} {}({})", self.ret.0, name, params.join(", "))
+    }
+}
+
+struct Member {
+    name:       String,
+    offset:     u64,            // in bits from the start of the aggregate
+    type_name:  String,
+    size:       Option<u64>,    // byte size of the member type
+    bit_size:   Option<u64>,    // bit-field width
+}
+
+/// Layout of a struct, union or class, or the enumerators of an enum.
+struct Layout {
+    size:           Option<u64>,
+    members:        Vec<Member>,
+    enumerators:    Vec<(String, i64)>,
+}
+
+/// DWARF type information of one file.
+struct Types {
+    units:          Vec<Unit>,
+    definitions:    HashMap<String, u64>,   // "struct foo" -> defining DIE, to complete declarations
+    little_endian:  bool,
+}
+
+fn aggregate_prefix(tag: u16) -> Option<&'static str> {
+    match tag {
+        dwarf::DW_TAG_STRUCTURE_TYPE => Some("struct"),
+        dwarf::DW_TAG_UNION_TYPE => Some("union"),
+        dwarf::DW_TAG_CLASS_TYPE => Some("class"),
+        dwarf::DW_TAG_ENUMERATION_TYPE => Some("enum"),
+        _ => None,
+    }
+}
+
+fn aggregate_key(die: &Die) -> Option<String> {
+    Some(format!("{} {}", aggregate_prefix(die.tag)?, die.name()?))
+}
+
+fn is_declaration(die: &Die) -> bool {
+    die.attr(dwarf::DW_AT_DECLARATION).is_some()
+}
+
+fn format_offset(bits: u64) -> String {
+    if bits.is_multiple_of(8) { format!("{}", bits / 8) } else { format!("{}:{}", bits / 8, bits % 8) }
+}
+
+fn format_size(size: Option<u64>) -> String {
+    size.map(|s| s.to_string()).unwrap_or("?".to_string())
+}
+
+impl Types {
+    fn load(elf: &ElfFile) -> Option<Types> {
+        let units = Dwarf::load(elf)?.units();
+        if units.is_empty() {
+            return None;
+        }
+        let mut definitions = HashMap::new();
+        for die in units.iter().flat_map(|u| u.dies.iter()) {
+            if aggregate_prefix(die.tag).is_some() && !is_declaration(die) {
+                if let Some(key) = aggregate_key(die) {
+                    definitions.entry(key).or_insert(die.offset);
+                }
+            }
+        }
+        Some(Types{units, definitions, little_endian: elf.is_little_endian()})
+    }
+
+    fn die(&self, offset: u64) -> Option<(&Unit, usize)> {
+        let i = self.units.partition_point(|u| u.offset <= offset);
+        let unit = self.units.get(i.checked_sub(1)?)?;
+        Some((unit, unit.die_index(offset)?))
+    }
+
+    /// Looks up an attribute on the DIE or on the declaration or abstract instance it completes.
+    fn origin_attr<'a>(&'a self, die: &'a Die, at: u16, depth: usize) -> Option<&'a AttrValue> {
+        if let Some(value) = die.attr(at) {
+            return Some(value);
+        }
+        if depth >= MAX_TYPE_DEPTH {
+            return None;
+        }
+        [dwarf::DW_AT_SPECIFICATION, dwarf::DW_AT_ABSTRACT_ORIGIN].iter().find_map(|&link| match die.attr(link) {
+            Some(AttrValue::Ref(r)) => self.die(*r).and_then(|(u, i)| self.origin_attr(&u.dies[i], at, depth + 1)),
+            _ => None,
+        })
+    }
+
+    fn origin_type(&self, die: &Die) -> Option<u64> {
+        match self.origin_attr(die, dwarf::DW_AT_TYPE, 0) {
+            Some(AttrValue::Ref(r)) => Some(*r),
+            _ => None,
+        }
+    }
+
+    /// Completes a struct declaration with its definition from another unit.
+    fn definition<'a>(&'a self, unit: &'a Unit, index: usize) -> (&'a Unit, usize) {
+        let die = &unit.dies[index];
+        if aggregate_prefix(die.tag).is_some() && is_declaration(die) {
+            if let Some(found) = aggregate_key(die).and_then(|k| self.definitions.get(&k)).and_then(|&o| self.die(o)) {
+                return found;
+            }
+        }
+        (unit, index)
+    }
+
+    /// Renders a type reference in C syntax. Struct types are named, not expanded.
+    fn type_name(&self, offset: Option<u64>, depth: usize) -> String {
+        let offset = match offset {
+            Some(offset) => offset,
+            None => return "void".to_string(),
+        };
+        let (unit, index) = match self.die(offset) {
+            Some(found) if depth < MAX_TYPE_DEPTH => found,
+            _ => return "?".to_string(),
+        };
+        let die = &unit.dies[index];
+        let inner = || self.type_name(die.type_ref(), depth + 1);
+        match die.tag {
+            tag if aggregate_prefix(tag).is_some() => {
+                aggregate_key(die).unwrap_or_else(|| format!("{} {{...}}", aggregate_prefix(tag).unwrap_or("")))
+            }
+            dwarf::DW_TAG_POINTER_TYPE => {
+                let target = die.type_ref().and_then(|t| self.die(t));
+                match target {
+                    Some((u, i)) if u.dies[i].tag == dwarf::DW_TAG_SUBROUTINE_TYPE => {
+                        let (ret, params) = self.subroutine(u, i, depth + 1);
+                        format!("{} (*)({})", ret, params)
+                    }
+                    _ => format!("{} *", inner()),
+                }
+            }
+            dwarf::DW_TAG_REFERENCE_TYPE => format!("{} &", inner()),
+            dwarf::DW_TAG_RVALUE_REFERENCE_TYPE => format!("{} &&", inner()),
+            dwarf::DW_TAG_CONST_TYPE => format!("const {}", inner()),
+            dwarf::DW_TAG_VOLATILE_TYPE => format!("volatile {}", inner()),
+            dwarf::DW_TAG_RESTRICT_TYPE => format!("{} restrict", inner()),
+            dwarf::DW_TAG_ATOMIC_TYPE => format!("_Atomic {}", inner()),
+            dwarf::DW_TAG_ARRAY_TYPE => {
+                let mut name = inner();
+                for c in unit.children(index) {
+                    let sub = &unit.dies[c];
+                    if sub.tag != dwarf::DW_TAG_SUBRANGE_TYPE {
+                        continue;
+                    }
+                    match sub.udata(dwarf::DW_AT_COUNT).or(sub.udata(dwarf::DW_AT_UPPER_BOUND).map(|u| u.wrapping_add(1))) {
+                        Some(count) => name += &format!("[{}]", count),
+                        None => name += "[]",
+                    }
+                }
+                name
+            }
+            dwarf::DW_TAG_SUBROUTINE_TYPE => {
+                let (ret, params) = self.subroutine(unit, index, depth + 1);
+                format!("{} ({})", ret, params)
+            }
+            _ => die.name().unwrap_or("?").to_string(),
+        }
+    }
+
+    fn subroutine(&self, unit: &Unit, index: usize, depth: usize) -> (String, String) {
+        let mut params = Vec::new();
+        for c in unit.children(index) {
+            match unit.dies[c].tag {
+                dwarf::DW_TAG_FORMAL_PARAMETER => params.push(self.type_name(unit.dies[c].type_ref(), depth)),
+                dwarf::DW_TAG_UNSPECIFIED_PARAMETERS => params.push("...".to_string()),
+                _ => {}
+            }
+        }
+        (self.type_name(unit.dies[index].type_ref(), depth), params.join(", "))
+    }
+
+    fn size_of(&self, offset: Option<u64>, depth: usize) -> Option<u64> {
+        if depth >= MAX_TYPE_DEPTH {
+            return None;
+        }
+        let (unit, index) = self.die(offset?)?;
+        let (unit, index) = self.definition(unit, index);
+        let die = &unit.dies[index];
+        if let Some(size) = die.udata(dwarf::DW_AT_BYTE_SIZE) {
+            return Some(size);
+        }
+        match die.tag {
+            dwarf::DW_TAG_TYPEDEF | dwarf::DW_TAG_CONST_TYPE | dwarf::DW_TAG_VOLATILE_TYPE
+            | dwarf::DW_TAG_RESTRICT_TYPE | dwarf::DW_TAG_ATOMIC_TYPE => self.size_of(die.type_ref(), depth + 1),
+            dwarf::DW_TAG_POINTER_TYPE | dwarf::DW_TAG_REFERENCE_TYPE
+            | dwarf::DW_TAG_RVALUE_REFERENCE_TYPE => Some(unit.addr_size as u64),
+            dwarf::DW_TAG_ARRAY_TYPE => {
+                let mut size = self.size_of(die.type_ref(), depth + 1)?;
+                for c in unit.children(index) {
+                    let sub = &unit.dies[c];
+                    if sub.tag == dwarf::DW_TAG_SUBRANGE_TYPE {
+                        let count = match sub.udata(dwarf::DW_AT_COUNT) {
+                            Some(count) => count,
+                            None => sub.udata(dwarf::DW_AT_UPPER_BOUND)?.checked_add(1)?,
+                        };
+                        size = size.checked_mul(count)?;
+                    }
+                }
+                Some(size)
+            }
+            _ => None,
+        }
+    }
+
+    fn described(&self, offset: Option<u64>) -> Described {
+        (self.type_name(offset, 0), self.size_of(offset, 0))
+    }
+
+    /// Bit offset of a member. DWARF 2/3 bit-fields count DW_AT_BIT_OFFSET from the most
+    /// significant bit of their storage unit.
+    fn member_offset(&self, die: &Die) -> u64 {
+        if let Some(bits) = die.udata(dwarf::DW_AT_DATA_BIT_OFFSET) {
+            return bits;
+        }
+        let bytes = match die.attr(dwarf::DW_AT_DATA_MEMBER_LOCATION) {
+            // DW_OP_PLUS_UCONST
+            Some(AttrValue::Block(expr)) if expr.first() == Some(&0x23) => {
+                dwarf::Cursor::new(&expr[1..], 0, self.little_endian).uleb().unwrap_or(0)
+            }
+            Some(value) => value.as_u64().unwrap_or(0),
+            None => 0,
+        };
+        match (die.udata(dwarf::DW_AT_BIT_OFFSET), die.udata(dwarf::DW_AT_BIT_SIZE)) {
+            (Some(bit_offset), Some(bit_size)) if self.little_endian => {
+                let storage = die.udata(dwarf::DW_AT_BYTE_SIZE).or(self.size_of(die.type_ref(), 0)).unwrap_or(0);
+                (bytes * 8 + storage * 8).wrapping_sub(bit_offset + bit_size)
+            }
+            (Some(bit_offset), _) => bytes * 8 + bit_offset,
+            _ => bytes * 8,
+        }
+    }
+
+    /// Symbol name of an external function or variable definition listed in `names`.
+    fn exported_name(&self, die: &Die, names: &HashSet<&str>) -> Option<String> {
+        if (die.tag != dwarf::DW_TAG_SUBPROGRAM && die.tag != dwarf::DW_TAG_VARIABLE) || is_declaration(die)
+            || self.origin_attr(die, dwarf::DW_AT_EXTERNAL, 0).is_none() {
+            return None;
+        }
+        [dwarf::DW_AT_LINKAGE_NAME, dwarf::DW_AT_MIPS_LINKAGE_NAME, dwarf::DW_AT_NAME].iter()
+            .find_map(|&at| self.origin_attr(die, at, 0).and_then(|v| v.as_str()))
+            .filter(|name| names.contains(name))
+            .map(|name| name.to_string())
+    }
+
+    /// Signatures of the exported functions and types of the exported variables found in
+    /// `names`, keyed by symbol name.
+    fn interface(&self, names: &HashSet<&str>) -> (BTreeMap<String, Function>, BTreeMap<String, Described>) {
+        let mut functions = BTreeMap::new();
+        let mut variables = BTreeMap::new();
+        for unit in self.units.iter() {
+            for (index, die) in unit.dies.iter().enumerate() {
+                let name = match self.exported_name(die, names) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                if die.tag == dwarf::DW_TAG_VARIABLE {
+                    variables.entry(name).or_insert_with(|| self.described(self.origin_type(die)));
+                    continue;
+                }
+                if functions.contains_key(&name) {
+                    continue;
+                }
+                let mut f = Function{ret: self.described(self.origin_type(die)), params: Vec::new(), variadic: false};
+                for c in unit.children(index) {
+                    match unit.dies[c].tag {
+                        dwarf::DW_TAG_FORMAL_PARAMETER => f.params.push(self.described(self.origin_type(&unit.dies[c]))),
+                        dwarf::DW_TAG_UNSPECIFIED_PARAMETERS => f.variadic = true,
+                        _ => {}
+                    }
+                }
+                functions.insert(name, f);
+            }
+        }
+        (functions, variables)
+    }
+
+    /// Records the layout of every struct, union and enum reachable from a type. Anonymous
+    /// aggregates are named after the typedef or member that holds them.
+    fn collect(&self, offset: Option<u64>, alias: Option<String>, layouts: &mut BTreeMap<String, Layout>, depth: usize) {
+        let (unit, index) = match offset.and_then(|o| self.die(o)) {
+            Some(found) if depth < MAX_TYPE_DEPTH => found,
+            _ => return,
+        };
+        let die = &unit.dies[index];
+        match die.tag {
+            dwarf::DW_TAG_TYPEDEF => self.collect(die.type_ref(), die.name().map(|n| n.to_string()), layouts, depth + 1),
+            dwarf::DW_TAG_CONST_TYPE | dwarf::DW_TAG_VOLATILE_TYPE | dwarf::DW_TAG_ATOMIC_TYPE => {
+                self.collect(die.type_ref(), alias, layouts, depth + 1)
+            }
+            dwarf::DW_TAG_POINTER_TYPE | dwarf::DW_TAG_REFERENCE_TYPE | dwarf::DW_TAG_RVALUE_REFERENCE_TYPE
+            | dwarf::DW_TAG_RESTRICT_TYPE | dwarf::DW_TAG_ARRAY_TYPE => self.collect(die.type_ref(), None, layouts, depth + 1),
+            dwarf::DW_TAG_SUBROUTINE_TYPE => {
+                self.collect(die.type_ref(), None, layouts, depth + 1);
+                for c in unit.children(index) {
+                    self.collect(unit.dies[c].type_ref(), None, layouts, depth + 1);
+                }
+            }
+            tag if aggregate_prefix(tag).is_some() => {
+                let key = match aggregate_key(die).or(alias) {
+                    Some(key) => key,
+                    None => return,
+                };
+                let (unit, index) = self.definition(unit, index);
+                if layouts.contains_key(&key) || is_declaration(&unit.dies[index]) {
+                    return;
+                }
+                let mut layout = Layout{size: unit.dies[index].udata(dwarf::DW_AT_BYTE_SIZE), members: Vec::new(),
+                                        enumerators: Vec::new()};
+                let mut nested = Vec::new();
+                for c in unit.children(index) {
+                    let child = &unit.dies[c];
+                    match child.tag {
+                        dwarf::DW_TAG_MEMBER if !is_declaration(child) => {
+                            let offset = self.member_offset(child);
+                            let name = child.name().map(|n| n.to_string())
+                                .unwrap_or_else(|| format!("<anonymous at {}>", format_offset(offset)));
+                            let (type_name, size) = self.described(child.type_ref());
+                            nested.push((child.type_ref(), format!("{}.{}", key, name)));
+                            layout.members.push(Member{name, offset, type_name, size,
+                                                       bit_size: child.udata(dwarf::DW_AT_BIT_SIZE)});
+                        }
+                        dwarf::DW_TAG_ENUMERATOR => {
+                            let value = match child.attr(dwarf::DW_AT_CONST_VALUE) {
+                                Some(AttrValue::Sdata(v)) => *v,
+                                Some(v) => v.as_u64().unwrap_or(0) as i64,
+                                None => 0,
+                            };
+                            layout.enumerators.push((child.name().unwrap_or("").to_string(), value));
+                        }
+                        _ => {}
+                    }
+                }
+                layouts.insert(key, layout);
+                for (type_ref, alias) in nested {
+                    self.collect(type_ref, Some(alias), layouts, depth + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Layouts of the types used by the exported functions and variables.
+    fn layouts(&self, names: &HashSet<&str>) -> BTreeMap<String, Layout> {
+        let mut layouts = BTreeMap::new();
+        for unit in self.units.iter() {
+            for (index, die) in unit.dies.iter().enumerate() {
+                if self.exported_name(die, names).is_none() {
+                    continue;
+                }
+                self.collect(self.origin_type(die), None, &mut layouts, 0);
+                for c in unit.children(index) {
+                    if unit.dies[c].tag == dwarf::DW_TAG_FORMAL_PARAMETER {
+                        self.collect(self.origin_type(&unit.dies[c]), None, &mut layouts, 0);
+                    }
+                }
+            }
+        }
+        layouts
+    }
+}
+
+/// The absolute symbols ld emits to name each version definition.
+fn is_version_marker(object: &Object, index: usize) -> bool {
+    let sym = &object.elf.dynamic_symbols[index];
+    sym.st_shndx == elf::SHN_ABS && object.versions.defs.iter().any(|d| d.name == sym.name)
+}
+
+/// Exported symbols: everything the old file defined must still be defined under the same
+/// version by the lookup rules ld.so applies, with the same kind and object size.
+fn compare_symbols(old: &Object, new: &Object, report: &mut AbiReport) {
+    let mut names: Vec<&String> = old.exports.keys().collect();
+    names.sort();
+    let mut seen = HashSet::new();
+    for name in names {
+        for &i in old.exports[name].iter().filter(|&&i| !is_version_marker(old, i)) {
+            let label = format!("{}{}", name, old.version_name(i).unwrap_or_default());
+            if !seen.insert(label.clone()) {
+                continue;
+            }
+            let old_sym = &old.elf.dynamic_symbols[i];
+            let new_sym = match new.define(name, old.versions.get(i).map(|(v, _)| v)) {
+                Some(j) => &new.elf.dynamic_symbols[j],
+                None => {
+                    report.push(Severity::Breaking, "symbol", format!("{} removed", label));
+                    continue;
+                }
+            };
+            if old_sym.is_function() != new_sym.is_function() {
+                report.push(Severity::Breaking, "symbol", format!("{} changed type {} -> {}", label,
+                    elf::get_symbol_type_name(old_sym.st_type()), elf::get_symbol_type_name(new_sym.st_type())));
+            } else if !old_sym.is_function() && old_sym.st_size != new_sym.st_size {
+                // Executables reserve the old size for copy relocations.
+                report.push(Severity::Breaking, "symbol", format!("{} changed size {} -> {}", label,
+                    old_sym.st_size, new_sym.st_size));
+            }
+            if old_sym.bind() != new_sym.bind() {
+                report.push(Severity::Compatible, "symbol", format!("{} changed binding {} -> {}", label,
+                    elf::get_symbol_bind_name(old_sym.bind()), elf::get_symbol_bind_name(new_sym.bind())));
+            }
+        }
+    }
+
+    let mut names: Vec<&String> = new.exports.keys().collect();
+    names.sort();
+    let mut seen = HashSet::new();
+    for name in names {
+        for &j in new.exports[name].iter().filter(|&&j| !is_version_marker(new, j)) {
+            let label = format!("{}{}", name, new.version_name(j).unwrap_or_default());
+            if seen.insert(label.clone()) && old.define(name, new.versions.get(j).map(|(v, _)| v)).is_none() {
+                report.push(Severity::Compatible, "symbol", format!("{} added", label));
+            }
+        }
+    }
+}
+
+fn compare_versions(old: &Object, new: &Object, report: &mut AbiReport) {
+    let names = |o: &Object| -> Vec<String> {
+        o.versions.defs.iter().filter(|d| d.flags & elf::VER_FLG_BASE == 0).map(|d| d.name.clone()).collect()
+    };
+    let (old_names, new_names) = (names(old), names(new));
+    for name in old_names.iter().filter(|n| !new_names.contains(n)) {
+        // ld.so refuses to load consumers that require a version the library no longer defines.
+        report.push(Severity::Breaking, "version", format!("version {} removed", name));
+    }
+    for name in new_names.iter().filter(|n| !old_names.contains(n)) {
+        report.push(Severity::Compatible, "version", format!("version {} added", name));
+    }
+}
+
+fn compare_functions(old: &BTreeMap<String, Function>, new: &BTreeMap<String, Function>, report: &mut AbiReport) {
+    for (name, f) in old.iter() {
+        let g = match new.get(name) {
+            Some(g) if g != f => g,
+            _ => continue,
+        };
+        let (before, after) = (f.signature(name), g.signature(name));
+        if before != after {
+            report.push(Severity::Breaking, "function", format!("{} -> {}", before, after));
+            continue;
+        }
+        // Same spelling, but a typedef now names a different size.
+        let pairs = std::iter::once((&f.ret, &g.ret)).chain(f.params.iter().zip(g.params.iter()));
+        for (what, (a, b)) in pairs.enumerate().filter(|(_, (a, b))| a.1 != b.1) {
+            let what = if what == 0 { "return type".to_string() } else { format!("parameter {}", what) };
+            report.push(Severity::Breaking, "function", format!("{}: {} {} changed size {} -> {}", before, what,
+                a.0, format_size(a.1), format_size(b.1)));
+        }
+    }
+}
+
+fn compare_variables(old: &BTreeMap<String, Described>, new: &BTreeMap<String, Described>,
+                     report: &mut AbiReport) {
+    for (name, a) in old.iter() {
+        match new.get(name) {
+            Some(b) if a.0 != b.0 => {
+                report.push(Severity::Breaking, "variable", format!("{}: type {} -> {}", name, a.0, b.0));
+            }
+            Some(b) if a.1 != b.1 => {
+                report.push(Severity::Breaking, "variable", format!("{}: {} changed size {} -> {}", name, a.0,
+                    format_size(a.1), format_size(b.1)));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Members may be appended into padding or a union without changing the size; anything that
+/// moves, retypes or removes an existing member, or changes the size, breaks consumers.
+fn compare_layouts(old: &BTreeMap<String, Layout>, new: &BTreeMap<String, Layout>, report: &mut AbiReport) {
+    for (key, a) in old.iter() {
+        let b = match new.get(key) {
+            Some(b) => b,
+            None => continue,
+        };
+        let resized = a.size != b.size;
+        if resized {
+            report.push(Severity::Breaking, "type", format!("{}: size {} -> {}", key, format_size(a.size),
+                format_size(b.size)));
+        }
+        for m in a.members.iter() {
+            let n = match b.members.iter().find(|n| n.name == m.name) {
+                Some(n) => n,
+                None => {
+                    report.push(Severity::Breaking, "type", format!("{}: member {} removed", key, m.name));
+                    continue;
+                }
+            };
+            if m.offset != n.offset {
+                report.push(Severity::Breaking, "type", format!("{}: member {} moved from offset {} to {}", key,
+                    m.name, format_offset(m.offset), format_offset(n.offset)));
+            }
+            if m.type_name != n.type_name {
+                report.push(Severity::Breaking, "type", format!("{}: member {} type {} -> {}", key, m.name,
+                    m.type_name, n.type_name));
+            } else if m.size != n.size || m.bit_size != n.bit_size {
+                let (old_size, new_size) = match (m.bit_size, n.bit_size) {
+                    (None, None) => (format_size(m.size), format_size(n.size)),
+                    _ => (format!("{} bits", format_size(m.bit_size)), format!("{} bits", format_size(n.bit_size))),
+                };
+                report.push(Severity::Breaking, "type", format!("{}: member {} {} changed size {} -> {}", key,
+                    m.name, m.type_name, old_size, new_size));
+            }
+        }
+        for n in b.members.iter().filter(|n| !a.members.iter().any(|m| m.name == n.name)) {
+            let severity = if resized { Severity::Breaking } else { Severity::Compatible };
+            report.push(severity, "type", format!("{}: member {} {} added at offset {}", key, n.type_name, n.name,
+                format_offset(n.offset)));
+        }
+        for (name, value) in a.enumerators.iter() {
+            match b.enumerators.iter().find(|e| &e.0 == name) {
+                None => report.push(Severity::Breaking, "type", format!("{}: {} removed", key, name)),
+                Some((_, v)) if v != value => {
+                    report.push(Severity::Breaking, "type", format!("{}: {} changed value {} -> {}", key, name,
+                        value, v));
+                }
+                _ => {}
+            }
+        }
+        for (name, value) in b.enumerators.iter().filter(|e| !a.enumerators.iter().any(|o| o.0 == e.0)) {
+            report.push(Severity::Compatible, "type", format!("{}: {} = {} added", key, name, value));
+        }
+    }
+}
+
+/// Compares the interface `new` offers to consumers built against `old`: SONAME, exported
+/// dynamic symbols, version definitions and, when both files carry DWARF, the signatures of
+/// exported functions, the types of exported variables and the layouts they depend on.
+pub fn compare(old: &Object, new: &Object) -> AbiReport {
+    let mut report = AbiReport{changes: Vec::new(), debug_info: false};
+    let (old_soname, new_soname) = (old.elf.soname(), new.elf.soname());
+    if old_soname != new_soname {
+        report.push(Severity::Breaking, "soname", format!("{} -> {}", old_soname.unwrap_or("(none)".to_string()),
+            new_soname.unwrap_or("(none)".to_string())));
+    }
+    compare_symbols(old, new, &mut report);
+    compare_versions(old, new, &mut report);
+
+    let (old_types, new_types) = match (Types::load(&old.elf), Types::load(&new.elf)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return report,
+    };
+    report.debug_info = true;
+    let names: HashSet<&str> = old.exports.keys().filter(|n| new.exports.contains_key(*n)).map(|n| n.as_str()).collect();
+    let (old_functions, old_variables) = old_types.interface(&names);
+    let (new_functions, new_variables) = new_types.interface(&names);
+    compare_functions(&old_functions, &new_functions, &mut report);
+    compare_variables(&old_variables, &new_variables, &mut report);
+    compare_layouts(&old_types.layouts(&names), &new_types.layouts(&names), &mut report);
+    report
+}
+
+pub fn show_report(old_path: &str, new_path: &str, report: &AbiReport) {
+    println!("--- {}", old_path);
+    println!("+++ {}", new_path);
+    if !report.debug_info {
+        println!("DWARF missing from one or both files: function signatures and type layouts were not compared.");
+    }
+    if report.changes.is_empty() {
+        println!("No ABI changes.");
+        return;
+    }
+    println!();
+    for c in report.changes.iter() {
+        let severity = match c.severity {
+            Severity::Breaking => "BREAKING",
+            Severity::Compatible => "compatible",
+        };
+        println!("  {:12}{:10}{}", severity, c.kind, c.message);
+    }
+    let breaking = report.changes.iter().filter(|c| c.severity == Severity::Breaking).count();
+    println!();
+    println!("{} breaking, {} compatible change(s)", breaking, report.changes.len() - breaking);
+}
@@ -0,0 +1,341 @@
+use crate::dwarf::Cursor;
+use crate::elf;
+use crate::elf::ElfFile;
+
+const FORMAT_VERSION: u8 = b'A';
+
+// Sub-subsection scopes
+const TAG_FILE: u64     = 1;
+const TAG_SECTION: u64  = 2;
+const TAG_SYMBOL: u64   = 3;
+
+// ARM EABI attributes checked for compatibility
+const TAG_CPU_ARCH_PROFILE: u64     = 7;
+const TAG_ABI_PCS_R9_USE: u64       = 14;
+const TAG_ABI_PCS_WCHAR_T: u64      = 18;
+const TAG_ABI_FP_NUMBER_MODEL: u64  = 23;
+const TAG_ABI_ALIGN_NEEDED: u64     = 24;
+const TAG_ABI_ALIGN_PRESERVED: u64  = 25;
+const TAG_ABI_ENUM_SIZE: u64        = 26;
+const TAG_ABI_VFP_ARGS: u64         = 28;
+const TAG_COMPATIBILITY: u64        = 32;
+const TAG_ABI_FP_16BIT_FORMAT: u64  = 38;
+
+// RISC-V attributes checked for compatibility
+const TAG_RISCV_STACK_ALIGN: u64    = 4;
+const TAG_RISCV_ARCH: u64           = 5;
+const TAG_RISCV_PRIV_SPEC: u64      = 8;
+const TAG_RISCV_PRIV_SPEC_MINOR: u64 = 10;
+const TAG_RISCV_ATOMIC_ABI: u64     = 14;
+
+const ARM_TAGS: [(u64, &str, &[&str]); 35] = [
+  (4 , "Tag_CPU_raw_name"            , &[]),
+  (5 , "Tag_CPU_name"                , &[]),
+  (6 , "Tag_CPU_arch"                , &["Pre-v4", "v4", "v4T", "v5T", "v5TE", "v5TEJ", "v6", "v6KZ", "v6T2", "v6K",
+                                         "v7", "v6-M", "v6S-M", "v7E-M", "v8", "v8-R", "v8-M.baseline",
+                                         "v8-M.mainline", "v8.1-A", "v8.2-A", "v8.3-A", "v8.1-M.mainline", "v9"]),
+  (7 , "Tag_CPU_arch_profile"        , &[]),
+  (8 , "Tag_ARM_ISA_use"             , &["No", "Yes"]),
+  (9 , "Tag_THUMB_ISA_use"           , &["No", "Thumb-1", "Thumb-2", "Yes"]),
+  (10, "Tag_FP_arch"                 , &["No", "VFPv1", "VFPv2", "VFPv3", "VFPv3-D16", "VFPv4", "VFPv4-D16",
+                                         "FP for ARMv8", "FPv5/FP-D16 for ARMv8"]),
+  (11, "Tag_WMMX_arch"               , &["No", "WMMXv1", "WMMXv2"]),
+  (12, "Tag_Advanced_SIMD_arch"      , &["No", "NEONv1", "NEONv1 with Fused-MAC", "NEON for ARMv8",
+                                         "NEON for ARMv8.1"]),
+  (13, "Tag_PCS_config"              , &["None", "Bare platform", "Linux application", "Linux DSO", "PalmOS 2004",
+                                         "PalmOS (reserved)", "SymbianOS 2004", "SymbianOS (reserved)"]),
+  (14, "Tag_ABI_PCS_R9_use"          , &["V6", "SB", "TLS", "Unused"]),
+  (15, "Tag_ABI_PCS_RW_data"         , &["Absolute", "PC-relative", "SB-relative", "None"]),
+  (16, "Tag_ABI_PCS_RO_data"         , &["Absolute", "PC-relative", "None"]),
+  (17, "Tag_ABI_PCS_GOT_use"         , &["None", "direct", "GOT-indirect"]),
+  (18, "Tag_ABI_PCS_wchar_t"         , &["None", "??? 1", "2", "??? 3", "4"]),
+  (19, "Tag_ABI_FP_rounding"         , &["Unused", "Needed"]),
+  (20, "Tag_ABI_FP_denormal"         , &["Unused", "Needed", "Sign only"]),
+  (21, "Tag_ABI_FP_exceptions"       , &["Unused", "Needed"]),
+  (22, "Tag_ABI_FP_user_exceptions"  , &["Unused", "Needed"]),
+  (23, "Tag_ABI_FP_number_model"     , &["Unused", "Finite", "RTABI", "IEEE 754"]),
+  (24, "Tag_ABI_align_needed"        , &["None", "8-byte", "4-byte", "??? 3"]),
+  (25, "Tag_ABI_align_preserved"     , &["None", "8-byte, except leaf SP", "8-byte", "??? 3"]),
+  (26, "Tag_ABI_enum_size"           , &["Unused", "small", "int", "forced to int"]),
+  (27, "Tag_ABI_HardFP_use"          , &["As Tag_FP_arch", "SP only", "Reserved", "Deprecated"]),
+  (28, "Tag_ABI_VFP_args"            , &["AAPCS", "VFP registers", "custom", "compatible"]),
+  (29, "Tag_ABI_WMMX_args"           , &["AAPCS", "WMMX registers", "custom"]),
+  (30, "Tag_ABI_optimization_goals"  , &["None", "Prefer Speed", "Aggressive Speed", "Prefer Size", "Aggressive Size",
+                                         "Prefer Debug", "Aggressive Debug"]),
+  (31, "Tag_ABI_FP_optimization_goals", &["None", "Prefer Speed", "Aggressive Speed", "Prefer Size", "Aggressive Size",
+                                         "Prefer Accuracy", "Aggressive Accuracy"]),
+  (32, "Tag_compatibility"           , &[]),
+  (34, "Tag_CPU_unaligned_access"    , &["None", "v6"]),
+  (36, "Tag_FP_HP_extension"         , &["Not Allowed", "Allowed"]),
+  (38, "Tag_ABI_FP_16bit_format"     , &["None", "IEEE 754", "Alternative Format"]),
+  (42, "Tag_MPextension_use"         , &["Not Allowed", "Allowed"]),
+  (44, "Tag_DIV_use"                 , &["Allowed in Thumb-ISA, v7-R or v7-M", "Not allowed",
+                                         "Allowed in v7-A with integer division extension"]),
+  (68, "Tag_Virtualization_use"      , &["Not Allowed", "TrustZone", "Virtualization Extensions",
+                                         "TrustZone and Virtualization Extensions"]),
+];
+
+const RISCV_TAGS: [(u64, &str, &[&str]); 8] = [
+  (4 , "Tag_RISCV_stack_align"       , &[]),
+  (5 , "Tag_RISCV_arch"              , &[]),
+  (6 , "Tag_RISCV_unaligned_access"  , &["No unaligned access", "Unaligned access"]),
+  (8 , "Tag_RISCV_priv_spec"         , &[]),
+  (10, "Tag_RISCV_priv_spec_minor"   , &[]),
+  (12, "Tag_RISCV_priv_spec_revision", &[]),
+  (14, "Tag_RISCV_atomic_abi"        , &["UNKNOWN", "A6C", "A6S", "A7"]),
+  (16, "Tag_RISCV_x3_reg_usage"      , &["UNKNOWN", "GP", "SCS", "TMP"]),
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Int(u64),
+    Str(String),
+    IntStr(u64, String),    // Tag_compatibility: flag and vendor name
+}
+
+pub struct Attribute {
+    pub tag:    u64,
+    pub value:  Value,
+}
+
+/// One vendor subsection of a build attributes section. Only file-scope attributes are kept;
+/// section and symbol scopes refine them for parts of the file.
+pub struct Subsection {
+    pub vendor: String,         // "aeabi", "riscv", "gnu"
+    pub attrs:  Vec<Attribute>,
+}
+
+impl Subsection {
+    fn int(&self, tag: u64) -> Option<u64> {
+        self.attrs.iter().find(|a| a.tag == tag).and_then(|a| match a.value {
+            Value::Int(v) => Some(v),
+            _ => None,
+        })
+    }
+
+    fn string(&self, tag: u64) -> Option<&str> {
+        self.attrs.iter().find(|a| a.tag == tag).and_then(|a| match &a.value {
+            Value::Str(s) => Some(s.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// Odd tags from 32 up hold strings in every vendor scheme; below that the vendor decides.
+fn is_string(vendor: &str, tag: u64) -> bool {
+    match vendor {
+        "aeabi" => tag == 4 || tag == 5 || tag == 67 || (tag > 32 && tag % 2 == 1),
+        _ => tag % 2 == 1,
+    }
+}
+
+fn tag_table(vendor: &str) -> &'static [(u64, &'static str, &'static [&'static str])] {
+    match vendor {
+        "aeabi" => &ARM_TAGS,
+        "riscv" => &RISCV_TAGS,
+        _ => &[],
+    }
+}
+
+fn tag_name(vendor: &str, tag: u64) -> String {
+    tag_table(vendor).iter().find(|t| t.0 == tag).map(|t| t.1.to_string()).unwrap_or(format!("Tag_unknown_{}", tag))
+}
+
+fn describe(vendor: &str, attr: &Attribute) -> String {
+    match (&attr.value, vendor, attr.tag) {
+        (Value::Str(s), _, _) => format!("\"{}\"", s),
+        (Value::IntStr(flag, s), _, _) => format!("flag = {}, vendor = \"{}\"", flag, s),
+        (Value::Int(v), "aeabi", TAG_CPU_ARCH_PROFILE) => match *v as u8 {
+            0 => "None".to_string(),
+            b'A' => "Application".to_string(),
+            b'R' => "Realtime".to_string(),
+            b'M' => "Microcontroller".to_string(),
+            b'S' => "Application or Realtime".to_string(),
+            _ => v.to_string(),
+        },
+        (Value::Int(v), "riscv", TAG_RISCV_STACK_ALIGN) => format!("{}-bytes", v),
+        (Value::Int(v), _, tag) => {
+            let names = tag_table(vendor).iter().find(|t| t.0 == tag).map(|t| t.2).unwrap_or(&[]);
+            names.get(*v as usize).map(|n| n.to_string()).unwrap_or(v.to_string())
+        }
+    }
+}
+
+fn parse_attributes(vendor: &str, c: &mut Cursor, end: usize, attrs: &mut Vec<Attribute>) -> Option<()> {
+    while c.pos < end {
+        let tag = c.uleb()?;
+        let value = if tag == TAG_COMPATIBILITY && vendor == "aeabi" {
+            Value::IntStr(c.uleb()?, c.cstr()?)
+        } else if is_string(vendor, tag) {
+            Value::Str(c.cstr()?)
+        } else {
+            Value::Int(c.uleb()?)
+        };
+        attrs.push(Attribute{tag, value});
+    }
+    Some(())
+}
+
+/// Decodes .ARM.attributes, .riscv.attributes and .gnu.attributes.
+pub fn parse(elf: &ElfFile) -> Vec<Subsection> {
+    let mut subsections = Vec::new();
+    let arch_type = match elf.e_machine {
+        elf::EM_ARM => Some(elf::SHT_ARM_ATTRIBUTES),
+        elf::EM_RISCV => Some(elf::SHT_RISCV_ATTRIBUTES),
+        _ => None,
+    };
+    for shdr in elf.section_headers.iter() {
+        if Some(shdr.sh_type) != arch_type && shdr.sh_type != elf::SHT_GNU_ATTRIBUTES {
+            continue;
+        }
+        let data = elf.section_data(shdr);
+        if data.first() != Some(&FORMAT_VERSION) {
+            continue;
+        }
+        let mut c = Cursor::new(data, 1, elf.is_little_endian());
+        while let Some(len) = c.u32() {
+            let start = c.pos - 4;
+            let end = (start + len as usize).min(data.len());
+            if len < 4 {
+                break;
+            }
+            let vendor = c.cstr().unwrap_or_default();
+            let mut attrs = Vec::new();
+            while c.pos < end {
+                let (at, scope) = (c.pos, c.uleb().unwrap_or(0));
+                let size = c.u32().unwrap_or(0) as usize;
+                let stop = (at + size).min(end);
+                if size < 5 {
+                    break;
+                }
+                match scope {
+                    TAG_FILE => {
+                        parse_attributes(&vendor, &mut c, stop, &mut attrs);
+                    }
+                    TAG_SECTION | TAG_SYMBOL => {}
+                    _ => {}
+                }
+                c.pos = stop;
+            }
+            subsections.push(Subsection{vendor, attrs});
+            c.pos = end;
+        }
+    }
+    subsections
+}
+
+pub fn show_attributes(elf: &ElfFile) {
+    for sub in parse(elf).iter() {
+        println!("Attribute Section: {}", sub.vendor);
+        println!("File Attributes");
+        for attr in sub.attrs.iter() {
+            println!("  {}: {}", tag_name(&sub.vendor, attr.tag), describe(&sub.vendor, attr));
+        }
+    }
+}
+
+fn vendor<'a>(subsections: &'a [Subsection], name: &str) -> Option<&'a Subsection> {
+    subsections.iter().find(|s| s.vendor == name)
+}
+
+/// XLEN and the base ISA of an ISA string such as "rv32i2p1_m2p0_c2p0".
+fn riscv_base(arch: &str) -> &str {
+    arch.split('_').next().map(|b| b.trim_end_matches(|c: char| c.is_ascii_digit() || c == 'p')).unwrap_or(arch)
+}
+
+fn arm_conflicts(a: &ElfFile, sa: Option<&Subsection>, b: &ElfFile, sb: Option<&Subsection>, out: &mut Vec<String>) {
+    let (ea, eb) = (a.e_flags >> 24, b.e_flags >> 24);
+    if ea != 0 && eb != 0 && ea != eb {
+        out.push(format!("EABI version {} vs {}", ea, eb));
+    }
+    let float = |e: u32| if e & elf::EF_ARM_ABI_FLOAT_HARD != 0 { Some("hard-float") }
+                         else if e & elf::EF_ARM_ABI_FLOAT_SOFT != 0 { Some("soft-float") } else { None };
+    if let (Some(fa), Some(fb)) = (float(a.e_flags), float(b.e_flags)) {
+        if fa != fb {
+            out.push(format!("e_flags {} ABI vs {} ABI", fa, fb));
+        }
+    }
+    let (sa, sb) = match (sa, sb) {
+        (Some(sa), Some(sb)) => (sa, sb),
+        _ => return,
+    };
+    let get = |s: &Subsection, tag| s.int(tag).unwrap_or(0);
+    let show = |tag, v| describe("aeabi", &Attribute{tag, value: Value::Int(v)});
+    // Objects that use no floating point (number model unused) fit either calling convention.
+    let (va, vb) = (get(sa, TAG_ABI_VFP_ARGS), get(sb, TAG_ABI_VFP_ARGS));
+    if va != vb && va != 3 && vb != 3 && get(sa, TAG_ABI_FP_NUMBER_MODEL) != 0 && get(sb, TAG_ABI_FP_NUMBER_MODEL) != 0 {
+        out.push(format!("Tag_ABI_VFP_args: float arguments in {} vs {}", show(TAG_ABI_VFP_ARGS, va),
+                         show(TAG_ABI_VFP_ARGS, vb)));
+    }
+    for (tag, unused) in [(TAG_ABI_PCS_WCHAR_T, None), (TAG_ABI_ENUM_SIZE, None), (TAG_ABI_FP_16BIT_FORMAT, None),
+                          (TAG_ABI_PCS_R9_USE, Some(3))] {
+        let (x, y) = (get(sa, tag), get(sb, tag));
+        let set = |v: u64| sa.int(tag).is_some() && sb.int(tag).is_some() && Some(v) != unused && (v != 0 || tag == TAG_ABI_PCS_R9_USE);
+        if x != y && set(x) && set(y) {
+            out.push(format!("{}: {} vs {}", tag_name("aeabi", tag), show(tag, x), show(tag, y)));
+        }
+    }
+    let (pa, pb) = (get(sa, TAG_CPU_ARCH_PROFILE) as u8, get(sb, TAG_CPU_ARCH_PROFILE) as u8);
+    let same_family = |p: u8, q: u8| p == q || (p == b'S' && (q == b'A' || q == b'R')) || (q == b'S' && (p == b'A' || p == b'R'));
+    if pa != 0 && pb != 0 && !same_family(pa, pb) {
+        out.push(format!("Tag_CPU_arch_profile: {} vs {}", show(TAG_CPU_ARCH_PROFILE, pa as u64),
+                         show(TAG_CPU_ARCH_PROFILE, pb as u64)));
+    }
+    // An object that needs an 8-byte aligned stack cannot be called from one that does not keep it.
+    for (x, y, nx, ny) in [(sa, sb, "first", "second"), (sb, sa, "second", "first")] {
+        if get(x, TAG_ABI_ALIGN_NEEDED) == 1 && get(y, TAG_ABI_ALIGN_PRESERVED) == 0 {
+            out.push(format!("the {} object needs 8-byte stack alignment that the {} does not preserve", nx, ny));
+        }
+    }
+}
+
+fn riscv_conflicts(a: &ElfFile, sa: Option<&Subsection>, b: &ElfFile, sb: Option<&Subsection>, out: &mut Vec<String>) {
+    if a.e_flags & elf::EF_RISCV_FLOAT_ABI != b.e_flags & elf::EF_RISCV_FLOAT_ABI {
+        out.push(format!("{} vs {}", elf::get_riscv_float_abi_name(a.e_flags), elf::get_riscv_float_abi_name(b.e_flags)));
+    }
+    if a.e_flags & elf::EF_RISCV_RVE != b.e_flags & elf::EF_RISCV_RVE {
+        out.push("RVE (16 registers) vs RVI (32 registers)".to_string());
+    }
+    let (sa, sb) = match (sa, sb) {
+        (Some(sa), Some(sb)) => (sa, sb),
+        _ => return,
+    };
+    if let (Some(x), Some(y)) = (sa.string(TAG_RISCV_ARCH), sb.string(TAG_RISCV_ARCH)) {
+        if x.get(..4) != y.get(..4) {
+            out.push(format!("Tag_RISCV_arch: {} vs {}", riscv_base(x), riscv_base(y)));
+        }
+    }
+    if let (Some(x), Some(y)) = (sa.int(TAG_RISCV_STACK_ALIGN), sb.int(TAG_RISCV_STACK_ALIGN)) {
+        if x != y {
+            out.push(format!("Tag_RISCV_stack_align: {}-bytes vs {}-bytes", x, y));
+        }
+    }
+    let priv_spec = |s: &Subsection| (s.int(TAG_RISCV_PRIV_SPEC), s.int(TAG_RISCV_PRIV_SPEC_MINOR));
+    if let ((Some(x), xm), (Some(y), ym)) = (priv_spec(sa), priv_spec(sb)) {
+        if (x, xm) != (y, ym) {
+            out.push(format!("Tag_RISCV_priv_spec: {}.{} vs {}.{}", x, xm.unwrap_or(0), y, ym.unwrap_or(0)));
+        }
+    }
+    // A6C and A7 disagree on the fences around atomics; A6S works with both.
+    let (x, y) = (sa.int(TAG_RISCV_ATOMIC_ABI).unwrap_or(0), sb.int(TAG_RISCV_ATOMIC_ABI).unwrap_or(0));
+    if (x, y) == (1, 3) || (x, y) == (3, 1) {
+        let show = |v| describe("riscv", &Attribute{tag: TAG_RISCV_ATOMIC_ABI, value: Value::Int(v)});
+        out.push(format!("Tag_RISCV_atomic_abi: {} vs {}", show(x), show(y)));
+    }
+}
+
+/// Reasons why two objects must not be linked together, from e_flags and build attributes.
+pub fn conflicts(a: &ElfFile, b: &ElfFile) -> Vec<String> {
+    let mut out = Vec::new();
+    if a.e_machine != b.e_machine {
+        out.push(format!("{} vs {}", elf::get_machine_name(a.e_machine), elf::get_machine_name(b.e_machine)));
+        return out;
+    }
+    let (pa, pb) = (parse(a), parse(b));
+    match a.e_machine {
+        elf::EM_ARM => arm_conflicts(a, vendor(&pa, "aeabi"), b, vendor(&pb, "aeabi"), &mut out),
+        elf::EM_RISCV => riscv_conflicts(a, vendor(&pa, "riscv"), b, vendor(&pb, "riscv"), &mut out),
+        _ => {}
+    }
+    out
+}
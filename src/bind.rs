@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::elf;
+use crate::elf::{ElfFile, Relocation, SymbolVersions, VersionEntry};
+
+/// A loaded object in the global lookup scope.
+pub struct Object {
+    pub path:       String,
+    pub name:       String,     // soname, or the file name when there is none
+    pub elf:        ElfFile,
+    pub versions:   SymbolVersions,
+    pub exports:    HashMap<String, Vec<usize>>,    // name -> defined dynamic symbol indexes
+    symbolic:       bool,       // DT_SYMBOLIC: looks itself up before the global scope
+}
+
+impl Object {
+    pub fn open(path: &str) -> Result<Object, String> {
+        let elf = ElfFile::open(path)?;
+        let name = elf.soname().unwrap_or_else(|| {
+            Path::new(path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or(path.to_string())
+        });
+        let mut exports: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, sym) in elf.dynamic_symbols.iter().enumerate() {
+            let bind = sym.bind();
+            if sym.name.is_empty() || sym.is_undefined() || sym.visibility() == elf::STV_HIDDEN
+                || sym.visibility() == elf::STV_INTERNAL
+                || !(bind == elf::STB_GLOBAL || bind == elf::STB_WEAK || bind == elf::STB_GNU_UNIQUE) {
+                continue;
+            }
+            exports.entry(sym.name.clone()).or_default().push(i);
+        }
+        let symbolic = elf.dynamic_value(elf::DT_SYMBOLIC).is_some()
+            || elf.dynamic_value(elf::DT_FLAGS).is_some_and(|f| f & elf::DF_SYMBOLIC != 0);
+        let versions = elf.versions();
+        Ok(Object{path: path.to_string(), name, elf, versions, exports, symbolic})
+    }
+
+    /// Finds a definition of `name` matching the requested version the way ld.so does: a
+    /// versioned reference takes the definition of that version, or an unversioned one from a
+    /// file without versioning; an unversioned reference takes the default version.
+    pub fn define(&self, name: &str, version: Option<&VersionEntry>) -> Option<usize> {
+        let candidates = self.exports.get(name)?;
+        candidates.iter().cloned().find(|&i| {
+            let hidden = self.versions.versym.get(i).is_some_and(|v| v & elf::VERSYM_HIDDEN != 0);
+            match (version, self.versions.get(i)) {
+                (Some(want), Some((have, _))) => have.name == want.name,
+                (Some(_), None) => !hidden,
+                (None, _) => !hidden,
+            }
+        })
+    }
+
+    /// "@VER" for a hidden version, "@@VER" for the default one.
+    pub fn version_name(&self, index: usize) -> Option<String> {
+        self.versions.get(index).map(|(v, hidden)| format!("{}{}", if hidden { "@" } else { "@@" }, v.name))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Outcome {
+    Bound(usize),       // object providing the definition
+    WeakUnresolved,     // weak reference without a definition, resolves to 0
+    Unresolved,
+}
+
+/// How one symbol reference of an object binds.
+pub struct Binding {
+    pub object:     usize,          // object making the reference
+    pub symbol:     String,
+    pub version:    Option<String>, // requested version
+    pub weak_ref:   bool,
+    pub copy:       bool,           // copy relocation, or bound to the executable's copy
+    pub outcome:    Outcome,
+    pub definers:   Vec<usize>,     // every object in scope defining the symbol, in lookup order
+    pub weak_def:   bool,           // the chosen definition is weak
+}
+
+impl Binding {
+    /// Another object in scope also defines the symbol but loses to the chosen definition.
+    pub fn is_interposed(&self) -> bool {
+        self.definers.len() > 1
+    }
+
+    /// A weak definition was chosen although a strong one comes later in the scope.
+    pub fn is_weak_fallback(&self, objects: &[Object]) -> bool {
+        self.weak_def && self.definers.iter().skip(1).any(|&o| {
+            objects[o].exports.get(&self.symbol).is_some_and(|syms| {
+                syms.iter().any(|&i| objects[o].elf.dynamic_symbols[i].bind() == elf::STB_GLOBAL)
+            })
+        })
+    }
+}
+
+/// Relocations of the sections applied against .dynsym.
+fn dynamic_relocations(elf: &ElfFile) -> Vec<Relocation> {
+    elf.section_headers.iter()
+        .filter(|s| s.sh_type == elf::SHT_RELA || s.sh_type == elf::SHT_REL)
+        .filter(|s| elf.section_headers.get(s.sh_link as usize).map(|l| l.sh_type) == Some(elf::SHT_DYNSYM))
+        .flat_map(|s| elf.relocations(s))
+        .collect()
+}
+
+/// Addresses in the executable that copy relocations fill.
+fn copy_targets(elf: &ElfFile) -> HashSet<u64> {
+    dynamic_relocations(elf).iter().filter(|r| Some(r.r_type) == elf.copy_type()).map(|r| r.r_offset).collect()
+}
+
+/// Looks up `name` in scope order. DT_SYMBOLIC objects search themselves first; copy
+/// relocations skip the executable. Like ld.so without LD_DYNAMIC_WEAK, the first definition
+/// wins whether it is weak or not. Returns the winner's symbol and all definers.
+fn lookup(objects: &[Object], requester: usize, name: &str, version: Option<&VersionEntry>,
+          copy: bool) -> (Option<(usize, usize)>, Vec<usize>) {
+    let mut order: Vec<usize> = (0..objects.len()).collect();
+    if objects[requester].symbolic {
+        order.retain(|&o| o != requester);
+        order.insert(0, requester);
+    }
+    let mut winner = None;
+    let mut definers = Vec::new();
+    for o in order.into_iter().filter(|&o| !(copy && o == 0)) {
+        if let Some(sym) = objects[o].define(name, version) {
+            winner.get_or_insert((o, sym));
+            definers.push(o);
+        }
+    }
+    (winner, definers)
+}
+
+/// Simulates symbol binding for every object in the scope. References are the undefined
+/// dynamic symbols plus every symbol named by a dynamic relocation, which is how a library's
+/// calls to its own exported functions can end up elsewhere.
+pub fn bind(objects: &[Object]) -> Vec<Binding> {
+    let mut bindings = Vec::new();
+    // Data copied into the executable is meant to be shared by every library referencing it,
+    // including through aliases at the same address.
+    let copies: HashSet<u64> = objects.first().map(|exe| copy_targets(&exe.elf)).unwrap_or_default();
+    for (o, object) in objects.iter().enumerate() {
+        let elf = &object.elf;
+        let copy_type = elf.copy_type();
+        let mut refs: BTreeMap<usize, bool> = BTreeMap::new();  // symbol index -> copy relocation
+        for r in dynamic_relocations(elf).iter().filter(|r| r.r_sym != 0) {
+            *refs.entry(r.r_sym as usize).or_insert(false) |= Some(r.r_type) == copy_type;
+        }
+        for (i, sym) in elf.dynamic_symbols.iter().enumerate().skip(1) {
+            if sym.is_undefined() && !sym.name.is_empty() {
+                refs.entry(i).or_insert(false);
+            }
+        }
+        for (&i, &copy) in refs.iter() {
+            let sym = match elf.dynamic_symbols.get(i) {
+                Some(sym) if !sym.name.is_empty() && sym.bind() != elf::STB_LOCAL => sym,
+                _ => continue,
+            };
+            let version = object.versions.get(i).map(|(v, _)| v);
+            let (winner, definers) = lookup(objects, o, &sym.name, version, copy);
+            let weak_ref = sym.bind() == elf::STB_WEAK && sym.is_undefined();
+            let outcome = match winner {
+                Some((w, _)) => Outcome::Bound(w),
+                None if weak_ref => Outcome::WeakUnresolved,
+                None => Outcome::Unresolved,
+            };
+            let weak_def = winner.is_some_and(|(w, s)| objects[w].elf.dynamic_symbols[s].bind() == elf::STB_WEAK);
+            let copy = copy || winner.is_some_and(|(w, s)| w == 0 && copies.contains(&objects[0].elf.dynamic_symbols[s].st_value));
+            bindings.push(Binding{object: o, symbol: sym.name.clone(), version: version.map(|v| v.name.clone()),
+                                  weak_ref, copy, outcome, definers, weak_def});
+        }
+    }
+    bindings
+}
+
+/// Versions an object requires from a loaded library that the library does not define.
+/// ld.so refuses to start the program for these unless the requirement is weak.
+pub fn version_errors(objects: &[Object]) -> Vec<String> {
+    let mut errors = Vec::new();
+    for object in objects.iter() {
+        for need in object.versions.needs.iter().filter(|n| n.flags & elf::VER_FLG_WEAK == 0) {
+            let file = match &need.file {
+                Some(file) => file,
+                None => continue,
+            };
+            let provider = match objects.iter().find(|p| &p.name == file) {
+                Some(provider) if !provider.versions.defs.is_empty() => provider,
+                _ => continue,
+            };
+            if !provider.versions.defs.iter().any(|d| d.name == need.name) {
+                errors.push(format!("version `{}' not found in {} (required by {})", need.name, provider.path, object.name));
+            }
+        }
+    }
+    errors
+}
+
+fn describe(objects: &[Object], b: &Binding) -> String {
+    let version = b.version.as_ref().map(|v| format!("@{}", v)).unwrap_or_default();
+    let weak = if b.weak_ref { " (weak)" } else { "" };
+    let target = match b.outcome {
+        Outcome::Bound(w) => objects[w].name.clone(),
+        Outcome::WeakUnresolved => "0 (no definition)".to_string(),
+        Outcome::Unresolved => "UNRESOLVED".to_string(),
+    };
+    let mut notes = Vec::new();
+    if b.copy {
+        notes.push("copy relocation".to_string());
+    }
+    if b.is_interposed() {
+        let others: Vec<&str> = b.definers.iter().skip(1).map(|&o| objects[o].name.as_str()).collect();
+        let own = b.definers.contains(&b.object) && b.outcome != Outcome::Bound(b.object);
+        notes.push(format!("{} {}", if own { "overrides its own definition in" } else { "shadows" }, others.join(", ")));
+    }
+    if b.is_weak_fallback(objects) {
+        notes.push("weak definition chosen before a strong one".to_string());
+    }
+    let notes = if notes.is_empty() { String::new() } else { format!("  [{}]", notes.join("; ")) };
+    format!("{}: {}{}{} => {}{}", objects[b.object].name, b.symbol, version, weak, target, notes)
+}
+
+/// Prints the lookup scope, version errors, interposed, weak and unresolved symbols. With
+/// `symbol`, shows every reference to that symbol and all of its definitions instead; with
+/// `all`, every binding.
+pub fn show_bindings(objects: &[Object], bindings: &[Binding], symbol: Option<&str>, all: bool) {
+    let names: Vec<&str> = objects.iter().map(|o| o.name.as_str()).collect();
+    println!("Lookup scope: {}", names.join(" "));
+    if let Some(symbol) = symbol {
+        println!();
+        println!("Definitions of {}:", symbol);
+        for object in objects.iter() {
+            for &i in object.exports.get(symbol).map(|v| v.as_slice()).unwrap_or(&[]) {
+                let sym = &object.elf.dynamic_symbols[i];
+                println!("    {}{} {} {} 0x{:x}", object.name, object.version_name(i).unwrap_or_default(),
+                         elf::get_symbol_bind_name(sym.bind()), elf::get_symbol_type_name(sym.st_type()), sym.st_value);
+            }
+        }
+        println!();
+        println!("References:");
+        for b in bindings.iter().filter(|b| b.symbol == symbol) {
+            println!("    {}", describe(objects, b));
+        }
+        return;
+    }
+    let errors = version_errors(objects);
+    if !errors.is_empty() {
+        println!();
+        println!("Version errors:");
+        for e in errors.iter() {
+            println!("    {}", e);
+        }
+    }
+    let interposed: Vec<&Binding> = bindings.iter().filter(|b| b.is_interposed() && !b.copy).collect();
+    let weak: Vec<&Binding> = bindings.iter()
+        .filter(|b| b.outcome == Outcome::WeakUnresolved || b.is_weak_fallback(objects)).collect();
+    let unresolved: Vec<&Binding> = bindings.iter().filter(|b| b.outcome == Outcome::Unresolved).collect();
+    show_section(objects, "Interposed symbols", &interposed);
+    show_section(objects, "Weak symbols", &weak);
+    show_section(objects, "Unresolved symbols", &unresolved);
+    if all {
+        show_section(objects, "Bindings", &bindings.iter().collect::<Vec<_>>());
+    }
+    println!();
+    println!("{} references, {} interposed, {} weak, {} unresolved, {} version errors",
+             bindings.len(), interposed.len(), weak.len(), unresolved.len(), errors.len());
+}
+
+fn show_section(objects: &[Object], title: &str, bindings: &[&Binding]) {
+    if bindings.is_empty() {
+        return;
+    }
+    println!();
+    println!("{}:", title);
+    for b in bindings.iter() {
+        println!("    {}", describe(objects, b));
+    }
+}
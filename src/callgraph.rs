@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use crate::cfg;
+use crate::cfg::Function;
+use crate::disasm::Flow;
+use crate::discover;
+use crate::elf;
+use crate::elf::ElfFile;
+use crate::plt;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum CallKind {
+    Direct,
+    Plt,        // call through a PLT entry to an imported function
+    Tail,       // jump to the start of another function
+    Reference,  // address taken, e.g. main handed to __libc_start_main
+}
+
+impl CallKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CallKind::Direct => "call",
+            CallKind::Plt => "plt",
+            CallKind::Tail => "tail",
+            CallKind::Reference => "ref",
+        }
+    }
+}
+
+pub struct Node {
+    pub name:   String,
+    pub addr:   u64,
+    pub size:   u64,
+    pub import: bool,   // PLT entry of a function defined elsewhere
+}
+
+pub struct CallGraph {
+    pub nodes:  Vec<Node>,
+    pub edges:  BTreeSet<(usize, usize, CallKind)>,     // (caller, callee, kind)
+    by_addr:    HashMap<u64, usize>,
+    starts:     Vec<(u64, usize)>,                      // node starts, by address
+    callers:    Vec<Vec<(usize, CallKind)>>,            // per node, in edge order
+    callees:    Vec<Vec<(usize, CallKind)>>,
+}
+
+impl CallGraph {
+    /// Builds the graph from the CFGs of every function: direct calls, calls through the
+    /// PLT, tail jumps to other functions and function addresses taken by instructions.
+    pub fn build(elf: &ElfFile, functions: &[Function]) -> CallGraph {
+        let mut g = CallGraph{nodes: Vec::new(), edges: BTreeSet::new(), by_addr: HashMap::new(), starts: Vec::new(),
+                              callers: Vec::new(), callees: Vec::new()};
+        for f in functions.iter() {
+            g.add_node(&f.name, f.start, f.end - f.start, false);
+        }
+        let plt: BTreeMap<u64, String> = plt::stubs(elf).into_iter().map(|s| (s.addr, format!("{}@plt", s.name))).collect();
+        for (&addr, name) in plt.iter() {
+            g.add_node(name, addr, 0, true);
+        }
+        let arm = elf.e_machine == elf::EM_ARM;
+        for f in functions.iter() {
+            let caller = g.by_addr[&f.start];
+            for insn in f.blocks.iter().flat_map(|b| b.insns.iter()) {
+                match insn.flow {
+                    Flow::Call(t) => {
+                        let t = if arm { t & !1 } else { t };
+                        let kind = if plt.contains_key(&t) { CallKind::Plt } else { CallKind::Direct };
+                        if !g.by_addr.contains_key(&t) && elf.section_for_vaddr(t).is_some_and(|s| s.is_exec()) {
+                            g.add_node(&format!("sub_{:x}", t), t, 0, false);
+                        }
+                        if let Some(&callee) = g.by_addr.get(&t) {
+                            g.edges.insert((caller, callee, kind));
+                        }
+                    }
+                    Flow::Next => {
+                        let referenced = insn.mem_ref.or_else(|| cfg::trailing_immediate(&insn.operands));
+                        if let Some(&callee) = referenced.and_then(|a| g.by_addr.get(&a)) {
+                            if callee != caller && !g.nodes[callee].import {
+                                g.edges.insert((caller, callee, CallKind::Reference));
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            for &(_, t) in f.tail_jumps.iter() {
+                if let Some(&callee) = g.by_addr.get(&t) {
+                    g.edges.insert((caller, callee, CallKind::Tail));
+                }
+            }
+        }
+        g.index();
+        g
+    }
+
+    /// Builds the lookup tables for `containing`, `callers` and `callees` once the graph
+    /// is complete.
+    fn index(&mut self) {
+        self.starts = self.nodes.iter().enumerate().map(|(i, n)| (n.addr, i)).collect();
+        self.starts.sort();
+        self.callers = vec![Vec::new(); self.nodes.len()];
+        self.callees = vec![Vec::new(); self.nodes.len()];
+        for &(from, to, kind) in self.edges.iter() {
+            self.callers[to].push((from, kind));
+            self.callees[from].push((to, kind));
+        }
+    }
+
+    fn add_node(&mut self, name: &str, addr: u64, size: u64, import: bool) {
+        if self.by_addr.contains_key(&addr) {
+            return;
+        }
+        self.by_addr.insert(addr, self.nodes.len());
+        self.nodes.push(Node{name: name.to_string(), addr, size, import});
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|n| n.name == name)
+    }
+
+    /// Returns the node whose function contains `addr`.
+    pub fn containing(&self, addr: u64) -> Option<usize> {
+        if let Some(&node) = self.by_addr.get(&addr) {
+            return Some(node);
+        }
+        let i = self.starts.partition_point(|&(start, _)| start < addr);
+        let &(start, node) = self.starts[..i].last()?;
+        if addr < start.saturating_add(self.nodes[node].size) { Some(node) } else { None }
+    }
+
+    /// Direct callers of `node`, with how they reach it.
+    pub fn callers(&self, node: usize) -> &[(usize, CallKind)] {
+        &self.callers[node]
+    }
+
+    pub fn callees(&self, node: usize) -> &[(usize, CallKind)] {
+        &self.callees[node]
+    }
+
+    /// Every node reachable from `roots`, including the roots themselves.
+    pub fn reachable(&self, roots: &[usize]) -> BTreeSet<usize> {
+        let mut seen: BTreeSet<usize> = roots.iter().cloned().collect();
+        let mut queue: VecDeque<usize> = roots.iter().cloned().collect();
+        while let Some(n) = queue.pop_front() {
+            for &(callee, _) in self.callees(n) {
+                if seen.insert(callee) {
+                    queue.push_back(callee);
+                }
+            }
+        }
+        seen
+    }
+
+    /// Functions nothing reaches from the entry point, DT_INIT/DT_FINI or the init and
+    /// fini arrays.
+    pub fn dead(&self, elf: &ElfFile) -> Vec<usize> {
+        let mut addrs = vec![elf.e_entry];
+        addrs.extend(elf.dynamic_value(elf::DT_INIT));
+        addrs.extend(elf.dynamic_value(elf::DT_FINI));
+        addrs.extend(discover::array_entries(elf));
+        let arm = elf.e_machine == elf::EM_ARM;
+        let roots: Vec<usize> = addrs.iter().filter_map(|&a| self.containing(if arm { a & !1 } else { a })).collect();
+        let live = self.reachable(&roots);
+        (0..self.nodes.len()).filter(|n| !live.contains(n) && !self.nodes[*n].import).collect()
+    }
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+pub fn show_text(g: &CallGraph) {
+    for (n, node) in g.nodes.iter().enumerate().filter(|(_, node)| !node.import) {
+        println!("{:016x} {}", node.addr, node.name);
+        for &(callee, kind) in g.callees(n) {
+            println!("    -> {} ({})", g.nodes[callee].name, kind.name());
+        }
+    }
+}
+
+pub fn show_dot(g: &CallGraph) {
+    println!("digraph callgraph {{");
+    println!("    node [shape=box, fontname=\"monospace\"];");
+    for node in g.nodes.iter().filter(|n| n.import) {
+        println!("    \"{}\" [style=dashed];", node.name.replace('"', "\\\""));
+    }
+    for &(from, to, kind) in g.edges.iter() {
+        let style = match kind {
+            CallKind::Tail => " [style=dashed]",
+            CallKind::Reference => " [style=dotted]",
+            _ => "",
+        };
+        println!("    \"{}\" -> \"{}\"{};", g.nodes[from].name.replace('"', "\\\""),
+                 g.nodes[to].name.replace('"', "\\\""), style);
+    }
+    println!("}}");
+}
+
+pub fn show_graphml(g: &CallGraph) {
+    println!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    println!("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">");
+    println!("  <key id=\"name\" for=\"node\" attr.name=\"name\" attr.type=\"string\"/>");
+    println!("  <key id=\"addr\" for=\"node\" attr.name=\"address\" attr.type=\"long\"/>");
+    println!("  <key id=\"import\" for=\"node\" attr.name=\"import\" attr.type=\"boolean\"/>");
+    println!("  <key id=\"kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>");
+    println!("  <graph id=\"callgraph\" edgedefault=\"directed\">");
+    for (n, node) in g.nodes.iter().enumerate() {
+        println!("    <node id=\"n{}\"><data key=\"name\">{}</data><data key=\"addr\">{}</data><data key=\"import\">{}</data></node>",
+                 n, xml_escape(&node.name), node.addr, node.import);
+    }
+    for &(from, to, kind) in g.edges.iter() {
+        println!("    <edge source=\"n{}\" target=\"n{}\"><data key=\"kind\">{}</data></edge>", from, to, kind.name());
+    }
+    println!("  </graph>");
+    println!("</graphml>");
+}
+
+/// Prints one "address name" line per node, in address order.
+pub fn show_nodes(g: &CallGraph, nodes: &[usize]) {
+    let mut sorted = nodes.to_vec();
+    sorted.sort_by_key(|&n| g.nodes[n].addr);
+    for n in sorted {
+        println!("{:016x} {}", g.nodes[n].addr, g.nodes[n].name);
+    }
+}
@@ -0,0 +1,840 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use crate::disasm;
+use crate::disasm::{Decoder, Flow, Instruction, Mapping, Symbolizer};
+use crate::discover;
+use crate::dwarf;
+use crate::elf;
+use crate::elf::ElfFile;
+use crate::plt;
+
+/// Upper bound on jump table entries when no bounds check is found.
+const MAX_TABLE_ENTRIES: usize = 1024;
+/// Instructions searched backwards along predecessors for the parts of a jump table idiom.
+const MAX_TABLE_BASE_SEARCH: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EdgeKind {
+    Fallthrough,
+    Jump,
+    Taken,      // conditional branch taken
+    Table,      // jump table entry
+}
+
+impl EdgeKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EdgeKind::Fallthrough => "fallthrough",
+            EdgeKind::Jump => "jump",
+            EdgeKind::Taken => "taken",
+            EdgeKind::Table => "table",
+        }
+    }
+}
+
+pub struct Block {
+    pub start:  u64,
+    pub end:    u64,        // one past the last instruction
+    pub insns:  Vec<Instruction>,
+    pub succs:  Vec<(u64, EdgeKind)>,
+}
+
+impl Block {
+    pub fn last(&self) -> &Instruction {
+        &self.insns[self.insns.len() - 1]
+    }
+}
+
+pub struct Function {
+    pub name:       String,
+    pub start:      u64,
+    pub end:        u64,
+    pub blocks:     Vec<Block>,             // sorted by address
+    pub tail_jumps: Vec<(u64, u64)>,        // (jump address, target outside the function)
+}
+
+impl Function {
+    pub fn block_at(&self, addr: u64) -> Option<&Block> {
+        let i = self.blocks.partition_point(|b| b.end <= addr);
+        self.blocks.get(i).filter(|b| b.start <= addr)
+    }
+}
+
+/// Returns (start, end, name) of every function, from symbols and .eh_frame FDEs, plus the
+/// starts recovered by discover::functions when .symtab is missing. Symbols without a size
+/// extend to the next function or the end of their section.
+pub fn function_bounds(elf: &ElfFile) -> Vec<(u64, u64, String)> {
+    let arm = elf.e_machine == elf::EM_ARM;
+    let mut starts: BTreeMap<u64, (u64, String)> = BTreeMap::new();
+    for sym in elf.best_symbols().iter() {
+        if !sym.is_function() || sym.section_index().is_none() || sym.name.is_empty() {
+            continue;
+        }
+        let addr = if arm { sym.st_value & !1 } else { sym.st_value };
+        let name = match sym.name.find('@') {
+            Some(i) => sym.name[..i].to_string(),
+            None => sym.name.clone(),
+        };
+        let entry = starts.entry(addr).or_insert((0, name));
+        entry.0 = entry.0.max(sym.st_size);
+    }
+    for (start, end) in dwarf::eh_frame_ranges(elf) {
+        let entry = starts.entry(start).or_insert((0, format!("sub_{:x}", start)));
+        entry.0 = entry.0.max(end - start);
+    }
+    if discover::is_stripped(elf) {
+        for (start, name) in discover::functions(elf) {
+            let entry = starts.entry(start).or_insert((0, name.clone()));
+            if entry.1.starts_with("sub_") {
+                entry.1 = name;
+            }
+        }
+    }
+    let addrs: Vec<u64> = starts.keys().cloned().collect();
+    let mut result = Vec::new();
+    for (i, (&start, (size, name))) in starts.iter().enumerate() {
+        // PLT stubs have FDEs too but are not functions of this file.
+        let section = match elf.section_for_vaddr(start) {
+            Some(s) if s.is_exec() && !s.name.starts_with(".plt") => s,
+            _ => continue,
+        };
+        let section_end = section.sh_addr + section.sh_size;
+        let end = if *size != 0 {
+            start + size
+        } else {
+            addrs.get(i + 1).cloned().unwrap_or(section_end).min(section_end)
+        };
+        result.push((start, end, name.clone()));
+    }
+    result
+}
+
+/// Recovers the basic blocks of the function at [start, end) by following control flow
+/// from its entry.
+pub fn build(elf: &ElfFile, decoder: &Decoder, symbols: &Symbolizer, start: u64, end: u64, name: &str) -> Function {
+    let shndx = disasm::section_index(elf, start);
+    let mut insns: BTreeMap<u64, Instruction> = BTreeMap::new();
+    let mut leaders: BTreeSet<u64> = BTreeSet::new();
+    let mut tables: HashMap<u64, Vec<u64>> = HashMap::new();
+    let mut tail_jumps = Vec::new();
+    let inside = |a: u64| a >= start && a < end;
+
+    leaders.insert(start);
+    let mut work = vec![start];
+    while let Some(mut pc) = work.pop() {
+        // Each path starts from a fresh decoder; Thumb IT state does not cross blocks.
+        let mut dec = *decoder;
+        while inside(pc) && !insns.contains_key(&pc) {
+            if let Some(mapping) = shndx.and_then(|i| symbols.mapping_at(i, pc)) {
+                if mapping == Mapping::Data {
+                    break;
+                }
+                dec.set_mapping(mapping);
+            }
+            let insn = match elf.read_vaddr(pc, (end - pc).min(disasm::MAX_INSN_BYTES as u64) as usize)
+                .and_then(|bytes| dec.decode(bytes, pc)) {
+                Some(insn) => insn,
+                None => break,
+            };
+            let next = pc + insn.len as u64;
+            let flow = insn.flow;
+            let mut targets = Vec::new();
+            match flow {
+                Flow::Jump(t) | Flow::Branch(t) => {
+                    if inside(t) {
+                        targets.push(t);
+                    } else {
+                        tail_jumps.push((pc, t));
+                    }
+                }
+                _ => {}
+            }
+            // "ldrls pc, [pc, r0, lsl #2]" either takes the table or falls through.
+            let conditional = flow == Flow::Next && dec.machine == elf::EM_ARM && insn.operands.starts_with("pc, ");
+            if flow == Flow::IndirectJump || conditional {
+                let found = jump_table(elf, &dec, &insn, &insns, start, end);
+                targets.extend(found.iter().cloned());
+                tables.insert(pc, found);
+            }
+            insns.insert(pc, insn);
+            for &t in targets.iter() {
+                leaders.insert(t);
+                work.push(t);
+            }
+            match flow {
+                Flow::Next | Flow::Call(_) | Flow::IndirectCall => pc = next,
+                Flow::Branch(_) => {
+                    leaders.insert(next);
+                    work.push(next);
+                    break;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut current: Option<Block> = None;
+    for (&addr, insn) in insns.iter() {
+        let split = match current.as_ref() {
+            Some(b) => b.end != addr || leaders.contains(&addr) || ends_block(b.last().flow)
+                       || tables.contains_key(&b.last().addr),
+            None => true,
+        };
+        if split {
+            if let Some(b) = current.take() {
+                blocks.push(b);
+            }
+            current = Some(Block{start: addr, end: addr, insns: Vec::new(), succs: Vec::new()});
+        }
+        let b = current.as_mut().unwrap();
+        b.end = addr + insn.len as u64;
+        b.insns.push(insn.clone());
+    }
+    if let Some(b) = current.take() {
+        blocks.push(b);
+    }
+
+    for b in blocks.iter_mut() {
+        let last = b.last();
+        let mut seen = HashSet::new();
+        let mut succs: Vec<(u64, EdgeKind)> = tables.get(&last.addr).map(|v| v.as_slice()).unwrap_or(&[]).iter()
+            .filter(|&&t| seen.insert(t))
+            .map(|&t| (t, EdgeKind::Table))
+            .collect();
+        match last.flow {
+            Flow::Jump(t) if inside(t) => succs.push((t, EdgeKind::Jump)),
+            Flow::Branch(t) => {
+                if inside(t) {
+                    succs.push((t, EdgeKind::Taken));
+                }
+                succs.push((b.end, EdgeKind::Fallthrough));
+            }
+            Flow::Next | Flow::Call(_) | Flow::IndirectCall => succs.push((b.end, EdgeKind::Fallthrough)),
+            _ => {}
+        }
+        succs.retain(|s| insns.contains_key(&s.0));
+        b.succs = succs;
+    }
+    Function{name: name.to_string(), start, end, blocks, tail_jumps}
+}
+
+/// Builds the CFG of every function, or only of the one named `only`.
+pub fn build_all(elf: &ElfFile, decoder: &Decoder, symbols: &Symbolizer, only: Option<&str>) -> Vec<Function> {
+    function_bounds(elf).iter()
+        .filter(|f| only.is_none_or(|name| f.2 == name))
+        .map(|f| build(elf, decoder, symbols, f.0, f.1, &f.2))
+        .collect()
+}
+
+fn ends_block(flow: Flow) -> bool {
+    !matches!(flow, Flow::Next | Flow::Call(_) | Flow::IndirectCall)
+}
+
+/// Parses the value of a trailing immediate operand such as "0x1f" or "#12". Bare numbers are
+/// not immediates: x86 prints branch targets that way.
+pub fn trailing_immediate(operands: &str) -> Option<u64> {
+    let last = operands.rsplit(',').next()?.trim();
+    let (marked, last) = match last.strip_prefix('#') {
+        Some(rest) => (true, rest),
+        None => (false, last),
+    };
+    match last.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None if marked => last.parse::<u64>().ok(),
+        None => None,
+    }
+}
+
+/// Addresses of the decoded instructions that can run right before the one at `addr`: the one
+/// falling through to it and the branches targeting it.
+fn predecessors(insns: &BTreeMap<u64, Instruction>, targets: &HashMap<u64, Vec<u64>>, addr: u64) -> Vec<u64> {
+    let mut preds = targets.get(&addr).cloned().unwrap_or_default();
+    if let Some((&at, prev)) = insns.range(..addr).next_back() {
+        if at + prev.len as u64 == addr && matches!(prev.flow, Flow::Next | Flow::Branch(_) | Flow::Call(_) | Flow::IndirectCall) {
+            preds.push(at);
+        }
+    }
+    preds
+}
+
+/// The base register of a memory operand such as "DWORD PTR [r9+r8*4]".
+fn memory_base(operand: &str) -> Option<&str> {
+    let open = operand.find('[')?;
+    let base = operand[open + 1..].split(['+', '-', ']']).next()?;
+    if base.is_empty() || base.contains('*') { None } else { Some(base) }
+}
+
+/// Maps each branch target to the decoded branches jumping there, for walking back along
+/// predecessors.
+fn branch_sources(insns: &BTreeMap<u64, Instruction>) -> HashMap<u64, Vec<u64>> {
+    let mut sources: HashMap<u64, Vec<u64>> = HashMap::new();
+    for (&at, i) in insns.iter() {
+        if let Flow::Jump(t) | Flow::Branch(t) = i.flow {
+            sources.entry(t).or_default().push(at);
+        }
+    }
+    sources
+}
+
+/// The nearest instruction before the one at `addr`, walking back along predecessors, for
+/// which `matches` holds.
+fn find_back<'a>(insns: &'a BTreeMap<u64, Instruction>, sources: &HashMap<u64, Vec<u64>>, addr: u64,
+                 matches: impl Fn(&Instruction) -> bool) -> Option<&'a Instruction> {
+    let mut seen = HashSet::new();
+    let mut work = predecessors(insns, sources, addr);
+    while let Some(at) = work.pop() {
+        if seen.len() >= MAX_TABLE_BASE_SEARCH || !seen.insert(at) {
+            continue;
+        }
+        let i = &insns[&at];
+        if matches(i) {
+            return Some(i);
+        }
+        work.extend(predecessors(insns, sources, at));
+    }
+    None
+}
+
+/// AArch64 w and x registers of the same number are one register.
+fn same_register(a: &str, b: &str) -> bool {
+    fn number(r: &str) -> Option<&str> {
+        r.strip_prefix(['w', 'x']).filter(|n| !n.is_empty() && n.bytes().all(|c| c.is_ascii_digit()))
+    }
+    a == b || number(a).is_some_and(|n| number(b) == Some(n))
+}
+
+/// True when `i` sets `reg`: its first operand, unless it is a compare, a store or a branch.
+fn writes(i: &Instruction, reg: &str) -> bool {
+    let m = i.mnemonic.as_str();
+    let reads_only = ["cmp", "cmn", "tst", "teq", "test", "sb", "sh", "sw", "sd"].contains(&m) || m.starts_with("st");
+    i.flow == Flow::Next && !reads_only && same_register(i.operands.split(',').next().unwrap_or("").trim(), reg)
+}
+
+/// The nearest instruction before the one at `addr` that sets `reg`.
+fn definition<'a>(insns: &'a BTreeMap<u64, Instruction>, sources: &HashMap<u64, Vec<u64>>, addr: u64,
+                  reg: &str) -> Option<&'a Instruction> {
+    find_back(insns, sources, addr, |i| writes(i, reg))
+}
+
+/// Finds the "lea reg, [rip+table]" that sets up the base of an x86-64 offset table for
+/// "jmp reg". Walks back along predecessors, from the jump's register through the register
+/// moves and the add of the base to the entry, so that a lea hoisted out of a loop is found
+/// and a lea of an unrelated register is not.
+fn table_base(insn: &Instruction, insns: &BTreeMap<u64, Instruction>, sources: &HashMap<u64, Vec<u64>>) -> Option<u64> {
+    let mut seen = HashSet::new();
+    let mut work: Vec<(u64, Vec<String>)> = predecessors(insns, sources, insn.addr).into_iter()
+        .map(|at| (at, vec![insn.operands.trim().to_string()]))
+        .collect();
+    while let Some((at, mut regs)) = work.pop() {
+        if seen.len() >= MAX_TABLE_BASE_SEARCH || !seen.insert(at) {
+            continue;
+        }
+        let i = &insns[&at];
+        let operands: Vec<&str> = i.operands.split(',').map(|o| o.trim()).collect();
+        let (dest, source) = (operands[0], operands.get(1).cloned().unwrap_or(""));
+        if let Some(index) = regs.iter().position(|r| r == dest) {
+            let is_register = source.starts_with(|c: char| c.is_ascii_alphabetic()) && !source.contains('[');
+            match i.mnemonic.as_str() {
+                "lea" if i.mem_ref.is_some() => return i.mem_ref,
+                // entry + base: either side may be the base.
+                "add" if is_register => regs.push(source.to_string()),
+                "mov" if is_register => regs[index] = source.to_string(),
+                // The entry loaded from [base+index*4] names the base too.
+                "mov" | "movsxd" | "movsx" if source.contains('[') => {
+                    regs.remove(index);
+                    if let Some(base) = memory_base(source) {
+                        regs.push(base.to_string());
+                    }
+                }
+                _ => {
+                    regs.remove(index);
+                }
+            }
+        }
+        if regs.is_empty() {
+            continue;
+        }
+        for pred in predecessors(insns, sources, at) {
+            work.push((pred, regs.clone()));
+        }
+    }
+    None
+}
+
+/// Entries allowed by the bounds check nearest before the jump at `addr` along its
+/// predecessors: "cmp index, N", or on RISC-V "bltu limit, index" or "bgeu limit, index"
+/// with "li limit, N".
+fn table_bound(insns: &BTreeMap<u64, Instruction>, sources: &HashMap<u64, Vec<u64>>, addr: u64) -> Option<usize> {
+    let check = find_back(insns, sources, addr, |i| ["cmp", "bltu", "bgeu"].contains(&i.mnemonic.as_str()))?;
+    let n = if check.mnemonic == "cmp" {
+        trailing_immediate(&check.operands)?
+    } else {
+        let li = definition(insns, sources, check.addr, check.operands.split(',').next()?.trim())?;
+        if li.mnemonic != "li" {
+            return None;
+        }
+        plt::immediate(li.operands.split(',').nth(1)?)?
+    };
+    Some((n as usize).saturating_add(1).min(MAX_TABLE_ENTRIES))
+}
+
+/// A jump table of `size`-byte entries at `addr`, each giving the target `base + (entry << shift)`.
+struct Table {
+    addr:   u64,
+    size:   usize,
+    signed: bool,   // entries are sign-extended
+    base:   u64,
+    shift:  u32,
+}
+
+/// The address in `reg` before the instruction at `addr`, set up by "adr reg, addr" or by
+/// "adrp reg, page; add reg, reg, #offset".
+fn aarch64_address(insns: &BTreeMap<u64, Instruction>, sources: &HashMap<u64, Vec<u64>>, addr: u64,
+                   reg: &str) -> Option<u64> {
+    let i = definition(insns, sources, addr, reg)?;
+    match i.mnemonic.as_str() {
+        "adr" | "adrp" => i.mem_ref,
+        "add" => {
+            let page = definition(insns, sources, i.addr, i.operands.split(',').nth(1)?.trim())
+                .filter(|p| p.mnemonic == "adrp")?.mem_ref?;
+            Some(page.wrapping_add(trailing_immediate(&i.operands)?))
+        }
+        _ => None,
+    }
+}
+
+/// The AArch64 table of "adrp/add xT, table; ldrb/ldrh/ldr wE, [xT, wI, uxtw]; adr xB, base;
+/// add xN, xB, wE, sxtb #2; br xN", with entries counting instructions from the adr's label.
+fn aarch64_table(insn: &Instruction, insns: &BTreeMap<u64, Instruction>, sources: &HashMap<u64, Vec<u64>>) -> Option<Table> {
+    let add = definition(insns, sources, insn.addr, insn.operands.trim())?;
+    let ops: Vec<&str> = add.operands.split(',').map(|o| o.trim()).collect();
+    if add.mnemonic != "add" || ops.len() != 4 {
+        return None;
+    }
+    let (extend, shift) = ops[3].split_once(" #")?;
+    let base = aarch64_address(insns, sources, add.addr, ops[1])?;
+    let load = definition(insns, sources, add.addr, ops[2])?;
+    let size = match load.mnemonic.as_str() {
+        "ldrb" => 1,
+        "ldrh" => 2,
+        "ldr" if load.operands.starts_with('w') => 4,
+        _ => return None,
+    };
+    let table_reg = load.operands.split_once('[')?.1.split([',', ']']).next()?.trim();
+    let table = aarch64_address(insns, sources, load.addr, table_reg)?;
+    Some(Table{addr: table, size, signed: extend.starts_with("sxt"), base, shift: shift.parse().ok()?})
+}
+
+/// The RISC-V table of "auipc/addi rT, table; add rA, rI, rT; lw rE, 0(rA); jr rE" with
+/// absolute entries, or with entries relative to the table when "add rD, rE, rT" comes
+/// before the jr.
+fn riscv_table(insn: &Instruction, insns: &BTreeMap<u64, Instruction>, sources: &HashMap<u64, Vec<u64>>) -> Option<Table> {
+    // The instructions setting the source registers of `i`, which has a destination first.
+    let inputs = |i: &Instruction| -> Vec<&Instruction> {
+        i.operands.split(',').skip(1).filter_map(|r| definition(insns, sources, i.addr, r.trim())).collect()
+    };
+    let mut load = definition(insns, sources, insn.addr, insn.operands.trim())?;
+    let mut base = 0;
+    if load.mnemonic == "add" {
+        let entry_and_table = inputs(load);
+        base = entry_and_table.iter().find_map(|i| i.mem_ref)?;
+        load = entry_and_table.into_iter().find(|i| i.mnemonic == "lw")?;
+    }
+    if load.mnemonic != "lw" {
+        return None;
+    }
+    let (offset, addr_reg) = load.operands.split_once(',')?.1.trim().trim_end_matches(')').split_once('(')?;
+    let sum = definition(insns, sources, load.addr, addr_reg).filter(|i| i.mnemonic == "add")?;
+    let table = inputs(sum).iter().find_map(|i| i.mem_ref)?;
+    Some(Table{addr: table.wrapping_add(offset.parse::<i64>().ok()? as u64), size: 4, signed: true, base, shift: 0})
+}
+
+/// Recognizes the common jump table idioms in front of an indirect jump and returns the
+/// table's targets inside [start, end):
+///   x86:     jmp [reg*4/8 + table]             absolute entries
+///   x86-64:  lea base, [rip+table] ... jmp reg signed 32-bit entries relative to the table,
+///            with the lea found along the register the jump uses
+///   AArch64: adr base ... add xN, base, wE, sxtb #2; br xN
+///                                              byte, halfword or word entries loaded from a
+///                                              table set up by adrp/add, in instructions
+///                                              from base
+///   ARM:     ldr pc, [pc, reg, lsl #2]         absolute entries after the jump
+///            add pc, pc, reg, lsl #2           a branch per entry after the jump
+///   Thumb:   tbb/tbh [pc, reg]                  inline byte/halfword offsets
+///   RISC-V:  auipc/addi ... lw reg ... jr reg  absolute 32-bit entries, or entries relative
+///                                              to the table when it is added back
+/// The bounds check nearest before the jump along its predecessors limits the table to
+/// N+1 entries.
+fn jump_table(elf: &ElfFile, decoder: &Decoder, insn: &Instruction, insns: &BTreeMap<u64, Instruction>,
+              start: u64, end: u64) -> Vec<u64> {
+    let sources = branch_sources(insns);
+    let count = table_bound(insns, &sources, insn.addr).unwrap_or(MAX_TABLE_ENTRIES);
+    let read = |addr: u64, size: usize| -> Option<u64> {
+        let bytes = elf.read_vaddr(addr, size)?;
+        let mut c = dwarf::Cursor::new(bytes, 0, elf.is_little_endian());
+        c.sized(size)
+    };
+    let inside = |a: u64| a >= start && a < end;
+    // Targets up to the first entry outside the function.
+    let entries = |table: Table| -> Vec<u64> {
+        let mut targets = Vec::new();
+        let bits = 64 - 8 * table.size as u32;
+        for i in 0..count {
+            let e = match read(table.addr + (i * table.size) as u64, table.size) {
+                Some(e) if table.signed => ((e << bits) as i64 >> bits) as u64,
+                Some(e) => e,
+                None => break,
+            };
+            let t = table.base.wrapping_add(e << table.shift);
+            if !inside(t) {
+                break;
+            }
+            targets.push(t);
+        }
+        targets
+    };
+    let mut targets = Vec::new();
+    match decoder.machine {
+        elf::EM_386 | elf::EM_X86_64 if insn.operands.contains('[') => {
+            // Absolute table indexed by a scaled register.
+            let operand = insn.operands.trim_end_matches(']');
+            let table = match (operand.contains('*'), operand.rfind("0x")) {
+                (true, Some(i)) => u64::from_str_radix(&operand[i + 2..], 16).ok(),
+                _ => None,
+            };
+            let size = if decoder.is_64 { 8 } else { 4 };
+            if let Some(table) = table {
+                targets = entries(Table{addr: table, size, signed: false, base: 0, shift: 0});
+            }
+        }
+        elf::EM_386 | elf::EM_X86_64 => {
+            // Position-independent table of offsets from its own address.
+            if let Some(base) = table_base(insn, insns, &sources) {
+                targets = entries(Table{addr: base, size: 4, signed: true, base, shift: 0});
+            }
+        }
+        elf::EM_AARCH64 => {
+            if let Some(table) = aarch64_table(insn, insns, &sources) {
+                targets = entries(table);
+            }
+        }
+        elf::EM_RISCV => {
+            if let Some(table) = riscv_table(insn, insns, &sources) {
+                targets = entries(table);
+            }
+        }
+        elf::EM_ARM if insn.mnemonic.starts_with("ldr") && insn.operands.starts_with("pc, [pc, ") => {
+            // pc reads 8 ahead, past the branch to the default case.
+            targets = entries(Table{addr: insn.addr + 8, size: 4, signed: false, base: 0, shift: 0});
+        }
+        elf::EM_ARM if insn.mnemonic.starts_with("add") && insn.operands.starts_with("pc, pc, ") => {
+            let mut dec = *decoder;
+            for i in 0..count {
+                let at = insn.addr + 8 + 4 * i as u64;
+                match elf.read_vaddr(at, 4).and_then(|bytes| dec.decode(bytes, at)) {
+                    Some(b) if inside(at) && matches!(b.flow, Flow::Jump(_)) => targets.push(at),
+                    _ => break,
+                }
+            }
+        }
+        elf::EM_ARM if insn.mnemonic == "tbb" || insn.mnemonic == "tbh" => {
+            let size = if insn.mnemonic == "tbb" { 1 } else { 2 };
+            let table = insn.addr + 4;
+            let mut table_end = end;
+            for i in 0..count {
+                let at = table + (i * size) as u64;
+                if at >= table_end {
+                    break;
+                }
+                match read(at, size) {
+                    Some(e) => {
+                        let t = table + 2 * e;
+                        if !inside(t) {
+                            break;
+                        }
+                        // The table cannot run into the code it branches to.
+                        table_end = table_end.min(t);
+                        targets.push(t);
+                    }
+                    None => break,
+                }
+            }
+        }
+        _ => {}
+    }
+    targets
+}
+
+/// Reads the addresses executed by the tracer from its output: each line starts with the
+/// address of the traced instruction.
+pub fn load_trace(path: &str) -> Result<HashSet<u64>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut addrs = HashSet::new();
+    for line in text.lines() {
+        let token = line.trim_start().split(|c: char| c == ':' || c.is_whitespace()).next().unwrap_or("");
+        if let Ok(addr) = u64::from_str_radix(token, 16) {
+            addrs.insert(addr);
+        }
+    }
+    Ok(addrs)
+}
+
+fn executed(block: &Block, trace: Option<&HashSet<u64>>) -> Option<bool> {
+    trace.map(|t| block.insns.iter().any(|i| t.contains(&i.addr)))
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn json_escape(s: &str) -> String {
+    let mut out = String::new();
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Prints the CFGs as a Graphviz digraph with one cluster per function. Executed blocks
+/// are filled when a trace is given.
+pub fn show_dot(functions: &[Function], decoder: &Decoder, trace: Option<&HashSet<u64>>) {
+    println!("digraph cfg {{");
+    println!("    node [shape=box, fontname=\"monospace\"];");
+    for (n, f) in functions.iter().enumerate() {
+        println!("    subgraph cluster_{} {{", n);
+        println!("        label=\"{}\";", dot_escape(&f.name));
+        for b in f.blocks.iter() {
+            let mut label = String::new();
+            for insn in b.insns.iter() {
+                label.push_str(&format!("{:x}: {}\\l", insn.addr, dot_escape(&decoder.text(insn).replace('\t', " "))));
+            }
+            let fill = match executed(b, trace) {
+                Some(true) => ", style=filled, fillcolor=\"palegreen\"",
+                Some(false) => ", style=filled, fillcolor=\"lightgray\"",
+                None => "",
+            };
+            println!("        \"{:x}\" [label=\"{}\"{}];", b.start, label, fill);
+        }
+        for b in f.blocks.iter() {
+            for (target, kind) in b.succs.iter() {
+                let style = match kind {
+                    EdgeKind::Taken => " [color=green]",
+                    EdgeKind::Table => " [style=dashed]",
+                    _ => "",
+                };
+                println!("        \"{:x}\" -> \"{:x}\"{};", b.start, target, style);
+            }
+        }
+        println!("    }}");
+    }
+    println!("}}");
+}
+
+pub fn show_json(functions: &[Function], trace: Option<&HashSet<u64>>) {
+    println!("{{\"functions\": [");
+    for (n, f) in functions.iter().enumerate() {
+        println!("  {{\"name\": \"{}\", \"start\": {}, \"end\": {}, \"blocks\": [", json_escape(&f.name), f.start, f.end);
+        for (m, b) in f.blocks.iter().enumerate() {
+            let succs: Vec<String> = b.succs.iter()
+                .map(|(t, k)| format!("{{\"target\": {}, \"kind\": \"{}\"}}", t, k.name())).collect();
+            let exec = match executed(b, trace) {
+                Some(e) => format!(", \"executed\": {}", e),
+                None => String::new(),
+            };
+            println!("    {{\"start\": {}, \"end\": {}, \"instructions\": {}{}, \"successors\": [{}]}}{}",
+                b.start, b.end, b.insns.len(), exec, succs.join(", "), if m + 1 < f.blocks.len() { "," } else { "" });
+        }
+        let tails: Vec<String> = f.tail_jumps.iter()
+            .map(|(from, to)| format!("{{\"from\": {}, \"target\": {}}}", from, to)).collect();
+        println!("  ], \"tail_jumps\": [{}]}}{}", tails.join(", "), if n + 1 < functions.len() { "," } else { "" });
+    }
+    println!("]}}");
+}
+
+/// Compares the statically recovered blocks with the addresses a trace executed.
+pub fn show_coverage(functions: &[Function], trace: &HashSet<u64>) {
+    let mut sorted: Vec<u64> = trace.iter().cloned().collect();
+    sorted.sort_unstable();
+    let mut total = 0;
+    let mut hit = 0;
+    for f in functions.iter() {
+        let missed: Vec<&Block> = f.blocks.iter().filter(|b| executed(b, Some(trace)) == Some(false)).collect();
+        // Executed addresses the static analysis never reached.
+        let first = sorted.partition_point(|&a| a < f.start);
+        let last = sorted.partition_point(|&a| a < f.end);
+        let unknown: Vec<u64> = sorted[first..last].iter().cloned().filter(|&a| f.block_at(a).is_none()).collect();
+        let executed_blocks = f.blocks.len() - missed.len();
+        total += f.blocks.len();
+        hit += executed_blocks;
+        if executed_blocks == 0 && unknown.is_empty() {
+            continue;
+        }
+        println!("{} ({:x}-{:x}): {}/{} blocks executed", f.name, f.start, f.end, executed_blocks, f.blocks.len());
+        for b in missed.iter() {
+            println!("    not executed  {:x}-{:x}", b.start, b.end);
+        }
+        for a in unknown.iter() {
+            println!("    not in cfg    {:x}", a);
+        }
+    }
+    println!("Total: {}/{} blocks executed", hit, total);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::Syntax;
+    use crate::testelf::Builder;
+
+    /// The successors of the block ending with the jump at `jump` in the CFG of `code`, loaded
+    /// at `addr` and built as one function.
+    fn successors(machine: u16, addr: u64, code: &[u8], jump: u64) -> Vec<(u64, EdgeKind)> {
+        let mut b = Builder::new(machine);
+        b.entry = addr;
+        let text = b.section(".text", elf::SHT_PROGBITS, elf::SHF_ALLOC | elf::SHF_EXECINSTR, addr, code);
+        b.load(text, text, elf::PF_R | elf::PF_X);
+        let elf = ElfFile::parse(b.build()).unwrap();
+        let decoder = Decoder::for_elf(&elf, Syntax::Intel).unwrap();
+        let f = build(&elf, &decoder, &Symbolizer::new(&elf), addr, addr + code.len() as u64, "f");
+        f.blocks.iter().find(|b| b.last().addr == jump).unwrap().succs.clone()
+    }
+
+    fn words(code: &[u32]) -> Vec<u8> {
+        code.iter().flat_map(|w| w.to_le_bytes()).collect()
+    }
+
+    fn table(targets: &[u64]) -> Vec<(u64, EdgeKind)> {
+        targets.iter().map(|&t| (t, EdgeKind::Table)).collect()
+    }
+
+    #[test]
+    fn x86_64_bound_along_predecessors() {
+        // The cmp nearest by address belongs to another path; the one guarding the jump
+        // bounds the table to 4 of its 6 entries.
+        let code = [
+            0x85, 0xf6,                                 // 401000: test esi, esi
+            0x75, 0x07,                                 // 401002: jne 40100b
+            0x83, 0xff, 0x03,                           // 401004: cmp edi, 0x3
+            0x77, 0x31,                                 // 401007: ja 40103a
+            0xeb, 0x07,                                 // 401009: jmp 401012
+            0x83, 0xfe, 0x40,                           // 40100b: cmp esi, 0x40
+            0x0f, 0x92, 0xc0,                           // 40100e: setb al
+            0xc3,                                       // 401011: ret
+            0x48, 0x8d, 0x15, 0x24, 0x00, 0x00, 0x00,   // 401012: lea rdx, [rip+0x24]
+            0x48, 0x63, 0x04, 0xba,                     // 401019: movsxd rax, DWORD PTR [rdx+rdi*4]
+            0x48, 0x01, 0xd0,                           // 40101d: add rax, rdx
+            0xff, 0xe0,                                 // 401020: jmp rax
+            0xb8, 0x01, 0x00, 0x00, 0x00, 0xc3,         // 401022: mov eax, 0x1; ret
+            0xb8, 0x02, 0x00, 0x00, 0x00, 0xc3,         // 401028: mov eax, 0x2; ret
+            0xb8, 0x03, 0x00, 0x00, 0x00, 0xc3,         // 40102e: mov eax, 0x3; ret
+            0xb8, 0x04, 0x00, 0x00, 0x00, 0xc3,         // 401034: mov eax, 0x4; ret
+            0x31, 0xc0, 0xc3,                           // 40103a: xor eax, eax; ret
+            0xe5, 0xff, 0xff, 0xff, 0xeb, 0xff, 0xff, 0xff, 0xf1, 0xff, 0xff, 0xff, 0xf7, 0xff, 0xff, 0xff,
+            0xe5, 0xff, 0xff, 0xff, 0xfd, 0xff, 0xff, 0xff,
+        ];
+        assert_eq!(successors(elf::EM_X86_64, 0x401000, &code, 0x401020),
+                   table(&[0x401022, 0x401028, 0x40102e, 0x401034]));
+    }
+
+    #[test]
+    fn aarch64_byte_and_halfword_tables() {
+        // ldrb w0, [x1, w0, uxtw] / add x0, x2, w0, sxtb #2 and
+        // ldrh w0, [x1, w0, uxtw #1] / add x0, x2, w0, sxth #2
+        let forms: [(u32, u32, &[u8]); 2] = [
+            (0x38604820, 0x8b208840, &[0, 2, 4, 6]),
+            (0x78605820, 0x8b20a840, &[0, 0, 2, 0, 4, 0, 6, 0]),
+        ];
+        for &(load, add, entries) in forms.iter() {
+            let mut code = words(&[
+                0x7100081f,                 // 400000: cmp w0, #0x2
+                0x540001a8,                 // 400004: b.hi 400038
+                0x90000001,                 // 400008: adrp x1, 400000
+                0x91010021,                 // 40000c: add x1, x1, #0x40
+                load,                       // 400010
+                0x10000062,                 // 400014: adr x2, 400020
+                add,                        // 400018
+                0xd61f0000,                 // 40001c: br x0
+                0x52800020, 0xd65f03c0,     // 400020: mov w0, #0x1; ret
+                0x52800040, 0xd65f03c0,     // 400028: mov w0, #0x2; ret
+                0x52800060, 0xd65f03c0,     // 400030: mov w0, #0x3; ret
+                0x52800000, 0xd65f03c0,     // 400038: mov w0, #0x0; ret
+            ]);
+            code.extend_from_slice(entries);
+            assert_eq!(successors(elf::EM_AARCH64, 0x400000, &code, 0x40001c), table(&[0x400020, 0x400028, 0x400030]));
+        }
+    }
+
+    #[test]
+    fn arm_ldr_pc_table() {
+        let code = words(&[
+            0xe3500002,                     // 10000: cmp r0, #2
+            0x979ff100,                     // 10004: ldrls pc, [pc, r0, lsl #2]
+            0xea000008,                     // 10008: b 10030
+            0x00010018, 0x00010020, 0x00010028,
+            0xe3a00001, 0xe12fff1e,         // 10018: mov r0, #1; bx lr
+            0xe3a00002, 0xe12fff1e,         // 10020: mov r0, #2; bx lr
+            0xe3a00003, 0xe12fff1e,         // 10028: mov r0, #3; bx lr
+            0xe3a00000, 0xe12fff1e,         // 10030: mov r0, #0; bx lr
+        ]);
+        let mut expected = table(&[0x10018, 0x10020, 0x10028]);
+        expected.push((0x10008, EdgeKind::Fallthrough));
+        assert_eq!(successors(elf::EM_ARM, 0x10000, &code, 0x10004), expected);
+    }
+
+    #[test]
+    fn arm_add_pc_branch_table() {
+        let code = words(&[
+            0xe3500002,                     // 10000: cmp r0, #2
+            0x908ff100,                     // 10004: addls pc, pc, r0, lsl #2
+            0xea000008,                     // 10008: b 10030
+            0xea000001,                     // 1000c: b 10018
+            0xea000002,                     // 10010: b 10020
+            0xea000003,                     // 10014: b 10028
+            0xe3a00001, 0xe12fff1e,         // 10018: mov r0, #1; bx lr
+            0xe3a00002, 0xe12fff1e,         // 10020: mov r0, #2; bx lr
+            0xe3a00003, 0xe12fff1e,         // 10028: mov r0, #3; bx lr
+            0xe3a00000, 0xe12fff1e,         // 10030: mov r0, #0; bx lr
+        ]);
+        let mut expected = table(&[0x1000c, 0x10010, 0x10014]);
+        expected.push((0x10008, EdgeKind::Fallthrough));
+        assert_eq!(successors(elf::EM_ARM, 0x10000, &code, 0x10004), expected);
+    }
+
+    #[test]
+    fn riscv_relative_and_absolute_tables() {
+        // The second add makes the entries relative to the table; a nop in its place leaves
+        // them absolute. Either way "li a5,2; bltu a5,a0" allows 3 of the 4.
+        let forms: [(u32, [u32; 4]); 2] = [
+            (0x00f50533, [-0x20i32 as u32, -0x18i32 as u32, -0x10i32 as u32, -0x8i32 as u32]),
+            (0x00000013, [0x10024, 0x1002c, 0x10034, 0x1003c]),
+        ];
+        for &(add, entries) in forms.iter() {
+            let mut code = words(&[
+                0x00200793,                 // 10000: li a5,2
+                0x02a7ec63,                 // 10004: bltu a5,a0,1003c
+                0x00000797,                 // 10008: auipc a5,0x0
+                0x03c78793,                 // 1000c: addi a5,a5,60
+                0x00251513,                 // 10010: slli a0,a0,0x2
+                0x00f50533,                 // 10014: add a0,a0,a5
+                0x00052503,                 // 10018: lw a0,0(a0)
+                add,                        // 1001c
+                0x00050067,                 // 10020: jr a0
+                0x00100513, 0x00008067,     // 10024: li a0,1; ret
+                0x00200513, 0x00008067,     // 1002c: li a0,2; ret
+                0x00300513, 0x00008067,     // 10034: li a0,3; ret
+                0x00000513, 0x00008067,     // 1003c: li a0,0; ret
+            ]);
+            code.extend(words(&entries));
+            assert_eq!(successors(elf::EM_RISCV, 0x10000, &code, 0x10020), table(&[0x10024, 0x1002c, 0x10034]));
+        }
+    }
+}
@@ -0,0 +1,536 @@
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::path::Path;
+
+use crate::elf;
+use crate::elf::ElfFile;
+
+const CACHE_MAGIC_OLD: &[u8] = b"ld.so-1.7.0";
+const CACHE_MAGIC_NEW: &[u8] = b"glibc-ld.so.cache1.1";
+const OLD_HEADER_SIZE: usize = 16;  // magic, version, nlibs
+const OLD_ENTRY_SIZE: usize  = 12;  // flags, key, value
+const NEW_HEADER_SIZE: usize = 48;  // magic, version, nlibs, len_strings, flags, extension offset
+const NEW_ENTRY_SIZE: usize  = 24;  // flags, key, value, osversion, hwcap
+
+// ld.so.cache entry flags
+const FLAG_ELF_LIBC6: i32               = 0x0003;
+const FLAG_TYPE_MASK: i32               = 0x00ff;
+const FLAG_ARCH_MASK: i32               = 0xff00;
+const FLAG_X8664_LIB64: i32             = 0x0300;
+const FLAG_ARM_LIBHF: i32               = 0x0900;
+const FLAG_AARCH64_LIB64: i32           = 0x0a00;
+const FLAG_ARM_LIBSF: i32               = 0x0b00;
+const FLAG_RISCV_FLOAT_ABI_SOFT: i32    = 0x0f00;
+const FLAG_RISCV_FLOAT_ABI_DOUBLE: i32  = 0x1000;
+
+/// A library entry of /etc/ld.so.cache.
+pub struct CacheEntry {
+    pub flags:  i32,    // library type and architecture (FLAG_ELF_LIBC6 | FLAG_X8664_LIB64, ...)
+    pub key:    String, // soname
+    pub value:  String, // path of the library
+}
+
+fn cache_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes.get(offset..offset + 4).map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+/// Parses /etc/ld.so.cache as written by ldconfig: the glibc table alone, or after the old
+/// libc5 table. Strings of the glibc table are relative to its header, strings of the old
+/// table to the end of its entries.
+pub fn parse_ld_so_cache(bytes: &[u8]) -> Result<Vec<CacheEntry>, String> {
+    let (table, entry_size, strings) = if bytes.starts_with(CACHE_MAGIC_NEW) {
+        (0, NEW_ENTRY_SIZE, 0)
+    } else if bytes.starts_with(CACHE_MAGIC_OLD) {
+        let nlibs = cache_u32(bytes, 12).ok_or("truncated ld.so.cache")? as usize;
+        let end = OLD_HEADER_SIZE + nlibs * OLD_ENTRY_SIZE;
+        let new = (end + 7) & !7;
+        match bytes.get(new..) {
+            Some(rest) if rest.starts_with(CACHE_MAGIC_NEW) => (new, NEW_ENTRY_SIZE, new),
+            _ => (0, OLD_ENTRY_SIZE, end),
+        }
+    } else {
+        return Err("ld.so.cache: unknown format".to_string());
+    };
+    let (nlibs, first) = if entry_size == NEW_ENTRY_SIZE {
+        (cache_u32(bytes, table + 20).ok_or("truncated ld.so.cache")?, table + NEW_HEADER_SIZE)
+    } else {
+        (cache_u32(bytes, 12).ok_or("truncated ld.so.cache")?, OLD_HEADER_SIZE)
+    };
+    let mut entries = Vec::new();
+    for i in 0..nlibs as usize {
+        let at = first + i * entry_size;
+        let (flags, key, value) = match (cache_u32(bytes, at), cache_u32(bytes, at + 4), cache_u32(bytes, at + 8)) {
+            (Some(f), Some(k), Some(v)) => (f as i32, k as usize, v as usize),
+            _ => return Err("truncated ld.so.cache".to_string()),
+        };
+        match (elf::read_cstr(bytes, strings + key), elf::read_cstr(bytes, strings + value)) {
+            (Some(key), Some(value)) => entries.push(CacheEntry{flags, key, value}),
+            _ => return Err(format!("ld.so.cache: bad string offset in entry {}", i)),
+        }
+    }
+    Ok(entries)
+}
+
+/// A DT_NEEDED entry and where the dynamic loader would find it.
+pub struct Dependency {
+    pub name:       String,
+    pub path:       Option<String>,     // None when not found
+    pub found_by:   &'static str,       // rpath, LD_LIBRARY_PATH, runpath, ld.so.cache, default, path
+    pub rejected:   Vec<String>,        // candidates skipped for a class or machine mismatch
+    pub repeated:   bool,               // loaded earlier; its dependencies are listed there
+    pub needed:     Vec<Dependency>,
+}
+
+/// Search path state of an object in the chain of loaders of the library being resolved.
+struct Requester {
+    origin:     String,             // directory of the object, for $ORIGIN
+    rpath:      Option<String>,
+    runpath:    Option<String>,
+    nodeflib:   bool,               // DF_1_NODEFLIB
+}
+
+impl Requester {
+    fn new(path: &str, elf: &ElfFile) -> Requester {
+        let dir = Path::new(path).parent().map(|p| if p.as_os_str().is_empty() { Path::new(".") } else { p })
+            .unwrap_or(Path::new("."));
+        let origin = std::fs::canonicalize(dir).unwrap_or(dir.to_path_buf()).to_string_lossy().to_string();
+        let nodeflib = elf.dynamic_value(elf::DT_FLAGS_1).is_some_and(|f| f & elf::DF_1_NODEFLIB != 0);
+        Requester{origin, rpath: elf.rpath(), runpath: elf.runpath(), nodeflib}
+    }
+}
+
+/// Resolves DT_NEEDED entries in the order ld.so searches: DT_RPATH of the requester and its
+/// loaders (unless the requester has DT_RUNPATH), LD_LIBRARY_PATH, DT_RUNPATH, ld.so.cache,
+/// then the default directories. Paths are looked up under `sysroot` for cross targets.
+pub struct Resolver {
+    sysroot:        String,
+    library_path:   Vec<String>,
+    cache:          Vec<CacheEntry>,
+    class:          u8,
+    data:           u8,
+    machine:        u16,
+    loaded:         HashMap<String, (String, &'static str)>,  // name or soname -> (path, found_by)
+    order:          Vec<String>,    // objects in load order, the global lookup scope
+}
+
+impl Resolver {
+    pub fn new(elf: &ElfFile, sysroot: &str) -> Resolver {
+        let sysroot = sysroot.trim_end_matches('/').to_string();
+        // An empty LD_LIBRARY_PATH element means the current directory.
+        let library_path = std::env::var("LD_LIBRARY_PATH").map(|v| {
+            v.split([':', ';']).map(|d| if d.is_empty() { ".".to_string() } else { d.to_string() }).collect()
+        }).unwrap_or_default();
+        let cache = std::fs::read(format!("{}/etc/ld.so.cache", sysroot)).ok()
+            .and_then(|bytes| parse_ld_so_cache(&bytes).ok())
+            .unwrap_or_default();
+        Resolver{sysroot, library_path, cache, class: elf.e_ident[elf::EI_CLASS], data: elf.e_ident[elf::EI_DATA],
+                 machine: elf.e_machine, loaded: HashMap::new(), order: Vec::new()}
+    }
+
+    /// Resolves the dependencies of the object at `path`, recursively. Like ld.so, libraries
+    /// are loaded breadth first, so a library is listed under the first object at the
+    /// shallowest level that needs it.
+    pub fn resolve(&mut self, path: &str, elf: &ElfFile) -> Vec<Dependency> {
+        // objects[i] = (search paths, index of the object that loaded it)
+        // The kernel maps the interpreter first; a DT_NEEDED on it matches by soname.
+        if let Some(interp) = elf.interpreter() {
+            if let Some(soname) = ElfFile::open(&self.rooted(&interp)).ok().and_then(|i| i.soname()) {
+                self.loaded.insert(soname, (self.rooted(&interp), "interpreter"));
+            }
+        }
+        self.order = vec![path.to_string()];
+        let mut objects: Vec<(Requester, Option<usize>)> = vec![(Requester::new(path, elf), None)];
+        let mut nodes: Vec<Option<Dependency>> = Vec::new();
+        let mut children: HashMap<Option<usize>, Vec<usize>> = HashMap::new();
+        let mut queue: VecDeque<(usize, Vec<String>, Option<usize>)> = VecDeque::new();
+        queue.push_back((0, elf.needed(), None));
+        while let Some((object, needed, node)) = queue.pop_front() {
+            for name in needed.iter() {
+                let mut chain = Vec::new();
+                let mut at = Some(object);
+                while let Some(i) = at {
+                    chain.push(&objects[i].0);
+                    at = objects[i].1;
+                }
+                let (dep, lib) = self.load(name, &chain);
+                if let Some(path) = dep.path.as_ref().filter(|p| !self.order.contains(p)) {
+                    self.order.push(path.clone());
+                }
+                children.entry(node).or_default().push(nodes.len());
+                if let (Some(lib), Some(path)) = (lib, dep.path.as_ref()) {
+                    objects.push((Requester::new(path, &lib), Some(object)));
+                    queue.push_back((objects.len() - 1, lib.needed(), Some(nodes.len())));
+                }
+                nodes.push(Some(dep));
+            }
+        }
+        build_tree(&mut nodes, &children, None)
+    }
+
+    /// Paths of the object passed to resolve() and every library it loaded, in load order.
+    pub fn load_order(&self) -> &[String] {
+        &self.order
+    }
+
+    /// Returns the interpreter named by PT_INTERP and whether it exists.
+    pub fn interpreter(&self, elf: &ElfFile) -> Option<(String, bool)> {
+        let interp = elf.interpreter()?;
+        let exists = Path::new(&self.rooted(&interp)).is_file();
+        Some((interp, exists))
+    }
+
+    fn rooted(&self, path: &str) -> String {
+        if path.starts_with('/') { format!("{}{}", self.sysroot, path) } else { path.to_string() }
+    }
+
+    /// Splits a search path and substitutes $ORIGIN, $LIB and $PLATFORM.
+    fn expand(&self, dirs: &str, origin: &str) -> Vec<String> {
+        let lib = if self.class == elf::ELFCLASS64 { "lib64" } else { "lib" };
+        let platform = platform_name(self.machine);
+        dirs.split(':').filter(|d| !d.is_empty()).map(|d| {
+            let relative = d.contains("ORIGIN");
+            let d = d.replace("${ORIGIN}", origin).replace("$ORIGIN", origin)
+                .replace("${LIB}", lib).replace("$LIB", lib)
+                .replace("${PLATFORM}", platform).replace("$PLATFORM", platform);
+            if relative { d } else { self.rooted(&d) }
+        }).collect()
+    }
+
+    fn default_dirs(&self) -> Vec<String> {
+        let mut dirs = Vec::new();
+        if let Some(triplet) = multiarch_triplet(self.machine, self.class) {
+            dirs.push(format!("/lib/{}", triplet));
+            dirs.push(format!("/usr/lib/{}", triplet));
+        }
+        if self.class == elf::ELFCLASS64 {
+            dirs.push("/lib64".to_string());
+            dirs.push("/usr/lib64".to_string());
+        }
+        dirs.push("/lib".to_string());
+        dirs.push("/usr/lib".to_string());
+        dirs.iter().map(|d| self.rooted(d)).collect()
+    }
+
+    /// Candidate files for `name` in search order, tagged with the path that produced them.
+    /// `chain` starts with the requesting object and follows its loaders up to the executable.
+    fn candidates(&self, name: &str, chain: &[&Requester]) -> Vec<(String, &'static str)> {
+        if name.contains('/') {
+            return vec![(self.rooted(name), "path")];
+        }
+        let requester = chain[0];
+        let mut dirs: Vec<(String, &'static str)> = Vec::new();
+        if requester.runpath.is_none() {
+            for r in chain.iter().filter(|r| r.runpath.is_none()) {
+                if let Some(rpath) = &r.rpath {
+                    dirs.extend(self.expand(rpath, &r.origin).into_iter().map(|d| (d, "rpath")));
+                }
+            }
+        }
+        dirs.extend(self.library_path.iter().map(|d| (d.clone(), "LD_LIBRARY_PATH")));
+        if let Some(runpath) = &requester.runpath {
+            dirs.extend(self.expand(runpath, &requester.origin).into_iter().map(|d| (d, "runpath")));
+        }
+        let mut files: Vec<(String, &'static str)> = dirs.into_iter()
+            .map(|(d, by)| (format!("{}/{}", d.trim_end_matches('/'), name), by))
+            .collect();
+        if !requester.nodeflib {
+            files.extend(self.cache.iter()
+                .filter(|e| e.key == name && e.flags & FLAG_TYPE_MASK == FLAG_ELF_LIBC6 && self.cache_arch(e.flags))
+                .map(|e| (self.rooted(&e.value), "ld.so.cache")));
+            files.extend(self.default_dirs().into_iter().map(|d| (format!("{}/{}", d, name), "default")));
+        }
+        files
+    }
+
+    /// Whether the architecture bits of a cache entry are the ones ld.so of the target accepts.
+    fn cache_arch(&self, flags: i32) -> bool {
+        let arch = flags & FLAG_ARCH_MASK;
+        match self.machine {
+            elf::EM_X86_64 if self.class == elf::ELFCLASS64 => arch == FLAG_X8664_LIB64,
+            elf::EM_386 => arch == 0,
+            elf::EM_AARCH64 => arch == FLAG_AARCH64_LIB64,
+            elf::EM_ARM => arch == FLAG_ARM_LIBHF || arch == FLAG_ARM_LIBSF,
+            elf::EM_RISCV => arch == FLAG_RISCV_FLOAT_ABI_SOFT || arch == FLAG_RISCV_FLOAT_ABI_DOUBLE,
+            _ => true,
+        }
+    }
+
+    /// Opens a candidate. Err(Some) describes a library built for another class or machine,
+    /// which ld.so skips while it keeps searching; Err(None) means there is no usable file.
+    fn check(&self, path: &str) -> Result<ElfFile, Option<String>> {
+        if !Path::new(path).is_file() {
+            return Err(None);
+        }
+        let elf = ElfFile::open(path).map_err(|_| None)?;
+        if elf.e_ident[elf::EI_CLASS] != self.class || elf.e_ident[elf::EI_DATA] != self.data || elf.e_machine != self.machine {
+            let class = if elf.is_64() { "ELF64" } else { "ELF32" };
+            let data = if elf.is_little_endian() { "LSB" } else { "MSB" };
+            return Err(Some(format!("{} ({} {} {})", path, class, data, elf::get_machine_name(elf.e_machine))));
+        }
+        if elf.e_type != elf::ET_DYN {
+            return Err(None);
+        }
+        Ok(elf)
+    }
+
+    /// Looks up one needed library. The parsed library is returned when this is its first load.
+    fn load(&mut self, name: &str, chain: &[&Requester]) -> (Dependency, Option<ElfFile>) {
+        let mut dep = Dependency{name: name.to_string(), path: None, found_by: "", rejected: Vec::new(),
+                                 repeated: false, needed: Vec::new()};
+        if let Some((path, found_by)) = self.loaded.get(name) {
+            dep.path = Some(path.clone());
+            dep.found_by = found_by;
+            dep.repeated = true;
+            return (dep, None);
+        }
+        for (path, found_by) in self.candidates(name, chain) {
+            let lib = match self.check(&path) {
+                Ok(lib) => lib,
+                Err(Some(mismatch)) => {
+                    if !dep.rejected.contains(&mismatch) {
+                        dep.rejected.push(mismatch);
+                    }
+                    continue;
+                }
+                Err(None) => continue,
+            };
+            // Later requests by soname or by the same file are satisfied by this load.
+            let already = self.loaded.values().any(|(p, _)| *p == path);
+            self.loaded.insert(name.to_string(), (path.clone(), found_by));
+            if let Some(soname) = lib.soname() {
+                self.loaded.entry(soname).or_insert((path.clone(), found_by));
+            }
+            dep.path = Some(path.clone());
+            dep.found_by = found_by;
+            dep.repeated = already;
+            return (dep, if already { None } else { Some(lib) });
+        }
+        (dep, None)
+    }
+}
+
+fn build_tree(nodes: &mut [Option<Dependency>], children: &HashMap<Option<usize>, Vec<usize>>,
+              parent: Option<usize>) -> Vec<Dependency> {
+    let mut deps = Vec::new();
+    for &i in children.get(&parent).map(|c| c.as_slice()).unwrap_or(&[]) {
+        if let Some(mut dep) = nodes[i].take() {
+            dep.needed = build_tree(nodes, children, Some(i));
+            deps.push(dep);
+        }
+    }
+    deps
+}
+
+fn platform_name(machine: u16) -> &'static str {
+    match machine {
+        elf::EM_X86_64 => "x86_64",
+        elf::EM_386 => "i686",
+        elf::EM_AARCH64 => "aarch64",
+        elf::EM_ARM => "v7l",
+        elf::EM_RISCV => "riscv",
+        _ => "",
+    }
+}
+
+/// Debian multiarch directory name for the target.
+fn multiarch_triplet(machine: u16, class: u8) -> Option<&'static str> {
+    match machine {
+        elf::EM_X86_64 => Some("x86_64-linux-gnu"),
+        elf::EM_386 => Some("i386-linux-gnu"),
+        elf::EM_AARCH64 => Some("aarch64-linux-gnu"),
+        elf::EM_ARM => Some("arm-linux-gnueabihf"),
+        elf::EM_RISCV if class == elf::ELFCLASS64 => Some("riscv64-linux-gnu"),
+        _ => None,
+    }
+}
+
+/// Lists missing libraries and libraries for which only mismatching files exist.
+pub fn problems(deps: &[Dependency]) -> Vec<String> {
+    let mut result = Vec::new();
+    for dep in deps.iter() {
+        match &dep.path {
+            None if dep.rejected.is_empty() => result.push(format!("{}: not found", dep.name)),
+            None => result.push(format!("{}: architecture mismatch: {}", dep.name, dep.rejected.join(", "))),
+            Some(_) => result.extend(problems(&dep.needed)),
+        }
+    }
+    result
+}
+
+fn show_deps(deps: &[Dependency], depth: usize) {
+    for dep in deps.iter() {
+        let indent = "    ".repeat(depth);
+        match &dep.path {
+            Some(path) => {
+                let repeated = if dep.repeated { " (already loaded)" } else { "" };
+                println!("{}{} => {} [{}]{}", indent, dep.name, path, dep.found_by, repeated);
+                for rejected in dep.rejected.iter() {
+                    println!("{}    skipped {}", indent, rejected);
+                }
+                show_deps(&dep.needed, depth + 1);
+            }
+            None if dep.rejected.is_empty() => println!("{}{} => not found", indent, dep.name),
+            None => println!("{}{} => not found (architecture mismatch: {})", indent, dep.name, dep.rejected.join(", ")),
+        }
+    }
+}
+
+pub fn show_tree(path: &str, interpreter: Option<(String, bool)>, deps: &[Dependency]) {
+    match interpreter {
+        Some((interp, true)) => println!("{} (interpreter => {})", path, interp),
+        Some((interp, false)) => println!("{} (interpreter => {}: not found)", path, interp),
+        None => println!("{}", path),
+    }
+    show_deps(deps, 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32s(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_ne_bytes()).collect()
+    }
+
+    /// A glibc table of (flags, key, value) entries, with its strings after the entries.
+    fn new_table(entries: &[(i32, &str, &str)]) -> Vec<u8> {
+        let mut strings = Vec::new();
+        let mut offsets = Vec::new();
+        let start = NEW_HEADER_SIZE + entries.len() * NEW_ENTRY_SIZE;
+        for s in entries.iter().flat_map(|e| [e.1, e.2]) {
+            offsets.push((start + strings.len()) as u32);
+            strings.extend_from_slice(s.as_bytes());
+            strings.push(0);
+        }
+        let mut bytes = CACHE_MAGIC_NEW.to_vec();
+        bytes.extend(u32s(&[entries.len() as u32, strings.len() as u32, 0, 0, 0, 0, 0]));
+        for (i, e) in entries.iter().enumerate() {
+            bytes.extend(u32s(&[e.0 as u32, offsets[2 * i], offsets[2 * i + 1], 0, 0, 0]));
+        }
+        bytes.extend(strings);
+        bytes
+    }
+
+    /// A libc5 table, whose string offsets count from the end of its entries.
+    fn old_table(entries: &[(i32, &str, &str)]) -> Vec<u8> {
+        let mut strings = Vec::new();
+        let mut bytes = CACHE_MAGIC_OLD.to_vec();
+        bytes.push(0);
+        bytes.extend(u32s(&[entries.len() as u32]));
+        for e in entries.iter() {
+            let key = strings.len() as u32;
+            strings.extend_from_slice(e.1.as_bytes());
+            strings.push(0);
+            let value = strings.len() as u32;
+            strings.extend_from_slice(e.2.as_bytes());
+            strings.push(0);
+            bytes.extend(u32s(&[e.0 as u32, key, value]));
+        }
+        bytes.extend(strings);
+        bytes
+    }
+
+    fn flatten(entries: &[CacheEntry]) -> Vec<(i32, &str, &str)> {
+        entries.iter().map(|e| (e.flags, e.key.as_str(), e.value.as_str())).collect()
+    }
+
+    const LIBS: [(i32, &str, &str); 2] = [
+        (FLAG_ELF_LIBC6 | FLAG_X8664_LIB64, "libc.so.6", "/lib/x86_64-linux-gnu/libc.so.6"),
+        (FLAG_ELF_LIBC6, "libc.so.6", "/lib/i386-linux-gnu/libc.so.6"),
+    ];
+
+    #[test]
+    fn glibc_cache() {
+        assert_eq!(flatten(&parse_ld_so_cache(&new_table(&LIBS)).unwrap()), LIBS);
+    }
+
+    #[test]
+    fn libc5_cache() {
+        assert_eq!(flatten(&parse_ld_so_cache(&old_table(&LIBS)).unwrap()), LIBS);
+    }
+
+    #[test]
+    fn glibc_cache_after_libc5_table() {
+        // The glibc table starts 8-byte aligned after the old entries and takes precedence.
+        let mut bytes = old_table(&LIBS[1..]);
+        bytes.truncate(OLD_HEADER_SIZE + OLD_ENTRY_SIZE);
+        bytes.resize((bytes.len() + 7) & !7, 0);
+        bytes.extend(new_table(&LIBS));
+        assert_eq!(flatten(&parse_ld_so_cache(&bytes).unwrap()), LIBS);
+    }
+
+    #[test]
+    fn bad_caches() {
+        assert_eq!(parse_ld_so_cache(b"not a cache").err().unwrap(), "ld.so.cache: unknown format");
+        let mut bytes = new_table(&LIBS);
+        bytes.truncate(NEW_HEADER_SIZE + NEW_ENTRY_SIZE);
+        assert_eq!(parse_ld_so_cache(&bytes).err().unwrap(), "ld.so.cache: bad string offset in entry 0");
+        bytes.truncate(NEW_HEADER_SIZE + 4);
+        assert_eq!(parse_ld_so_cache(&bytes).err().unwrap(), "truncated ld.so.cache");
+    }
+
+    fn resolver() -> Resolver {
+        Resolver{sysroot: "/sys".to_string(), library_path: vec!["/ld".to_string()],
+                 cache: LIBS.iter().map(|e| CacheEntry{flags: e.0, key: e.1.to_string(), value: e.2.to_string()}).collect(),
+                 class: elf::ELFCLASS64, data: 1, machine: elf::EM_X86_64, loaded: HashMap::new(), order: Vec::new()}
+    }
+
+    fn requester(origin: &str, rpath: Option<&str>, runpath: Option<&str>) -> Requester {
+        Requester{origin: origin.to_string(), rpath: rpath.map(String::from), runpath: runpath.map(String::from),
+                  nodeflib: false}
+    }
+
+    /// The candidates searched before the default directories.
+    fn searched(chain: &[&Requester]) -> Vec<(String, &'static str)> {
+        resolver().candidates("libc.so.6", chain).into_iter().filter(|c| c.1 != "default").collect()
+    }
+
+    fn paths(list: &[(&str, &'static str)]) -> Vec<(String, &'static str)> {
+        list.iter().map(|&(p, by)| (p.to_string(), by)).collect()
+    }
+
+    #[test]
+    fn expand_substitutions() {
+        let r = resolver();
+        assert_eq!(r.expand("$ORIGIN/../lib::/opt/$LIB:/usr/${LIB}/${PLATFORM}:${ORIGIN}", "/app/bin"),
+                   ["/app/bin/../lib", "/sys/opt/lib64", "/sys/usr/lib64/x86_64", "/app/bin"]);
+    }
+
+    #[test]
+    fn rpath_of_loaders() {
+        // A library without DT_RUNPATH searches the DT_RPATH of the executable that loaded it.
+        let exe = requester("/app/bin", Some("$ORIGIN/../lib:/r"), None);
+        let lib = requester("/app/lib", None, None);
+        assert_eq!(searched(&[&lib, &exe]), paths(&[
+            ("/app/bin/../lib/libc.so.6", "rpath"),
+            ("/sys/r/libc.so.6", "rpath"),
+            ("/ld/libc.so.6", "LD_LIBRARY_PATH"),
+            ("/sys/lib/x86_64-linux-gnu/libc.so.6", "ld.so.cache"),
+        ]));
+    }
+
+    #[test]
+    fn runpath_hides_rpath() {
+        // DT_RUNPATH comes after LD_LIBRARY_PATH and disables every DT_RPATH, the requester's
+        // own included.
+        let exe = requester("/app/bin", Some("/r"), None);
+        let lib = requester("/app/lib", Some("/own"), Some("$ORIGIN"));
+        assert_eq!(searched(&[&lib, &exe]), paths(&[
+            ("/ld/libc.so.6", "LD_LIBRARY_PATH"),
+            ("/app/lib/libc.so.6", "runpath"),
+            ("/sys/lib/x86_64-linux-gnu/libc.so.6", "ld.so.cache"),
+        ]));
+        // A loader's DT_RPATH is skipped when that loader has DT_RUNPATH.
+        let exe = requester("/app/bin", Some("/r"), Some("/u"));
+        let lib = requester("/app/lib", None, None);
+        assert_eq!(searched(&[&lib, &exe])[0], ("/ld/libc.so.6".to_string(), "LD_LIBRARY_PATH"));
+    }
+
+    #[test]
+    fn nodeflib_and_paths() {
+        let mut exe = requester("/app/bin", None, None);
+        exe.nodeflib = true;
+        assert_eq!(resolver().candidates("libc.so.6", &[&exe]), paths(&[("/ld/libc.so.6", "LD_LIBRARY_PATH")]));
+        assert_eq!(resolver().candidates("/opt/libc.so.6", &[&exe]), paths(&[("/sys/opt/libc.so.6", "path")]));
+    }
+}
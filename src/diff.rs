@@ -0,0 +1,330 @@
+use std::collections::BTreeMap;
+
+use crate::elf;
+use crate::elf::{ElfFile, ProgramHeader, SectionHeader, Symbol};
+
+pub struct FieldChange {
+    pub field:  &'static str,
+    pub old:    String,
+    pub new:    String,
+}
+
+pub struct SizeChange {
+    pub name:       String,
+    pub old_size:   u64,
+    pub new_size:   u64,
+}
+
+impl SizeChange {
+    pub fn delta(&self) -> i64 {
+        self.new_size as i64 - self.old_size as i64
+    }
+}
+
+pub struct SymbolChange {
+    pub name:   String,
+    pub old:    Symbol,
+    pub new:    Symbol,
+}
+
+/// Structural differences between two ELF files.
+pub struct ElfDiff {
+    pub file_size:          (u64, u64),
+    pub header:             Vec<FieldChange>,
+    pub sections_added:     Vec<SectionHeader>,
+    pub sections_removed:   Vec<SectionHeader>,
+    pub sections_resized:   Vec<SizeChange>,
+    pub segments_added:     Vec<ProgramHeader>,
+    pub segments_removed:   Vec<ProgramHeader>,
+    pub segments_resized:   Vec<(ProgramHeader, ProgramHeader)>,
+    pub symbols_added:      Vec<Symbol>,
+    pub symbols_removed:    Vec<Symbol>,
+    pub symbols_resized:    Vec<SymbolChange>,
+    pub symbols_changed:    Vec<SymbolChange>,
+    pub needed_added:       Vec<String>,
+    pub needed_removed:     Vec<String>,
+    pub dynamic_changes:    Vec<FieldChange>,
+}
+
+impl ElfDiff {
+    pub fn is_empty(&self) -> bool {
+        self.file_size.0 == self.file_size.1
+            && self.header.is_empty()
+            && self.sections_added.is_empty()
+            && self.sections_removed.is_empty()
+            && self.sections_resized.is_empty()
+            && self.segments_added.is_empty()
+            && self.segments_removed.is_empty()
+            && self.segments_resized.is_empty()
+            && self.symbols_added.is_empty()
+            && self.symbols_removed.is_empty()
+            && self.symbols_resized.is_empty()
+            && self.symbols_changed.is_empty()
+            && self.needed_added.is_empty()
+            && self.needed_removed.is_empty()
+            && self.dynamic_changes.is_empty()
+    }
+}
+
+pub fn diff(old: &ElfFile, new: &ElfFile) -> ElfDiff {
+    let (sections_added, sections_removed, sections_resized) = diff_sections(old, new);
+    let (segments_added, segments_removed, segments_resized) = diff_segments(old, new);
+    let (symbols_added, symbols_removed, symbols_resized, symbols_changed) = diff_symbols(old, new);
+
+    let old_needed = old.needed();
+    let new_needed = new.needed();
+    let needed_added = new_needed.iter().filter(|n| !old_needed.contains(n)).cloned().collect();
+    let needed_removed = old_needed.iter().filter(|n| !new_needed.contains(n)).cloned().collect();
+
+    let mut dynamic_changes = Vec::new();
+    push_change(&mut dynamic_changes, "SONAME", old.soname().unwrap_or_default(), new.soname().unwrap_or_default());
+    push_change(&mut dynamic_changes, "RPATH", old.rpath().unwrap_or_default(), new.rpath().unwrap_or_default());
+    push_change(&mut dynamic_changes, "RUNPATH", old.runpath().unwrap_or_default(), new.runpath().unwrap_or_default());
+    push_change(&mut dynamic_changes, "Interpreter", old.interpreter().unwrap_or_default(), new.interpreter().unwrap_or_default());
+
+    ElfDiff {
+        file_size: (old.bytes().len() as u64, new.bytes().len() as u64),
+        header: diff_header(old, new),
+        sections_added,
+        sections_removed,
+        sections_resized,
+        segments_added,
+        segments_removed,
+        segments_resized,
+        symbols_added,
+        symbols_removed,
+        symbols_resized,
+        symbols_changed,
+        needed_added,
+        needed_removed,
+        dynamic_changes,
+    }
+}
+
+fn push_change(changes: &mut Vec<FieldChange>, field: &'static str, old: String, new: String) {
+    if old != new {
+        changes.push(FieldChange{field, old, new});
+    }
+}
+
+fn diff_header(old: &ElfFile, new: &ElfFile) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    push_change(&mut changes, "Class", old.e_ident[elf::EI_CLASS].to_string(), new.e_ident[elf::EI_CLASS].to_string());
+    push_change(&mut changes, "Data", old.e_ident[elf::EI_DATA].to_string(), new.e_ident[elf::EI_DATA].to_string());
+    push_change(&mut changes, "OS/ABI", old.e_ident[elf::EI_OSABI].to_string(), new.e_ident[elf::EI_OSABI].to_string());
+    push_change(&mut changes, "ABI Version", old.e_ident[elf::EI_ABIVERSION].to_string(), new.e_ident[elf::EI_ABIVERSION].to_string());
+    push_change(&mut changes, "Type", old.e_type.to_string(), new.e_type.to_string());
+    push_change(&mut changes, "Machine", elf::get_machine_name(old.e_machine), elf::get_machine_name(new.e_machine));
+    push_change(&mut changes, "Version", format!("0x{:x}", old.e_version), format!("0x{:x}", new.e_version));
+    push_change(&mut changes, "Entry point address", format!("0x{:x}", old.e_entry), format!("0x{:x}", new.e_entry));
+    push_change(&mut changes, "Flags", format!("0x{:x}", old.e_flags), format!("0x{:x}", new.e_flags));
+    push_change(&mut changes, "Size of this header", old.e_ehsize.to_string(), new.e_ehsize.to_string());
+    push_change(&mut changes, "Number of program headers", old.phnum().to_string(), new.phnum().to_string());
+    push_change(&mut changes, "Number of section headers", old.shnum().to_string(), new.shnum().to_string());
+    changes
+}
+
+fn diff_sections(old: &ElfFile, new: &ElfFile) -> (Vec<SectionHeader>, Vec<SectionHeader>, Vec<SizeChange>) {
+    let old_sections = keyed(old.section_headers.iter().filter(|s| s.sh_type != elf::SHT_NULL), |s| s.name.clone());
+    let new_sections = keyed(new.section_headers.iter().filter(|s| s.sh_type != elf::SHT_NULL), |s| s.name.clone());
+
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut resized = Vec::new();
+    for (key, shdr) in new_sections.iter() {
+        if !old_sections.contains_key(key) {
+            added.push((*shdr).clone());
+        }
+    }
+    for (key, shdr) in old_sections.iter() {
+        match new_sections.get(key) {
+            None => removed.push((*shdr).clone()),
+            Some(new_shdr) if new_shdr.sh_size != shdr.sh_size => resized.push(SizeChange {
+                name: key.0.clone(),
+                old_size: shdr.sh_size,
+                new_size: new_shdr.sh_size,
+            }),
+            Some(_) => {}
+        }
+    }
+    (added, removed, resized)
+}
+
+/// Segments have no name, so they are paired by type and flags in header order.
+fn diff_segments(old: &ElfFile, new: &ElfFile) -> (Vec<ProgramHeader>, Vec<ProgramHeader>, Vec<(ProgramHeader, ProgramHeader)>) {
+    let old_segments = keyed(old.program_headers.iter(), |p| format!("{:x}/{:x}", p.p_type, p.p_flags));
+    let new_segments = keyed(new.program_headers.iter(), |p| format!("{:x}/{:x}", p.p_type, p.p_flags));
+
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut resized = Vec::new();
+    for (key, phdr) in new_segments.iter() {
+        if !old_segments.contains_key(key) {
+            added.push((*phdr).clone());
+        }
+    }
+    for (key, phdr) in old_segments.iter() {
+        match new_segments.get(key) {
+            None => removed.push((*phdr).clone()),
+            Some(new_phdr) if new_phdr.p_filesz != phdr.p_filesz || new_phdr.p_memsz != phdr.p_memsz => {
+                resized.push(((*phdr).clone(), (*new_phdr).clone()));
+            }
+            Some(_) => {}
+        }
+    }
+    (added, removed, resized)
+}
+
+fn is_comparable_symbol(sym: &Symbol) -> bool {
+    !sym.name.is_empty() && sym.st_type() != elf::STT_SECTION && sym.st_type() != elf::STT_FILE
+}
+
+fn diff_symbols(old: &ElfFile, new: &ElfFile) -> (Vec<Symbol>, Vec<Symbol>, Vec<SymbolChange>, Vec<SymbolChange>) {
+    // Compare like with like: fall back to .dynsym on both sides if either is stripped.
+    let (old_syms, new_syms) = if old.symbols.is_empty() || new.symbols.is_empty() {
+        (&old.dynamic_symbols, &new.dynamic_symbols)
+    } else {
+        (&old.symbols, &new.symbols)
+    };
+    let old_syms = keyed(old_syms.iter().filter(|s| is_comparable_symbol(s)), |s| s.name.clone());
+    let new_syms = keyed(new_syms.iter().filter(|s| is_comparable_symbol(s)), |s| s.name.clone());
+
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut resized = Vec::new();
+    let mut changed = Vec::new();
+    for (key, sym) in new_syms.iter() {
+        if !old_syms.contains_key(key) {
+            added.push((*sym).clone());
+        }
+    }
+    for (key, sym) in old_syms.iter() {
+        let new_sym = match new_syms.get(key) {
+            Some(new_sym) => new_sym,
+            None => {
+                removed.push((*sym).clone());
+                continue;
+            }
+        };
+        let change = SymbolChange{name: key.0.clone(), old: (*sym).clone(), new: (*new_sym).clone()};
+        if sym.st_info != new_sym.st_info || sym.visibility() != new_sym.visibility()
+            || sym.is_undefined() != new_sym.is_undefined() {
+            changed.push(change);
+        } else if sym.st_size != new_sym.st_size {
+            resized.push(change);
+        }
+    }
+    (added, removed, resized, changed)
+}
+
+/// Keys items by name plus an occurrence counter so duplicated names
+/// (e.g. static functions in different units) are paired in order.
+fn keyed<'a, T, I, F>(items: I, key: F) -> BTreeMap<(String, usize), &'a T>
+where
+    I: Iterator<Item = &'a T>,
+    F: Fn(&T) -> String,
+{
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    let mut map = BTreeMap::new();
+    for item in items {
+        let name = key(item);
+        let count = seen.entry(name.clone()).or_insert(0);
+        map.insert((name, *count), item);
+        *count += 1;
+    }
+    map
+}
+
+fn format_delta(delta: i64) -> String {
+    if delta >= 0 { format!("+{}", delta) } else { format!("{}", delta) }
+}
+
+fn symbol_summary(sym: &Symbol) -> String {
+    format!("{} {} size {}", elf::get_symbol_bind_name(sym.bind()), elf::get_symbol_type_name(sym.st_type()), sym.st_size)
+}
+
+pub fn show_diff(old_path: &str, new_path: &str, d: &ElfDiff) {
+    println!("--- {}", old_path);
+    println!("+++ {}", new_path);
+    if d.is_empty() {
+        println!("No structural differences.");
+        return;
+    }
+
+    let (old_size, new_size) = d.file_size;
+    println!("File size: {} -> {} ({})", old_size, new_size, format_delta(new_size as i64 - old_size as i64));
+
+    if !d.header.is_empty() {
+        println!();
+        println!("ELF Header:");
+        for c in d.header.iter() {
+            println!("  {:36}{} -> {}", c.field, c.old, c.new);
+        }
+    }
+
+    if !d.sections_added.is_empty() || !d.sections_removed.is_empty() || !d.sections_resized.is_empty() {
+        println!();
+        println!("Sections:");
+        for s in d.sections_added.iter() {
+            println!("  + {:24} {:16} size {}", s.name, elf::get_section_type_name(s.sh_type), s.sh_size);
+        }
+        for s in d.sections_removed.iter() {
+            println!("  - {:24} {:16} size {}", s.name, elf::get_section_type_name(s.sh_type), s.sh_size);
+        }
+        for c in d.sections_resized.iter() {
+            println!("  ~ {:24} size {} -> {} ({})", c.name, c.old_size, c.new_size, format_delta(c.delta()));
+        }
+    }
+
+    if !d.segments_added.is_empty() || !d.segments_removed.is_empty() || !d.segments_resized.is_empty() {
+        println!();
+        println!("Segments:");
+        for p in d.segments_added.iter() {
+            println!("  + {:14} {} vaddr 0x{:x} filesz {} memsz {}", elf::get_segment_type_name(p.p_type),
+                elf::get_segment_flags_name(p.p_flags), p.p_vaddr, p.p_filesz, p.p_memsz);
+        }
+        for p in d.segments_removed.iter() {
+            println!("  - {:14} {} vaddr 0x{:x} filesz {} memsz {}", elf::get_segment_type_name(p.p_type),
+                elf::get_segment_flags_name(p.p_flags), p.p_vaddr, p.p_filesz, p.p_memsz);
+        }
+        for (o, n) in d.segments_resized.iter() {
+            println!("  ~ {:14} {} filesz {} -> {} ({}) memsz {} -> {} ({})", elf::get_segment_type_name(o.p_type),
+                elf::get_segment_flags_name(o.p_flags),
+                o.p_filesz, n.p_filesz, format_delta(n.p_filesz as i64 - o.p_filesz as i64),
+                o.p_memsz, n.p_memsz, format_delta(n.p_memsz as i64 - o.p_memsz as i64));
+        }
+    }
+
+    if !d.symbols_added.is_empty() || !d.symbols_removed.is_empty()
+        || !d.symbols_resized.is_empty() || !d.symbols_changed.is_empty() {
+        println!();
+        println!("Symbols:");
+        for s in d.symbols_added.iter() {
+            println!("  + {} ({})", s.name, symbol_summary(s));
+        }
+        for s in d.symbols_removed.iter() {
+            println!("  - {} ({})", s.name, symbol_summary(s));
+        }
+        for c in d.symbols_resized.iter() {
+            println!("  ~ {} size {} -> {} ({})", c.name, c.old.st_size, c.new.st_size,
+                format_delta(c.new.st_size as i64 - c.old.st_size as i64));
+        }
+        for c in d.symbols_changed.iter() {
+            println!("  ! {} ({}) -> ({})", c.name, symbol_summary(&c.old), symbol_summary(&c.new));
+        }
+    }
+
+    if !d.needed_added.is_empty() || !d.needed_removed.is_empty() || !d.dynamic_changes.is_empty() {
+        println!();
+        println!("Dynamic dependencies:");
+        for n in d.needed_added.iter() {
+            println!("  + NEEDED {}", n);
+        }
+        for n in d.needed_removed.iter() {
+            println!("  - NEEDED {}", n);
+        }
+        for c in d.dynamic_changes.iter() {
+            println!("  ~ {} \"{}\" -> \"{}\"", c.field, c.old, c.new);
+        }
+    }
+}
@@ -0,0 +1,566 @@
+use std::collections::HashSet;
+
+use crate::elf;
+use crate::elf::ElfFile;
+use crate::discover;
+use crate::plt;
+
+pub mod aarch64;
+pub mod arm;
+pub mod riscv;
+pub mod rx;
+pub mod thumb;
+pub mod x86;
+
+/// Longest instruction encoding of any supported machine.
+pub const MAX_INSN_BYTES: usize = 15;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Syntax {
+    Intel,
+    Att,
+}
+
+/// How control leaves an instruction.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Flow {
+    Next,           // falls through to the next instruction
+    Jump(u64),      // unconditional direct jump
+    Branch(u64),    // conditional direct jump, may fall through
+    Call(u64),      // direct call
+    IndirectJump,
+    IndirectCall,
+    Return,
+    Stop,           // hlt, ud2 and other instructions that never fall through
+}
+
+impl Flow {
+    pub fn target(&self) -> Option<u64> {
+        match *self {
+            Flow::Jump(t) | Flow::Branch(t) | Flow::Call(t) => Some(t),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Instruction {
+    pub addr:       u64,
+    pub len:        usize,
+    pub mnemonic:   String,
+    pub operands:   String,
+    pub flow:       Flow,
+    pub mem_ref:    Option<u64>,    // absolute address of a PC-relative or absolute memory operand
+}
+
+impl Instruction {
+    pub fn text(&self) -> String {
+        if self.operands.is_empty() {
+            self.mnemonic.clone()
+        } else {
+            format!("{:6} {}", self.mnemonic, self.operands)
+        }
+    }
+}
+
+/// Code or data, as marked by ARM and AArch64 mapping symbols ($a/$x, $t and $d).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Mapping {
+    Arm,    // A32 or A64 code
+    Thumb,  // T32 code
+    Data,   // literal pool or other inline data
+}
+
+/// Decodes instructions for the machine of an ELF file.
+#[derive(Clone, Copy)]
+pub struct Decoder {
+    pub machine:    u16,
+    pub is_64:      bool,
+    pub syntax:     Syntax,
+    pub thumb:      bool,   // ARM: decode T32 rather than A32
+    pub big_endian: bool,   // ARM: instructions are stored big-endian (BE-32)
+    it_state:       u8,     // ARM: ITSTATE of the enclosing IT block, 0 outside one
+    hi_part:        riscv::HiPart,
+}
+
+impl Decoder {
+    pub fn for_elf(elf: &ElfFile, syntax: Syntax) -> Option<Decoder> {
+        let mut decoder = Decoder{machine: elf.e_machine, is_64: false, syntax, thumb: false, big_endian: false, it_state: 0,
+                                   hi_part: None};
+        match elf.e_machine {
+            elf::EM_386 => {}
+            elf::EM_X86_64 | elf::EM_AARCH64 => decoder.is_64 = true,
+            elf::EM_RISCV => decoder.is_64 = elf.is_64(),
+            elf::EM_RX => {}
+            elf::EM_ARM => {
+                // BE-8 images keep little-endian instructions even when the data is big-endian.
+                decoder.big_endian = !elf.is_little_endian() && elf.e_flags & elf::EF_ARM_BE8 == 0;
+                decoder.thumb = elf.e_entry & 1 != 0;
+            }
+            _ => return None,
+        }
+        Some(decoder)
+    }
+
+    /// Switches between A32 and T32 decoding at a mapping symbol.
+    pub fn set_mapping(&mut self, mapping: Mapping) {
+        let thumb = match mapping {
+            Mapping::Arm => false,
+            Mapping::Thumb => true,
+            Mapping::Data => return,
+        };
+        if thumb != self.thumb {
+            self.thumb = thumb;
+            self.it_state = 0;
+        }
+    }
+
+    /// Decodes one instruction at the start of `bytes`, located at `addr`. Thumb IT blocks
+    /// make the decoding of an instruction depend on the ones before it.
+    pub fn decode(&mut self, bytes: &[u8], addr: u64) -> Option<Instruction> {
+        match self.machine {
+            elf::EM_386 | elf::EM_X86_64 => x86::decode(bytes, addr, self.is_64, self.syntax),
+            elf::EM_AARCH64 => aarch64::decode(bytes, addr),
+            elf::EM_ARM if self.thumb => thumb::decode(bytes, addr, self.big_endian, &mut self.it_state),
+            elf::EM_ARM => arm::decode(bytes, addr, self.big_endian),
+            elf::EM_RISCV => riscv::decode(bytes, addr, self.is_64, &mut self.hi_part),
+            elf::EM_RX => rx::decode(bytes, addr),
+            _ => None,
+        }
+    }
+
+    /// Number of bytes skipped over undecodable bytes at the start of `bytes`: the minimum
+    /// instruction alignment, or the full length of a 32-bit T32 encoding.
+    pub fn skip_len(&self, bytes: &[u8]) -> usize {
+        match self.machine {
+            elf::EM_AARCH64 => 4,
+            elf::EM_ARM if self.thumb => thumb::length(bytes, self.big_endian),
+            elf::EM_ARM => 4,
+            elf::EM_RISCV => riscv::length(bytes),
+            _ => 1,
+        }
+    }
+
+    fn is_arm_family(&self) -> bool {
+        self.machine == elf::EM_ARM || self.machine == elf::EM_AARCH64
+    }
+
+    fn read_u16(&self, bytes: &[u8]) -> u16 {
+        if self.big_endian {
+            u16::from_be_bytes([bytes[0], bytes[1]])
+        } else {
+            u16::from_le_bytes([bytes[0], bytes[1]])
+        }
+    }
+
+    fn read_u32(&self, bytes: &[u8]) -> u32 {
+        if self.big_endian {
+            u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        } else {
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        }
+    }
+
+    /// The "bytes" column of a listing line. ARM and RISC-V tools print instruction words (or
+    /// Thumb and compressed halfwords) rather than individual bytes.
+    fn encoding(&self, bytes: &[u8]) -> String {
+        if !self.is_arm_family() && self.machine != elf::EM_RISCV {
+            return format_bytes(&bytes[..bytes.len().min(7)]);
+        }
+        match bytes.len() {
+            4 if self.thumb => format!("{:04x} {:04x}", self.read_u16(bytes), self.read_u16(&bytes[2..])),
+            4 => format!("{:08x}", self.read_u32(bytes)),
+            2 => format!("{:04x}", self.read_u16(bytes)),
+            _ => format_bytes(bytes),
+        }
+    }
+
+    /// Formats the address and encoding columns of a listing line, followed by `text`.
+    fn line(&self, addr: u64, encoding: &str, text: &str) -> String {
+        if self.is_arm_family() {
+            let width = if self.thumb { 9 } else { 8 };
+            format!("{:8x}:\t{:width$} \t{}", addr, encoding, text, width = width)
+        } else if self.machine == elf::EM_RISCV {
+            let width = if encoding.len() > 4 { 18 } else { 20 };
+            format!("{:8x}:\t{:width$}\t{}", addr, encoding, text, width = width)
+        } else {
+            format!("{:8x}:\t{:21}\t{}", addr, encoding, text)
+        }
+    }
+
+    /// Formats decoded text; only x86 listings pad the mnemonic, the others separate it from
+    /// its operands with a tab.
+    pub fn text(&self, insn: &Instruction) -> String {
+        let x86 = self.machine == elf::EM_386 || self.machine == elf::EM_X86_64;
+        if !x86 && !insn.operands.is_empty() {
+            format!("{}\t{}", insn.mnemonic, insn.operands)
+        } else {
+            insn.text()
+        }
+    }
+}
+
+#[derive(Clone)]
+struct SymbolEntry {
+    addr:   u64,
+    size:   u64,
+    name:   String,
+    shndx:  Option<usize>,  // section of an object file symbol; linked files share one address space
+}
+
+#[derive(Clone, Copy)]
+struct MappingEntry {
+    shndx:  usize,
+    addr:   u64,
+    kind:   Mapping,
+}
+
+/// Maps addresses back to "symbol+offset" names.
+pub struct Symbolizer {
+    syms:       Vec<SymbolEntry>,
+    mappings:   Vec<MappingEntry>,  // sorted by section, then address
+}
+
+/// Returns the mapping a symbol name stands for: "$a", "$t", "$x" or "$d", optionally
+/// followed by ".suffix".
+fn mapping_kind(name: &str) -> Option<Mapping> {
+    let kind = match name.split('.').next()? {
+        "$a" | "$x" => Mapping::Arm,
+        "$t" => Mapping::Thumb,
+        "$d" => Mapping::Data,
+        _ => return None,
+    };
+    Some(kind)
+}
+
+impl Symbolizer {
+    pub fn new(elf: &ElfFile) -> Symbolizer {
+        let arm = elf.e_machine == elf::EM_ARM;
+        let mut syms = Vec::new();
+        let mut mappings = Vec::new();
+        for sym in elf.symbols.iter().chain(elf.dynamic_symbols.iter()) {
+            let st_type = sym.st_type();
+            if sym.name.is_empty() || sym.section_index().is_none()
+                || st_type == elf::STT_SECTION || st_type == elf::STT_FILE || st_type == elf::STT_TLS {
+                continue;
+            }
+            if let (Some(kind), Some(shndx)) = (mapping_kind(&sym.name), sym.section_index()) {
+                if elf.e_machine == elf::EM_ARM || elf.e_machine == elf::EM_AARCH64 {
+                    mappings.push(MappingEntry{shndx, addr: sym.st_value, kind});
+                    continue;
+                }
+            }
+            // Object file symbols are offsets in their section. Sections all start at 0 there,
+            // so only code symbols are kept: data at the same offsets would name code.
+            let mut addr = sym.st_value;
+            let mut shndx = None;
+            if elf.e_type == elf::ET_REL {
+                match sym.section_index().and_then(|i| elf.section_headers.get(i).map(|s| (i, s))) {
+                    Some((i, shdr)) if shdr.is_exec() => {
+                        addr = shdr.sh_addr.wrapping_add(sym.st_value);
+                        shndx = Some(i);
+                    }
+                    _ => continue,
+                }
+            }
+            // ARM marks Thumb functions by setting bit 0 of their address.
+            if arm && sym.is_function() {
+                addr &= !1;
+            }
+            syms.push(SymbolEntry{addr, size: sym.st_size, name: strip_version(&sym.name), shndx});
+        }
+        // Name PLT stubs and the GOT slots they jump through after the imported symbol.
+        if elf.e_type != elf::ET_REL {
+            for stub in plt::stubs(elf) {
+                syms.push(SymbolEntry{addr: stub.addr, size: stub.size, name: format!("{}@plt", stub.name), shndx: None});
+            }
+            for (slot, name) in plt::got_slots(elf) {
+                syms.push(SymbolEntry{addr: slot, size: if elf.is_64() { 8 } else { 4 }, name: format!("{}@got", name),
+                                       shndx: None});
+            }
+        }
+        // Stripped binaries get sub_XXXX names for the function starts that can be recovered.
+        if discover::is_stripped(elf) {
+            let known: HashSet<u64> = syms.iter().map(|s: &SymbolEntry| s.addr).collect();
+            for (addr, name) in discover::functions(elf).into_iter().filter(|(a, _)| !known.contains(a)) {
+                syms.push(SymbolEntry{addr, size: 0, name, shndx: None});
+            }
+        }
+        // Without mapping symbols, fall back to the instruction set of each ARM function.
+        if arm && mappings.is_empty() {
+            for sym in elf.best_symbols().iter().filter(|s| s.is_function()) {
+                let shndx = match sym.section_index() {
+                    Some(shndx) => shndx,
+                    None => continue,
+                };
+                let kind = if sym.st_value & 1 != 0 { Mapping::Thumb } else { Mapping::Arm };
+                mappings.push(MappingEntry{shndx, addr: sym.st_value & !1, kind});
+            }
+        }
+        mappings.sort_by_key(|m| (m.shndx, m.addr));
+        mappings.dedup_by_key(|m| (m.shndx, m.addr));
+        let mut s = Symbolizer{syms, mappings};
+        s.sort();
+        s
+    }
+
+    fn sort(&mut self) {
+        // Prefer sized, global-looking names when several symbols share an address.
+        self.syms.sort_by(|a, b| a.addr.cmp(&b.addr).then(b.size.cmp(&a.size)).then(a.name.cmp(&b.name)));
+        self.syms.dedup_by(|a, b| a.addr == b.addr && a.name == b.name);
+    }
+
+    /// The symbols that apply inside section `shndx`: in object files, those defined in it.
+    pub fn for_section(&self, shndx: usize) -> Symbolizer {
+        let syms = self.syms.iter().filter(|s| s.shndx.is_none_or(|i| i == shndx)).cloned().collect();
+        Symbolizer{syms, mappings: self.mappings.clone()}
+    }
+
+    /// Returns the name of a symbol starting exactly at `addr`.
+    pub fn symbol_at(&self, addr: u64) -> Option<&str> {
+        let i = self.syms.partition_point(|s| s.addr < addr);
+        self.syms.get(i).filter(|s| s.addr == addr).map(|s| s.name.as_str())
+    }
+
+    pub fn addr_of(&self, name: &str) -> Option<u64> {
+        self.syms.iter().find(|s| s.name == name).map(|s| s.addr)
+    }
+
+    /// Returns the symbol containing `addr`, or the closest one before it.
+    pub fn lookup(&self, addr: u64) -> Option<(&str, u64)> {
+        let i = self.syms.partition_point(|s| s.addr <= addr);
+        if i == 0 {
+            return None;
+        }
+        let candidates = &self.syms[..i];
+        let nearest = &candidates[i - 1];
+        for s in candidates.iter().rev().take_while(|s| s.addr.saturating_add(s.size) > addr || s.addr == nearest.addr) {
+            if s.addr.saturating_add(s.size) > addr {
+                return Some((&s.name, addr - s.addr));
+            }
+        }
+        Some((&nearest.name, addr - nearest.addr))
+    }
+
+    /// Formats `addr` like objdump does: "<name+0x10>".
+    pub fn format(&self, addr: u64) -> String {
+        match self.lookup(addr) {
+            Some((name, 0)) => format!("<{}>", name),
+            Some((name, off)) => format!("<{}+0x{:x}>", name, off),
+            None => String::new(),
+        }
+    }
+
+    /// Returns the section and [start, end) of symbol `name`. Unsized symbols, such as
+    /// recovered function starts and assembler labels, run up to the next symbol in their
+    /// section or the section end, as objdump lists them.
+    pub fn extent(&self, elf: &ElfFile, name: &str) -> Option<(usize, u64, u64)> {
+        let sym = self.syms.iter().find(|s| s.name == name)?;
+        let shndx = sym.shndx.or_else(|| section_index(elf, sym.addr))?;
+        let shdr = elf.section_headers.get(shndx)?;
+        let section_end = shdr.sh_addr.saturating_add(shdr.sh_size);
+        if sym.size > 0 {
+            return Some((shndx, sym.addr, sym.addr.saturating_add(sym.size).min(section_end)));
+        }
+        let i = self.syms.partition_point(|s| s.addr <= sym.addr);
+        let next = self.syms[i..].iter().find(|s| s.shndx.is_none_or(|j| j == shndx)).map(|s| s.addr);
+        Some((shndx, sym.addr, next.map_or(section_end, |a| a.min(section_end))))
+    }
+
+    /// Returns the mapping in effect at `addr` within section `shndx`.
+    pub fn mapping_at(&self, shndx: usize, addr: u64) -> Option<Mapping> {
+        let i = self.mappings.partition_point(|m| (m.shndx, m.addr) <= (shndx, addr));
+        self.mappings[..i].last().filter(|m| m.shndx == shndx).map(|m| m.kind)
+    }
+
+    /// Returns the address of the first mapping symbol after `addr` within section `shndx`.
+    fn next_mapping(&self, shndx: usize, addr: u64) -> Option<u64> {
+        let i = self.mappings.partition_point(|m| (m.shndx, m.addr) <= (shndx, addr));
+        self.mappings.get(i).filter(|m| m.shndx == shndx).map(|m| m.addr)
+    }
+
+    /// Returns the next symbol start after `addr`.
+    fn next_symbol(&self, addr: u64) -> Option<u64> {
+        let i = self.syms.partition_point(|s| s.addr <= addr);
+        self.syms.get(i).map(|s| s.addr)
+    }
+}
+
+/// Returns the index of the section containing `addr`, for mapping symbol lookups.
+pub fn section_index(elf: &ElfFile, addr: u64) -> Option<usize> {
+    elf.section_headers.iter().position(|s| s.contains_vaddr(addr))
+}
+
+fn strip_version(name: &str) -> String {
+    match name.find('@') {
+        Some(i) => name[..i].to_string(),
+        None => name.to_string(),
+    }
+}
+
+fn format_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<String>>().join(" ")
+}
+
+/// Formats one decoded instruction as a listing line, with symbolic targets.
+pub fn format_line(decoder: &Decoder, insn: &Instruction, bytes: &[u8], symbols: &Symbolizer) -> String {
+    let mut line = decoder.line(insn.addr, &decoder.encoding(&bytes[..insn.len]), &decoder.text(insn));
+    if let (elf::EM_RISCV, Some(addr)) = (decoder.machine, insn.mem_ref) {
+        // Addresses completed from a lui/auipc pair, including jalr call targets.
+        line.push_str(&format!(" # {:x} {}", addr, symbols.format(addr)));
+        return line.trim_end().to_string();
+    }
+    if let Some(target) = insn.flow.target() {
+        let name = symbols.format(target);
+        if !name.is_empty() {
+            line.push(' ');
+            line.push_str(&name);
+        }
+    } else if let Some(addr) = insn.mem_ref {
+        match decoder.machine {
+            // A64 literal and ADR operands are already the target address.
+            elf::EM_AARCH64 => {
+                let name = symbols.format(addr);
+                if !name.is_empty() {
+                    line.push(' ');
+                    line.push_str(&name);
+                }
+            }
+            elf::EM_ARM => {
+                let name = symbols.format(addr);
+                if name.is_empty() {
+                    line.push_str(&format!("\t@ ({:x})", addr));
+                } else {
+                    line.push_str(&format!("\t@ ({:x} {})", addr, name));
+                }
+            }
+            _ => line.push_str(&format!("        # {:x} {}", addr, symbols.format(addr))),
+        }
+    }
+    if decoder.is_arm_family() || decoder.machine == elf::EM_RISCV {
+        return line;
+    }
+    // objdump continues long encodings on the following line.
+    let mut rest = &bytes[insn.len.min(7)..insn.len];
+    let mut addr = insn.addr + 7;
+    while !rest.is_empty() {
+        let n = rest.len().min(7);
+        line.push_str(&format!("\n{:8x}:\t{}", addr, format_bytes(&rest[..n])));
+        rest = &rest[n..];
+        addr += n as u64;
+    }
+    line
+}
+
+/// Prints an objdump -d style listing of every executable section.
+pub fn show_disassembly(elf: &ElfFile, decoder: &Decoder, symbols: &Symbolizer, only: Option<&str>) {
+    for (shndx, shdr) in elf.section_headers.iter().enumerate() {
+        if !shdr.is_exec() || shdr.sh_type == elf::SHT_NOBITS {
+            continue;
+        }
+        if let Some(name) = only {
+            if shdr.name != name {
+                continue;
+            }
+        }
+        println!();
+        println!("Disassembly of section {}:", shdr.name);
+        show_range(elf.section_data(shdr), shdr.sh_addr, shndx, decoder, &symbols.for_section(shndx));
+    }
+}
+
+/// Lists `data` located at `start`, printing a label wherever a symbol begins. On ARM,
+/// the mapping symbols of section `shndx` select A32, T32 or data.
+fn show_range(data: &[u8], start: u64, shndx: usize, decoder: &Decoder, symbols: &Symbolizer) {
+    let mut decoder = *decoder;
+    let mut mapping = None;
+    let mut pos = 0;
+    while pos < data.len() {
+        let addr = start + pos as u64;
+        if let Some(name) = symbols.symbol_at(addr) {
+            println!();
+            println!("{:016x} <{}>:", addr, name);
+        }
+        let current = symbols.mapping_at(shndx, addr);
+        if current != mapping {
+            if let Some(m) = current {
+                decoder.set_mapping(m);
+            }
+            mapping = current;
+        }
+        if mapping == Some(Mapping::Data) {
+            // Data runs up to the next mapping symbol or label.
+            let end = [symbols.next_mapping(shndx, addr), symbols.next_symbol(addr)].iter()
+                .flatten().map(|&a| (a - start) as usize).min().unwrap_or(data.len()).min(data.len());
+            pos += show_data(&decoder, &data[pos..end.max(pos + 1)], addr);
+            continue;
+        }
+        match decoder.decode(&data[pos..], addr) {
+            Some(insn) => {
+                println!("{}", format_line(&decoder, &insn, &data[pos..], symbols));
+                pos += insn.len;
+            }
+            None => {
+                let n = decoder.skip_len(&data[pos..]).min(data.len() - pos);
+                println!("{}", decoder.line(addr, &decoder.encoding(&data[pos..pos + n]), "(bad)"));
+                pos += n;
+            }
+        }
+    }
+}
+
+/// Prints one .word/.short/.byte line of inline data and returns the bytes consumed.
+fn show_data(decoder: &Decoder, data: &[u8], addr: u64) -> usize {
+    let (directive, value, n) = if data.len() >= 4 && addr & 3 == 0 {
+        (".word", format!("{:08x}", decoder.read_u32(data)), 4)
+    } else if data.len() >= 2 && addr & 1 == 0 {
+        (".short", format!("{:04x}", decoder.read_u16(data)), 2)
+    } else {
+        (".byte", format!("{:02x}", data[0]), 1)
+    };
+    println!("{}\t0x{}", decoder.line(addr, &value, directive), value);
+    n
+}
+
+/// Disassembles a single function by symbol name. Returns false if the symbol is unknown.
+pub fn show_function(elf: &ElfFile, decoder: &Decoder, symbols: &Symbolizer, name: &str) -> bool {
+    let (shndx, start, end) = match symbols.extent(elf, name) {
+        Some(extent) => extent,
+        None => return false,
+    };
+    let shdr = &elf.section_headers[shndx];
+    match elf.section_data(shdr).get((start - shdr.sh_addr) as usize..(end - shdr.sh_addr) as usize) {
+        Some(data) => {
+            show_range(data, start, shndx, decoder, symbols);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testelf::Builder;
+
+    #[test]
+    fn stripped_main_has_an_extent() {
+        let code = [
+            0x48, 0x8d, 0x3d, 0x09, 0x00, 0x00, 0x00,   // 401000: lea rdi, [rip+0x9]  (main)
+            0xe8, 0x14, 0x00, 0x00, 0x00,               // 401007: call 401020
+            0xf4, 0x90, 0x90, 0x90,                     // 40100c: hlt; nop padding
+            0x31, 0xc0, 0xc3, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,
+            0xc3,                                       // 401020: ret
+        ];
+        let mut b = Builder::new(elf::EM_X86_64);
+        b.entry = 0x401000;
+        let text = b.section(".text", elf::SHT_PROGBITS, elf::SHF_ALLOC | elf::SHF_EXECINSTR, 0x401000, &code);
+        b.load(text, text, elf::PF_R | elf::PF_X);
+        let elf = ElfFile::parse(b.build()).unwrap();
+        let symbols = Symbolizer::new(&elf);
+        assert_eq!(symbols.extent(&elf, "main"), Some((text, 0x401010, 0x401020)));
+        assert_eq!(symbols.extent(&elf, "sub_401020"), Some((text, 0x401020, 0x401021)));
+        let decoder = Decoder::for_elf(&elf, Syntax::Intel).unwrap();
+        assert!(show_function(&elf, &decoder, &symbols, "main"));
+    }
+}
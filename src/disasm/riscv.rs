@@ -0,0 +1,830 @@
+use crate::disasm::{Flow, Instruction};
+
+const REGS: [&str; 32] = ["zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2",
+                          "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+                          "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7",
+                          "s8", "s9", "s10", "s11", "t3", "t4", "t5", "t6"];
+const FREGS: [&str; 32] = ["ft0", "ft1", "ft2", "ft3", "ft4", "ft5", "ft6", "ft7",
+                           "fs0", "fs1", "fa0", "fa1", "fa2", "fa3", "fa4", "fa5",
+                           "fa6", "fa7", "fs2", "fs3", "fs4", "fs5", "fs6", "fs7",
+                           "fs8", "fs9", "fs10", "fs11", "ft8", "ft9", "ft10", "ft11"];
+const ROUNDING: [&str; 8] = ["rne", "rtz", "rdn", "rup", "rmm", "", "", "dyn"];
+const FP_FORMATS: [&str; 4] = ["s", "d", "h", "q"];
+
+const CSRS: [(u32, &str); 54] = [
+    (0x001, "fflags"), (0x002, "frm"), (0x003, "fcsr"),
+    (0xc00, "cycle"), (0xc01, "time"), (0xc02, "instret"),
+    (0xc80, "cycleh"), (0xc81, "timeh"), (0xc82, "instreth"),
+    (0x100, "sstatus"), (0x104, "sie"), (0x105, "stvec"), (0x106, "scounteren"),
+    (0x140, "sscratch"), (0x141, "sepc"), (0x142, "scause"), (0x143, "stval"), (0x144, "sip"),
+    (0x180, "satp"),
+    (0x300, "mstatus"), (0x301, "misa"), (0x302, "medeleg"), (0x303, "mideleg"), (0x304, "mie"),
+    (0x305, "mtvec"), (0x306, "mcounteren"), (0x310, "mstatush"),
+    (0x340, "mscratch"), (0x341, "mepc"), (0x342, "mcause"), (0x343, "mtval"), (0x344, "mip"),
+    (0x3a0, "pmpcfg0"), (0x3a1, "pmpcfg1"), (0x3a2, "pmpcfg2"), (0x3a3, "pmpcfg3"),
+    (0x3b0, "pmpaddr0"), (0x3b1, "pmpaddr1"), (0x3b2, "pmpaddr2"), (0x3b3, "pmpaddr3"),
+    (0x7a0, "tselect"), (0x7a1, "tdata1"), (0x7a2, "tdata2"), (0x7a3, "tdata3"),
+    (0x7b0, "dcsr"), (0x7b1, "dpc"), (0x7b2, "dscratch0"), (0x7b3, "dscratch1"),
+    (0xb00, "mcycle"), (0xb02, "minstret"),
+    (0xf11, "mvendorid"), (0xf12, "marchid"), (0xf13, "mimpid"), (0xf14, "mhartid"),
+];
+
+/// Register and value loaded by the last lui/auipc, so that the addi, load, store or jalr
+/// completing the address can show it.
+pub type HiPart = Option<(u32, u64)>;
+
+struct Decoded {
+    mnemonic:   String,
+    operands:   Vec<String>,
+    flow:       Flow,
+    mem_ref:    Option<u64>,
+}
+
+fn ok(mnemonic: &str, operands: Vec<String>) -> Option<Decoded> {
+    Some(Decoded{mnemonic: mnemonic.to_string(), operands, flow: Flow::Next, mem_ref: None})
+}
+
+fn with_flow(mut d: Option<Decoded>, flow: Flow) -> Option<Decoded> {
+    if let Some(d) = d.as_mut() {
+        d.flow = flow;
+    }
+    d
+}
+
+fn with_ref(mut d: Option<Decoded>, addr: Option<u64>) -> Option<Decoded> {
+    if let Some(d) = d.as_mut() {
+        d.mem_ref = addr;
+    }
+    d
+}
+
+fn bits(w: u32, hi: u32, lo: u32) -> u32 {
+    (w >> lo) & ((1u32 << (hi - lo + 1)) - 1)
+}
+
+fn sext(v: u32, width: u32) -> i64 {
+    (((v as u64) << (64 - width)) as i64) >> (64 - width)
+}
+
+fn x(n: u32) -> String {
+    REGS[n as usize & 31].to_string()
+}
+
+fn f(n: u32) -> String {
+    FREGS[n as usize & 31].to_string()
+}
+
+fn csr_name(csr: u32) -> String {
+    if let Some(c) = CSRS.iter().find(|c| c.0 == csr) {
+        return c.1.to_string();
+    }
+    match csr {
+        0xc03..=0xc1f => format!("hpmcounter{}", csr - 0xc00),
+        0xc83..=0xc9f => format!("hpmcounter{}h", csr - 0xc80),
+        0xb03..=0xb1f => format!("mhpmcounter{}", csr - 0xb00),
+        0xb83..=0xb9f => format!("mhpmcounter{}h", csr - 0xb80),
+        0x323..=0x33f => format!("mhpmevent{}", csr - 0x320),
+        0x3a0..=0x3af => format!("pmpcfg{}", csr - 0x3a0),
+        0x3b0..=0x3ef => format!("pmpaddr{}", csr - 0x3b0),
+        _ => format!("0x{:x}", csr),
+    }
+}
+
+/// Length of the instruction starting at `bytes`: 2 for the C extension, 4 otherwise.
+pub fn length(bytes: &[u8]) -> usize {
+    match bytes.first() {
+        Some(b) if b & 3 == 3 => 4,
+        _ => 2,
+    }
+}
+
+pub fn decode(bytes: &[u8], addr: u64, is_64: bool, hi: &mut HiPart) -> Option<Instruction> {
+    let half = u16::from_le_bytes([*bytes.first()?, *bytes.get(1)?]) as u32;
+    let (w, len) = if half == 0 {
+        return Some(Instruction{addr, len: 2, mnemonic: "unimp".to_string(), operands: String::new(),
+                                flow: Flow::Stop, mem_ref: None});
+    } else if half & 3 != 3 {
+        (expand(half, is_64)?, 2)
+    } else {
+        if half & 0x1f == 0x1f {
+            return None;
+        }
+        (u32::from_le_bytes([half as u8, (half >> 8) as u8, *bytes.get(2)?, *bytes.get(3)?]), 4)
+    };
+    let d = decode32(w, addr, is_64, hi);
+    update_hi(w, addr, hi);
+    let d = d?;
+    let mask = if is_64 { u64::MAX } else { 0xffff_ffff };
+    let flow = match d.flow {
+        Flow::Jump(t) => Flow::Jump(t & mask),
+        Flow::Branch(t) => Flow::Branch(t & mask),
+        Flow::Call(t) => Flow::Call(t & mask),
+        other => other,
+    };
+    Some(Instruction{addr, len, mnemonic: d.mnemonic, operands: d.operands.join(","), flow,
+                     mem_ref: d.mem_ref.map(|a| a & mask)})
+}
+
+/// Tracks lui/auipc results; any other write to the register forgets them.
+fn update_hi(w: u32, addr: u64, hi: &mut HiPart) {
+    let rd = bits(w, 11, 7);
+    match w & 0x7f {
+        0x37 => *hi = Some((rd, sext(w & 0xffff_f000, 32) as u64)),
+        0x17 => *hi = Some((rd, addr.wrapping_add(sext(w & 0xffff_f000, 32) as u64))),
+        0x23 | 0x27 | 0x63 | 0x0f => {}
+        _ => {
+            if hi.map(|h| h.0) == Some(rd) {
+                *hi = None;
+            }
+        }
+    }
+}
+
+/// Address formed from a register completed by a lui/auipc and a 12-bit offset.
+fn resolve(hi: &HiPart, rs1: u32, imm: i64) -> Option<u64> {
+    match *hi {
+        Some((r, base)) if r == rs1 && r != 0 => Some(base.wrapping_add(imm as u64)),
+        _ => None,
+    }
+}
+
+fn i_type(rd: u32, rs1: u32, f3: u32, imm: i32, op: u32) -> u32 {
+    ((imm as u32 & 0xfff) << 20) | (rs1 << 15) | (f3 << 12) | (rd << 7) | op
+}
+
+fn s_type(rs1: u32, rs2: u32, f3: u32, imm: i32, op: u32) -> u32 {
+    let imm = imm as u32;
+    (bits(imm, 11, 5) << 25) | (rs2 << 20) | (rs1 << 15) | (f3 << 12) | (bits(imm, 4, 0) << 7) | op
+}
+
+fn r_type(f7: u32, rd: u32, rs1: u32, rs2: u32, f3: u32, op: u32) -> u32 {
+    (f7 << 25) | (rs2 << 20) | (rs1 << 15) | (f3 << 12) | (rd << 7) | op
+}
+
+fn b_type(rs1: u32, rs2: u32, f3: u32, imm: i32) -> u32 {
+    let imm = imm as u32;
+    (bits(imm, 12, 12) << 31) | (bits(imm, 10, 5) << 25) | (rs2 << 20) | (rs1 << 15) | (f3 << 12)
+        | (bits(imm, 4, 1) << 8) | (bits(imm, 11, 11) << 7) | 0x63
+}
+
+fn j_type(rd: u32, imm: i32) -> u32 {
+    let imm = imm as u32;
+    (bits(imm, 20, 20) << 31) | (bits(imm, 10, 1) << 21) | (bits(imm, 11, 11) << 20) | (bits(imm, 19, 12) << 12)
+        | (rd << 7) | 0x6f
+}
+
+/// Expands a compressed instruction into the 32-bit instruction it stands for.
+fn expand(h: u32, is_64: bool) -> Option<u32> {
+    let rd = bits(h, 11, 7);
+    let rs2 = bits(h, 6, 2);
+    let rd_ = bits(h, 4, 2) + 8;
+    let rs1_ = bits(h, 9, 7) + 8;
+    let ci_imm = sext((bits(h, 12, 12) << 5) | bits(h, 6, 2), 6) as i32;
+    // Offsets of the register-based loads and stores, for word and doubleword accesses.
+    let lw_off = ((bits(h, 5, 5) << 6) | (bits(h, 12, 10) << 3) | (bits(h, 6, 6) << 2)) as i32;
+    let ld_off = ((bits(h, 6, 5) << 6) | (bits(h, 12, 10) << 3)) as i32;
+    match (h & 3, bits(h, 15, 13)) {
+        (0, 0) => {
+            let imm = (bits(h, 10, 7) << 6) | (bits(h, 12, 11) << 4) | (bits(h, 5, 5) << 3) | (bits(h, 6, 6) << 2);
+            if imm == 0 {
+                return None;
+            }
+            Some(i_type(rd_, 2, 0, imm as i32, 0x13))
+        }
+        (0, 1) => Some(i_type(rd_, rs1_, 3, ld_off, 0x07)),
+        (0, 2) => Some(i_type(rd_, rs1_, 2, lw_off, 0x03)),
+        (0, 3) if is_64 => Some(i_type(rd_, rs1_, 3, ld_off, 0x03)),
+        (0, 3) => Some(i_type(rd_, rs1_, 2, lw_off, 0x07)),
+        (0, 5) => Some(s_type(rs1_, rd_, 3, ld_off, 0x27)),
+        (0, 6) => Some(s_type(rs1_, rd_, 2, lw_off, 0x23)),
+        (0, 7) if is_64 => Some(s_type(rs1_, rd_, 3, ld_off, 0x23)),
+        (0, 7) => Some(s_type(rs1_, rd_, 2, lw_off, 0x27)),
+        (1, 0) => Some(i_type(rd, rd, 0, ci_imm, 0x13)),
+        (1, 1) if is_64 => {
+            if rd == 0 {
+                return None;
+            }
+            Some(i_type(rd, rd, 0, ci_imm, 0x1b))
+        }
+        (1, 1) | (1, 5) => {
+            let imm = sext((bits(h, 12, 12) << 11) | (bits(h, 8, 8) << 10) | (bits(h, 10, 9) << 8) | (bits(h, 6, 6) << 7)
+                           | (bits(h, 7, 7) << 6) | (bits(h, 2, 2) << 5) | (bits(h, 11, 11) << 4) | (bits(h, 5, 3) << 1), 12);
+            Some(j_type(if bits(h, 15, 13) == 1 { 1 } else { 0 }, imm as i32))
+        }
+        (1, 2) => Some(i_type(rd, 0, 0, ci_imm, 0x13)),
+        (1, 3) => {
+            if rd == 2 {
+                let imm = sext((bits(h, 12, 12) << 9) | (bits(h, 4, 3) << 7) | (bits(h, 5, 5) << 6)
+                               | (bits(h, 2, 2) << 5) | (bits(h, 6, 6) << 4), 10) as i32;
+                if imm == 0 {
+                    return None;
+                }
+                return Some(i_type(2, 2, 0, imm, 0x13));
+            }
+            Some(((ci_imm as u32) << 12) | (rd << 7) | 0x37)
+        }
+        (1, 4) => {
+            let shamt = (bits(h, 12, 12) << 5) | bits(h, 6, 2);
+            match bits(h, 11, 10) {
+                0 => Some(i_type(rs1_, rs1_, 5, shamt as i32, 0x13)),
+                1 => Some(i_type(rs1_, rs1_, 5, (0x400 | shamt) as i32, 0x13)),
+                2 => Some(i_type(rs1_, rs1_, 7, ci_imm, 0x13)),
+                _ => {
+                    let rs2_ = bits(h, 4, 2) + 8;
+                    let (f7, f3, op) = match (bits(h, 12, 12), bits(h, 6, 5)) {
+                        (0, 0) => (0x20, 0, 0x33),
+                        (0, 1) => (0, 4, 0x33),
+                        (0, 2) => (0, 6, 0x33),
+                        (0, 3) => (0, 7, 0x33),
+                        (1, 0) if is_64 => (0x20, 0, 0x3b),
+                        (1, 1) if is_64 => (0, 0, 0x3b),
+                        _ => return None,
+                    };
+                    Some(r_type(f7, rs1_, rs1_, rs2_, f3, op))
+                }
+            }
+        }
+        (1, 6) | (1, 7) => {
+            let imm = sext((bits(h, 12, 12) << 8) | (bits(h, 6, 5) << 6) | (bits(h, 2, 2) << 5) | (bits(h, 11, 10) << 3)
+                           | (bits(h, 4, 3) << 1), 9);
+            Some(b_type(rs1_, 0, bits(h, 15, 13) & 1, imm as i32))
+        }
+        (2, 0) => {
+            let shamt = (bits(h, 12, 12) << 5) | bits(h, 6, 2);
+            Some(i_type(rd, rd, 1, shamt as i32, 0x13))
+        }
+        (2, 1) => Some(i_type(rd, 2, 3, ((bits(h, 4, 2) << 6) | (bits(h, 12, 12) << 5) | (bits(h, 6, 5) << 3)) as i32, 0x07)),
+        (2, 2) | (2, 3) => {
+            let lw = bits(h, 15, 13) == 2;
+            if rd == 0 && (lw || is_64) {
+                return None;
+            }
+            let ld = ((bits(h, 4, 2) << 6) | (bits(h, 12, 12) << 5) | (bits(h, 6, 5) << 3)) as i32;
+            let lw_sp = ((bits(h, 3, 2) << 6) | (bits(h, 12, 12) << 5) | (bits(h, 6, 4) << 2)) as i32;
+            match (lw, is_64) {
+                (true, _) => Some(i_type(rd, 2, 2, lw_sp, 0x03)),
+                (false, true) => Some(i_type(rd, 2, 3, ld, 0x03)),
+                (false, false) => Some(i_type(rd, 2, 2, lw_sp, 0x07)),
+            }
+        }
+        (2, 4) => match (bits(h, 12, 12), rd, rs2) {
+            (0, 0, 0) => None,
+            (0, _, 0) => Some(i_type(0, rd, 0, 0, 0x67)),
+            (0, _, _) => Some(i_type(rd, rs2, 0, 0, 0x13)),
+            (1, 0, 0) => Some(0x0010_0073),
+            (1, _, 0) => Some(i_type(1, rd, 0, 0, 0x67)),
+            _ => Some(r_type(0, rd, rd, rs2, 0, 0x33)),
+        },
+        (2, 5) => Some(s_type(2, rs2, 3, ((bits(h, 9, 7) << 6) | (bits(h, 12, 10) << 3)) as i32, 0x27)),
+        (2, 6) | (2, 7) => {
+            let sw = bits(h, 15, 13) == 6;
+            let sd = ((bits(h, 9, 7) << 6) | (bits(h, 12, 10) << 3)) as i32;
+            let sw_sp = ((bits(h, 8, 7) << 6) | (bits(h, 12, 9) << 2)) as i32;
+            match (sw, is_64) {
+                (true, _) => Some(s_type(2, rs2, 2, sw_sp, 0x23)),
+                (false, true) => Some(s_type(2, rs2, 3, sd, 0x23)),
+                (false, false) => Some(s_type(2, rs2, 2, sw_sp, 0x27)),
+            }
+        }
+        _ => None,
+    }
+}
+
+fn decode32(w: u32, addr: u64, is_64: bool, hi: &HiPart) -> Option<Decoded> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let rs2 = bits(w, 24, 20);
+    let f3 = bits(w, 14, 12);
+    let imm_i = sext(bits(w, 31, 20), 12);
+    let imm_s = sext((bits(w, 31, 25) << 5) | bits(w, 11, 7), 12);
+    let addr_mask = if is_64 { u64::MAX } else { 0xffff_ffff };
+    match w & 0x7f {
+        0x37 => ok("lui", vec![x(rd), format!("0x{:x}", bits(w, 31, 12))]),
+        0x17 => ok("auipc", vec![x(rd), format!("0x{:x}", bits(w, 31, 12))]),
+        0x6f => {
+            let imm = sext((bits(w, 31, 31) << 20) | (bits(w, 19, 12) << 12) | (bits(w, 20, 20) << 11)
+                           | (bits(w, 30, 21) << 1), 21);
+            let dest = addr.wrapping_add(imm as u64) & addr_mask;
+            match rd {
+                0 => with_flow(ok("j", vec![format!("{:x}", dest)]), Flow::Jump(dest)),
+                1 => with_flow(ok("jal", vec![format!("{:x}", dest)]), Flow::Call(dest)),
+                _ => with_flow(ok("jal", vec![x(rd), format!("{:x}", dest)]), Flow::Call(dest)),
+            }
+        }
+        0x67 if f3 == 0 => {
+            let dest = resolve(hi, rs1, imm_i);
+            let target = if imm_i == 0 { x(rs1) } else { format!("{}({})", imm_i, x(rs1)) };
+            match (rd, rs1, imm_i) {
+                (0, 1, 0) => with_flow(ok("ret", vec![]), Flow::Return),
+                (0, _, _) => with_ref(with_flow(ok("jr", vec![target]), dest.map_or(Flow::IndirectJump, Flow::Jump)), dest),
+                (1, _, _) => with_ref(with_flow(ok("jalr", vec![target]), dest.map_or(Flow::IndirectCall, Flow::Call)), dest),
+                _ => {
+                    let flow = dest.map_or(Flow::IndirectCall, Flow::Call);
+                    with_ref(with_flow(ok("jalr", vec![x(rd), format!("{}({})", imm_i, x(rs1))]), flow), dest)
+                }
+            }
+        }
+        0x63 => {
+            let imm = sext((bits(w, 31, 31) << 12) | (bits(w, 7, 7) << 11) | (bits(w, 30, 25) << 5) | (bits(w, 11, 8) << 1), 13);
+            let dest = addr.wrapping_add(imm as u64) & addr_mask;
+            let target = format!("{:x}", dest);
+            let (name, ops) = match (f3, rs1, rs2) {
+                (0, _, 0) => ("beqz", vec![x(rs1), target]),
+                (1, _, 0) => ("bnez", vec![x(rs1), target]),
+                (4, _, 0) => ("bltz", vec![x(rs1), target]),
+                (5, 0, _) => ("blez", vec![x(rs2), target]),
+                (5, _, 0) => ("bgez", vec![x(rs1), target]),
+                (4, 0, _) => ("bgtz", vec![x(rs2), target]),
+                (2 | 3, _, _) => return None,
+                _ => (["beq", "bne", "", "", "blt", "bge", "bltu", "bgeu"][f3 as usize], vec![x(rs1), x(rs2), target]),
+            };
+            with_flow(ok(name, ops), Flow::Branch(dest))
+        }
+        0x03 => {
+            let name = match f3 {
+                0 => "lb",
+                1 => "lh",
+                2 => "lw",
+                3 if is_64 => "ld",
+                4 => "lbu",
+                5 => "lhu",
+                6 if is_64 => "lwu",
+                _ => return None,
+            };
+            with_ref(ok(name, vec![x(rd), format!("{}({})", imm_i, x(rs1))]), resolve(hi, rs1, imm_i))
+        }
+        0x23 => {
+            let name = match f3 {
+                0 => "sb",
+                1 => "sh",
+                2 => "sw",
+                3 if is_64 => "sd",
+                _ => return None,
+            };
+            with_ref(ok(name, vec![x(rs2), format!("{}({})", imm_s, x(rs1))]), resolve(hi, rs1, imm_s))
+        }
+        0x07 | 0x27 => {
+            let name = match (w & 0x7f, f3) {
+                (0x07, 2) => "flw",
+                (0x07, 3) => "fld",
+                (0x27, 2) => "fsw",
+                (0x27, 3) => "fsd",
+                _ => return None,
+            };
+            let (reg, imm) = if w & 0x7f == 0x07 { (rd, imm_i) } else { (rs2, imm_s) };
+            with_ref(ok(name, vec![f(reg), format!("{}({})", imm, x(rs1))]), resolve(hi, rs1, imm))
+        }
+        0x13 => op_imm(w, is_64, hi),
+        0x1b if is_64 => op_imm32(w),
+        0x33 => op(w, is_64),
+        0x3b if is_64 => op32(w),
+        0x0f if rd == 0 && rs1 == 0 => match f3 {
+            0 => fence(w),
+            1 if imm_i == 0 => ok("fence.i", vec![]),
+            _ => None,
+        },
+        0x73 => system(w),
+        0x2f => atomic(w, is_64),
+        0x43 | 0x47 | 0x4b | 0x4f => {
+            let fmt = bits(w, 26, 25);
+            if fmt > 1 {
+                return None;
+            }
+            let name = ["fmadd", "fmsub", "fnmsub", "fnmadd"][bits(w, 3, 2) as usize];
+            let mut ops = vec![f(rd), f(rs1), f(rs2), f(bits(w, 31, 27))];
+            rounding(&mut ops, f3)?;
+            ok(&format!("{}.{}", name, FP_FORMATS[fmt as usize]), ops)
+        }
+        0x53 => op_fp(w, is_64),
+        _ => None,
+    }
+}
+
+/// Appends the rounding mode operand unless it is the dynamic one.
+fn rounding(ops: &mut Vec<String>, rm: u32) -> Option<()> {
+    match ROUNDING[rm as usize] {
+        "" => None,
+        "dyn" => Some(()),
+        name => {
+            ops.push(name.to_string());
+            Some(())
+        }
+    }
+}
+
+fn op_imm(w: u32, is_64: bool, hi: &HiPart) -> Option<Decoded> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let imm = sext(bits(w, 31, 20), 12);
+    let shamt = if is_64 { bits(w, 25, 20) } else { bits(w, 24, 20) };
+    let funct6 = bits(w, 31, 26);
+    if !is_64 && bits(w, 14, 12) & 3 == 1 && bits(w, 25, 25) != 0 {
+        return None;
+    }
+    let shift = |name: &str| ok(name, vec![x(rd), x(rs1), format!("0x{:x}", shamt)]);
+    match bits(w, 14, 12) {
+        0 => {
+            let d = match (rd, rs1, imm) {
+                (0, 0, 0) => ok("nop", vec![]),
+                (_, 0, _) => ok("li", vec![x(rd), imm.to_string()]),
+                (_, _, 0) => ok("mv", vec![x(rd), x(rs1)]),
+                _ => ok("addi", vec![x(rd), x(rs1), imm.to_string()]),
+            };
+            with_ref(d, resolve(hi, rs1, imm))
+        }
+        2 => ok("slti", vec![x(rd), x(rs1), imm.to_string()]),
+        3 if imm == 1 => ok("seqz", vec![x(rd), x(rs1)]),
+        3 => ok("sltiu", vec![x(rd), x(rs1), imm.to_string()]),
+        4 if imm == -1 => ok("not", vec![x(rd), x(rs1)]),
+        4 => ok("xori", vec![x(rd), x(rs1), imm.to_string()]),
+        6 => ok("ori", vec![x(rd), x(rs1), imm.to_string()]),
+        7 => ok("andi", vec![x(rd), x(rs1), imm.to_string()]),
+        1 => match (funct6, bits(w, 31, 20)) {
+            (0x00, _) => shift("slli"),
+            (0x18, 0x600) => ok("clz", vec![x(rd), x(rs1)]),
+            (0x18, 0x601) => ok("ctz", vec![x(rd), x(rs1)]),
+            (0x18, 0x602) => ok("cpop", vec![x(rd), x(rs1)]),
+            (0x18, 0x604) => ok("sext.b", vec![x(rd), x(rs1)]),
+            (0x18, 0x605) => ok("sext.h", vec![x(rd), x(rs1)]),
+            (0x0a, _) => shift("bseti"),
+            (0x12, _) => shift("bclri"),
+            (0x1a, _) => shift("binvi"),
+            _ => None,
+        },
+        _ => match (funct6, bits(w, 31, 20)) {
+            (0x00, _) => shift("srli"),
+            (0x10, _) => shift("srai"),
+            (0x0a, 0x287) => ok("orc.b", vec![x(rd), x(rs1)]),
+            (0x1a, 0x698) if !is_64 => ok("rev8", vec![x(rd), x(rs1)]),
+            (0x1a, 0x6b8) if is_64 => ok("rev8", vec![x(rd), x(rs1)]),
+            (0x18, _) => shift("rori"),
+            (0x12, _) => shift("bexti"),
+            _ => None,
+        },
+    }
+}
+
+fn op_imm32(w: u32) -> Option<Decoded> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let imm = sext(bits(w, 31, 20), 12);
+    let shamt = bits(w, 24, 20);
+    let shift = |name: &str| ok(name, vec![x(rd), x(rs1), format!("0x{:x}", shamt)]);
+    match (bits(w, 14, 12), bits(w, 31, 25)) {
+        (0, _) if imm == 0 => ok("sext.w", vec![x(rd), x(rs1)]),
+        (0, _) => ok("addiw", vec![x(rd), x(rs1), imm.to_string()]),
+        (1, 0x00) => shift("slliw"),
+        (1, 0x04 | 0x05) => ok("slli.uw", vec![x(rd), x(rs1), format!("0x{:x}", bits(w, 25, 20))]),
+        (1, 0x30) => match shamt {
+            0 => ok("clzw", vec![x(rd), x(rs1)]),
+            1 => ok("ctzw", vec![x(rd), x(rs1)]),
+            2 => ok("cpopw", vec![x(rd), x(rs1)]),
+            _ => None,
+        },
+        (5, 0x00) => shift("srliw"),
+        (5, 0x20) => shift("sraiw"),
+        (5, 0x30) => shift("roriw"),
+        _ => None,
+    }
+}
+
+fn op(w: u32, is_64: bool) -> Option<Decoded> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let rs2 = bits(w, 24, 20);
+    let name = match (bits(w, 31, 25), bits(w, 14, 12)) {
+        (0x00, 0) => "add",
+        (0x20, 0) if rs1 == 0 => return ok("neg", vec![x(rd), x(rs2)]),
+        (0x20, 0) => "sub",
+        (0x00, 1) => "sll",
+        (0x00, 2) if rs2 == 0 => return ok("sltz", vec![x(rd), x(rs1)]),
+        (0x00, 2) if rs1 == 0 => return ok("sgtz", vec![x(rd), x(rs2)]),
+        (0x00, 2) => "slt",
+        (0x00, 3) if rs1 == 0 => return ok("snez", vec![x(rd), x(rs2)]),
+        (0x00, 3) => "sltu",
+        (0x00, 4) => "xor",
+        (0x00, 5) => "srl",
+        (0x20, 5) => "sra",
+        (0x00, 6) => "or",
+        (0x00, 7) => "and",
+        (0x01, f3) => ["mul", "mulh", "mulhsu", "mulhu", "div", "divu", "rem", "remu"][f3 as usize],
+        (0x20, 4) => "xnor",
+        (0x20, 6) => "orn",
+        (0x20, 7) => "andn",
+        (0x05, 1) => "clmul",
+        (0x05, 2) => "clmulr",
+        (0x05, 3) => "clmulh",
+        (0x05, 4) => "min",
+        (0x05, 5) => "minu",
+        (0x05, 6) => "max",
+        (0x05, 7) => "maxu",
+        (0x10, 2) => "sh1add",
+        (0x10, 4) => "sh2add",
+        (0x10, 6) => "sh3add",
+        (0x30, 1) => "rol",
+        (0x30, 5) => "ror",
+        (0x24, 1) => "bclr",
+        (0x24, 5) => "bext",
+        (0x14, 1) => "bset",
+        (0x34, 1) => "binv",
+        (0x04, 4) if rs2 == 0 && !is_64 => return ok("zext.h", vec![x(rd), x(rs1)]),
+        _ => return None,
+    };
+    ok(name, vec![x(rd), x(rs1), x(rs2)])
+}
+
+fn op32(w: u32) -> Option<Decoded> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let rs2 = bits(w, 24, 20);
+    let name = match (bits(w, 31, 25), bits(w, 14, 12)) {
+        (0x00, 0) => "addw",
+        (0x20, 0) if rs1 == 0 => return ok("negw", vec![x(rd), x(rs2)]),
+        (0x20, 0) => "subw",
+        (0x00, 1) => "sllw",
+        (0x00, 5) => "srlw",
+        (0x20, 5) => "sraw",
+        (0x01, 0) => "mulw",
+        (0x01, 4) => "divw",
+        (0x01, 5) => "divuw",
+        (0x01, 6) => "remw",
+        (0x01, 7) => "remuw",
+        (0x04, 0) if rs2 == 0 => return ok("zext.w", vec![x(rd), x(rs1)]),
+        (0x04, 0) => "add.uw",
+        (0x04, 4) if rs2 == 0 => return ok("zext.h", vec![x(rd), x(rs1)]),
+        (0x10, 2) => "sh1add.uw",
+        (0x10, 4) => "sh2add.uw",
+        (0x10, 6) => "sh3add.uw",
+        (0x30, 1) => "rolw",
+        (0x30, 5) => "rorw",
+        _ => return None,
+    };
+    ok(name, vec![x(rd), x(rs1), x(rs2)])
+}
+
+fn fence(w: u32) -> Option<Decoded> {
+    let pred = bits(w, 27, 24);
+    let succ = bits(w, 23, 20);
+    let set = |v: u32| -> String { ["i", "o", "r", "w"].iter().enumerate().filter(|(i, _)| v >> (3 - i) & 1 != 0).map(|(_, c)| *c).collect() };
+    if bits(w, 31, 28) == 8 && pred == 3 && succ == 3 {
+        return ok("fence.tso", vec![]);
+    }
+    if bits(w, 31, 28) != 0 {
+        return None;
+    }
+    if pred == 15 && succ == 15 {
+        return ok("fence", vec![]);
+    }
+    if pred == 0 || succ == 0 {
+        return None;
+    }
+    ok("fence", vec![set(pred), set(succ)])
+}
+
+fn system(w: u32) -> Option<Decoded> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let csr = bits(w, 31, 20);
+    let f3 = bits(w, 14, 12);
+    if f3 == 0 {
+        if bits(w, 31, 25) == 0x09 && rd == 0 {
+            let mut ops = Vec::new();
+            if rs1 != 0 || bits(w, 24, 20) != 0 {
+                ops.push(x(rs1));
+            }
+            if bits(w, 24, 20) != 0 {
+                ops.push(x(bits(w, 24, 20)));
+            }
+            return ok("sfence.vma", ops);
+        }
+        if rd != 0 || rs1 != 0 {
+            return None;
+        }
+        return match csr {
+            0x000 => ok("ecall", vec![]),
+            0x001 => ok("ebreak", vec![]),
+            0x102 => with_flow(ok("sret", vec![]), Flow::Return),
+            0x302 => with_flow(ok("mret", vec![]), Flow::Return),
+            0x7b2 => with_flow(ok("dret", vec![]), Flow::Return),
+            0x105 => ok("wfi", vec![]),
+            _ => None,
+        };
+    }
+    if f3 == 4 {
+        return None;
+    }
+    if w == 0xc000_1073 {
+        return with_flow(ok("unimp", vec![]), Flow::Stop);
+    }
+    let name = csr_name(csr);
+    let imm = f3 >= 5;
+    let source = if imm { rs1.to_string() } else { x(rs1) };
+    let base = ["", "csrrw", "csrrs", "csrrc", "", "csrrwi", "csrrsi", "csrrci"][f3 as usize];
+    match (f3, rd, rs1) {
+        (2, _, 0) => match csr {
+            0xc00 | 0xc01 | 0xc02 | 0xc80 | 0xc81 | 0xc82 => ok(&format!("rd{}", name), vec![x(rd)]),
+            0x001 => ok("frflags", vec![x(rd)]),
+            0x002 => ok("frrm", vec![x(rd)]),
+            0x003 => ok("frcsr", vec![x(rd)]),
+            _ => ok("csrr", vec![x(rd), name]),
+        },
+        (1, _, _) if csr <= 3 => {
+            let short = ["", "fsflags", "fsrm", "fscsr"][csr as usize];
+            if rd == 0 { ok(short, vec![source]) } else { ok(short, vec![x(rd), source]) }
+        }
+        (_, 0, _) => ok(&format!("csr{}", &base[4..]), vec![name, source]),
+        _ => ok(base, vec![x(rd), name, source]),
+    }
+}
+
+fn atomic(w: u32, is_64: bool) -> Option<Decoded> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let rs2 = bits(w, 24, 20);
+    let size = match bits(w, 14, 12) {
+        2 => "w",
+        3 if is_64 => "d",
+        _ => return None,
+    };
+    let order = ["", ".rl", ".aq", ".aqrl"][bits(w, 26, 25) as usize];
+    let base = format!("({})", x(rs1));
+    let name = match bits(w, 31, 27) {
+        0x02 if rs2 == 0 => return ok(&format!("lr.{}{}", size, order), vec![x(rd), base]),
+        0x03 => "sc",
+        0x01 => "amoswap",
+        0x00 => "amoadd",
+        0x04 => "amoxor",
+        0x0c => "amoand",
+        0x08 => "amoor",
+        0x10 => "amomin",
+        0x14 => "amomax",
+        0x18 => "amominu",
+        0x1c => "amomaxu",
+        _ => return None,
+    };
+    ok(&format!("{}.{}{}", name, size, order), vec![x(rd), x(rs2), base])
+}
+
+fn op_fp(w: u32, is_64: bool) -> Option<Decoded> {
+    let rd = bits(w, 11, 7);
+    let rs1 = bits(w, 19, 15);
+    let rs2 = bits(w, 24, 20);
+    let rm = bits(w, 14, 12);
+    let fmt = bits(w, 26, 25);
+    if fmt > 1 {
+        return None;
+    }
+    let t = FP_FORMATS[fmt as usize];
+    let arith = |name: &str, ops: Vec<String>| {
+        let mut ops = ops;
+        rounding(&mut ops, rm)?;
+        ok(&format!("{}.{}", name, t), ops)
+    };
+    let ints = ["w", "wu", "l", "lu"];
+    match bits(w, 31, 27) {
+        0x00 => arith("fadd", vec![f(rd), f(rs1), f(rs2)]),
+        0x01 => arith("fsub", vec![f(rd), f(rs1), f(rs2)]),
+        0x02 => arith("fmul", vec![f(rd), f(rs1), f(rs2)]),
+        0x03 => arith("fdiv", vec![f(rd), f(rs1), f(rs2)]),
+        0x0b if rs2 == 0 => arith("fsqrt", vec![f(rd), f(rs1)]),
+        0x04 => {
+            let (name, alias) = match rm {
+                0 => ("fsgnj", "fmv"),
+                1 => ("fsgnjn", "fneg"),
+                2 => ("fsgnjx", "fabs"),
+                _ => return None,
+            };
+            if rs1 == rs2 {
+                return ok(&format!("{}.{}", alias, t), vec![f(rd), f(rs1)]);
+            }
+            ok(&format!("{}.{}", name, t), vec![f(rd), f(rs1), f(rs2)])
+        }
+        0x05 if rm < 2 => ok(&format!("{}.{}", ["fmin", "fmax"][rm as usize], t), vec![f(rd), f(rs1), f(rs2)]),
+        0x08 => match (fmt, rs2) {
+            (0, 1) => {
+                let mut ops = vec![f(rd), f(rs1)];
+                rounding(&mut ops, rm)?;
+                ok("fcvt.s.d", ops)
+            }
+            (1, 0) => ok("fcvt.d.s", vec![f(rd), f(rs1)]),
+            _ => None,
+        },
+        0x14 if rm < 3 => ok(&format!("{}.{}", ["fle", "flt", "feq"][rm as usize], t), vec![x(rd), f(rs1), f(rs2)]),
+        0x18 if rs2 < 4 && (is_64 || rs2 < 2) => {
+            let mut ops = vec![x(rd), f(rs1)];
+            rounding(&mut ops, rm)?;
+            ok(&format!("fcvt.{}.{}", ints[rs2 as usize], t), ops)
+        }
+        0x1a if rs2 < 4 && (is_64 || rs2 < 2) => {
+            let mut ops = vec![f(rd), x(rs1)];
+            // Widening int-to-double conversions are exact and take no rounding mode.
+            if !(fmt == 1 && rs2 < 2) {
+                rounding(&mut ops, rm)?;
+            } else if rm != 0 {
+                return None;
+            }
+            ok(&format!("fcvt.{}.{}", t, ints[rs2 as usize]), ops)
+        }
+        0x1c if rs2 == 0 && rm == 0 && (fmt == 0 || is_64) => ok(&format!("fmv.x.{}", if fmt == 0 { "w" } else { "d" }), vec![x(rd), f(rs1)]),
+        0x1c if rs2 == 0 && rm == 1 => ok(&format!("fclass.{}", t), vec![x(rd), f(rs1)]),
+        0x1e if rs2 == 0 && rm == 0 && (fmt == 0 || is_64) => ok(&format!("fmv.{}.x", if fmt == 0 { "w" } else { "d" }), vec![f(rd), x(rs1)]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decodes `cases` in sequence from address 0 and compares objdump's text; a 16-bit
+    /// value is a compressed instruction.
+    fn check(cases: &[(u32, &str)], is_64: bool) {
+        let mut hi = None;
+        let mut addr = 0;
+        for &(word, expected) in cases.iter() {
+            let len = if word & 3 == 3 { 4 } else { 2 };
+            let insn = decode(&word.to_le_bytes()[..len], addr, is_64, &mut hi).unwrap_or_else(|| panic!("{:08x} did not decode", word));
+            assert_eq!(insn.len, len, "{:08x}", word);
+            let text = if insn.operands.is_empty() { insn.mnemonic } else { format!("{} {}", insn.mnemonic, insn.operands) };
+            assert_eq!(text, expected, "{:08x}", word);
+            addr += len as u64;
+        }
+    }
+
+    // llvm-mc encodings, one or more per instruction class; the comment is the llvm-mc
+    // source where objdump spells the instruction differently.
+    #[test]
+    fn rv64_encodings() {
+        check(&[
+            (0x01058513, "addi a0,a1,16"),
+            (0xfff00513, "li a0,-1"),
+            (0x00060593, "mv a1,a2"),
+            (0x00351513, "slli a0,a0,0x3"),             // slli a0, a0, 3
+            (0x4025d593, "srai a1,a1,0x2"),             // srai a1, a1, 2
+            (0x0ff57513, "andi a0,a0,255"),
+            (0x0015051b, "addiw a0,a0,1"),
+            (0x00c58533, "add a0,a1,a2"),
+            (0x40c58533, "sub a0,a1,a2"),
+            (0x00c5b533, "sltu a0,a1,a2"),
+            (0x00c5c533, "xor a0,a1,a2"),
+            (0x00c5853b, "addw a0,a1,a2"),
+            (0x02c58533, "mul a0,a1,a2"),
+            (0x02c5c533, "div a0,a1,a2"),
+            (0x02c5f53b, "remuw a0,a1,a2"),
+            (0x12345537, "lui a0,0x12345"),
+            (0x00001297, "auipc t0,0x1"),
+            (0x00813503, "ld a0,8(sp)"),
+            (0xffc42583, "lw a1,-4(s0)"),
+            (0x00054603, "lbu a2,0(a0)"),
+            (0x00113c23, "sd ra,24(sp)"),
+            (0x00b52223, "sw a1,4(a0)"),
+            (0x00b50863, "beq a0,a1,68"),               // beq a0, a1, 16
+            (0xfe051ce3, "bnez a0,54"),                 // bne a0, zero, -8
+            (0x02b54063, "blt a0,a1,80"),               // blt a0, a1, 32
+            (0x00b57463, "bgeu a0,a1,6c"),              // bgeu a0, a1, 8
+            (0x100000ef, "jal 168"),                    // jal ra, 0x100
+            (0xfe1ff06f, "j 4c"),                       // j -0x20
+            (0x000300e7, "jalr t1"),                    // jalr ra, 0(t1)
+            (0x00008067, "ret"),
+            (0x1005b52f, "lr.d a0,(a1)"),
+            (0x18c535af, "sc.d a1,a2,(a0)"),
+            (0x00b6252f, "amoadd.w a0,a1,(a2)"),
+            (0x0ff0000f, "fence"),
+            (0x00000073, "ecall"),
+            (0x00100073, "ebreak"),
+            (0x30002573, "csrr a0,mstatus"),
+            (0x30551073, "csrw mtvec,a0"),
+            (0x00452507, "flw fa0,4(a0)"),
+            (0x00813427, "fsd fs0,8(sp)"),
+            (0x02c5f553, "fadd.d fa0,fa1,fa2"),
+            (0x10c5f553, "fmul.s fa0,fa1,fa2"),
+            (0xd2050553, "fcvt.d.w fa0,a0"),
+            (0xe2050553, "fmv.x.d a0,fa0"),
+            (0xa2b52553, "feq.d a0,fa0,fa1"),
+            (0x6ac5f543, "fmadd.d fa0,fa1,fa2,fa3"),
+            (0x0505, "addi a0,a0,1"),                   // c.addi a0, 1
+            (0x4595, "li a1,5"),                        // c.li a1, 5
+            (0x852e, "mv a0,a1"),                       // c.mv a0, a1
+            (0x952e, "add a0,a0,a1"),                   // c.add a0, a1
+            (0x60a2, "ld ra,8(sp)"),                    // c.ldsp ra, 8(sp)
+            (0xe406, "sd ra,8(sp)"),                    // c.sdsp ra, 8(sp)
+            (0x41c8, "lw a0,4(a1)"),                    // c.lw a0, 4(a1)
+            (0xa801, "j d6"),                           // c.j 16
+            (0xdd75, "beqz a0,c4"),                     // c.beqz a0, -4
+            (0x8082, "ret"),                            // c.jr ra
+            (0x713d, "addi sp,sp,-32"),                 // c.addi16sp sp, -32
+            (0x0001, "nop"),                            // c.nop
+            (0x2505, "addiw a0,a0,1"),                  // c.addiw a0, 1
+        ], true);
+    }
+
+    #[test]
+    fn rv32_encodings() {
+        check(&[
+            (0x2021, "jal 8"),                          // c.jal 8
+            (0x0505, "addi a0,a0,1"),                   // c.addi a0, 1
+            (0x01058513, "addi a0,a1,16"),
+            (0x61c8, "flw fa0,4(a1)"),                  // c.flw fa0, 4(a1)
+        ], false);
+    }
+}
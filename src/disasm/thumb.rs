@@ -0,0 +1,949 @@
+use crate::disasm::arm::{self, address, barrier_option, bit, bits, branch_flow, extend_name, extend_ops, imm_offset,
+                         ok, parallel_name, pc_flow, psr_fields, reg, reg_list, saturate_ops, sext, shifted_reg, target,
+                         with_flow, with_ref, with_value, Decoded, CONDS, SHIFTS};
+use crate::disasm::{Flow, Instruction};
+
+/// Decodes one T32 instruction. `it` holds the ITSTATE bits (firstcond:mask) and is updated as
+/// instructions of an IT block are consumed.
+pub fn decode(bytes: &[u8], addr: u64, big_endian: bool, it: &mut u8) -> Option<Instruction> {
+    let half = |i: usize| -> Option<u32> {
+        let b = [*bytes.get(i)?, *bytes.get(i + 1)?];
+        Some(if big_endian { u16::from_be_bytes(b) } else { u16::from_le_bytes(b) } as u32)
+    };
+    let hw1 = half(0)?;
+    let in_it = *it & 0xf != 0;
+    let c = if in_it { CONDS[(*it >> 4) as usize] } else { "" };
+    if hw1 & 0xff00 == 0xbf00 && hw1 & 0xf != 0 {
+        *it = (hw1 & 0xff) as u8;
+        return Some(it_instruction(hw1).into_instruction(addr, 2));
+    }
+    if in_it {
+        *it = if *it & 7 == 0 { 0 } else { (*it & 0xe0) | ((*it << 1) & 0x1f) };
+    }
+    if hw1 >> 11 >= 0b11101 {
+        let w = (hw1 << 16) | half(2)?;
+        Some(decode32(w, addr, c)?.into_instruction(addr, 4))
+    } else {
+        Some(decode16(hw1, addr, c, in_it)?.into_instruction(addr, 2))
+    }
+}
+
+/// Length of the T32 instruction starting at `bytes`, judged from its first halfword.
+pub fn length(bytes: &[u8], big_endian: bool) -> usize {
+    let hi = if big_endian { bytes.first() } else { bytes.get(1) };
+    match hi {
+        Some(&b) if b >> 3 >= 0b11101 => 4,
+        _ => 2,
+    }
+}
+
+fn it_instruction(h: u32) -> Decoded {
+    let firstcond = bits(h, 7, 4);
+    let mask = bits(h, 3, 0);
+    let mut name = "it".to_string();
+    let mut m = mask;
+    while m & 7 != 0 {
+        name.push(if (m >> 3) & 1 == firstcond & 1 { 't' } else { 'e' });
+        m = (m << 1) & 0xf;
+    }
+    let cond = if firstcond >= 14 { "al" } else { CONDS[firstcond as usize] };
+    Decoded{mnemonic: name, operands: vec![cond.to_string()], comment: None, flow: Flow::Next, mem_ref: None}
+}
+
+fn decode16(h: u32, addr: u64, c: &str, in_it: bool) -> Option<Decoded> {
+    let s = if in_it { "" } else { "s" };
+    let lo = |n: u32| reg(bits(h, n + 2, n));
+    let pc = (addr.wrapping_add(4)) & !3;
+    match h >> 10 {
+        0b000000..=0b001111 => match bits(h, 13, 11) {
+            0..=2 => {
+                let ty = bits(h, 12, 11);
+                let imm5 = bits(h, 10, 6);
+                if ty == 0 && imm5 == 0 {
+                    return ok(&format!("mov{}{}", s, c), vec![lo(0), lo(3)]);
+                }
+                let amount = if imm5 == 0 { 32 } else { imm5 };
+                ok(&format!("{}{}{}", SHIFTS[ty as usize], s, c), vec![lo(0), lo(3), format!("#{}", amount)])
+            }
+            3 => {
+                let name = if bit(h, 9) { "sub" } else { "add" };
+                let operand = if bit(h, 10) { format!("#{}", bits(h, 8, 6)) } else { lo(6) };
+                ok(&format!("{}{}{}", name, s, c), vec![lo(0), lo(3), operand])
+            }
+            op => {
+                let imm = format!("#{}", bits(h, 7, 0));
+                match op {
+                    4 => ok(&format!("mov{}{}", s, c), vec![lo(8), imm]),
+                    5 => ok(&format!("cmp{}", c), vec![lo(8), imm]),
+                    6 => ok(&format!("add{}{}", s, c), vec![lo(8), imm]),
+                    _ => ok(&format!("sub{}{}", s, c), vec![lo(8), imm]),
+                }
+            }
+        },
+        0b010000 => {
+            let op = bits(h, 9, 6);
+            let name = ["and", "eor", "lsl", "lsr", "asr", "adc", "sbc", "ror",
+                        "tst", "neg", "cmp", "cmn", "orr", "mul", "bic", "mvn"][op as usize];
+            match op {
+                8 | 10 | 11 => ok(&format!("{}{}", name, c), vec![lo(0), lo(3)]),
+                13 => ok(&format!("mul{}{}", s, c), vec![lo(0), lo(3), lo(0)]),
+                _ => ok(&format!("{}{}{}", name, s, c), vec![lo(0), lo(3)]),
+            }
+        }
+        0b010001 => {
+            let rd = (bits(h, 7, 7) << 3) | bits(h, 2, 0);
+            let rm = bits(h, 6, 3);
+            match bits(h, 9, 8) {
+                0 => with_flow(ok(&format!("add{}", c), vec![reg(rd), reg(rm)]),
+                               if rd == 15 { pc_flow(c, Flow::IndirectJump) } else { Flow::Next }),
+                1 => ok(&format!("cmp{}", c), vec![reg(rd), reg(rm)]),
+                2 => {
+                    let flow = match (rd, rm) {
+                        (15, 14) => pc_flow(c, Flow::Return),
+                        (15, _) => pc_flow(c, Flow::IndirectJump),
+                        _ => Flow::Next,
+                    };
+                    with_flow(ok(&format!("mov{}", c), vec![reg(rd), reg(rm)]), flow)
+                }
+                _ => {
+                    if bit(h, 7) {
+                        with_flow(ok(&format!("blx{}", c), vec![reg(rm)]), Flow::IndirectCall)
+                    } else {
+                        let flow = if rm == 14 { Flow::Return } else { Flow::IndirectJump };
+                        with_flow(ok(&format!("bx{}", c), vec![reg(rm)]), pc_flow(c, flow))
+                    }
+                }
+            }
+        }
+        0b010010 | 0b010011 => {
+            let imm = bits(h, 7, 0) * 4;
+            with_ref(ok(&format!("ldr{}", c), vec![lo(8), format!("[pc, #{}]", imm)]), pc.wrapping_add(imm as u64))
+        }
+        0b010100..=0b010111 => {
+            let name = ["str", "strh", "strb", "ldrsb", "ldr", "ldrh", "ldrb", "ldrsh"][bits(h, 11, 9) as usize];
+            ok(&format!("{}{}", name, c), vec![lo(0), format!("[{}, {}]", lo(3), lo(6))])
+        }
+        0b011000..=0b100011 => {
+            let load = bit(h, 11);
+            let imm5 = bits(h, 10, 6);
+            let (name, imm) = match bits(h, 15, 12) {
+                0b0110 => ("r", imm5 * 4),
+                0b0111 => ("rb", imm5),
+                _ => ("rh", imm5 * 2),
+            };
+            let name = format!("{}{}{}", if load { "ld" } else { "st" }, name, c);
+            ok(&name, vec![lo(0), format!("[{}, #{}]", lo(3), imm)])
+        }
+        0b100100..=0b100111 => {
+            let name = format!("{}{}", if bit(h, 11) { "ldr" } else { "str" }, c);
+            ok(&name, vec![lo(8), format!("[sp, #{}]", bits(h, 7, 0) * 4)])
+        }
+        0b101000 | 0b101001 => {
+            let imm = bits(h, 7, 0) * 4;
+            with_ref(ok(&format!("add{}", c), vec![lo(8), "pc".to_string(), format!("#{}", imm)]), pc.wrapping_add(imm as u64))
+        }
+        0b101010 | 0b101011 => ok(&format!("add{}", c), vec![lo(8), "sp".to_string(), format!("#{}", bits(h, 7, 0) * 4)]),
+        0b101100..=0b101111 => misc16(h, addr, c),
+        0b110000..=0b110011 => {
+            let rn = bits(h, 10, 8);
+            let list = bits(h, 7, 0);
+            if list == 0 {
+                return None;
+            }
+            if bit(h, 11) {
+                let wb = if list >> rn & 1 == 0 { "!" } else { "" };
+                ok(&format!("ldmia{}", c), vec![format!("{}{}", reg(rn), wb), reg_list(list)])
+            } else {
+                ok(&format!("stmia{}", c), vec![format!("{}!", reg(rn)), reg_list(list)])
+            }
+        }
+        0b110100..=0b110111 => {
+            let cond = bits(h, 11, 8);
+            match cond {
+                14 => with_flow(ok("udf", vec![format!("#{}", bits(h, 7, 0))]), Flow::Stop),
+                15 => ok(&format!("svc{}", c), vec![bits(h, 7, 0).to_string()]),
+                _ => {
+                    let dest = addr.wrapping_add(4).wrapping_add((sext(bits(h, 7, 0), 8) << 1) as u64);
+                    let cc = CONDS[cond as usize];
+                    with_flow(ok(&format!("b{}.n", cc), vec![target(dest)]), branch_flow(cc, dest))
+                }
+            }
+        }
+        0b111000 | 0b111001 => {
+            let dest = addr.wrapping_add(4).wrapping_add((sext(bits(h, 10, 0), 11) << 1) as u64);
+            with_flow(ok(&format!("b{}.n", c), vec![target(dest)]), branch_flow(c, dest))
+        }
+        _ => None,
+    }
+}
+
+fn misc16(h: u32, addr: u64, c: &str) -> Option<Decoded> {
+    let lo = |n: u32| reg(bits(h, n + 2, n));
+    match bits(h, 11, 8) {
+        0b0000 => {
+            let name = if bit(h, 7) { "sub" } else { "add" };
+            ok(&format!("{}{}", name, c), vec!["sp".to_string(), format!("#{}", bits(h, 6, 0) * 4)])
+        }
+        0b0001 | 0b0011 | 0b1001 | 0b1011 => {
+            let offset = (bits(h, 9, 9) << 6) | (bits(h, 7, 3) << 1);
+            let dest = addr.wrapping_add(4).wrapping_add(offset as u64);
+            let name = if bit(h, 11) { "cbnz" } else { "cbz" };
+            with_flow(ok(name, vec![lo(0), target(dest)]), Flow::Branch(dest & 0xffff_ffff))
+        }
+        0b0010 => {
+            let name = ["sxth", "sxtb", "uxth", "uxtb"][bits(h, 7, 6) as usize];
+            ok(&format!("{}{}", name, c), vec![lo(0), lo(3)])
+        }
+        0b0100 | 0b0101 => {
+            let list = bits(h, 7, 0) | (bits(h, 8, 8) << 14);
+            if list == 0 {
+                return None;
+            }
+            ok(&format!("push{}", c), vec![reg_list(list)])
+        }
+        0b1100 | 0b1101 => {
+            let list = bits(h, 7, 0) | (bits(h, 8, 8) << 15);
+            if list == 0 {
+                return None;
+            }
+            let flow = if bit(h, 8) { pc_flow(c, Flow::Return) } else { Flow::Next };
+            with_flow(ok(&format!("pop{}", c), vec![reg_list(list)]), flow)
+        }
+        0b0110 => match bits(h, 7, 5) {
+            0b000 if bits(h, 4, 4) == 1 && bits(h, 2, 0) == 0 => ok("setpan", vec![format!("#{}", bits(h, 3, 3))]),
+            0b010 if bits(h, 4, 4) == 1 && bits(h, 2, 0) == 0 => {
+                ok("setend", vec![if bit(h, 3) { "be" } else { "le" }.to_string()])
+            }
+            0b011 if !bit(h, 3) => arm::cps(if bit(h, 4) { 0b11 } else { 0b10 }, false, bits(h, 2, 0), 0),
+            _ => None,
+        },
+        0b1010 => match bits(h, 7, 6) {
+            0 => ok(&format!("rev{}", c), vec![lo(0), lo(3)]),
+            1 => ok(&format!("rev16{}", c), vec![lo(0), lo(3)]),
+            2 => with_flow(ok("hlt", vec![format!("#{}", bits(h, 5, 0))]), Flow::Stop),
+            _ => ok(&format!("revsh{}", c), vec![lo(0), lo(3)]),
+        },
+        0b1110 => ok("bkpt", vec![format!("0x{:04x}", bits(h, 7, 0))]),
+        0b1111 => {
+            arm::hint(bits(h, 7, 4), c)
+        }
+        _ => None,
+    }
+}
+
+/// ThumbExpandImm: the modified immediate of 32-bit data-processing instructions.
+fn thumb_expand_imm(imm12: u32) -> Option<u32> {
+    let imm8 = imm12 & 0xff;
+    if imm12 >> 10 == 0 {
+        return match (imm12 >> 8) & 3 {
+            0 => Some(imm8),
+            _ if imm8 == 0 => None,
+            1 => Some((imm8 << 16) | imm8),
+            2 => Some((imm8 << 24) | (imm8 << 8)),
+            _ => Some(imm8 * 0x0101_0101),
+        };
+    }
+    Some((0x80 | (imm12 & 0x7f)).rotate_right(imm12 >> 7))
+}
+
+fn decode32(w: u32, addr: u64, c: &str) -> Option<Decoded> {
+    let op2 = bits(w, 26, 20);
+    match bits(w, 28, 27) {
+        0b01 => {
+            if op2 & 0b1100100 == 0 {
+                load_store_multiple(w, c)
+            } else if op2 & 0b1100100 == 0b0000100 {
+                load_store_dual(w, addr, c)
+            } else if op2 & 0b1100000 == 0b0100000 {
+                shifted_register(w, c)
+            } else {
+                coprocessor(w, addr, c)
+            }
+        }
+        0b10 => {
+            if bit(w, 15) {
+                branch_misc(w, addr, c)
+            } else if bit(w, 25) {
+                plain_imm(w, addr, c)
+            } else {
+                modified_imm(w, c)
+            }
+        }
+        _ => {
+            if op2 & 0b1110001 == 0 || (op2 & 0b1100001 == 1 && op2 & 0b110 != 0b110) {
+                load_store_single(w, addr, c)
+            } else if op2 & 0b1110001 == 0b0010000 {
+                conditional(arm::neon_load_store(0xf400_0000 | (w & 0x00ff_ffff)), c)
+            } else if op2 & 0b1110000 == 0b0100000 {
+                data_register(w, c)
+            } else if op2 & 0b1111000 == 0b0110000 {
+                multiply(w, c)
+            } else if op2 & 0b1111000 == 0b0111000 {
+                long_multiply(w, c)
+            } else if op2 & 0b1000000 != 0 {
+                coprocessor(w, addr, c)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+fn coprocessor(w: u32, addr: u64, c: &str) -> Option<Decoded> {
+    if bits(w, 27, 24) == 0b1111 {
+        return conditional(arm::neon_data(0xf200_0000 | (bits(w, 28, 28) << 24) | (w & 0x00ff_ffff)), c);
+    }
+    let a32 = w & 0x0fff_ffff;
+    if bit(w, 28) {
+        if bits(w, 11, 9) == 0b101 {
+            return if bits(w, 27, 24) == 0b1110 && !bit(w, 4) { arm::vfp_v8(0xf000_0000 | a32) } else { None };
+        }
+        return arm::coprocessor(0xf000_0000 | a32, "", addr.wrapping_add(4) & !3);
+    }
+    arm::coprocessor(0xe000_0000 | a32, c, addr.wrapping_add(4) & !3)
+}
+
+/// Adds the IT condition to an Advanced SIMD mnemonic, ahead of its data type suffix.
+fn conditional(d: Option<Decoded>, c: &str) -> Option<Decoded> {
+    let mut d = d?;
+    let at = d.mnemonic.find('.').unwrap_or(d.mnemonic.len());
+    d.mnemonic.insert_str(at, c);
+    Some(d)
+}
+
+fn load_store_multiple(w: u32, c: &str) -> Option<Decoded> {
+    let wb = bit(w, 21);
+    let load = bit(w, 20);
+    let rn = bits(w, 19, 16);
+    let list = bits(w, 15, 0);
+    match bits(w, 24, 23) {
+        0b01 | 0b10 => {
+            let ia = bits(w, 24, 23) == 0b01;
+            let pc_loaded = load && list & 0x8000 != 0;
+            if rn == 13 && wb && (ia == load) {
+                let name = if load { "pop" } else { "push" };
+                let flow = if pc_loaded { pc_flow(c, Flow::Return) } else { Flow::Next };
+                return with_flow(ok(&format!("{}{}.w", name, c), vec![reg_list(list)]), flow);
+            }
+            let name = format!("{}{}{}{}", if load { "ldm" } else { "stm" }, if ia { "ia" } else { "db" }, c,
+                               if ia { ".w" } else { "" });
+            let flow = if pc_loaded { pc_flow(c, Flow::IndirectJump) } else { Flow::Next };
+            with_flow(ok(&name, vec![format!("{}{}", reg(rn), if wb { "!" } else { "" }), reg_list(list)]), flow)
+        }
+        mode => {
+            let suffix = if mode == 0 { "db" } else { "ia" };
+            let wb = if wb { "!" } else { "" };
+            if load {
+                with_flow(ok(&format!("rfe{}", suffix), vec![format!("{}{}", reg(rn), wb)]), Flow::Return)
+            } else {
+                ok(&format!("srs{}", suffix), vec![format!("sp{}", wb), format!("#{}", bits(w, 4, 0))])
+            }
+        }
+    }
+}
+
+fn load_store_dual(w: u32, addr: u64, c: &str) -> Option<Decoded> {
+    let p = bit(w, 24);
+    let u = bit(w, 23);
+    let wb = bit(w, 21);
+    let load = bit(w, 20);
+    let rn = bits(w, 19, 16);
+    let rt = bits(w, 15, 12);
+    let rt2 = bits(w, 11, 8);
+    let imm8 = bits(w, 7, 0);
+    if p || wb {
+        let name = format!("{}{}", if load { "ldrd" } else { "strd" }, c);
+        let offset = imm8 * 4;
+        let d = ok(&name, vec![reg(rt), reg(rt2), address(rn, p, wb && p, imm_offset(p, u, wb, offset))]);
+        if rn == 15 && p && !wb {
+            let base = (addr.wrapping_add(4)) & !3;
+            let dest = if u { base.wrapping_add(offset as u64) } else { base.wrapping_sub(offset as u64) };
+            return with_ref(d, dest);
+        }
+        return with_value(d, if u { offset as i64 } else { -(offset as i64) });
+    }
+    let base = format!("[{}]", reg(rn));
+    match (bits(w, 24, 23), bits(w, 21, 20)) {
+        (0, 0) => {
+            let address = if imm8 == 0 { base } else { format!("[{}, #{}]", reg(rn), imm8 * 4) };
+            ok(&format!("strex{}", c), vec![reg(rt2), reg(rt), address])
+        }
+        (0, 1) => {
+            let address = if imm8 == 0 { base } else { format!("[{}, #{}]", reg(rn), imm8 * 4) };
+            ok(&format!("ldrex{}", c), vec![reg(rt), address])
+        }
+        (1, 0) => {
+            let rd = bits(w, 3, 0);
+            let (name, pair, status) = match bits(w, 7, 4) {
+                0b0100 => ("strexb", false, true),
+                0b0101 => ("strexh", false, true),
+                0b0111 => ("strexd", true, true),
+                0b1000 => ("stlb", false, false),
+                0b1001 => ("stlh", false, false),
+                0b1010 => ("stl", false, false),
+                0b1100 => ("stlexb", false, true),
+                0b1101 => ("stlexh", false, true),
+                0b1110 => ("stlex", false, true),
+                0b1111 => ("stlexd", true, true),
+                _ => return None,
+            };
+            let mut ops = Vec::new();
+            if status {
+                ops.push(reg(rd));
+            }
+            ops.push(reg(rt));
+            if pair {
+                ops.push(reg(rt2));
+            }
+            ops.push(base);
+            ok(&format!("{}{}", name, c), ops)
+        }
+        (1, 1) => {
+            let rm = bits(w, 3, 0);
+            let (name, pair) = match bits(w, 7, 4) {
+                0b0000 => {
+                    return with_flow(ok(&format!("tbb{}", c), vec![format!("[{}, {}]", reg(rn), reg(rm))]),
+                                     pc_flow(c, Flow::IndirectJump));
+                }
+                0b0001 => {
+                    return with_flow(ok(&format!("tbh{}", c), vec![format!("[{}, {}, lsl #1]", reg(rn), reg(rm))]),
+                                     pc_flow(c, Flow::IndirectJump));
+                }
+                0b0100 => ("ldrexb", false),
+                0b0101 => ("ldrexh", false),
+                0b0111 => ("ldrexd", true),
+                0b1000 => ("ldab", false),
+                0b1001 => ("ldah", false),
+                0b1010 => ("lda", false),
+                0b1100 => ("ldaexb", false),
+                0b1101 => ("ldaexh", false),
+                0b1110 => ("ldaex", false),
+                0b1111 => ("ldaexd", true),
+                _ => return None,
+            };
+            let mut ops = vec![reg(rt)];
+            if pair {
+                ops.push(reg(rt2));
+            }
+            ops.push(base);
+            ok(&format!("{}{}", name, c), ops)
+        }
+        _ => None,
+    }
+}
+
+/// Names the data-processing operations shared by the register and immediate forms. Returns the
+/// mnemonic and whether Rd/Rn are dropped for the compare and move aliases.
+fn data_op(op: u32, s: bool, rd: u32, rn: u32) -> Option<(&'static str, bool, bool)> {
+    Some(match op {
+        0b0000 if rd == 15 && s => ("tst", false, true),
+        0b0000 => ("and", true, true),
+        0b0001 => ("bic", true, true),
+        0b0010 if rn == 15 => ("mov", true, false),
+        0b0010 => ("orr", true, true),
+        0b0011 if rn == 15 => ("mvn", true, false),
+        0b0011 => ("orn", true, true),
+        0b0100 if rd == 15 && s => ("teq", false, true),
+        0b0100 => ("eor", true, true),
+        0b1000 if rd == 15 && s => ("cmn", false, true),
+        0b1000 => ("add", true, true),
+        0b1010 => ("adc", true, true),
+        0b1011 => ("sbc", true, true),
+        0b1101 if rd == 15 && s => ("cmp", false, true),
+        0b1101 => ("sub", true, true),
+        0b1110 => ("rsb", true, true),
+        _ => return None,
+    })
+}
+
+/// Whether objdump marks the 32-bit encoding of an operation with ".w".
+fn wide_suffix(name: &str) -> &'static str {
+    match name {
+        "orn" | "teq" | "pkhbt" | "pkhtb" => "",
+        _ => ".w",
+    }
+}
+
+fn shifted_register(w: u32, c: &str) -> Option<Decoded> {
+    let op = bits(w, 24, 21);
+    let s = bit(w, 20);
+    let rn = bits(w, 19, 16);
+    let rd = bits(w, 11, 8);
+    let rm = bits(w, 3, 0);
+    let imm = (bits(w, 14, 12) << 2) | bits(w, 7, 6);
+    let ty = bits(w, 5, 4);
+    let suffix = if s { "s" } else { "" };
+    if op == 0b0110 {
+        if s {
+            return None;
+        }
+        let mut ops = vec![reg(rd), reg(rn), reg(rm)];
+        if bit(w, 5) {
+            ops.push(format!("asr #{}", if imm == 0 { 32 } else { imm }));
+            return ok(&format!("pkhtb{}", c), ops);
+        }
+        if imm != 0 {
+            ops.push(format!("lsl #{}", imm));
+        }
+        return ok(&format!("pkhbt{}", c), ops);
+    }
+    let (name, has_rd, has_rn) = data_op(op, s, rd, rn)?;
+    if name == "mov" {
+        let shift = arm::imm_shift(ty, imm);
+        let name = match &shift {
+            None => "mov".to_string(),
+            Some(text) if text == "rrx" => return ok(&format!("rrx{}{}", suffix, c), vec![reg(rd), reg(rm)]),
+            Some(_) => SHIFTS[ty as usize].to_string(),
+        };
+        let mut ops = vec![reg(rd), reg(rm)];
+        if name != "mov" {
+            ops.push(format!("#{}", if imm == 0 { 32 } else { imm }));
+        }
+        return ok(&format!("{}{}{}.w", name, suffix, c), ops);
+    }
+    let mut ops = Vec::new();
+    if has_rd {
+        ops.push(reg(rd));
+    }
+    if has_rn {
+        ops.push(reg(rn));
+    }
+    ops.push(shifted_reg(rm, ty, imm));
+    let suffix = if has_rd { suffix } else { "" };
+    ok(&format!("{}{}{}{}", name, suffix, c, wide_suffix(name)), ops)
+}
+
+fn modified_imm(w: u32, c: &str) -> Option<Decoded> {
+    let op = bits(w, 24, 21);
+    let s = bit(w, 20);
+    let rn = bits(w, 19, 16);
+    let rd = bits(w, 11, 8);
+    let imm12 = (bits(w, 26, 26) << 11) | (bits(w, 14, 12) << 8) | bits(w, 7, 0);
+    let value = thumb_expand_imm(imm12)?;
+    let (name, has_rd, has_rn) = data_op(op, s, rd, rn)?;
+    let mut ops = Vec::new();
+    if has_rd {
+        ops.push(reg(rd));
+    }
+    if has_rn {
+        ops.push(reg(rn));
+    }
+    ops.push(format!("#{}", value as i32));
+    let suffix = if s && has_rd { "s" } else { "" };
+    with_value(ok(&format!("{}{}{}{}", name, suffix, c, wide_suffix(name)), ops), value as i32 as i64)
+}
+
+fn plain_imm(w: u32, addr: u64, c: &str) -> Option<Decoded> {
+    let rn = bits(w, 19, 16);
+    let rd = bits(w, 11, 8);
+    let imm12 = (bits(w, 26, 26) << 11) | (bits(w, 14, 12) << 8) | bits(w, 7, 0);
+    let imm16 = (rn << 12) | imm12;
+    let shift = (bits(w, 14, 12) << 2) | bits(w, 7, 6);
+    let field = bits(w, 4, 0);
+    match bits(w, 24, 20) {
+        0b00000 | 0b01010 => {
+            let add = bits(w, 24, 20) == 0;
+            let name = format!("{}{}", if add { "addw" } else { "subw" }, c);
+            let d = ok(&name, vec![reg(rd), reg(rn), format!("#{}", imm12)]);
+            if rn == 15 {
+                let base = addr.wrapping_add(4) & !3;
+                let dest = if add { base.wrapping_add(imm12 as u64) } else { base.wrapping_sub(imm12 as u64) };
+                return with_ref(d, dest);
+            }
+            with_value(d, imm12 as i64)
+        }
+        0b00100 => with_value(ok(&format!("movw{}", c), vec![reg(rd), format!("#{}", imm16)]), imm16 as i64),
+        0b01100 => with_value(ok(&format!("movt{}", c), vec![reg(rd), format!("#{}", imm16)]), imm16 as i64),
+        0b10010 if shift == 0 => ok(&format!("ssat16{}", c), vec![reg(rd), format!("#{}", field + 1), reg(rn)]),
+        0b11010 if shift == 0 => ok(&format!("usat16{}", c), vec![reg(rd), format!("#{}", field), reg(rn)]),
+        0b10000 | 0b10010 => ok(&format!("ssat{}", c), saturate_ops(rd, field + 1, rn, bit(w, 21), shift)),
+        0b11000 | 0b11010 => ok(&format!("usat{}", c), saturate_ops(rd, field, rn, bit(w, 21), shift)),
+        0b10100 | 0b11100 => {
+            let name = if bit(w, 23) { "ubfx" } else { "sbfx" };
+            ok(&format!("{}{}", name, c), vec![reg(rd), reg(rn), format!("#{}", shift), format!("#{}", field + 1)])
+        }
+        0b10110 => {
+            if field < shift {
+                return None;
+            }
+            let width = format!("#{}", field - shift + 1);
+            if rn == 15 {
+                ok(&format!("bfc{}", c), vec![reg(rd), format!("#{}", shift), width])
+            } else {
+                ok(&format!("bfi{}", c), vec![reg(rd), reg(rn), format!("#{}", shift), width])
+            }
+        }
+        _ => None,
+    }
+}
+
+fn branch_misc(w: u32, addr: u64, c: &str) -> Option<Decoded> {
+    let op1 = bits(w, 14, 12);
+    let op = bits(w, 26, 20);
+    let s = bits(w, 26, 26);
+    let pc = addr.wrapping_add(4);
+    if op1 == 0b010 && op == 0b1111111 {
+        let imm16 = (bits(w, 19, 16) << 12) | bits(w, 11, 0);
+        return with_flow(ok("udf.w", vec![format!("#{}", imm16)]), Flow::Stop);
+    }
+    if op1 & 0b101 == 0 {
+        if op & 0b0111000 != 0b0111000 {
+            let imm = (s << 20) | (bits(w, 11, 11) << 19) | (bits(w, 13, 13) << 18) | (bits(w, 21, 16) << 12) | (bits(w, 10, 0) << 1);
+            let dest = pc.wrapping_add(sext(imm, 21) as u64);
+            let cc = CONDS[bits(w, 25, 22) as usize];
+            return with_flow(ok(&format!("b{}.w", cc), vec![target(dest)]), branch_flow(cc, dest));
+        }
+        return misc_control(w, c);
+    }
+    let j1 = bits(w, 13, 13);
+    let j2 = bits(w, 11, 11);
+    let i1 = !(j1 ^ s) & 1;
+    let i2 = !(j2 ^ s) & 1;
+    let imm = (s << 24) | (i1 << 23) | (i2 << 22) | (bits(w, 25, 16) << 12) | (bits(w, 10, 0) << 1);
+    let offset = sext(imm, 25);
+    match op1 {
+        0b001 | 0b011 => {
+            let dest = pc.wrapping_add(offset as u64);
+            with_flow(ok(&format!("b{}.w", c), vec![target(dest)]), branch_flow(c, dest))
+        }
+        0b100 | 0b110 => {
+            if bit(w, 0) {
+                return None;
+            }
+            let dest = (pc & !3).wrapping_add(offset as u64);
+            with_flow(ok(&format!("blx{}", c), vec![target(dest)]), Flow::Call(dest & 0xffff_ffff))
+        }
+        _ => {
+            let dest = pc.wrapping_add(offset as u64);
+            with_flow(ok(&format!("bl{}", c), vec![target(dest)]), Flow::Call(dest & 0xffff_ffff))
+        }
+    }
+}
+
+fn misc_control(w: u32, c: &str) -> Option<Decoded> {
+    let rn = bits(w, 19, 16);
+    match bits(w, 26, 20) {
+        0b0111000 | 0b0111001 if !bit(w, 5) => {
+            ok(&format!("msr{}", c), vec![psr_fields(bit(w, 20), bits(w, 11, 8)), reg(rn)])
+        }
+        0b0111010 => {
+            if bits(w, 10, 8) != 0 {
+                return arm::cps(bits(w, 10, 9), bit(w, 8), bits(w, 7, 5), bits(w, 4, 0));
+            }
+            let d = arm::hint(bits(w, 7, 0), c)?;
+            let name = if d.operands.is_empty() { format!("{}.w", d.mnemonic) } else { d.mnemonic.clone() };
+            Some(Decoded{mnemonic: name, ..d})
+        }
+        0b0111011 => {
+            let option = bits(w, 3, 0);
+            match bits(w, 7, 4) {
+                0b0010 => ok(&format!("clrex{}", c), vec![]),
+                0b0100 => ok(&format!("dsb{}", c), vec![barrier_option(option)]),
+                0b0101 => ok(&format!("dmb{}", c), vec![barrier_option(option)]),
+                0b0110 => ok(&format!("isb{}", c), vec![if option == 15 { "sy".to_string() } else { format!("#{}", option) }]),
+                0b0111 if option == 0 => ok("sb", vec![]),
+                _ => None,
+            }
+        }
+        0b0111100 => with_flow(ok(&format!("bxj{}", c), vec![reg(rn)]), pc_flow(c, Flow::IndirectJump)),
+        0b0111101 => {
+            let imm8 = bits(w, 7, 0);
+            if imm8 == 0 {
+                return with_flow(ok(&format!("eret{}", c), vec![]), pc_flow(c, Flow::Return));
+            }
+            with_flow(ok(&format!("subs{}", c), vec!["pc".to_string(), "lr".to_string(), format!("#{}", imm8)]),
+                      pc_flow(c, Flow::Return))
+        }
+        0b0111110 | 0b0111111 if !bit(w, 5) => {
+            ok(&format!("mrs{}", c), vec![reg(bits(w, 11, 8)), if bit(w, 20) { "SPSR" } else { "CPSR" }.to_string()])
+        }
+        0b1111110 if bits(w, 14, 12) == 0 => ok("hvc", vec![format!("#{}", (rn << 12) | bits(w, 11, 0))]),
+        0b1111111 if bits(w, 14, 12) == 0 => ok(&format!("smc{}", c), vec![format!("#{}", rn)]),
+        _ => None,
+    }
+}
+
+fn load_store_single(w: u32, addr: u64, c: &str) -> Option<Decoded> {
+    let signed = bit(w, 24);
+    let size = bits(w, 22, 21);
+    let load = bit(w, 20);
+    let rn = bits(w, 19, 16);
+    let rt = bits(w, 15, 12);
+    if size == 3 || (!load && signed) || (signed && size == 2) {
+        return None;
+    }
+    let suffix = ["b", "h", ""][size as usize];
+    let unprivileged = rn != 15 && !bit(w, 23) && bits(w, 11, 8) == 0b1110;
+    let preload = load && rt == 15 && size < 2 && !unprivileged;
+    let name = if preload {
+        match (signed, size) {
+            (false, 0) => "pld".to_string(),
+            (false, _) => "pldw".to_string(),
+            (true, 0) => "pli".to_string(),
+            _ => return None,
+        }
+    } else {
+        format!("{}r{}{}", if load { "ld" } else { "st" }, if signed { "s" } else { "" }, suffix)
+    };
+    let flow = |d: Option<Decoded>, from_stack: bool| {
+        if load && rt == 15 && size == 2 {
+            with_flow(d, pc_flow(c, if from_stack { Flow::Return } else { Flow::IndirectJump }))
+        } else {
+            d
+        }
+    };
+    let mut ops = Vec::new();
+    if !preload {
+        ops.push(reg(rt));
+    }
+    let wide = |name: &str| if preload { format!("{}{}", name, c) } else { format!("{}{}.w", name, c) };
+    if rn == 15 {
+        if !load {
+            return None;
+        }
+        let u = bit(w, 23);
+        let imm = bits(w, 11, 0);
+        ops.push(format!("[pc, #{}{}]", if u { "" } else { "-" }, imm));
+        let base = addr.wrapping_add(4) & !3;
+        let dest = if u { base.wrapping_add(imm as u64) } else { base.wrapping_sub(imm as u64) };
+        return flow(with_ref(ok(&wide(&name), ops), dest), false);
+    }
+    if bit(w, 23) {
+        let imm = bits(w, 11, 0);
+        ops.push(address(rn, true, false, imm_offset(true, true, false, imm)));
+        return flow(with_value(ok(&wide(&name), ops), imm as i64), false);
+    }
+    if bit(w, 11) {
+        let p = bit(w, 10);
+        let u = bit(w, 9);
+        let wb = bit(w, 8);
+        let imm = bits(w, 7, 0);
+        if p && u && !wb {
+            if preload {
+                return None;
+            }
+            ops.push(address(rn, true, false, imm_offset(true, true, false, imm)));
+            return with_value(ok(&format!("{}t{}", name, c), ops), imm as i64);
+        }
+        if !p && !wb {
+            return None;
+        }
+        if preload && (wb || !p || u) {
+            return None;
+        }
+        ops.push(address(rn, p, wb, imm_offset(p, u, wb, imm)));
+        let value = if u { imm as i64 } else { -(imm as i64) };
+        let d = ok(&format!("{}{}", name, c), ops);
+        return flow(with_value(d, value), rn == 13 && !p && u && imm == 4);
+    }
+    if bits(w, 11, 6) != 0 {
+        return None;
+    }
+    let rm = bits(w, 3, 0);
+    let shift = bits(w, 5, 4);
+    let offset = if shift == 0 { reg(rm) } else { format!("{}, lsl #{}", reg(rm), shift) };
+    ops.push(format!("[{}, {}]", reg(rn), offset));
+    flow(ok(&wide(&name), ops), false)
+}
+
+fn data_register(w: u32, c: &str) -> Option<Decoded> {
+    let op1 = bits(w, 23, 20);
+    let op2 = bits(w, 7, 4);
+    let rn = bits(w, 19, 16);
+    let rd = bits(w, 11, 8);
+    let rm = bits(w, 3, 0);
+    if bits(w, 15, 12) != 0b1111 {
+        return None;
+    }
+    if op2 == 0 && op1 < 8 {
+        let suffix = if bit(w, 20) { "s" } else { "" };
+        return ok(&format!("{}{}{}.w", SHIFTS[bits(w, 22, 21) as usize], suffix, c), vec![reg(rd), reg(rn), reg(rm)]);
+    }
+    if op1 < 6 && op2 & 0b1000 != 0 {
+        let op = [0b011, 0b111, 0b000, 0b100, 0b010, 0b110][op1 as usize];
+        let name = extend_name(op, rn != 15)?;
+        let d = ok(&format!("{}{}", name, c), extend_ops(rd, rn, rm, bits(w, 5, 4)))?;
+        let wide = matches!(d.mnemonic.as_str(), "sxth" | "sxtb" | "uxth" | "uxtb");
+        return Some(if wide { Decoded{mnemonic: format!("{}.w", d.mnemonic), ..d} } else { d });
+    }
+    if op1 & 0b1000 != 0 && op2 & 0b1000 == 0 {
+        let op = match bits(w, 22, 20) {
+            0b001 => 0,
+            0b010 => 1,
+            0b110 => 2,
+            0b101 => 3,
+            0b000 => 4,
+            0b100 => 7,
+            _ => return None,
+        };
+        let prefix = match bits(w, 5, 4) {
+            0 => 1,
+            1 => 2,
+            2 => 3,
+            _ => return None,
+        };
+        let name = parallel_name(bit(w, 6), prefix, op)?;
+        return ok(&format!("{}{}", name, c), vec![reg(rd), reg(rn), reg(rm)]);
+    }
+    match (op1, op2) {
+        (0b1000, 0b1000..=0b1011) => {
+            let name = ["qadd", "qdadd", "qsub", "qdsub"][bits(w, 5, 4) as usize];
+            ok(&format!("{}{}", name, c), vec![reg(rd), reg(rm), reg(rn)])
+        }
+        (0b1001, 0b1000..=0b1011) => {
+            let name = ["rev.w", "rev16.w", "rbit", "revsh.w"][bits(w, 5, 4) as usize];
+            let name = match name.split_once('.') {
+                Some((base, wide)) => format!("{}{}.{}", base, c, wide),
+                None => format!("{}{}", name, c),
+            };
+            ok(&name, vec![reg(rd), reg(rn)])
+        }
+        (0b1010, 0b1000) => ok(&format!("sel{}", c), vec![reg(rd), reg(rn), reg(rm)]),
+        (0b1011, 0b1000) => ok(&format!("clz{}", c), vec![reg(rd), reg(rn)]),
+        (0b1100 | 0b1101, 0b1000..=0b1010) => {
+            let name = format!("crc32{}{}", if op1 == 0b1101 { "c" } else { "" }, ["b", "h", "w"][bits(w, 5, 4) as usize]);
+            ok(&name, vec![reg(rd), reg(rn), reg(rm)])
+        }
+        _ => None,
+    }
+}
+
+fn multiply(w: u32, c: &str) -> Option<Decoded> {
+    let op1 = bits(w, 22, 20);
+    let op2 = bits(w, 5, 4);
+    let rn = bits(w, 19, 16);
+    let ra = bits(w, 15, 12);
+    let rd = bits(w, 11, 8);
+    let rm = bits(w, 3, 0);
+    let three = |name: String| ok(&name, vec![reg(rd), reg(rn), reg(rm)]);
+    let four = |name: String| ok(&name, vec![reg(rd), reg(rn), reg(rm), reg(ra)]);
+    if bits(w, 7, 6) != 0 {
+        return None;
+    }
+    let x = if bit(w, 4) { "x" } else { "" };
+    let r = if bit(w, 4) { "r" } else { "" };
+    match (op1, op2) {
+        (0, 0) if ra == 15 => three(format!("mul{}", c)),
+        (0, 0) => four(format!("mla{}", c)),
+        (0, 1) => four(format!("mls{}", c)),
+        (1, _) => {
+            let xy = format!("{}{}", if bit(w, 5) { "t" } else { "b" }, if bit(w, 4) { "t" } else { "b" });
+            if ra == 15 { three(format!("smul{}{}", xy, c)) } else { four(format!("smla{}{}", xy, c)) }
+        }
+        (2, 0 | 1) => if ra == 15 { three(format!("smuad{}{}", x, c)) } else { four(format!("smlad{}{}", x, c)) },
+        (3, 0 | 1) => {
+            let y = if bit(w, 4) { "t" } else { "b" };
+            if ra == 15 { three(format!("smulw{}{}", y, c)) } else { four(format!("smlaw{}{}", y, c)) }
+        }
+        (4, 0 | 1) => if ra == 15 { three(format!("smusd{}{}", x, c)) } else { four(format!("smlsd{}{}", x, c)) },
+        (5, 0 | 1) => if ra == 15 { three(format!("smmul{}{}", r, c)) } else { four(format!("smmla{}{}", r, c)) },
+        (6, 0 | 1) => four(format!("smmls{}{}", r, c)),
+        (7, 0) => if ra == 15 { three(format!("usad8{}", c)) } else { four(format!("usada8{}", c)) },
+        _ => None,
+    }
+}
+
+fn long_multiply(w: u32, c: &str) -> Option<Decoded> {
+    let op1 = bits(w, 22, 20);
+    let op2 = bits(w, 7, 4);
+    let rn = bits(w, 19, 16);
+    let rdlo = bits(w, 15, 12);
+    let rdhi = bits(w, 11, 8);
+    let rm = bits(w, 3, 0);
+    let long = |name: String| ok(&name, vec![reg(rdlo), reg(rdhi), reg(rn), reg(rm)]);
+    match (op1, op2) {
+        (0b000, 0) => long(format!("smull{}", c)),
+        (0b001, 0b1111) => ok(&format!("sdiv{}", c), vec![reg(rdhi), reg(rn), reg(rm)]),
+        (0b010, 0) => long(format!("umull{}", c)),
+        (0b011, 0b1111) => ok(&format!("udiv{}", c), vec![reg(rdhi), reg(rn), reg(rm)]),
+        (0b100, 0) => long(format!("smlal{}", c)),
+        (0b100, 0b1000..=0b1011) => {
+            let xy = format!("{}{}", if bit(w, 5) { "t" } else { "b" }, if bit(w, 4) { "t" } else { "b" });
+            long(format!("smlal{}{}", xy, c))
+        }
+        (0b100, 0b1100 | 0b1101) => long(format!("smlald{}{}", if bit(w, 4) { "x" } else { "" }, c)),
+        (0b101, 0b1100 | 0b1101) => long(format!("smlsld{}{}", if bit(w, 4) { "x" } else { "" }, c)),
+        (0b110, 0) => long(format!("umlal{}", c)),
+        (0b110, 0b0110) => long(format!("umaal{}", c)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// llvm-mc encodings as halfwords, one or more per instruction class, decoded in
+    /// sequence from address 0 so branch targets and IT blocks carry over; the comment is
+    /// the llvm-mc source where objdump spells the instruction differently.
+    const CASES: &[(&[u16], &str)] = &[
+        (&[0x2001], "movs r0, #1"),
+        (&[0x1cd1], "adds r1, r2, #3"),
+        (&[0x1a40], "subs r0, r0, r1"),
+        (&[0x280a], "cmp r0, #10"),
+        (&[0x0111], "lsls r1, r2, #4"),
+        (&[0x4008], "ands r0, r1"),
+        (&[0x4680], "mov r8, r0"),
+        (&[0xb004], "add sp, #16"),
+        (&[0xb082], "sub sp, #8"),
+        (&[0x6848], "ldr r0, [r1, #4]"),
+        (&[0x9002], "str r0, [sp, #8]"),
+        (&[0x5d1a], "ldrb r2, [r3, r4]"),
+        (&[0x4802], "ldr r0, [pc, #8]"),
+        (&[0xb530], "push {r4, r5, lr}"),
+        (&[0xbd30], "pop {r4, r5, pc}"),
+        (&[0xf000, 0xb806], "b.w 2e"),                              // b .+0x10
+        (&[0x4770], "bx lr"),
+        (&[0x4798], "blx r3"),
+        (&[0xb108], "cbz r0, 2c"),                                  // cbz r0, .+6
+        (&[0xbf08], "it eq"),
+        (&[0x2001], "moveq r0, #1"),
+        (&[0xbf14], "ite ne"),
+        (&[0x2102], "movne r1, #2"),
+        (&[0x2103], "moveq r1, #3"),
+        (&[0xd000], "beq.n 36"),                                    // beq .+4
+        (&[0xdf00], "svc 0"),                                       // svc #0
+        (&[0xbf00], "nop"),
+        (&[0xb2c8], "uxtb r0, r1"),
+        (&[0xf501, 0x5080], "add.w r0, r1, #4096\t@ 0x1000"),       // add.w r0, r1, #0x1000
+        (&[0xf5ad, 0x7d00], "sub.w sp, sp, #512\t@ 0x200"),         // sub.w sp, sp, #512
+        (&[0xf241, 0x2034], "movw r0, #4660\t@ 0x1234"),            // movw r0, #0x1234
+        (&[0xf2c5, 0x6078], "movt r0, #22136\t@ 0x5678"),           // movt r0, #0x5678
+        (&[0xea41, 0x00c2], "orr.w r0, r1, r2, lsl #3"),
+        (&[0xfb01, 0xf002], "mul r0, r1, r2"),
+        (&[0xfb01, 0x3002], "mla r0, r1, r2, r3"),
+        (&[0xfb91, 0xf0f2], "sdiv r0, r1, r2"),
+        (&[0xf3c1, 0x1007], "ubfx r0, r1, #4, #8"),
+        (&[0xf8d1, 0x0100], "ldr.w r0, [r1, #256]\t@ 0x100"),       // ldr.w r0, [r1, #0x100]
+        (&[0xf841, 0x0d04], "str r0, [r1, #-4]!"),                  // str.w r0, [r1, #-4]!
+        (&[0xe9dd, 0x0102], "ldrd r0, r1, [sp, #8]"),
+        (&[0xf851, 0x0022], "ldr.w r0, [r1, r2, lsl #2]"),
+        (&[0xe92d, 0x41f0], "push.w {r4, r5, r6, r7, r8, lr}"),
+        (&[0xe8bd, 0x81f0], "pop.w {r4, r5, r6, r7, r8, pc}"),
+        (&[0xf000, 0xf87e], "bl 176"),                              // bl .+0x100
+        (&[0xf000, 0xe8fe], "blx 278"),                             // blx .+0x200
+        (&[0xf000, 0xbffe], "b.w 107e"),                            // b.w .+0x1000
+        (&[0xf47f, 0xafbd], "bne.w 0"),                             // bne.w .-0x82
+        (&[0xe8df, 0xf000], "tbb [pc, r0]"),
+        (&[0xe851, 0x0f00], "ldrex r0, [r1]"),
+        (&[0xf3bf, 0x8f5b], "dmb ish"),
+        (&[0xf3ef, 0x8000], "mrs r0, CPSR"),                        // mrs r0, apsr
+        (&[0xee30, 0x0a81], "vadd.f32 s0, s1, s2"),
+        (&[0xed90, 0x0b02], "vldr d0, [r0, #8]"),
+        (&[0xed2d, 0x8b04], "vpush {d8-d9}"),                       // vpush {d8, d9}
+    ];
+
+    #[test]
+    fn encodings() {
+        let mut it = 0;
+        let mut addr = 0;
+        for &(halves, expected) in CASES.iter() {
+            let bytes: Vec<u8> = halves.iter().flat_map(|h| h.to_le_bytes()).collect();
+            let insn = decode(&bytes, addr, false, &mut it).unwrap_or_else(|| panic!("{:04x?} did not decode", halves));
+            assert_eq!(insn.len, bytes.len(), "{:04x?}", halves);
+            let text = if insn.operands.is_empty() { insn.mnemonic } else { format!("{} {}", insn.mnemonic, insn.operands) };
+            assert_eq!(text, expected, "{:04x?}", halves);
+            addr += insn.len as u64;
+        }
+    }
+}
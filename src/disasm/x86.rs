@@ -0,0 +1,2273 @@
+use crate::disasm::{Flow, Instruction, Syntax, MAX_INSN_BYTES};
+
+const REG8: [&str; 16] = ["al", "cl", "dl", "bl", "spl", "bpl", "sil", "dil",
+                          "r8b", "r9b", "r10b", "r11b", "r12b", "r13b", "r14b", "r15b"];
+const REG8_LEGACY: [&str; 8] = ["al", "cl", "dl", "bl", "ah", "ch", "dh", "bh"];
+const REG16: [&str; 16] = ["ax", "cx", "dx", "bx", "sp", "bp", "si", "di",
+                           "r8w", "r9w", "r10w", "r11w", "r12w", "r13w", "r14w", "r15w"];
+const REG32: [&str; 16] = ["eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi",
+                           "r8d", "r9d", "r10d", "r11d", "r12d", "r13d", "r14d", "r15d"];
+const REG64: [&str; 16] = ["rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi",
+                           "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15"];
+const SEGS: [&str; 8] = ["es", "cs", "ss", "ds", "fs", "gs", "?", "?"];
+
+// Opcode table flags
+const I64: u16      = 0x01;     // invalid in 64-bit mode
+const O64: u16      = 0x02;     // valid only in 64-bit mode
+const D64: u16      = 0x04;     // default operand size is 64 bits in 64-bit mode
+const F64: u16      = 0x08;     // operand size forced to 64 bits in 64-bit mode
+const SSE: u16      = 0x10;     // uses the mandatory prefix; VEX/EVEX forms get a "v" prefix
+const VEX: u16      = 0x20;     // only valid with a VEX/EVEX prefix
+const GRP: u16      = 0x40;     // mnemonic selected by ModRM.reg
+const PFX: u16      = 0x80;     // prefix byte
+const LEG_PFX: u16  = 0x100;    // legacy-only encoding that still selects on the mandatory prefix
+
+#[derive(Clone, Copy)]
+struct Op {
+    mnem:   &'static str,
+    args:   &'static str,
+    flags:  u16,
+}
+
+const fn op(mnem: &'static str, args: &'static str, flags: u16) -> Op {
+    Op{mnem, args, flags}
+}
+
+const BAD: Op = op("", "", 0);
+
+fn gen(mnem: &'static str, args: &'static str) -> Option<Op> {
+    Some(op(mnem, args, 0))
+}
+
+fn sse(mnem: &'static str, args: &'static str) -> Option<Op> {
+    Some(op(mnem, args, SSE))
+}
+
+fn vex(mnem: &'static str, args: &'static str) -> Option<Op> {
+    Some(op(mnem, args, VEX | SSE))
+}
+
+fn leg(mnem: &'static str, args: &'static str) -> Option<Op> {
+    Some(op(mnem, args, LEG_PFX))
+}
+
+const ONE_BYTE: [Op; 256] = [
+    // 00
+    op("add", "Eb,Gb", 0), op("add", "Ev,Gv", 0), op("add", "Gb,Eb", 0), op("add", "Gv,Ev", 0),
+    op("add", "AL,Ib", 0), op("add", "rAX,Iz", 0), op("push", "ES", I64), op("pop", "ES", I64),
+    op("or", "Eb,Gb", 0), op("or", "Ev,Gv", 0), op("or", "Gb,Eb", 0), op("or", "Gv,Ev", 0),
+    op("or", "AL,Ib", 0), op("or", "rAX,Iz", 0), op("push", "CS", I64), BAD,
+    // 10
+    op("adc", "Eb,Gb", 0), op("adc", "Ev,Gv", 0), op("adc", "Gb,Eb", 0), op("adc", "Gv,Ev", 0),
+    op("adc", "AL,Ib", 0), op("adc", "rAX,Iz", 0), op("push", "SS", I64), op("pop", "SS", I64),
+    op("sbb", "Eb,Gb", 0), op("sbb", "Ev,Gv", 0), op("sbb", "Gb,Eb", 0), op("sbb", "Gv,Ev", 0),
+    op("sbb", "AL,Ib", 0), op("sbb", "rAX,Iz", 0), op("push", "DS", I64), op("pop", "DS", I64),
+    // 20
+    op("and", "Eb,Gb", 0), op("and", "Ev,Gv", 0), op("and", "Gb,Eb", 0), op("and", "Gv,Ev", 0),
+    op("and", "AL,Ib", 0), op("and", "rAX,Iz", 0), op("es", "", PFX), op("daa", "", I64),
+    op("sub", "Eb,Gb", 0), op("sub", "Ev,Gv", 0), op("sub", "Gb,Eb", 0), op("sub", "Gv,Ev", 0),
+    op("sub", "AL,Ib", 0), op("sub", "rAX,Iz", 0), op("cs", "", PFX), op("das", "", I64),
+    // 30
+    op("xor", "Eb,Gb", 0), op("xor", "Ev,Gv", 0), op("xor", "Gb,Eb", 0), op("xor", "Gv,Ev", 0),
+    op("xor", "AL,Ib", 0), op("xor", "rAX,Iz", 0), op("ss", "", PFX), op("aaa", "", I64),
+    op("cmp", "Eb,Gb", 0), op("cmp", "Ev,Gv", 0), op("cmp", "Gb,Eb", 0), op("cmp", "Gv,Ev", 0),
+    op("cmp", "AL,Ib", 0), op("cmp", "rAX,Iz", 0), op("ds", "", PFX), op("aas", "", I64),
+    // 40
+    op("inc", "Zv", I64), op("inc", "Zv", I64), op("inc", "Zv", I64), op("inc", "Zv", I64),
+    op("inc", "Zv", I64), op("inc", "Zv", I64), op("inc", "Zv", I64), op("inc", "Zv", I64),
+    op("dec", "Zv", I64), op("dec", "Zv", I64), op("dec", "Zv", I64), op("dec", "Zv", I64),
+    op("dec", "Zv", I64), op("dec", "Zv", I64), op("dec", "Zv", I64), op("dec", "Zv", I64),
+    // 50
+    op("push", "Zv", D64), op("push", "Zv", D64), op("push", "Zv", D64), op("push", "Zv", D64),
+    op("push", "Zv", D64), op("push", "Zv", D64), op("push", "Zv", D64), op("push", "Zv", D64),
+    op("pop", "Zv", D64), op("pop", "Zv", D64), op("pop", "Zv", D64), op("pop", "Zv", D64),
+    op("pop", "Zv", D64), op("pop", "Zv", D64), op("pop", "Zv", D64), op("pop", "Zv", D64),
+    // 60
+    op("pushaw/pusha/", "", I64), op("popaw/popa/", "", I64), op("bound", "Gv,Ma", I64), op("movsxd", "Gv,Ed", 0),
+    op("fs", "", PFX), op("gs", "", PFX), op("data16", "", PFX), op("addr32", "", PFX),
+    op("push", "Iz", D64), op("imul", "Gv,Ev,Iz", 0), op("push", "Ibs", D64), op("imul", "Gv,Ev,Ibs", 0),
+    op("ins", "Yb,DX", 0), op("ins", "Yz,DX", 0), op("outs", "DX,Xb", 0), op("outs", "DX,Xz", 0),
+    // 70
+    op("jo", "Jb", F64), op("jno", "Jb", F64), op("jb", "Jb", F64), op("jae", "Jb", F64),
+    op("je", "Jb", F64), op("jne", "Jb", F64), op("jbe", "Jb", F64), op("ja", "Jb", F64),
+    op("js", "Jb", F64), op("jns", "Jb", F64), op("jp", "Jb", F64), op("jnp", "Jb", F64),
+    op("jl", "Jb", F64), op("jge", "Jb", F64), op("jle", "Jb", F64), op("jg", "Jb", F64),
+    // 80
+    op("grp1", "Eb,Ib", GRP), op("grp1", "Ev,Iz", GRP), op("grp1", "Eb,Ib", GRP | I64), op("grp1", "Ev,Ibs", GRP),
+    op("test", "Eb,Gb", 0), op("test", "Ev,Gv", 0), op("xchg", "Eb,Gb", 0), op("xchg", "Ev,Gv", 0),
+    op("mov", "Eb,Gb", 0), op("mov", "Ev,Gv", 0), op("mov", "Gb,Eb", 0), op("mov", "Gv,Ev", 0),
+    op("mov", "Rv/Mw,Sw", 0), op("lea", "Gv,M", 0), op("mov", "Sw,Ew", 0), op("grp1a", "Ev", GRP | D64),
+    // 90
+    op("nop", "", 0), op("xchg", "Zv,rAX", 0), op("xchg", "Zv,rAX", 0), op("xchg", "Zv,rAX", 0),
+    op("xchg", "Zv,rAX", 0), op("xchg", "Zv,rAX", 0), op("xchg", "Zv,rAX", 0), op("xchg", "Zv,rAX", 0),
+    op("cbw/cwde/cdqe", "", 0), op("cwd/cdq/cqo", "", 0), op("lcall", "Ap", I64), op("fwait", "", 0),
+    op("pushfw/pushf/pushf", "", D64), op("popfw/popf/popf", "", D64), op("sahf", "", 0), op("lahf", "", 0),
+    // A0
+    op("mov", "AL,Ob", 0), op("mov", "rAX,Ov", 0), op("mov", "Ob,AL", 0), op("mov", "Ov,rAX", 0),
+    op("movs", "Yb,Xb", 0), op("movs", "Yv,Xv", 0), op("cmps", "Xb,Yb", 0), op("cmps", "Xv,Yv", 0),
+    op("test", "AL,Ib", 0), op("test", "rAX,Iz", 0), op("stos", "Yb,AL", 0), op("stos", "Yv,rAX", 0),
+    op("lods", "AL,Xb", 0), op("lods", "rAX,Xv", 0), op("scas", "AL,Yb", 0), op("scas", "rAX,Yv", 0),
+    // B0
+    op("mov", "Zb,Ib", 0), op("mov", "Zb,Ib", 0), op("mov", "Zb,Ib", 0), op("mov", "Zb,Ib", 0),
+    op("mov", "Zb,Ib", 0), op("mov", "Zb,Ib", 0), op("mov", "Zb,Ib", 0), op("mov", "Zb,Ib", 0),
+    op("mov", "Zv,Iv", 0), op("mov", "Zv,Iv", 0), op("mov", "Zv,Iv", 0), op("mov", "Zv,Iv", 0),
+    op("mov", "Zv,Iv", 0), op("mov", "Zv,Iv", 0), op("mov", "Zv,Iv", 0), op("mov", "Zv,Iv", 0),
+    // C0
+    op("grp2", "Eb,Ib", GRP), op("grp2", "Ev,Ib", GRP), op("ret", "Iw", F64), op("ret", "", F64),
+    op("les", "Gz,Mp", I64), op("lds", "Gz,Mp", I64), op("grp11", "Eb,Ib", GRP), op("grp11", "Ev,Iz", GRP),
+    op("enter", "Iw,Ib", D64), op("leave", "", D64), op("retf", "Iw", 0), op("retf", "", 0),
+    op("int3", "", 0), op("int", "Ib", 0), op("into", "", I64), op("iretw/iret/iretq", "", 0),
+    // D0
+    op("grp2", "Eb,1", GRP), op("grp2", "Ev,1", GRP), op("grp2", "Eb,CL", GRP), op("grp2", "Ev,CL", GRP),
+    op("aam", "Ib", I64), op("aad", "Ib", I64), BAD, op("xlat", "", 0),
+    op("x87", "", 0), op("x87", "", 0), op("x87", "", 0), op("x87", "", 0),
+    op("x87", "", 0), op("x87", "", 0), op("x87", "", 0), op("x87", "", 0),
+    // E0
+    op("loopne", "Jb", F64), op("loope", "Jb", F64), op("loop", "Jb", F64), op("jcxz", "Jb", F64),
+    op("in", "AL,Ib", 0), op("in", "eAX,Ib", 0), op("out", "Ib,AL", 0), op("out", "Ib,eAX", 0),
+    op("call", "Jz", F64), op("jmp", "Jz", F64), op("ljmp", "Ap", I64), op("jmp", "Jb", F64),
+    op("in", "AL,DX", 0), op("in", "eAX,DX", 0), op("out", "DX,AL", 0), op("out", "DX,eAX", 0),
+    // F0
+    op("lock", "", PFX), op("int1", "", 0), op("repnz", "", PFX), op("repz", "", PFX),
+    op("hlt", "", 0), op("cmc", "", 0), op("grp3", "Eb", GRP), op("grp3", "Ev", GRP),
+    op("clc", "", 0), op("stc", "", 0), op("cli", "", 0), op("sti", "", 0),
+    op("cld", "", 0), op("std", "", 0), op("grp4", "", GRP), op("grp5", "", GRP),
+];
+
+fn group_op(group: &str, reg: u8, is_mem: bool, args: &'static str) -> Option<Op> {
+    let r = reg as usize;
+    match group {
+        "grp1" => Some(op(["add", "or", "adc", "sbb", "and", "sub", "xor", "cmp"][r], args, 0)),
+        "grp1a" => if r == 0 { Some(op("pop", args, D64)) } else { None },
+        "grp2" => Some(op(["rol", "ror", "rcl", "rcr", "shl", "shr", "shl", "sar"][r], args, 0)),
+        "grp3" => {
+            let wide = args == "Ev";
+            match r {
+                0 | 1 => Some(op("test", if wide { "Ev,Iz" } else { "Eb,Ib" }, 0)),
+                _ => Some(op(["", "", "not", "neg", "mul", "imul", "div", "idiv"][r], args, 0)),
+            }
+        }
+        "grp4" => match r {
+            0 => gen("inc", "Eb"),
+            1 => gen("dec", "Eb"),
+            _ => None,
+        },
+        "grp5" => match r {
+            0 => gen("inc", "Ev"),
+            1 => gen("dec", "Ev"),
+            2 => Some(op("call", "Ev", F64)),
+            3 if is_mem => gen("lcall", "Mp"),
+            4 => Some(op("jmp", "Ev", F64)),
+            5 if is_mem => gen("ljmp", "Mp"),
+            6 => Some(op("push", "Ev", D64)),
+            _ => None,
+        },
+        "grp11" => if r == 0 { gen("mov", args) } else { None },
+        _ => None,
+    }
+}
+
+/// Two-byte opcode map (0F xx).
+fn lookup_0f(b: u8, pp: u8, reg: u8, is_mem: bool, is_vex: bool) -> Option<Op> {
+    match b {
+        0x00 => match reg {
+            0 => gen("sldt", "Rv/Mw"), 1 => gen("str", "Rv/Mw"), 2 => gen("lldt", "Ew"),
+            3 => gen("ltr", "Ew"), 4 => gen("verr", "Ew"), 5 => gen("verw", "Ew"), _ => None,
+        },
+        0x01 => None,   // group 7 is handled by the decoder, it depends on the full ModRM byte
+        0x02 => gen("lar", "Gv,Rv/Mw"),
+        0x03 => gen("lsl", "Gv,Rv/Mw"),
+        0x05 => Some(op("syscall", "", O64)),
+        0x06 => gen("clts", ""),
+        0x07 => Some(op("sysretd|sysretq", "", O64)),
+        0x08 => gen("invd", ""),
+        0x09 => gen("wbinvd", ""),
+        0x0b => gen("ud2", ""),
+        0x0d => match reg { 1 => gen("prefetchw", "Mb"), 2 => gen("prefetchwt1", "Mb"), _ => gen("prefetch", "Mb") },
+        0x10 => match pp {
+            0 => sse("movups", "Vps,Wps"), 1 => sse("movupd", "Vpd,Wpd"),
+            2 => sse("movss", "Vx,Hr,Wss"), _ => sse("movsd", "Vx,Hr,Wsd"),
+        },
+        0x11 => match pp {
+            0 => sse("movups", "Wps,Vps"), 1 => sse("movupd", "Wpd,Vpd"),
+            2 => sse("movss", "Wss,Hr,Vss"), _ => sse("movsd", "Wsd,Hr,Vsd"),
+        },
+        0x12 => match pp {
+            0 if is_mem => sse("movlps", "Vq,Hq,Mq"), 0 => sse("movhlps", "Vq,Hq,Uq"),
+            1 => sse("movlpd", "Vq,Hq,Mq"), 2 => sse("movsldup", "Vx,Wx"), _ => sse("movddup", "Vx,Wx"),
+        },
+        0x13 => match pp { 0 => sse("movlps", "Mq,Vq"), 1 => sse("movlpd", "Mq,Vq"), _ => None },
+        0x14 => match pp { 0 => sse("unpcklps", "Vx,Hx,Wx"), 1 => sse("unpcklpd", "Vx,Hx,Wx"), _ => None },
+        0x15 => match pp { 0 => sse("unpckhps", "Vx,Hx,Wx"), 1 => sse("unpckhpd", "Vx,Hx,Wx"), _ => None },
+        0x16 => match pp {
+            0 if is_mem => sse("movhps", "Vdq,Hq,Mq"), 0 => sse("movlhps", "Vdq,Hq,Uq"),
+            1 => sse("movhpd", "Vdq,Hq,Mq"), 2 => sse("movshdup", "Vx,Wx"), _ => None,
+        },
+        0x17 => match pp { 0 => sse("movhps", "Mq,Vq"), 1 => sse("movhpd", "Mq,Vq"), _ => None },
+        0x18 if is_mem && reg < 4 => gen(["prefetchnta", "prefetcht0", "prefetcht1", "prefetcht2"][reg as usize], "Mb"),
+        0x18..=0x1f => gen("nop", "Ev"),
+        0x20 => Some(op("mov", "Rv,Cd", F64)),
+        0x21 => Some(op("mov", "Rv,Dd", F64)),
+        0x22 => Some(op("mov", "Cd,Rv", F64)),
+        0x23 => Some(op("mov", "Dd,Rv", F64)),
+        0x28 => match pp { 0 => sse("movaps", "Vps,Wps"), 1 => sse("movapd", "Vpd,Wpd"), _ => None },
+        0x29 => match pp { 0 => sse("movaps", "Wps,Vps"), 1 => sse("movapd", "Wpd,Vpd"), _ => None },
+        0x2a => match pp {
+            0 => leg("cvtpi2ps", "Vps,Qq"), 1 => leg("cvtpi2pd", "Vpd,Qq"),
+            2 => sse("cvtsi2ss", "Vss,Hss,Ey"), _ => sse("cvtsi2sd", "Vsd,Hsd,Ey"),
+        },
+        0x2b => match pp { 0 => sse("movntps", "Mps,Vps"), 1 => sse("movntpd", "Mpd,Vpd"), _ => None },
+        0x2c => match pp {
+            0 => leg("cvttps2pi", "Pq,Wq"), 1 => leg("cvttpd2pi", "Pq,Wpd"),
+            2 => sse("cvttss2si", "Gy,Wss"), _ => sse("cvttsd2si", "Gy,Wsd"),
+        },
+        0x2d => match pp {
+            0 => leg("cvtps2pi", "Pq,Wq"), 1 => leg("cvtpd2pi", "Pq,Wpd"),
+            2 => sse("cvtss2si", "Gy,Wss"), _ => sse("cvtsd2si", "Gy,Wsd"),
+        },
+        0x2e => match pp { 0 => sse("ucomiss", "Vss,Wss"), 1 => sse("ucomisd", "Vsd,Wsd"), _ => None },
+        0x2f => match pp { 0 => sse("comiss", "Vss,Wss"), 1 => sse("comisd", "Vsd,Wsd"), _ => None },
+        0x30 => gen("wrmsr", ""),
+        0x31 => gen("rdtsc", ""),
+        0x32 => gen("rdmsr", ""),
+        0x33 => gen("rdpmc", ""),
+        0x34 => gen("sysenter", ""),
+        0x35 => gen("sysexit", ""),
+        0x37 => gen("getsec", ""),
+        0x40..=0x4f => Some(op(cmov_name(b), "Gv,Ev", 0)),
+        0x50 => match pp { 0 => sse("movmskps", "Gd,Ux"), 1 => sse("movmskpd", "Gd,Ux"), _ => None },
+        0x51 => ps_pd_ss_sd(pp, ["sqrtps", "sqrtpd", "sqrtss", "sqrtsd"], true),
+        0x52 => match pp { 0 => sse("rsqrtps", "Vps,Wps"), 2 => sse("rsqrtss", "Vss,Hss,Wss"), _ => None },
+        0x53 => match pp { 0 => sse("rcpps", "Vps,Wps"), 2 => sse("rcpss", "Vss,Hss,Wss"), _ => None },
+        0x54 => match pp { 0 => sse("andps", "Vps,Hps,Wps"), 1 => sse("andpd", "Vpd,Hpd,Wpd"), _ => None },
+        0x55 => match pp { 0 => sse("andnps", "Vps,Hps,Wps"), 1 => sse("andnpd", "Vpd,Hpd,Wpd"), _ => None },
+        0x56 => match pp { 0 => sse("orps", "Vps,Hps,Wps"), 1 => sse("orpd", "Vpd,Hpd,Wpd"), _ => None },
+        0x57 => match pp { 0 => sse("xorps", "Vps,Hps,Wps"), 1 => sse("xorpd", "Vpd,Hpd,Wpd"), _ => None },
+        0x58 => ps_pd_ss_sd(pp, ["addps", "addpd", "addss", "addsd"], false),
+        0x59 => ps_pd_ss_sd(pp, ["mulps", "mulpd", "mulss", "mulsd"], false),
+        0x5a => match pp {
+            0 => sse("cvtps2pd", "Vpd,Wh"), 1 => sse("cvtpd2ps", "Vh,Wpd"),
+            2 => sse("cvtss2sd", "Vsd,Hx,Wss"), _ => sse("cvtsd2ss", "Vss,Hx,Wsd"),
+        },
+        0x5b => match pp {
+            0 => sse("cvtdq2ps", "Vps,Wx"), 1 => sse("cvtps2dq", "Vx,Wps"), 2 => sse("cvttps2dq", "Vx,Wps"), _ => None,
+        },
+        0x5c => ps_pd_ss_sd(pp, ["subps", "subpd", "subss", "subsd"], false),
+        0x5d => ps_pd_ss_sd(pp, ["minps", "minpd", "minss", "minsd"], false),
+        0x5e => ps_pd_ss_sd(pp, ["divps", "divpd", "divss", "divsd"], false),
+        0x5f => ps_pd_ss_sd(pp, ["maxps", "maxpd", "maxss", "maxsd"], false),
+        0x60..=0x6b => {
+            let name = ["punpcklbw", "punpcklwd", "punpckldq", "packsswb", "pcmpgtb", "pcmpgtw",
+                        "pcmpgtd", "packuswb", "punpckhbw", "punpckhwd", "punpckhdq", "packssdw"][(b - 0x60) as usize];
+            if b <= 0x62 && pp == 0 {
+                // The low unpacks only read half of an MMX operand.
+                leg(name, "Pq,Qd")
+            } else {
+                mmx_or_sse(pp, name)
+            }
+        }
+        0x6c => if pp == 1 { sse("punpcklqdq", "Vx,Hx,Wx") } else { None },
+        0x6d => if pp == 1 { sse("punpckhqdq", "Vx,Hx,Wx") } else { None },
+        0x6e => match pp { 0 => leg("movd|movq", "Pq,Ey"), 1 => sse("movd|movq", "Vy,Ey"), _ => None },
+        0x6f => match pp {
+            0 => leg("movq", "Pq,Qq"), 1 => sse("movdqa", "Vx,Wx"), 2 => sse("movdqu", "Vx,Wx"), _ => None,
+        },
+        0x70 => match pp {
+            0 => leg("pshufw", "Pq,Qq,Ib"), 1 => sse("pshufd", "Vx,Wx,Ib"),
+            2 => sse("pshufhw", "Vx,Wx,Ib"), _ => sse("pshuflw", "Vx,Wx,Ib"),
+        },
+        0x71..=0x73 => {
+            let names: [&'static str; 8] = match b {
+                0x71 => ["", "", "psrlw", "", "psraw", "", "psllw", ""],
+                0x72 => ["", "", "psrld", "", "psrad", "", "pslld", ""],
+                _ => ["", "", "psrlq", "psrldq", "", "", "psllq", "pslldq"],
+            };
+            let name = names[reg as usize];
+            if name.is_empty() || is_mem {
+                None
+            } else if pp == 1 {
+                sse(name, "Hx,Ux,Ib")
+            } else if pp == 0 && !name.ends_with("dq") {
+                leg(name, "Nq,Ib")
+            } else {
+                None
+            }
+        }
+        0x74..=0x76 => mmx_or_sse(pp, ["pcmpeqb", "pcmpeqw", "pcmpeqd"][(b - 0x74) as usize]),
+        0x77 => if pp == 0 { if is_vex { vex("vzeroupper", "") } else { gen("emms", "") } } else { None },
+        0x78 => if pp == 0 { gen("vmread", "Ey,Gy") } else { None },
+        0x79 => if pp == 0 { gen("vmwrite", "Gy,Ey") } else { None },
+        0x7c => match pp { 1 => sse("haddpd", "Vpd,Hpd,Wpd"), 3 => sse("haddps", "Vps,Hps,Wps"), _ => None },
+        0x7d => match pp { 1 => sse("hsubpd", "Vpd,Hpd,Wpd"), 3 => sse("hsubps", "Vps,Hps,Wps"), _ => None },
+        0x7e => match pp {
+            0 => leg("movd|movq", "Ey,Pq"), 1 => sse("movd|movq", "Ey,Vy"), 2 => sse("movq", "Vq,Wq"), _ => None,
+        },
+        0x7f => match pp {
+            0 => leg("movq", "Qq,Pq"), 1 => sse("movdqa", "Wx,Vx"), 2 => sse("movdqu", "Wx,Vx"), _ => None,
+        },
+        0x80..=0x8f => Some(op(jcc_name(b), "Jz", F64)),
+        0x90..=0x9f => Some(op(setcc_name(b), "Eb", 0)),
+        0xa0 => Some(op("push", "FS", D64)),
+        0xa1 => Some(op("pop", "FS", D64)),
+        0xa2 => gen("cpuid", ""),
+        0xa3 => gen("bt", "Ev,Gv"),
+        0xa4 => gen("shld", "Ev,Gv,Ib"),
+        0xa5 => gen("shld", "Ev,Gv,CL"),
+        0xa8 => Some(op("push", "GS", D64)),
+        0xa9 => Some(op("pop", "GS", D64)),
+        0xaa => gen("rsm", ""),
+        0xab => gen("bts", "Ev,Gv"),
+        0xac => gen("shrd", "Ev,Gv,Ib"),
+        0xad => gen("shrd", "Ev,Gv,CL"),
+        0xae => {
+            if is_mem {
+                match (pp, reg) {
+                    (0, 0) => gen("fxsave", "M"), (0, 1) => gen("fxrstor", "M"),
+                    (0, 2) => sse("ldmxcsr", "Md"), (0, 3) => sse("stmxcsr", "Md"),
+                    (0, 4) => gen("xsave", "M"), (0, 5) => gen("xrstor", "M"),
+                    (0, 6) => gen("xsaveopt", "M"), (0, 7) => gen("clflush", "Mb"),
+                    (1, 6) => leg("clwb", "Mb"), (1, 7) => leg("clflushopt", "Mb"),
+                    _ => None,
+                }
+            } else {
+                match (pp, reg) {
+                    (0, 5) => gen("lfence", ""), (0, 6) => gen("mfence", ""), (0, 7) => gen("sfence", ""),
+                    (2, 0) => leg("rdfsbase", "Ry"), (2, 1) => leg("rdgsbase", "Ry"),
+                    (2, 2) => leg("wrfsbase", "Ry"), (2, 3) => leg("wrgsbase", "Ry"),
+                    (2, 5) => leg("incsspd|incsspq", "Ry"),
+                    _ => None,
+                }
+            }
+        }
+        0xaf => gen("imul", "Gv,Ev"),
+        0xb0 => gen("cmpxchg", "Eb,Gb"),
+        0xb1 => gen("cmpxchg", "Ev,Gv"),
+        0xb2 => gen("lss", "Gv,Mp"),
+        0xb3 => gen("btr", "Ev,Gv"),
+        0xb4 => gen("lfs", "Gv,Mp"),
+        0xb5 => gen("lgs", "Gv,Mp"),
+        0xb6 => gen("movzx", "Gv,Eb"),
+        0xb7 => gen("movzx", "Gv,Ew"),
+        0xb8 => if pp == 2 { leg("popcnt", "Gv,Ev") } else { None },
+        0xb9 => gen("ud1", "Gv,Ev"),
+        0xba => match reg {
+            4 => gen("bt", "Ev,Ib"), 5 => gen("bts", "Ev,Ib"), 6 => gen("btr", "Ev,Ib"), 7 => gen("btc", "Ev,Ib"),
+            _ => None,
+        },
+        0xbb => gen("btc", "Ev,Gv"),
+        0xbc => if pp == 2 { leg("tzcnt", "Gv,Ev") } else { gen("bsf", "Gv,Ev") },
+        0xbd => if pp == 2 { leg("lzcnt", "Gv,Ev") } else { gen("bsr", "Gv,Ev") },
+        0xbe => gen("movsx", "Gv,Eb"),
+        0xbf => gen("movsx", "Gv,Ew"),
+        0xc0 => gen("xadd", "Eb,Gb"),
+        0xc1 => gen("xadd", "Ev,Gv"),
+        0xc2 => match pp {
+            0 => sse("cmpps", "Vps,Hps,Wps,Ib"), 1 => sse("cmppd", "Vpd,Hpd,Wpd,Ib"),
+            2 => sse("cmpss", "Vss,Hss,Wss,Ib"), _ => sse("cmpsd", "Vsd,Hsd,Wsd,Ib"),
+        },
+        0xc3 => if pp == 0 { gen("movnti", "My,Gy") } else { None },
+        0xc4 => match pp { 0 => leg("pinsrw", "Pq,Ry/Mw,Ib"), 1 => sse("pinsrw", "Vdq,Hdq,Ry/Mw,Ib"), _ => None },
+        0xc5 => match pp { 0 => leg("pextrw", "Gd,Nq,Ib"), 1 => sse("pextrw", "Gd,Udq,Ib"), _ => None },
+        0xc6 => match pp { 0 => sse("shufps", "Vps,Hps,Wps,Ib"), 1 => sse("shufpd", "Vpd,Hpd,Wpd,Ib"), _ => None },
+        0xc7 => match (reg, is_mem, pp) {
+            (1, true, _) => gen("cmpxchg8b|cmpxchg16b", "Mq"),
+            (3, true, 0) => gen("xrstors", "M"),
+            (4, true, 0) => gen("xsavec", "M"),
+            (5, true, 0) => gen("xsaves", "M"),
+            (6, true, 0) => gen("vmptrld", "Mq"),
+            (7, true, 0) => gen("vmptrst", "Mq"),
+            (6, false, _) => gen("rdrand", "Rv"),
+            (7, false, 2) => leg("rdpid", "Ry"),
+            (7, false, _) => gen("rdseed", "Rv"),
+            _ => None,
+        },
+        0xc8..=0xcf => gen("bswap", "Zy"),
+        0xd0 => match pp { 1 => sse("addsubpd", "Vpd,Hpd,Wpd"), 3 => sse("addsubps", "Vps,Hps,Wps"), _ => None },
+        0xd6 => match pp { 1 => sse("movq", "Wq,Vq"), 2 => leg("movq2dq", "Vdq,Nq"), 3 => leg("movdq2q", "Pq,Uq"), _ => None },
+        0xd7 => match pp { 0 => leg("pmovmskb", "Gd,Nq"), 1 => sse("pmovmskb", "Gd,Ux"), _ => None },
+        0xe6 => match pp {
+            1 => sse("cvttpd2dq", "Vh,Wpd"), 2 => sse("cvtdq2pd", "Vx,Wh"), 3 => sse("cvtpd2dq", "Vh,Wpd"), _ => None,
+        },
+        0xe7 => match pp { 0 => leg("movntq", "Mq,Pq"), 1 => sse("movntdq", "Mx,Vx"), _ => None },
+        0xf0 => if pp == 3 { sse("lddqu", "Vx,Mx") } else { None },
+        0xf7 => match pp { 0 => leg("maskmovq", "Pq,Nq"), 1 => sse("maskmovdqu", "Vdq,Udq"), _ => None },
+        0xff => gen("ud0", "Gv,Ev"),
+        0xd1..=0xfe => {
+            let name = [
+                "", "psrlw", "psrld", "psrlq", "paddq", "pmullw", "", "",
+                "psubusb", "psubusw", "pminub", "pand", "paddusb", "paddusw", "pmaxub", "pandn",
+                "pavgb", "psraw", "psrad", "pavgw", "pmulhuw", "pmulhw", "", "",
+                "psubsb", "psubsw", "pminsw", "por", "paddsb", "paddsw", "pmaxsw", "pxor",
+                "", "psllw", "pslld", "psllq", "pmuludq", "pmaddwd", "psadbw", "",
+                "psubb", "psubw", "psubd", "psubq", "paddb", "paddw", "paddd", "",
+            ][(b - 0xd0) as usize];
+            if name.is_empty() {
+                return None;
+            }
+            let shift = matches!(b, 0xd1..=0xd3 | 0xe1 | 0xe2 | 0xf1..=0xf3);
+            match pp {
+                0 => leg(name, "Pq,Qq"),
+                1 if shift => sse(name, "Vx,Hx,Wdq"),
+                1 => sse(name, "Vx,Hx,Wx"),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn cmov_name(b: u8) -> &'static str {
+    ["cmovo", "cmovno", "cmovb", "cmovae", "cmove", "cmovne", "cmovbe", "cmova",
+     "cmovs", "cmovns", "cmovp", "cmovnp", "cmovl", "cmovge", "cmovle", "cmovg"][(b & 0xf) as usize]
+}
+
+fn jcc_name(b: u8) -> &'static str {
+    ["jo", "jno", "jb", "jae", "je", "jne", "jbe", "ja",
+     "js", "jns", "jp", "jnp", "jl", "jge", "jle", "jg"][(b & 0xf) as usize]
+}
+
+fn setcc_name(b: u8) -> &'static str {
+    ["seto", "setno", "setb", "setae", "sete", "setne", "setbe", "seta",
+     "sets", "setns", "setp", "setnp", "setl", "setge", "setle", "setg"][(b & 0xf) as usize]
+}
+
+fn ps_pd_ss_sd(pp: u8, names: [&'static str; 4], unary: bool) -> Option<Op> {
+    let args = match (pp, unary) {
+        (0, false) => "Vps,Hps,Wps", (1, false) => "Vpd,Hpd,Wpd",
+        (0, true) => "Vps,Wps", (1, true) => "Vpd,Wpd",
+        (2, _) => "Vss,Hss,Wss", _ => "Vsd,Hsd,Wsd",
+    };
+    Some(op(names[pp as usize], args, SSE))
+}
+
+fn mmx_or_sse(pp: u8, name: &'static str) -> Option<Op> {
+    match pp {
+        0 => leg(name, "Pq,Qq"),
+        1 => sse(name, "Vx,Hx,Wx"),
+        _ => None,
+    }
+}
+
+/// Three-byte opcode map 0F 38.
+fn lookup_0f38(b: u8, pp: u8, reg: u8, is_vex: bool, w: bool) -> Option<Op> {
+    let ssse3 = |name: &'static str| -> Option<Op> {
+        match pp { 0 => leg(name, "Pq,Qq"), 1 => sse(name, "Vx,Hx,Wx"), _ => None }
+    };
+    let p66 = |name: &'static str, args: &'static str| -> Option<Op> {
+        if pp == 1 { sse(name, args) } else { None }
+    };
+    let v66 = |name: &'static str, args: &'static str| -> Option<Op> {
+        if pp == 1 && is_vex { vex(name, args) } else { None }
+    };
+    match b {
+        0x00 => ssse3("pshufb"), 0x01 => ssse3("phaddw"), 0x02 => ssse3("phaddd"), 0x03 => ssse3("phaddsw"),
+        0x04 => ssse3("pmaddubsw"), 0x05 => ssse3("phsubw"), 0x06 => ssse3("phsubd"), 0x07 => ssse3("phsubsw"),
+        0x08 => ssse3("psignb"), 0x09 => ssse3("psignw"), 0x0a => ssse3("psignd"), 0x0b => ssse3("pmulhrsw"),
+        0x0c => v66("vpermilps", "Vx,Hx,Wx"), 0x0d => v66("vpermilpd", "Vx,Hx,Wx"),
+        0x0e => v66("vtestps", "Vx,Wx"), 0x0f => v66("vtestpd", "Vx,Wx"),
+        0x10 if !is_vex => p66("pblendvb", "Vdq,Wdq,XMM0"),
+        0x13 => v66("vcvtph2ps", "Vx,Wh"),
+        0x14 if !is_vex => p66("blendvps", "Vdq,Wdq,XMM0"),
+        0x15 if !is_vex => p66("blendvpd", "Vdq,Wdq,XMM0"),
+        0x16 => v66("vpermps", "Vqq,Hqq,Wqq"),
+        0x17 => p66("ptest", "Vx,Wx"),
+        0x18 => v66("vbroadcastss", "Vx,Wd"),
+        0x19 => v66("vbroadcastsd", "Vqq,Wq"),
+        0x1a => v66("vbroadcastf128", "Vqq,Mdq"),
+        0x1c => match pp { 0 => leg("pabsb", "Pq,Qq"), 1 => sse("pabsb", "Vx,Wx"), _ => None },
+        0x1d => match pp { 0 => leg("pabsw", "Pq,Qq"), 1 => sse("pabsw", "Vx,Wx"), _ => None },
+        0x1e => match pp { 0 => leg("pabsd", "Pq,Qq"), 1 => sse("pabsd", "Vx,Wx"), _ => None },
+        0x20 => p66("pmovsxbw", "Vx,Wh"), 0x21 => p66("pmovsxbd", "Vx,Wf"), 0x22 => p66("pmovsxbq", "Vx,We"),
+        0x23 => p66("pmovsxwd", "Vx,Wh"), 0x24 => p66("pmovsxwq", "Vx,Wf"), 0x25 => p66("pmovsxdq", "Vx,Wh"),
+        0x28 => p66("pmuldq", "Vx,Hx,Wx"), 0x29 => p66("pcmpeqq", "Vx,Hx,Wx"),
+        0x2a => p66("movntdqa", "Vx,Mx"), 0x2b => p66("packusdw", "Vx,Hx,Wx"),
+        0x2c => v66("vmaskmovps", "Vx,Hx,Mx"), 0x2d => v66("vmaskmovpd", "Vx,Hx,Mx"),
+        0x2e => v66("vmaskmovps", "Mx,Hx,Vx"), 0x2f => v66("vmaskmovpd", "Mx,Hx,Vx"),
+        0x30 => p66("pmovzxbw", "Vx,Wh"), 0x31 => p66("pmovzxbd", "Vx,Wf"), 0x32 => p66("pmovzxbq", "Vx,We"),
+        0x33 => p66("pmovzxwd", "Vx,Wh"), 0x34 => p66("pmovzxwq", "Vx,Wf"), 0x35 => p66("pmovzxdq", "Vx,Wh"),
+        0x36 => v66("vpermd", "Vqq,Hqq,Wqq"), 0x37 => p66("pcmpgtq", "Vx,Hx,Wx"),
+        0x38 => p66("pminsb", "Vx,Hx,Wx"), 0x39 => p66("pminsd", "Vx,Hx,Wx"),
+        0x3a => p66("pminuw", "Vx,Hx,Wx"), 0x3b => p66("pminud", "Vx,Hx,Wx"),
+        0x3c => p66("pmaxsb", "Vx,Hx,Wx"), 0x3d => p66("pmaxsd", "Vx,Hx,Wx"),
+        0x3e => p66("pmaxuw", "Vx,Hx,Wx"), 0x3f => p66("pmaxud", "Vx,Hx,Wx"),
+        0x40 => p66("pmulld", "Vx,Hx,Wx"), 0x41 => p66("phminposuw", "Vdq,Wdq"),
+        0x45 => v66("vpsrlvd|vpsrlvq", "Vx,Hx,Wx"), 0x46 => v66("vpsravd|vpsravq", "Vx,Hx,Wx"),
+        0x47 => v66("vpsllvd|vpsllvq", "Vx,Hx,Wx"),
+        0x58 => v66("vpbroadcastd", "Vx,Wd"), 0x59 => v66("vpbroadcastq", "Vx,Wq"),
+        0x5a => v66("vbroadcasti128", "Vqq,Mdq"),
+        0x78 => v66("vpbroadcastb", "Vx,Wb"), 0x79 => v66("vpbroadcastw", "Vx,Ww"),
+        0x8c => v66("vpmaskmovd|vpmaskmovq", "Vx,Hx,Mx"),
+        0x8e => v66("vpmaskmovd|vpmaskmovq", "Mx,Hx,Vx"),
+        0x90 => v66("vpgatherdd|vpgatherdq", "Vx,M,Hx"), 0x91 => v66("vpgatherqd|vpgatherqq", "Vx,M,Hx"),
+        0x92 => v66("vgatherdps|vgatherdpd", "Vx,M,Hx"), 0x93 => v66("vgatherqps|vgatherqpd", "Vx,M,Hx"),
+        0x96..=0x9f | 0xa6..=0xaf | 0xb6..=0xbf if pp == 1 && is_vex => fma_op(b, w),
+        0x26 | 0x27 if is_vex => None,  // EVEX vptestm/vptestnm only
+        0xc8..=0xcd if pp == 0 && !is_vex => {
+            let name = ["sha1nexte", "sha1msg1", "sha1msg2", "sha256rnds2", "sha256msg1", "sha256msg2"][(b - 0xc8) as usize];
+            if b == 0xcb { gen(name, "Vdq,Wdq,XMM0") } else { gen(name, "Vdq,Wdq") }
+        }
+        0xdb => p66("aesimc", "Vdq,Wdq"),
+        0xdc => p66("aesenc", "Vx,Hx,Wx"), 0xdd => p66("aesenclast", "Vx,Hx,Wx"),
+        0xde => p66("aesdec", "Vx,Hx,Wx"), 0xdf => p66("aesdeclast", "Vx,Hx,Wx"),
+        0xf0 if !is_vex => match pp { 0 | 1 => gen("movbe", "Gv,Mv"), 3 => leg("crc32", "Gy,Eb"), _ => None },
+        0xf1 if !is_vex => match pp { 0 | 1 => gen("movbe", "Mv,Gv"), 3 => leg("crc32", "Gy,Ev"), _ => None },
+        0xf2 if is_vex && pp == 0 => Some(op("andn", "Gy,By,Ey", VEX)),
+        0xf3 if is_vex && pp == 0 => match reg {
+            1 => Some(op("blsr", "By,Ey", VEX)), 2 => Some(op("blsmsk", "By,Ey", VEX)),
+            3 => Some(op("blsi", "By,Ey", VEX)), _ => None,
+        },
+        0xf5 if is_vex => match pp {
+            0 => Some(op("bzhi", "Gy,Ey,By", VEX)), 2 => Some(op("pext", "Gy,By,Ey", VEX)),
+            3 => Some(op("pdep", "Gy,By,Ey", VEX)), _ => None,
+        },
+        0xf6 => match (pp, is_vex) {
+            (3, true) => Some(op("mulx", "Gy,By,Ey", VEX)),
+            (1, false) => leg("adcx", "Gy,Ey"),
+            (2, false) => leg("adox", "Gy,Ey"),
+            _ => None,
+        },
+        0xf7 if is_vex => match pp {
+            0 => Some(op("bextr", "Gy,Ey,By", VEX)), 1 => Some(op("shlx", "Gy,Ey,By", VEX)),
+            2 => Some(op("sarx", "Gy,Ey,By", VEX)), _ => Some(op("shrx", "Gy,Ey,By", VEX)),
+        },
+        _ => None,
+    }
+}
+
+fn fma_op(b: u8, w: bool) -> Option<Op> {
+    let name = match b {
+        0x96 => "vfmaddsub132ps|vfmaddsub132pd",
+        0x97 => "vfmsubadd132ps|vfmsubadd132pd",
+        0x98 => "vfmadd132ps|vfmadd132pd",
+        0x99 => "vfmadd132ss|vfmadd132sd",
+        0x9a => "vfmsub132ps|vfmsub132pd",
+        0x9b => "vfmsub132ss|vfmsub132sd",
+        0x9c => "vfnmadd132ps|vfnmadd132pd",
+        0x9d => "vfnmadd132ss|vfnmadd132sd",
+        0x9e => "vfnmsub132ps|vfnmsub132pd",
+        0x9f => "vfnmsub132ss|vfnmsub132sd",
+        0xa6 => "vfmaddsub213ps|vfmaddsub213pd",
+        0xa7 => "vfmsubadd213ps|vfmsubadd213pd",
+        0xa8 => "vfmadd213ps|vfmadd213pd",
+        0xa9 => "vfmadd213ss|vfmadd213sd",
+        0xaa => "vfmsub213ps|vfmsub213pd",
+        0xab => "vfmsub213ss|vfmsub213sd",
+        0xac => "vfnmadd213ps|vfnmadd213pd",
+        0xad => "vfnmadd213ss|vfnmadd213sd",
+        0xae => "vfnmsub213ps|vfnmsub213pd",
+        0xaf => "vfnmsub213ss|vfnmsub213sd",
+        0xb6 => "vfmaddsub231ps|vfmaddsub231pd",
+        0xb7 => "vfmsubadd231ps|vfmsubadd231pd",
+        0xb8 => "vfmadd231ps|vfmadd231pd",
+        0xb9 => "vfmadd231ss|vfmadd231sd",
+        0xba => "vfmsub231ps|vfmsub231pd",
+        0xbb => "vfmsub231ss|vfmsub231sd",
+        0xbc => "vfnmadd231ps|vfnmadd231pd",
+        0xbd => "vfnmadd231ss|vfnmadd231sd",
+        0xbe => "vfnmsub231ps|vfnmsub231pd",
+        0xbf => "vfnmsub231ss|vfnmsub231sd",
+        _ => return None,
+    };
+    let args = match (b & 1 == 1 && b & 0xf >= 9, w) {
+        (false, _) => "Vx,Hx,Wx",
+        (true, false) => "Vss,Hss,Wss",
+        (true, true) => "Vsd,Hsd,Wsd",
+    };
+    Some(op(name, args, VEX | SSE))
+}
+
+/// Three-byte opcode map 0F 3A.
+fn lookup_0f3a(b: u8, pp: u8, is_vex: bool) -> Option<Op> {
+    let p66 = |name: &'static str, args: &'static str| -> Option<Op> {
+        if pp == 1 { sse(name, args) } else { None }
+    };
+    let v66 = |name: &'static str, args: &'static str| -> Option<Op> {
+        if pp == 1 && is_vex { vex(name, args) } else { None }
+    };
+    match b {
+        0x00 => v66("vpermq", "Vqq,Wqq,Ib"), 0x01 => v66("vpermpd", "Vqq,Wqq,Ib"),
+        0x02 => v66("vpblendd", "Vx,Hx,Wx,Ib"),
+        0x04 => v66("vpermilps", "Vx,Wx,Ib"), 0x05 => v66("vpermilpd", "Vx,Wx,Ib"),
+        0x06 => v66("vperm2f128", "Vqq,Hqq,Wqq,Ib"),
+        0x08 => p66("roundps", "Vx,Wx,Ib"), 0x09 => p66("roundpd", "Vx,Wx,Ib"),
+        0x0a => p66("roundss", "Vss,Hss,Wss,Ib"), 0x0b => p66("roundsd", "Vsd,Hsd,Wsd,Ib"),
+        0x0c => p66("blendps", "Vx,Hx,Wx,Ib"), 0x0d => p66("blendpd", "Vx,Hx,Wx,Ib"),
+        0x0e => p66("pblendw", "Vx,Hx,Wx,Ib"),
+        0x0f => match pp { 0 => leg("palignr", "Pq,Qq,Ib"), 1 => sse("palignr", "Vx,Hx,Wx,Ib"), _ => None },
+        0x14 => p66("pextrb", "Rd/Mb,Vdq,Ib"), 0x15 => p66("pextrw", "Rd/Mw,Vdq,Ib"),
+        0x16 => p66("pextrd|pextrq", "Ey,Vdq,Ib"), 0x17 => p66("extractps", "Ed,Vdq,Ib"),
+        0x18 => v66("vinsertf128", "Vqq,Hqq,Wdq,Ib"), 0x19 => v66("vextractf128", "Wdq,Vqq,Ib"),
+        0x1d => v66("vcvtps2ph", "Wh,Vx,Ib"),
+        0x20 => p66("pinsrb", "Vdq,Hdq,Rd/Mb,Ib"), 0x21 => p66("insertps", "Vdq,Hdq,Udq/Md,Ib"),
+        0x22 => p66("pinsrd|pinsrq", "Vdq,Hdq,Ey,Ib"),
+        0x38 => v66("vinserti128", "Vqq,Hqq,Wdq,Ib"), 0x39 => v66("vextracti128", "Wdq,Vqq,Ib"),
+        0x40 => p66("dpps", "Vx,Hx,Wx,Ib"), 0x41 => p66("dppd", "Vdq,Hdq,Wdq,Ib"),
+        0x42 => p66("mpsadbw", "Vx,Hx,Wx,Ib"), 0x44 => p66("pclmulqdq", "Vdq,Hdq,Wdq,Ib"),
+        0x46 => v66("vperm2i128", "Vqq,Hqq,Wqq,Ib"),
+        0x4a => v66("vblendvps", "Vx,Hx,Wx,Lx"), 0x4b => v66("vblendvpd", "Vx,Hx,Wx,Lx"),
+        0x4c => v66("vpblendvb", "Vx,Hx,Wx,Lx"),
+        0x60 => p66("pcmpestrm", "Vdq,Wdq,Ib"), 0x61 => p66("pcmpestri", "Vdq,Wdq,Ib"),
+        0x62 => p66("pcmpistrm", "Vdq,Wdq,Ib"), 0x63 => p66("pcmpistri", "Vdq,Wdq,Ib"),
+        0xcc if pp == 0 && !is_vex => gen("sha1rnds4", "Vdq,Wdq,Ib"),
+        0xdf => p66("aeskeygenassist", "Vdq,Wdq,Ib"),
+        0xf0 if is_vex && pp == 3 => Some(op("rorx", "Gy,Ey,Ib", VEX)),
+        0x30 if is_vex && pp == 1 => Some(op("kshiftrb|kshiftrw", "KGk,KRk,Ib", VEX)),
+        0x31 if is_vex && pp == 1 => Some(op("kshiftrd|kshiftrq", "KGk,KRk,Ib", VEX)),
+        0x32 if is_vex && pp == 1 => Some(op("kshiftlb|kshiftlw", "KGk,KRk,Ib", VEX)),
+        0x33 if is_vex && pp == 1 => Some(op("kshiftld|kshiftlq", "KGk,KRk,Ib", VEX)),
+        _ => None,
+    }
+}
+
+/// Opcode mask register instructions (VEX 0F 41-4B, 90-93, 98-99).
+fn lookup_kmask(b: u8, pp: u8, l: bool) -> Option<Op> {
+    // Names are "W0|W1"; pp selects between the word/quad and byte/dword forms.
+    let (name, args) = match (b, pp, l) {
+        (0x41, 0, true) => ("kandw|kandq", "KGk,KVk,KEk"), (0x41, 1, true) => ("kandb|kandd", "KGk,KVk,KEk"),
+        (0x42, 0, true) => ("kandnw|kandnq", "KGk,KVk,KEk"), (0x42, 1, true) => ("kandnb|kandnd", "KGk,KVk,KEk"),
+        (0x44, 0, false) => ("knotw|knotq", "KGk,KEk"), (0x44, 1, false) => ("knotb|knotd", "KGk,KEk"),
+        (0x45, 0, true) => ("korw|korq", "KGk,KVk,KEk"), (0x45, 1, true) => ("korb|kord", "KGk,KVk,KEk"),
+        (0x46, 0, true) => ("kxnorw|kxnorq", "KGk,KVk,KEk"), (0x46, 1, true) => ("kxnorb|kxnord", "KGk,KVk,KEk"),
+        (0x47, 0, true) => ("kxorw|kxorq", "KGk,KVk,KEk"), (0x47, 1, true) => ("kxorb|kxord", "KGk,KVk,KEk"),
+        (0x4a, 0, true) => ("kaddw|kaddq", "KGk,KVk,KEk"), (0x4a, 1, true) => ("kaddb|kaddd", "KGk,KVk,KEk"),
+        (0x4b, 0, true) => ("kunpckwd|kunpckdq", "KGk,KVk,KEk"), (0x4b, 1, true) => ("kunpckbw|", "KGk,KVk,KEk"),
+        (0x90, 0, false) => ("kmovw|kmovq", "KGk,KEk"), (0x90, 1, false) => ("kmovb|kmovd", "KGk,KEk"),
+        (0x91, 0, false) => ("kmovw|kmovq", "KEk,KGk"), (0x91, 1, false) => ("kmovb|kmovd", "KEk,KGk"),
+        (0x92, 0, false) => ("kmovw|", "KGk,Rd"), (0x92, 1, false) => ("kmovb|", "KGk,Rd"),
+        (0x92, 3, false) => ("kmovd|kmovq", "KGk,Ry"),
+        (0x93, 0, false) => ("kmovw|", "Gd,KRk"), (0x93, 1, false) => ("kmovb|", "Gd,KRk"),
+        (0x93, 3, false) => ("kmovd|kmovq", "Gy,KRk"),
+        (0x98, 0, false) => ("kortestw|kortestq", "KGk,KEk"), (0x98, 1, false) => ("kortestb|kortestd", "KGk,KEk"),
+        (0x99, 0, false) => ("ktestw|ktestq", "KGk,KEk"), (0x99, 1, false) => ("ktestb|ktestd", "KGk,KEk"),
+        _ => return None,
+    };
+    Some(op(name, args, VEX))
+}
+
+/// EVEX encodings whose mnemonic differs from the VEX form of the same opcode.
+fn evex_override(map: u8, b: u8, pp: u8, w: bool) -> Option<Op> {
+    let dq = |d: &'static str, q: &'static str| if w { q } else { d };
+    let name = match (map, pp, b) {
+        (1, 1, 0x6f) | (1, 1, 0x7f) => dq("vmovdqa32", "vmovdqa64"),
+        (1, 2, 0x6f) | (1, 2, 0x7f) => dq("vmovdqu32", "vmovdqu64"),
+        (1, 3, 0x6f) | (1, 3, 0x7f) => dq("vmovdqu8", "vmovdqu16"),
+        (1, 1, 0xdb) => dq("vpandd", "vpandq"),
+        (1, 1, 0xdf) => dq("vpandnd", "vpandnq"),
+        (1, 1, 0xeb) => dq("vpord", "vporq"),
+        (1, 1, 0xef) => dq("vpxord", "vpxorq"),
+        (1, 1, 0x64) => "vpcmpgtb", (1, 1, 0x65) => "vpcmpgtw", (1, 1, 0x66) => "vpcmpgtd",
+        (1, 1, 0x74) => "vpcmpeqb", (1, 1, 0x75) => "vpcmpeqw", (1, 1, 0x76) => "vpcmpeqd",
+        (2, 1, 0x29) => "vpcmpeqq", (2, 1, 0x37) => "vpcmpgtq",
+        (2, 1, 0x64) => dq("vpblendmd", "vpblendmq"),
+        (2, 1, 0x65) => dq("vblendmps", "vblendmpd"),
+        (2, 1, 0x75) => dq("vpermi2b", "vpermi2w"),
+        (2, 1, 0x76) => dq("vpermi2d", "vpermi2q"),
+        (2, 1, 0x77) => dq("vpermi2ps", "vpermi2pd"),
+        (2, 1, 0x7d) => dq("vpermt2b", "vpermt2w"),
+        (2, 1, 0x7e) => dq("vpermt2d", "vpermt2q"),
+        (2, 1, 0x7f) => dq("vpermt2ps", "vpermt2pd"),
+        (2, 1, 0x7a) => "vpbroadcastb", (2, 1, 0x7b) => "vpbroadcastw",
+        (2, 1, 0x7c) => dq("vpbroadcastd", "vpbroadcastq"),
+        (2, 1, 0x36) => dq("vpermd", "vpermq"),
+        (2, 1, 0x16) => dq("vpermps", "vpermpd"),
+        (2, 1, 0x59) => dq("vbroadcasti32x2", "vpbroadcastq"),
+        (3, 1, 0x25) => dq("vpternlogd", "vpternlogq"),
+        (3, 1, 0x1e) => dq("vpcmpud", "vpcmpuq"), (3, 1, 0x1f) => dq("vpcmpd", "vpcmpq"),
+        (3, 1, 0x3e) => dq("vpcmpub", "vpcmpuw"), (3, 1, 0x3f) => dq("vpcmpb", "vpcmpw"),
+        (2, 1, 0x26) => dq("vptestmb", "vptestmw"), (2, 2, 0x26) => dq("vptestnmb", "vptestnmw"),
+        (2, 1, 0x27) => dq("vptestmd", "vptestmq"), (2, 2, 0x27) => dq("vptestnmd", "vptestnmq"),
+        (3, 1, 0x18) => dq("vinsertf32x4", "vinsertf64x2"), (3, 1, 0x19) => dq("vextractf32x4", "vextractf64x2"),
+        (3, 1, 0x1a) => dq("vinsertf32x8", "vinsertf64x4"), (3, 1, 0x1b) => dq("vextractf32x8", "vextractf64x4"),
+        (3, 1, 0x38) => dq("vinserti32x4", "vinserti64x2"), (3, 1, 0x39) => dq("vextracti32x4", "vextracti64x2"),
+        (3, 1, 0x3a) => dq("vinserti32x8", "vinserti64x4"), (3, 1, 0x3b) => dq("vextracti32x8", "vextracti64x4"),
+        (1, 0, 0xc2) => "vcmpps", (1, 1, 0xc2) => "vcmppd", (1, 2, 0xc2) => "vcmpss", (1, _, 0xc2) => "vcmpsd",
+        _ => return None,
+    };
+    let args = match (map, b) {
+        (1, 0x64..=0x66) | (1, 0x74..=0x76) | (2, 0x29) | (2, 0x37) | (2, 0x26) | (2, 0x27) => "KGk,Hx,Wx",
+        (3, 0x1e) | (3, 0x1f) | (3, 0x3e) | (3, 0x3f) => "KGk,Hx,Wx,Ib",
+        (1, 0x7f) => "Wx,Vx",
+        (2, 0x7a..=0x7c) => "Vx,Ry",
+        (2, 0x59) => "Vx,Wq",
+        (3, 0x25) => "Vx,Hx,Wx,Ib",
+        (1, 0xc2) => match pp { 0 => "KGk,Hps,Wps,Ib", 1 => "KGk,Hpd,Wpd,Ib", 2 => "KGk,Hss,Wss,Ib", _ => "KGk,Hsd,Wsd,Ib" },
+        (3, 0x18) | (3, 0x38) => "Vx,Hx,Wdq,Ib",
+        (3, 0x1a) | (3, 0x3a) => "Vx,Hx,Wqq,Ib",
+        (3, 0x19) | (3, 0x39) => "Wdq,Vx,Ib",
+        (3, 0x1b) | (3, 0x3b) => "Wqq,Vx,Ib",
+        (1, 0x6f) => "Vx,Wx",
+        _ => "Vx,Hx,Wx",
+    };
+    Some(op(name, args, VEX | SSE))
+}
+
+const CMP_PREDICATES: [&str; 32] = [
+    "eq", "lt", "le", "unord", "neq", "nlt", "nle", "ord",
+    "eq_uq", "nge", "ngt", "false", "neq_oq", "ge", "gt", "true",
+    "eq_os", "lt_oq", "le_oq", "unord_s", "neq_us", "nlt_uq", "nle_uq", "ord_s",
+    "eq_us", "nge_uq", "ngt_uq", "false_os", "neq_os", "ge_oq", "gt_oq", "true_us",
+];
+
+const X87_MEM: [[&str; 8]; 8] = [
+    ["fadd", "fmul", "fcom", "fcomp", "fsub", "fsubr", "fdiv", "fdivr"],
+    ["fld", "", "fst", "fstp", "fldenv", "fldcw", "fnstenv", "fnstcw"],
+    ["fiadd", "fimul", "ficom", "ficomp", "fisub", "fisubr", "fidiv", "fidivr"],
+    ["fild", "fisttp", "fist", "fistp", "", "fld", "", "fstp"],
+    ["fadd", "fmul", "fcom", "fcomp", "fsub", "fsubr", "fdiv", "fdivr"],
+    ["fld", "fisttp", "fst", "fstp", "frstor", "", "fnsave", "fnstsw"],
+    ["fiadd", "fimul", "ficom", "ficomp", "fisub", "fisubr", "fidiv", "fidivr"],
+    ["fild", "fisttp", "fist", "fistp", "fbld", "fild", "fbstp", "fistp"],
+];
+
+const X87_MEM_SIZE: [[u16; 8]; 8] = [
+    [4, 4, 4, 4, 4, 4, 4, 4],
+    [4, 0, 4, 4, 0, 2, 0, 2],
+    [4, 4, 4, 4, 4, 4, 4, 4],
+    [4, 4, 4, 4, 0, 10, 0, 10],
+    [8, 8, 8, 8, 8, 8, 8, 8],
+    [8, 8, 8, 8, 0, 0, 0, 2],
+    [2, 2, 2, 2, 2, 2, 2, 2],
+    [2, 2, 2, 2, 10, 8, 10, 8],
+];
+
+const D9_REG: [&str; 32] = [
+    "fchs", "fabs", "", "", "ftst", "fxam", "", "",
+    "fld1", "fldl2t", "fldl2e", "fldpi", "fldlg2", "fldln2", "fldz", "",
+    "f2xm1", "fyl2x", "fptan", "fpatan", "fxtract", "fprem1", "fdecstp", "fincstp",
+    "fprem", "fyl2xp1", "fsqrt", "fsincos", "frndint", "fscale", "fsin", "fcos",
+];
+
+#[derive(Clone, Debug)]
+struct Mem {
+    size:       u16,
+    seg:        Option<&'static str>,
+    base:       Option<String>,
+    index:      Option<String>,
+    scale:      u8,
+    disp:       i64,
+    has_disp:   bool,
+    disp8:      bool,   // EVEX compressed displacement, scaled by the access size
+    rip:        bool,
+    absolute:   bool,   // no base and no index: a plain address
+    bcst:       u16,    // EVEX broadcast element count
+}
+
+#[derive(Clone, Debug)]
+enum Operand {
+    Reg(String),
+    Imm(u64),
+    Rel(u64),
+    Mem(Mem),
+    Far(u16, u32),
+    One,    // implicit shift count
+}
+
+struct Dec<'a> {
+    bytes:      &'a [u8],
+    pos:        usize,
+    addr:       u64,
+    mode64:     bool,
+    opsize:     bool,
+    data16:     usize,  // redundant 0x66 prefixes
+    adsize:     bool,
+    lock:       bool,
+    rep:        u8,
+    seg:        Option<&'static str>,
+    seg_used:   bool,
+    rex:        u8,
+    rex_present: bool,
+    vex:        bool,
+    evex:       bool,
+    map:        u8,
+    pp:         u8,
+    vl:         u16,
+    vex_w:      bool,
+    vvvv:       u8,
+    evex_z:     bool,
+    evex_aaa:   u8,
+    evex_b:     bool,
+    evex_rr:    bool,
+    evex_vv:    bool,
+    modrm:      Option<u8>,
+    mem:        Option<Mem>,
+    flags:      u16,
+    pp_used:    bool,
+    vsib_qword_index: bool,
+    evex_rc:    u8,
+    sae:        Option<&'static str>,
+}
+
+impl<'a> Dec<'a> {
+    fn u8(&mut self) -> Option<u8> {
+        if self.pos >= MAX_INSN_BYTES {
+            return None;
+        }
+        let b = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn imm(&mut self, size: usize) -> Option<u64> {
+        let mut v = 0u64;
+        for i in 0..size {
+            v |= (self.u8()? as u64) << (8 * i);
+        }
+        Some(v)
+    }
+
+    fn simm(&mut self, size: usize) -> Option<i64> {
+        let v = self.imm(size)?;
+        let shift = 64 - 8 * size as u32;
+        Some(((v << shift) as i64) >> shift)
+    }
+
+    fn rex_w(&self) -> bool {
+        if self.vex { self.vex_w } else { self.rex & 8 != 0 }
+    }
+
+    fn rex_r(&self) -> u8 {
+        (self.rex >> 2) & 1
+    }
+
+    fn rex_x(&self) -> u8 {
+        (self.rex >> 1) & 1
+    }
+
+    fn rex_b(&self) -> u8 {
+        self.rex & 1
+    }
+
+    /// Effective operand size in bits.
+    fn osize(&self) -> u16 {
+        let opsize = self.opsize && !(self.pp_used && self.pp == 1);
+        if self.mode64 {
+            if self.flags & F64 != 0 {
+                return 64;
+            }
+            if self.rex_w() {
+                return 64;
+            }
+            if self.flags & D64 != 0 {
+                return if opsize { 16 } else { 64 };
+            }
+        }
+        if opsize { 16 } else { 32 }
+    }
+
+    fn asize(&self) -> u16 {
+        match (self.mode64, self.adsize) {
+            (true, false) => 64,
+            (true, true) => 32,
+            (false, false) => 32,
+            (false, true) => 16,
+        }
+    }
+
+    fn gpr(&self, index: u8, size: u16) -> String {
+        let i = (index & 0xf) as usize;
+        match size {
+            8 => if self.rex_present || i >= 8 { REG8[i].to_string() } else { REG8_LEGACY[i].to_string() },
+            16 => REG16[i].to_string(),
+            32 => REG32[i].to_string(),
+            _ => REG64[i].to_string(),
+        }
+    }
+
+    fn vreg(&self, index: u8, bits: u16) -> String {
+        match bits {
+            512 => format!("zmm{}", index),
+            256 => format!("ymm{}", index),
+            _ => format!("xmm{}", index),
+        }
+    }
+
+    fn modrm(&mut self) -> Option<u8> {
+        if let Some(m) = self.modrm {
+            return Some(m);
+        }
+        let m = self.u8()?;
+        self.modrm = Some(m);
+        if m >> 6 != 3 {
+            self.mem = Some(self.parse_mem(m)?);
+        }
+        Some(m)
+    }
+
+    fn parse_mem(&mut self, m: u8) -> Option<Mem> {
+        let md = m >> 6;
+        let rm = m & 7;
+        let mut mem = Mem {
+            size: 0, seg: self.mem_seg(), base: None, index: None, scale: 1, disp: 0,
+            has_disp: false, disp8: false, rip: false, absolute: false, bcst: 0,
+        };
+        if self.asize() == 16 {
+            let (base, index) = match rm {
+                0 => (Some("bx"), Some("si")), 1 => (Some("bx"), Some("di")),
+                2 => (Some("bp"), Some("si")), 3 => (Some("bp"), Some("di")),
+                4 => (Some("si"), None), 5 => (Some("di"), None),
+                6 => (Some("bp"), None), _ => (Some("bx"), None),
+            };
+            if md == 0 && rm == 6 {
+                mem.disp = self.simm(2)? & 0xffff;
+                mem.has_disp = true;
+                mem.absolute = true;
+            } else {
+                mem.base = base.map(|s| s.to_string());
+                mem.index = index.map(|s| s.to_string());
+                mem.scale = 0;  // 16-bit addressing has no scale
+                match md {
+                    1 => { mem.disp = self.simm(1)?; mem.has_disp = true; mem.disp8 = true; }
+                    2 => { mem.disp = self.simm(2)?; mem.has_disp = true; }
+                    _ => {}
+                }
+            }
+            return Some(mem);
+        }
+
+        let areg = |d: &Dec, i: u8| -> String {
+            if d.asize() == 64 { REG64[i as usize].to_string() } else { REG32[i as usize].to_string() }
+        };
+        if rm == 4 {
+            let sib = self.u8()?;
+            let scale = 1u8 << (sib >> 6);
+            let index = ((sib >> 3) & 7) | (self.rex_x() << 3);
+            let base_field = sib & 7;
+            mem.scale = scale;
+            if self.flags & VSIB != 0 {
+                let vidx = index | if self.evex && !self.evex_vv { 16 } else { 0 };
+                mem.index = Some(self.vreg(vidx, self.vsib_index_bits()));
+            } else if index != 4 {
+                mem.index = Some(areg(self, index));
+            } else if sib >> 6 != 0 || (base_field != 4 && !(base_field == 5 && md == 0)) || (!self.mode64 && base_field == 5 && md == 0) {
+                // objdump spells out an explicit "no index" SIB encoding as riz/eiz.
+                mem.index = Some(if self.asize() == 64 { "riz" } else { "eiz" }.to_string());
+            }
+            if base_field == 5 && md == 0 {
+                mem.disp = self.simm(4)?;
+                mem.has_disp = true;
+                if mem.index.is_none() {
+                    mem.absolute = true;
+                }
+            } else {
+                mem.base = Some(areg(self, base_field | (self.rex_b() << 3)));
+            }
+        } else if rm == 5 && md == 0 {
+            mem.disp = self.simm(4)?;
+            mem.has_disp = true;
+            if self.mode64 {
+                mem.rip = true;
+                mem.base = Some(if self.asize() == 64 { "rip".to_string() } else { "eip".to_string() });
+            } else {
+                mem.absolute = true;
+            }
+        } else {
+            mem.base = Some(areg(self, rm | (self.rex_b() << 3)));
+        }
+        match md {
+            1 => { mem.disp = self.simm(1)?; mem.has_disp = true; mem.disp8 = true; }
+            2 => { mem.disp = self.simm(4)?; mem.has_disp = true; }
+            _ => {}
+        }
+        if self.asize() == 32 && mem.absolute {
+            mem.disp &= 0xffff_ffff;
+        }
+        Some(mem)
+    }
+
+    /// Gathers with dword indices and qword elements use a half-width index register.
+    fn vsib_index_bits(&self) -> u16 {
+        if !self.vsib_qword_index && self.vex_w { (self.vl / 2).max(128) } else { self.vl }
+    }
+
+    /// Segment override applied to memory operands. In 64-bit mode only fs and gs have an effect.
+    fn mem_seg(&mut self) -> Option<&'static str> {
+        let seg = match self.seg {
+            Some(seg) if self.mode64 && seg != "fs" && seg != "gs" => None,
+            seg => seg,
+        };
+        if seg.is_some() {
+            self.seg_used = true;
+        }
+        seg
+    }
+
+    fn is_reg_form(&self) -> bool {
+        self.modrm.map(|m| m >> 6 == 3).unwrap_or(false)
+    }
+
+    /// Returns the memory operand sized to `size` bytes (`elem` is the broadcast element size).
+    fn mem_operand(&self, size: u16, elem: u16) -> Option<Operand> {
+        let mut mem = self.mem.clone()?;
+        mem.size = size;
+        if self.evex && self.evex_b && elem != 0 && size > elem {
+            mem.bcst = size / elem;
+            mem.size = elem;
+        }
+        if self.evex && mem.disp8 {
+            let n = if mem.size == 0 { 1 } else { mem.size as i64 };
+            mem.disp *= n;
+        }
+        Some(Operand::Mem(mem))
+    }
+
+    /// Vector register width and memory size for a vector operand size suffix.
+    fn vsize(&self, sz: &str) -> (u16, u16) {
+        let vl = self.vl;
+        match sz {
+            "x" | "ps" | "pd" => (vl, vl / 8),
+            "qq" => (256, 32),
+            "dq" => (128, 16),
+            "h" => ((vl / 2).max(128), vl / 16),
+            "f" => (128, vl / 32),
+            "e" => (128, vl / 64),
+            "ss" | "d" => (128, 4),
+            "sd" | "q" => (128, 8),
+            "w" => (128, 2),
+            "b" => (128, 1),
+            "y" => (128, if self.rex_w() { 8 } else { 4 }),
+            _ => (vl, vl / 8),
+        }
+    }
+
+    fn elem_size(&self) -> u16 {
+        if self.vex_w { 8 } else { 4 }
+    }
+
+    fn gpr_size(&self, sz: &str) -> u16 {
+        match sz {
+            "b" => 8,
+            "w" => 16,
+            "d" => 32,
+            "q" => 64,
+            "y" => if self.mode64 && self.rex_w() { 64 } else { 32 },
+            "z" => if self.osize() == 16 { 16 } else { 32 },
+            _ => self.osize(),
+        }
+    }
+
+    fn operand(&mut self, tok: &str, opcode: u8) -> Option<Operand> {
+        let fixed = match tok {
+            "AL" => Some("al".to_string()),
+            "CL" => Some("cl".to_string()),
+            "DX" => Some("dx".to_string()),
+            "rAX" => Some(self.gpr(0, self.osize())),
+            "eAX" => Some(self.gpr(0, if self.osize() == 16 { 16 } else { 32 })),
+            "XMM0" => Some("xmm0".to_string()),
+            "ES" | "CS" | "SS" | "DS" | "FS" | "GS" => Some(tok.to_lowercase()),
+            _ => None,
+        };
+        if let Some(reg) = fixed {
+            return Some(Operand::Reg(reg));
+        }
+        if tok == "1" {
+            return Some(Operand::One);
+        }
+        if let Some(slash) = tok.find('/') {
+            let (r, m) = (&tok[..slash], &tok[slash + 1..]);
+            return if self.is_reg_form() { self.operand(r, opcode) } else { self.operand(m, opcode) };
+        }
+
+        let (kind, sz) = tok.split_at(1);
+        match kind {
+            "E" | "M" | "R" => {
+                let m = self.modrm()?;
+                if m >> 6 == 3 {
+                    if kind == "M" {
+                        return None;
+                    }
+                    let size = self.gpr_size(sz);
+                    Some(Operand::Reg(self.gpr((m & 7) | (self.rex_b() << 3), size)))
+                } else {
+                    if kind == "R" {
+                        return None;
+                    }
+                    let size = match sz {
+                        "" => 0,
+                        "p" => match self.osize() { 16 => 4, 32 => 6, _ => 10 },
+                        "a" => self.osize() / 4,
+                        "x" | "ps" | "pd" | "dq" | "qq" => self.vsize(sz).1,
+                        _ => self.gpr_size(sz) / 8,
+                    };
+                    let elem = if sz == "x" || sz == "ps" || sz == "pd" { self.elem_size() } else { 0 };
+                    self.mem_operand(size, elem)
+                }
+            }
+            "G" => {
+                let m = self.modrm()?;
+                let reg = ((m >> 3) & 7) | (self.rex_r() << 3);
+                if sz == "dx" || sz == "qx" {
+                    // Gather instructions encode a vector register in ModRM.reg.
+                    return Some(Operand::Reg(self.vreg(reg, self.vl)));
+                }
+                Some(Operand::Reg(self.gpr(reg, self.gpr_size(sz))))
+            }
+            "B" => {
+                let size = self.gpr_size(sz);
+                Some(Operand::Reg(self.gpr(self.vvvv & 0xf, size)))
+            }
+            "Z" => {
+                let index = (opcode & 7) | (self.rex_b() << 3);
+                Some(Operand::Reg(self.gpr(index, self.gpr_size(sz))))
+            }
+            "I" => {
+                let osize = self.osize();
+                let v = match sz {
+                    "b" => self.imm(1)?,
+                    "bs" => (self.simm(1)? as u64) & mask(osize),
+                    "w" => self.imm(2)?,
+                    "z" => {
+                        if osize == 16 { self.imm(2)? } else { (self.simm(4)? as u64) & mask(osize) }
+                    }
+                    "v" => {
+                        if osize == 64 { self.imm(8)? } else if osize == 16 { self.imm(2)? } else { self.imm(4)? }
+                    }
+                    _ => return None,
+                };
+                Some(Operand::Imm(v))
+            }
+            "J" => {
+                let rel = match sz {
+                    "b" => self.simm(1)?,
+                    _ => if !self.mode64 && self.opsize { self.simm(2)? } else { self.simm(4)? },
+                };
+                let next = self.addr.wrapping_add(self.pos as u64);
+                let mut target = next.wrapping_add(rel as u64);
+                if !self.mode64 {
+                    target &= if self.opsize && sz != "b" { 0xffff } else { 0xffff_ffff };
+                }
+                Some(Operand::Rel(target))
+            }
+            "O" => {
+                let width = (self.asize() / 8) as usize;
+                let addr = self.imm(width)?;
+                // objdump leaves the access size of moffs operands to the register operand.
+                Some(Operand::Mem(Mem {
+                    size: 0, seg: self.mem_seg(), base: None, index: None, scale: 1, disp: addr as i64,
+                    has_disp: true, disp8: false, rip: false, absolute: true, bcst: 0,
+                }))
+            }
+            "A" => {
+                let off = if self.opsize { self.imm(2)? as u32 } else { self.imm(4)? as u32 };
+                let seg = self.imm(2)? as u16;
+                Some(Operand::Far(seg, off))
+            }
+            "S" => {
+                let m = self.modrm()?;
+                Some(Operand::Reg(SEGS[((m >> 3) & 7) as usize].to_string()))
+            }
+            "C" => {
+                let m = self.modrm()?;
+                Some(Operand::Reg(format!("cr{}", ((m >> 3) & 7) | (self.rex_r() << 3))))
+            }
+            "D" => {
+                let m = self.modrm()?;
+                Some(Operand::Reg(format!("dr{}", ((m >> 3) & 7) | (self.rex_r() << 3))))
+            }
+            "X" | "Y" => {
+                let size = match sz { "b" => 1, "z" => if self.osize() == 16 { 2 } else { 4 }, _ => self.osize() / 8 };
+                let reg = if kind == "X" { 6 } else { 7 };
+                let base = match self.asize() { 16 => REG16[reg], 32 => REG32[reg], _ => REG64[reg] };
+                let seg = if kind == "X" { Some(self.mem_seg().unwrap_or("ds")) } else { Some("es") };
+                Some(Operand::Mem(Mem {
+                    size, seg, base: Some(base.to_string()), index: None, scale: 1, disp: 0,
+                    has_disp: false, disp8: false, rip: false, absolute: false, bcst: 0,
+                }))
+            }
+            "V" => {
+                let m = self.modrm()?;
+                let mut index = ((m >> 3) & 7) | (self.rex_r() << 3);
+                if self.evex && self.evex_rr {
+                    index |= 16;
+                }
+                let (bits, _) = self.vsize(sz);
+                Some(Operand::Reg(self.vreg(index, bits)))
+            }
+            "W" | "U" => {
+                let m = self.modrm()?;
+                let (bits, msize) = self.vsize(sz);
+                if m >> 6 == 3 {
+                    let mut index = (m & 7) | (self.rex_b() << 3);
+                    if self.evex && self.rex_x() != 0 {
+                        index |= 16;
+                    }
+                    Some(Operand::Reg(self.vreg(index, bits)))
+                } else {
+                    if kind == "U" {
+                        return None;
+                    }
+                    let elem = match sz { "x" | "ps" | "pd" | "h" => self.elem_size(), _ => 0 };
+                    self.mem_operand(msize, elem)
+                }
+            }
+            "H" => {
+                if !self.vex {
+                    return None;
+                }
+                if sz == "r" && !self.is_reg_form() {
+                    return None;
+                }
+                let index = self.vvvv | if self.evex && self.evex_vv { 16 } else { 0 };
+                let (bits, _) = self.vsize(if sz == "r" { "dq" } else { sz });
+                Some(Operand::Reg(self.vreg(index, bits)))
+            }
+            "L" => {
+                let b = self.u8()?;
+                Some(Operand::Reg(self.vreg(b >> 4, self.vl)))
+            }
+            "P" => {
+                let m = self.modrm()?;
+                Some(Operand::Reg(format!("mm{}", (m >> 3) & 7)))
+            }
+            "Q" | "N" => {
+                let m = self.modrm()?;
+                if m >> 6 == 3 {
+                    Some(Operand::Reg(format!("mm{}", m & 7)))
+                } else if kind == "N" {
+                    None
+                } else {
+                    self.mem_operand(if sz == "d" { 4 } else { 8 }, 0)
+                }
+            }
+            "K" => {
+                let m = self.modrm()?;
+                match sz {
+                    "Gk" => Some(Operand::Reg(format!("k{}", (m >> 3) & 7))),
+                    "Vk" => Some(Operand::Reg(format!("k{}", self.vvvv & 7))),
+                    "Rk" if m >> 6 == 3 => Some(Operand::Reg(format!("k{}", m & 7))),
+                    "Rk" => None,
+                    _ => {
+                        if m >> 6 == 3 {
+                            Some(Operand::Reg(format!("k{}", m & 7)))
+                        } else {
+                            // The mask width is the mnemonic suffix: b, w, d or q.
+                            let width = match self.pp { 1 if self.vex_w => 4, 1 => 1, _ if self.vex_w => 8, _ => 2 };
+                            self.mem_operand(width, 0)
+                        }
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+const VSIB: u16 = 0x200;
+
+fn mask(bits: u16) -> u64 {
+    if bits >= 64 { u64::MAX } else { (1u64 << bits) - 1 }
+}
+
+struct Decoded {
+    prefix:     String,
+    mnem:       String,
+    ops:        Vec<Operand>,
+}
+
+fn choose_by_size(mnem: &str, osize: u16) -> String {
+    let parts: Vec<&str> = mnem.split('/').collect();
+    if parts.len() < 2 {
+        return mnem.to_string();
+    }
+    let i = match osize { 16 => 0, 32 => 1, _ => 2 };
+    parts.get(i).copied().filter(|p| !p.is_empty()).unwrap_or(parts[parts.len() - 2]).to_string()
+}
+
+fn choose_by_w(mnem: &str, w: bool) -> String {
+    match mnem.find('|') {
+        Some(i) => if w { mnem[i + 1..].to_string() } else { mnem[..i].to_string() },
+        None => mnem.to_string(),
+    }
+}
+
+impl<'a> Dec<'a> {
+    fn new(bytes: &'a [u8], addr: u64, mode64: bool) -> Dec<'a> {
+        Dec {
+            bytes, pos: 0, addr, mode64, opsize: false, data16: 0, adsize: false, lock: false, rep: 0, seg: None, seg_used: false,
+            rex: 0, rex_present: false, vex: false, evex: false, map: 0, pp: 0, vl: 128, vex_w: false,
+            vvvv: 0, evex_z: false, evex_aaa: 0, evex_b: false, evex_rr: false, evex_vv: false,
+            modrm: None, mem: None, flags: 0, pp_used: false, vsib_qword_index: false, evex_rc: 0, sae: None,
+        }
+    }
+
+    fn legacy_pp(&self) -> u8 {
+        match self.rep {
+            0xf3 => 2,
+            0xf2 => 3,
+            _ => if self.opsize { 1 } else { 0 },
+        }
+    }
+
+    fn decode(&mut self) -> Option<Decoded> {
+        // Legacy prefixes, then REX.
+        let mut b;
+        loop {
+            b = self.u8()?;
+            match b {
+                0xf0 => self.lock = true,
+                0xf2 | 0xf3 => self.rep = b,
+                0x26 | 0x2e | 0x36 | 0x3e | 0x64 | 0x65 => {
+                    self.seg = Some(match b { 0x26 => "es", 0x2e => "cs", 0x36 => "ss", 0x3e => "ds", 0x64 => "fs", _ => "gs" });
+                }
+                0x66 => {
+                    if self.opsize {
+                        self.data16 += 1;
+                    }
+                    self.opsize = true;
+                }
+                0x67 => self.adsize = true,
+                0x40..=0x4f if self.mode64 => {
+                    self.rex = b & 0xf;
+                    self.rex_present = true;
+                    b = self.u8()?;
+                    if let 0x40..=0x4f = b {
+                        // Only the last REX prefix counts.
+                        self.rex = b & 0xf;
+                        continue;
+                    }
+                    break;
+                }
+                _ => break,
+            }
+        }
+
+        match b {
+            0xc4 | 0xc5 if self.mode64 || self.peek().map(|n| n >> 6 == 3).unwrap_or(false) => return self.decode_vex(b),
+            0x62 if self.mode64 || self.peek().map(|n| n >> 6 == 3).unwrap_or(false) => return self.decode_evex(),
+            0x0f => return self.decode_0f(),
+            0xd8..=0xdf => return self.decode_x87(b),
+            0x9b if matches!(self.peek(), Some(0xd8..=0xdf)) => {
+                // fwait folds into the following x87 instruction; the no-wait forms lose their "n".
+                let esc = self.u8()?;
+                let mut d = self.decode_x87(esc)?;
+                if d.mnem.starts_with("fn") && d.mnem != "fnop" {
+                    d.mnem.remove(1);
+                }
+                return Some(d);
+            }
+            _ => {}
+        }
+
+        let mut entry = ONE_BYTE[b as usize];
+        if entry.flags & PFX != 0 {
+            return None;
+        }
+        if self.mode64 && entry.flags & I64 != 0 {
+            return None;
+        }
+        if entry.mnem.is_empty() {
+            return None;
+        }
+        if b == 0x63 && !self.mode64 {
+            entry = op("arpl", "Ew,Gw", 0);
+        }
+        if b == 0x90 {
+            if self.rex_b() != 0 || (self.opsize && !self.rex_w()) {
+                entry = op("xchg", "Zv,rAX", 0);
+            } else if self.rep == 0xf3 {
+                self.rep = 0;
+                entry = op("pause", "", 0);
+            }
+        }
+        if entry.flags & GRP != 0 {
+            let m = self.modrm()?;
+            if (b == 0xc6 || b == 0xc7) && m == 0xf8 {
+                entry = if b == 0xc6 { op("xabort", "Ib", 0) } else { op("xbegin", "Jz", 0) };
+            } else {
+                let mut g = group_op(entry.mnem, (m >> 3) & 7, m >> 6 != 3, entry.args)?;
+                g.flags |= entry.flags & (I64 | D64 | F64);
+                entry = g;
+            }
+        }
+        self.flags = entry.flags;
+
+        let mut mnem = entry.mnem.to_string();
+        if mnem.contains('/') {
+            mnem = choose_by_size(&mnem, self.osize());
+        }
+        if b == 0xe3 {
+            mnem = match self.asize() { 16 => "jcxz", 32 => "jecxz", _ => "jrcxz" }.to_string();
+        }
+        if self.mode64 && (0xa0..=0xa3).contains(&b) {
+            mnem = "movabs".to_string();
+        }
+        if b == 0xd7 {
+            let mem = Mem {
+                size: 1, seg: Some(self.mem_seg().unwrap_or("ds")), base: Some(self.gpr(3, self.asize())), index: None,
+                scale: 1, disp: 0, has_disp: false, disp8: false, rip: false, absolute: false, bcst: 0,
+            };
+            return Some(self.finish(mnem, vec![Operand::Mem(mem)]));
+        }
+        let ops = self.operands(entry.args, b)?;
+        if (0xb8..=0xbf).contains(&b) && self.osize() == 64 && self.mode64 {
+            mnem = "movabs".to_string();
+        }
+        if self.mode64 && self.opsize && matches!(b, 0xc2 | 0xc3 | 0xc8 | 0xc9) {
+            mnem.push('w');
+        }
+        Some(self.finish(mnem, ops))
+    }
+
+    fn operands(&mut self, args: &str, opcode: u8) -> Option<Vec<Operand>> {
+        let mut ops = Vec::new();
+        if args.is_empty() {
+            return Some(ops);
+        }
+        // ModRM (and SIB/displacement) always precede immediates.
+        let needs_modrm = args.split(',').any(|t| {
+            if matches!(t, "DX" | "CL" | "CS" | "DS" | "ES" | "SS") {
+                return false;
+            }
+            let k = t.as_bytes()[0];
+            matches!(k, b'E' | b'G' | b'M' | b'R' | b'S' | b'C' | b'D' | b'V' | b'W' | b'U' | b'P' | b'Q' | b'N' | b'K')
+        });
+        if needs_modrm {
+            self.modrm()?;
+        }
+        for tok in args.split(',') {
+            if tok.starts_with('H') && (!self.vex || (tok == "Hr" && !self.is_reg_form())) {
+                continue;
+            }
+            ops.push(self.operand(tok, opcode)?);
+        }
+        Some(ops)
+    }
+
+    fn finish(&mut self, mnem: String, ops: Vec<Operand>) -> Decoded {
+        let mut prefix = String::new();
+        if self.lock {
+            prefix.push_str("lock ");
+        }
+        let is_string = matches!(mnem.as_str(), "movs" | "cmps" | "stos" | "lods" | "scas" | "ins" | "outs");
+        let is_branch = mnem == "call" || mnem == "jmp" || mnem == "ret" || (mnem.starts_with('j') && ops.iter().any(|o| matches!(o, Operand::Rel(_))));
+        if !self.pp_used {
+            match self.rep {
+                0xf3 if is_string => prefix.push_str(if mnem == "cmps" || mnem == "scas" { "repz " } else { "rep " }),
+                0xf2 if is_string => prefix.push_str("repnz "),
+                0xf2 if is_branch => prefix.push_str("bnd "),
+                0xf3 if mnem != "pause" => prefix.push_str("repz "),
+                0xf2 => prefix.push_str("repnz "),
+                _ => {}
+            }
+        }
+        if self.seg == Some("ds") && (mnem == "call" || mnem == "jmp") && !ops.iter().any(|o| matches!(o, Operand::Rel(_))) {
+            prefix.push_str("notrack ");
+        } else if let Some(seg) = self.seg {
+            if !self.seg_used {
+                prefix.push_str(seg);
+                prefix.push(' ');
+            }
+        }
+        for _ in 0..self.data16 {
+            prefix.insert_str(0, "data16 ");
+        }
+        Decoded{prefix, mnem, ops}
+    }
+
+    fn decode_0f(&mut self) -> Option<Decoded> {
+        let b = self.u8()?;
+        match b {
+            0x38 => {
+                let b = self.u8()?;
+                self.map = 2;
+                return self.decode_mapped(b);
+            }
+            0x3a => {
+                let b = self.u8()?;
+                self.map = 3;
+                return self.decode_mapped(b);
+            }
+            0x01 => return self.decode_grp7(),
+            0x1e if self.rep == 0xf3 => {
+                match self.peek() {
+                    Some(0xfa) => { self.pos += 1; self.rep = 0; return Some(self.finish("endbr64".to_string(), Vec::new())); }
+                    Some(0xfb) => { self.pos += 1; self.rep = 0; return Some(self.finish("endbr32".to_string(), Vec::new())); }
+                    Some(m) if m >> 6 == 3 && (m >> 3) & 7 == 1 => {
+                        self.rep = 0;
+                        self.pp_used = true;
+                        self.flags = 0;
+                        let name = if self.rex_w() { "rdsspq" } else { "rdsspd" };
+                        let ops = self.operands("Ry", b)?;
+                        return Some(self.finish(name.to_string(), ops));
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+        self.map = 1;
+        self.decode_mapped(b)
+    }
+
+    /// Decodes an opcode from map 1-3 once prefixes and any VEX/EVEX header are consumed.
+    fn decode_mapped(&mut self, b: u8) -> Option<Decoded> {
+        let pp = if self.vex { self.pp } else { self.legacy_pp() };
+        let has_modrm = self.modrm_peek_needed(b);
+        let (reg, is_mem) = match has_modrm {
+            true => {
+                let m = self.peek()?;
+                ((m >> 3) & 7, m >> 6 != 3)
+            }
+            false => (0, false),
+        };
+        let w = self.rex_w();
+        let entry = if self.evex {
+            evex_override(self.map, b, pp, w).or_else(|| self.lookup(b, pp, reg, is_mem, w))
+        } else if self.vex && self.map == 1 && matches!(b, 0x41..=0x4b | 0x90..=0x99) {
+            lookup_kmask(b, pp, self.vl == 256)
+        } else {
+            self.lookup(b, pp, reg, is_mem, w)
+        }?;
+        if entry.mnem.is_empty() {
+            return None;
+        }
+        if self.vex && entry.flags & (SSE | VEX) == 0 {
+            return None;
+        }
+        if !self.vex && entry.flags & VEX != 0 {
+            return None;
+        }
+        if self.mode64 && entry.flags & I64 != 0 {
+            return None;
+        }
+        if !self.mode64 && entry.flags & O64 != 0 && !(b == 0x05 || b == 0x07) {
+            return None;
+        }
+        self.flags = entry.flags;
+        if self.map == 2 && (0x90..=0x93).contains(&b) {
+            self.flags |= VSIB;
+            self.vsib_qword_index = b & 1 == 1;
+        }
+        if has_modrm {
+            // Parsed only now so that VSIB addressing is known.
+            self.modrm()?;
+        }
+        if entry.flags & (SSE | LEG_PFX) != 0 && !self.vex && pp != 0 {
+            self.pp_used = true;
+            if pp >= 2 {
+                self.rep = 0;
+            }
+        }
+        if self.vex {
+            self.pp_used = true;
+        }
+
+        let mut mnem = choose_by_w(entry.mnem, w);
+        if self.vex && entry.flags & SSE != 0 && entry.flags & VEX == 0 {
+            mnem = format!("v{}", mnem);
+        }
+        if self.vex && b == 0x77 && self.map == 1 && self.vl == 256 {
+            mnem = "vzeroall".to_string();
+        }
+        if mnem.contains('/') {
+            mnem = choose_by_size(&mnem, self.osize());
+        }
+        let mut ops = self.operands(entry.args, b)?;
+        if self.flags & VSIB != 0 {
+            // Gathers: the element size follows W, qword indices with dword elements fill half a vector.
+            let half = self.vsib_qword_index && !w;
+            let bits = if half { (self.vl / 2).max(128) } else { self.vl };
+            let m = self.modrm.unwrap_or(0);
+            let dest = ((m >> 3) & 7) | (self.rex_r() << 3);
+            ops[0] = Operand::Reg(self.vreg(dest, bits));
+            if let Some(Operand::Mem(mem)) = ops.get_mut(1) {
+                mem.size = if w { 8 } else { 4 };
+            }
+            if let Some(last) = ops.get_mut(2) {
+                *last = Operand::Reg(self.vreg(self.vvvv, bits));
+            }
+        }
+        if mnem == "cmpxchg16b" {
+            if let Some(Operand::Mem(m)) = ops.first_mut() {
+                m.size = 16;
+            }
+        }
+        if self.map == 3 && b == 0x44 {
+            // pclmulqdq selects the quadwords with bits 0 and 4 of the immediate.
+            if let Some(&Operand::Imm(sel)) = ops.last() {
+                if sel & !0x11 == 0 {
+                    ops.pop();
+                    let lo = if sel & 1 != 0 { "hq" } else { "lq" };
+                    let hi = if sel & 0x10 != 0 { "hq" } else { "lq" };
+                    mnem = mnem.replace("pclmulqdq", &format!("pclmul{}{}dq", lo, hi));
+                }
+            }
+        }
+        if self.map == 1 && b == 0xc2 {
+            // cmpps and friends spell the predicate immediate as part of the mnemonic.
+            let limit = if self.vex { 32 } else { 8 };
+            if let Some(&Operand::Imm(pred)) = ops.last() {
+                if pred < limit {
+                    ops.pop();
+                    let at = if self.vex { 4 } else { 3 };
+                    mnem = format!("{}{}{}", &mnem[..at], CMP_PREDICATES[pred as usize], &mnem[at..]);
+                }
+            }
+        }
+        if self.evex && self.map == 3 && matches!(b, 0x1e | 0x1f | 0x3e | 0x3f) {
+            // vpcmp[u]{b,w,d,q} likewise, with the integer predicates.
+            if let Some(&Operand::Imm(pred)) = ops.last() {
+                let names = ["eq", "lt", "le", "false", "neq", "nlt", "nle", "true"];
+                if pred < 8 {
+                    ops.pop();
+                    mnem = format!("vpcmp{}{}", names[pred as usize], &mnem[5..]);
+                }
+            }
+        }
+        if self.evex && self.evex_b && self.is_reg_form() {
+            self.sae = Some(if self.map == 1 && (b == 0x2e || b == 0x2f) {
+                "{sae}"
+            } else {
+                ["{rn-sae}", "{rd-sae}", "{ru-sae}", "{rz-sae}"][self.evex_rc as usize]
+            });
+        }
+        Some(self.finish(mnem, ops))
+    }
+
+    fn modrm_peek_needed(&self, b: u8) -> bool {
+        match self.map {
+            1 => !matches!(b, 0x05..=0x0b | 0x0e | 0x30..=0x37 | 0x77 | 0x80..=0x8f | 0xa0..=0xa2 | 0xa8..=0xaa | 0xc8..=0xcf),
+            _ => true,
+        }
+    }
+
+    fn lookup(&self, b: u8, pp: u8, reg: u8, is_mem: bool, w: bool) -> Option<Op> {
+        match self.map {
+            1 => lookup_0f(b, pp, reg, is_mem, self.vex),
+            2 => lookup_0f38(b, pp, reg, self.vex, w),
+            3 => lookup_0f3a(b, pp, self.vex),
+            _ => None,
+        }
+    }
+
+    fn decode_grp7(&mut self) -> Option<Decoded> {
+        let m = self.modrm()?;
+        self.map = 1;
+        if m >> 6 == 3 {
+            let name = match m {
+                0xc1 => "vmcall", 0xc2 => "vmlaunch", 0xc3 => "vmresume", 0xc4 => "vmxoff",
+                0xc8 => "monitor", 0xc9 => "mwait", 0xca => "clac", 0xcb => "stac", 0xcf => "encls",
+                0xd0 => "xgetbv", 0xd1 => "xsetbv", 0xd4 => "vmfunc", 0xd5 => "xend", 0xd6 => "xtest", 0xd7 => "enclu",
+                0xee => "rdpkru", 0xef => "wrpkru", 0xf8 => "swapgs", 0xf9 => "rdtscp",
+                0xfa => "monitorx", 0xfb => "mwaitx", 0xfc => "clzero",
+                _ => match (m >> 3) & 7 {
+                    4 => { let ops = self.operands("Rv/Mw", 1)?; return Some(self.finish("smsw".to_string(), ops)); }
+                    6 => { let ops = self.operands("Ew", 1)?; return Some(self.finish("lmsw".to_string(), ops)); }
+                    _ => return None,
+                },
+            };
+            return Some(self.finish(name.to_string(), Vec::new()));
+        }
+        self.flags = F64;
+        let (name, args) = match (m >> 3) & 7 {
+            0 => ("sgdt", "M"), 1 => ("sidt", "M"), 2 => ("lgdt", "M"), 3 => ("lidt", "M"),
+            4 => ("smsw", "Mw"), 6 => ("lmsw", "Mw"), 7 => ("invlpg", "Mb"), _ => return None,
+        };
+        let ops = self.operands(args, 1)?;
+        Some(self.finish(name.to_string(), ops))
+    }
+
+    fn decode_x87(&mut self, b: u8) -> Option<Decoded> {
+        let m = self.modrm()?;
+        let row = (b - 0xd8) as usize;
+        let reg = ((m >> 3) & 7) as usize;
+        if m >> 6 != 3 {
+            let name = X87_MEM[row][reg];
+            if name.is_empty() {
+                return None;
+            }
+            let op = self.mem_operand(X87_MEM_SIZE[row][reg], 0)?;
+            return Some(self.finish(name.to_string(), vec![op]));
+        }
+        let i = (m & 7) as usize;
+        let st = |n: usize| Operand::Reg(format!("st({})", n));
+        let st0 = || Operand::Reg("st".to_string());
+        let (name, ops): (&str, Vec<Operand>) = match (row, reg) {
+            (0, _) => {
+                let name = X87_MEM[0][reg];
+                if reg == 2 || reg == 3 { (name, vec![st(i)]) } else { (name, vec![st0(), st(i)]) }
+            }
+            (1, 0) => ("fld", vec![st(i)]),
+            (1, 1) => ("fxch", vec![st(i)]),
+            (1, 2) if i == 0 => ("fnop", vec![]),
+            (1, 4..=7) => {
+                let name = D9_REG[(reg - 4) * 8 + i];
+                if name.is_empty() { return None; }
+                (name, vec![])
+            }
+            (2, 0..=3) => (["fcmovb", "fcmove", "fcmovbe", "fcmovu"][reg], vec![st0(), st(i)]),
+            (2, 5) if i == 1 => ("fucompp", vec![]),
+            (3, 0..=3) => (["fcmovnb", "fcmovne", "fcmovnbe", "fcmovnu"][reg], vec![st0(), st(i)]),
+            (3, 4) if i == 2 => ("fnclex", vec![]),
+            (3, 4) if i == 3 => ("fninit", vec![]),
+            (3, 5) => ("fucomi", vec![st0(), st(i)]),
+            (3, 6) => ("fcomi", vec![st0(), st(i)]),
+            (4, 0) => ("fadd", vec![st(i), st0()]),
+            (4, 1) => ("fmul", vec![st(i), st0()]),
+            (4, 4) => ("fsubr", vec![st(i), st0()]),
+            (4, 5) => ("fsub", vec![st(i), st0()]),
+            (4, 6) => ("fdivr", vec![st(i), st0()]),
+            (4, 7) => ("fdiv", vec![st(i), st0()]),
+            (5, 0) => ("ffree", vec![st(i)]),
+            (5, 2) => ("fst", vec![st(i)]),
+            (5, 3) => ("fstp", vec![st(i)]),
+            (5, 4) => ("fucom", vec![st(i)]),
+            (5, 5) => ("fucomp", vec![st(i)]),
+            (6, 0) => ("faddp", vec![st(i), st0()]),
+            (6, 1) => ("fmulp", vec![st(i), st0()]),
+            (6, 3) if i == 1 => ("fcompp", vec![]),
+            (6, 4) => ("fsubrp", vec![st(i), st0()]),
+            (6, 5) => ("fsubp", vec![st(i), st0()]),
+            (6, 6) => ("fdivrp", vec![st(i), st0()]),
+            (6, 7) => ("fdivp", vec![st(i), st0()]),
+            (7, 4) if i == 0 => ("fnstsw", vec![Operand::Reg("ax".to_string())]),
+            (7, 5) => ("fucomip", vec![st0(), st(i)]),
+            (7, 6) => ("fcomip", vec![st0(), st(i)]),
+            _ => return None,
+        };
+        Some(self.finish(name.to_string(), ops))
+    }
+
+    fn decode_vex(&mut self, first: u8) -> Option<Decoded> {
+        if self.rex_present || self.opsize || self.rep != 0 || self.lock {
+            return None;
+        }
+        self.vex = true;
+        let p1 = self.u8()?;
+        let mut rex = 0u8;
+        if p1 & 0x80 == 0 { rex |= 4; }
+        if first == 0xc5 {
+            self.map = 1;
+            self.vvvv = (!p1 >> 3) & 0xf;
+            self.vl = if p1 & 4 != 0 { 256 } else { 128 };
+            self.pp = p1 & 3;
+        } else {
+            if p1 & 0x40 == 0 { rex |= 2; }
+            if p1 & 0x20 == 0 { rex |= 1; }
+            self.map = p1 & 0x1f;
+            let p2 = self.u8()?;
+            self.vex_w = p2 & 0x80 != 0;
+            if self.vex_w { rex |= 8; }
+            self.vvvv = (!p2 >> 3) & 0xf;
+            self.vl = if p2 & 4 != 0 { 256 } else { 128 };
+            self.pp = p2 & 3;
+        }
+        if !self.mode64 {
+            rex &= 8;
+            self.vvvv &= 7;
+        }
+        self.rex = rex;
+        let b = self.u8()?;
+        if self.map == 0 || self.map > 3 {
+            return None;
+        }
+        self.decode_mapped(b)
+    }
+
+    fn decode_evex(&mut self) -> Option<Decoded> {
+        if self.rex_present || self.opsize || self.rep != 0 || self.lock {
+            return None;
+        }
+        self.vex = true;
+        self.evex = true;
+        let p0 = self.u8()?;
+        let p1 = self.u8()?;
+        let p2 = self.u8()?;
+        if p1 & 0x04 == 0 {
+            return None;
+        }
+        let mut rex = 0u8;
+        if p0 & 0x80 == 0 { rex |= 4; }
+        if p0 & 0x40 == 0 { rex |= 2; }
+        if p0 & 0x20 == 0 { rex |= 1; }
+        self.evex_rr = p0 & 0x10 == 0;
+        self.map = p0 & 0x7;
+        self.vex_w = p1 & 0x80 != 0;
+        if self.vex_w { rex |= 8; }
+        self.vvvv = (!p1 >> 3) & 0xf;
+        self.pp = p1 & 3;
+        self.evex_z = p2 & 0x80 != 0;
+        self.vl = match (p2 >> 5) & 3 { 0 => 128, 1 => 256, _ => 512 };
+        self.evex_b = p2 & 0x10 != 0;
+        self.evex_vv = p2 & 0x08 == 0;
+        self.evex_aaa = p2 & 7;
+        if !self.mode64 {
+            rex &= 8;
+            self.vvvv &= 7;
+            self.evex_rr = false;
+            self.evex_vv = false;
+        }
+        self.rex = rex;
+        let b = self.u8()?;
+        if self.map == 0 || self.map > 3 {
+            return None;
+        }
+        // Register-form EVEX.b selects embedded rounding in L'L, which implies 512-bit vectors.
+        if self.evex_b && self.peek().map(|m| m >> 6 == 3).unwrap_or(false) {
+            self.evex_rc = (p2 >> 5) & 3;
+            self.vl = 512;
+        }
+        self.decode_mapped(b)
+    }
+}
+
+fn intel_mem(m: &Mem) -> String {
+    let mut s = String::new();
+    let size = match m.size {
+        1 => "BYTE", 2 => "WORD", 4 => "DWORD", 6 => "FWORD", 8 => "QWORD",
+        10 => "TBYTE", 16 => "XMMWORD", 32 => "YMMWORD", 64 => "ZMMWORD", _ => "",
+    };
+    if !size.is_empty() {
+        s.push_str(size);
+        s.push_str(if m.bcst > 0 { " BCST " } else { " PTR " });
+    }
+    if let Some(seg) = m.seg {
+        s.push_str(seg);
+        s.push(':');
+    }
+    if m.absolute {
+        if m.seg.is_none() {
+            s.push_str("ds:");
+        }
+        s.push_str(&format!("0x{:x}", m.disp as u64));
+    } else {
+        s.push('[');
+        let mut first = true;
+        if let Some(base) = &m.base {
+            s.push_str(base);
+            first = false;
+        }
+        if let Some(index) = &m.index {
+            if !first {
+                s.push('+');
+            }
+            s.push_str(index);
+            if m.scale > 0 {
+                s.push_str(&format!("*{}", m.scale));
+            }
+            first = false;
+        }
+        if m.has_disp || first {
+            if m.rip {
+                s.push_str(&format!("+0x{:x}", m.disp as u64));
+            } else if m.disp < 0 {
+                s.push_str(&format!("-0x{:x}", m.disp.unsigned_abs()));
+            } else if first {
+                s.push_str(&format!("0x{:x}", m.disp));
+            } else {
+                s.push_str(&format!("+0x{:x}", m.disp));
+            }
+        }
+        s.push(']');
+    }
+    s
+}
+
+fn att_mem(m: &Mem) -> String {
+    let mut s = String::new();
+    if let Some(seg) = m.seg {
+        s.push('%');
+        s.push_str(seg);
+        s.push(':');
+    }
+    if m.absolute {
+        s.push_str(&format!("0x{:x}", m.disp as u64));
+    } else {
+        if m.has_disp || (m.base.is_none() && m.index.is_some()) {
+            if m.disp < 0 {
+                s.push_str(&format!("-0x{:x}", m.disp.unsigned_abs()));
+            } else {
+                s.push_str(&format!("0x{:x}", m.disp));
+            }
+        }
+        s.push('(');
+        if let Some(base) = &m.base {
+            s.push('%');
+            s.push_str(base);
+        }
+        if let Some(index) = &m.index {
+            s.push_str(&format!(",%{}", index));
+            if m.scale > 0 {
+                s.push_str(&format!(",{}", m.scale));
+            }
+        }
+        s.push(')');
+    }
+    if m.bcst > 0 {
+        s.push_str(&format!("{{1to{}}}", m.bcst));
+    }
+    s
+}
+
+fn att_mnemonic(mnem: &str, ops: &[Operand], default_size: u16) -> String {
+    let src_size = |i: usize| match ops.get(i) {
+        Some(Operand::Mem(m)) => m.size,
+        Some(Operand::Reg(r)) => reg_size(r),
+        _ => 0,
+    };
+    let suffix = |size: u16| match size { 1 => "b", 2 => "w", 4 => "l", 8 => "q", _ => "" };
+    match mnem {
+        "movzx" | "movsx" => {
+            let base = if mnem == "movzx" { "movz" } else { "movs" };
+            format!("{}{}{}", base, suffix(src_size(1)), suffix(src_size(0)))
+        }
+        "movsxd" => "movslq".to_string(),
+        "cbw" => "cbtw".to_string(),
+        "cwde" => "cwtl".to_string(),
+        "cdqe" => "cltq".to_string(),
+        "cwd" => "cwtd".to_string(),
+        "cdq" => "cltd".to_string(),
+        "cqo" => "cqto".to_string(),
+        "retf" => "lret".to_string(),
+        // AT&T keeps the historical operand swap of the x87 reverse forms with an st(i) destination.
+        "fsub" | "fsubr" | "fdiv" | "fdivr" | "fsubp" | "fsubrp" | "fdivp" | "fdivrp"
+            if matches!(ops.first(), Some(Operand::Reg(r)) if r.starts_with("st(")) && ops.len() == 2 => {
+            match mnem.find('r') {
+                Some(i) => format!("{}{}", &mnem[..i], &mnem[i + 1..]),
+                None if mnem.ends_with('p') => format!("{}rp", &mnem[..mnem.len() - 1]),
+                None => format!("{}r", mnem),
+            }
+        }
+        _ => {
+            let has_reg = ops.iter().any(|o| matches!(o, Operand::Reg(_)));
+            let implied = mnem.starts_with("set") || mnem.starts_with("prefetch") || mnem.starts_with("clflush")
+                || matches!(mnem, "ldmxcsr" | "stmxcsr" | "clwb" | "invlpg" | "cmpxchg8b" | "cmpxchg16b" | "vmptrld" | "vmptrst")
+                || (matches!(mnem, "push" | "pop" | "call" | "jmp") && ops.iter().any(|o| matches!(o, Operand::Mem(m) if m.size == default_size)));
+            let mem = ops.iter().find_map(|o| match o { Operand::Mem(m) => Some(m.size), _ => None });
+            match mem {
+                Some(size) if mnem.starts_with('f') && !mnem.starts_with("fx") => {
+                    if matches!(mnem, "fldcw" | "fnstcw" | "fnstsw" | "fldenv" | "fnstenv" | "frstor" | "fnsave" | "fbld" | "fbstp") {
+                        mnem.to_string()
+                    } else if mnem.starts_with("fi") {
+                        format!("{}{}", mnem, match size { 2 => "s", 4 => "l", 8 => "ll", _ => "" })
+                    } else {
+                        format!("{}{}", mnem, match size { 4 => "s", 8 => "l", 10 => "t", _ => "" })
+                    }
+                }
+                Some(size) if matches!(mnem, "cvtsi2ss" | "cvtsi2sd" | "vcvtsi2ss" | "vcvtsi2sd") && default_size == 8 => {
+                    format!("{}{}", mnem, suffix(size))
+                }
+                Some(size) if matches!(mnem, "crc32" | "ins" | "outs") => {
+                    format!("{}{}", mnem, suffix(size))
+                }
+                Some(size) if !has_reg && !implied && size <= 8 && mnem != "xlat" => {
+                    format!("{}{}", mnem, suffix(size))
+                }
+                _ => mnem.to_string(),
+            }
+        }
+    }
+}
+
+fn reg_size(r: &str) -> u16 {
+    if REG8.contains(&r) || REG8_LEGACY.contains(&r) {
+        1
+    } else if REG16.contains(&r) {
+        2
+    } else if REG32.contains(&r) {
+        4
+    } else if REG64.contains(&r) {
+        8
+    } else {
+        0
+    }
+}
+
+fn flow_of(mnem: &str, ops: &[Operand]) -> Flow {
+    let rel = ops.iter().find_map(|o| match o { Operand::Rel(t) => Some(*t), _ => None });
+    match mnem {
+        "jmp" => match rel { Some(t) => Flow::Jump(t), None => Flow::IndirectJump },
+        "ljmp" => Flow::IndirectJump,
+        "call" => match rel { Some(t) => Flow::Call(t), None => Flow::IndirectCall },
+        "lcall" => Flow::IndirectCall,
+        "ret" | "retf" | "iret" | "iretw" | "iretq" | "sysret" | "sysexit" => Flow::Return,
+        "hlt" | "ud2" | "ud0" | "ud1" | "int3" => Flow::Stop,
+        _ if mnem.starts_with('j') || mnem.starts_with("loop") => match rel {
+            Some(t) => Flow::Branch(t),
+            None => Flow::Next,
+        },
+        _ => Flow::Next,
+    }
+}
+
+/// Decodes one x86 instruction (64-bit mode when `mode64`, 32-bit protected mode otherwise).
+pub fn decode(bytes: &[u8], addr: u64, mode64: bool, syntax: Syntax) -> Option<Instruction> {
+    let mut d = Dec::new(bytes, addr, mode64);
+    let decoded = d.decode()?;
+    let len = d.pos;
+    let next = addr.wrapping_add(len as u64);
+
+    let mut mem_ref = None;
+    let mut ops = decoded.ops;
+    for o in ops.iter_mut() {
+        if let Operand::Mem(m) = o {
+            if m.rip {
+                let target = next.wrapping_add(m.disp as u64);
+                mem_ref = Some(if d.asize() == 32 { target & 0xffff_ffff } else { target });
+            } else if m.absolute && decoded.mnem != "lea" {
+                mem_ref = Some(m.disp as u64);
+            }
+        }
+    }
+    let flow = flow_of(&decoded.mnem, &ops);
+    let branch = matches!(flow, Flow::IndirectJump | Flow::IndirectCall);
+    let osize = d.osize();
+
+    let reg_prefix = if syntax == Syntax::Att { "%" } else { "" };
+    let mask_deco = if d.evex && d.evex_aaa != 0 {
+        format!("{{{}k{}}}{}", reg_prefix, d.evex_aaa, if d.evex_z { "{z}" } else { "" })
+    } else if d.evex && d.evex_z {
+        "{z}".to_string()
+    } else {
+        String::new()
+    };
+
+    let (mnemonic, operands) = match syntax {
+        Syntax::Intel => {
+            let mut parts: Vec<String> = ops.iter().map(|o| match o {
+                Operand::Reg(r) => r.clone(),
+                Operand::Imm(v) => format!("0x{:x}", v),
+                Operand::Rel(t) => format!("{:x}", t),
+                Operand::Mem(m) => intel_mem(m),
+                Operand::Far(s, o) => format!("0x{:x}:0x{:x}", s, o),
+                Operand::One => "1".to_string(),
+            }).collect();
+            if let Some(first) = parts.first_mut() {
+                first.push_str(&mask_deco);
+            }
+            if let (Some(sae), Some(last)) = (d.sae, parts.last_mut()) {
+                last.push_str(sae);
+            }
+            let mnem = match decoded.mnem.as_str() {
+                "lcall" => "call",
+                "ljmp" => "jmp",
+                "cmpxchg16b" => {
+                    parts[0] = parts[0].replace("XMMWORD", "OWORD");
+                    "cmpxchg16b"
+                }
+                mnem => mnem,
+            };
+            (format!("{}{}", decoded.prefix, mnem), parts.join(","))
+        }
+        Syntax::Att => {
+            let port_io = matches!(decoded.mnem.as_str(), "in" | "out" | "ins" | "outs");
+            let mut parts: Vec<String> = ops.iter().map(|o| match o {
+                Operand::Reg(r) if r == "dx" && port_io => "(%dx)".to_string(),
+                Operand::Reg(r) if r.starts_with("dr") => format!("%db{}", &r[2..]),
+                Operand::Reg(r) => format!("%{}", r),
+                Operand::Imm(v) => format!("$0x{:x}", v),
+                Operand::Rel(t) => format!("{:x}", t),
+                Operand::Mem(m) => att_mem(m),
+                Operand::Far(s, o) => format!("$0x{:x},$0x{:x}", s, o),
+                Operand::One => String::new(),
+            }).filter(|p| !p.is_empty()).collect();
+            if branch && !matches!(ops.first(), Some(Operand::Far(..))) {
+                if let Some(first) = parts.first_mut() {
+                    first.insert(0, '*');
+                }
+            }
+            if let Some(first) = parts.first_mut() {
+                first.push_str(&mask_deco);
+            }
+            if decoded.mnem != "enter" && decoded.mnem != "bound" {
+                parts.reverse();
+            }
+            if let Some(sae) = d.sae {
+                parts.insert(0, sae.to_string());
+            }
+            let mut mnem = att_mnemonic(&decoded.mnem, &ops, if mode64 { 8 } else { 4 });
+            if (mnem == "push" || mnem == "pop") && ops.iter().any(|o| matches!(o, Operand::Imm(_))) && osize == 16 {
+                mnem.push('w');
+            }
+            (format!("{}{}", decoded.prefix, mnem), parts.join(","))
+        }
+    };
+    Some(Instruction{addr, len, mnemonic: mnemonic.trim_end().to_string(), operands, flow, mem_ref})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// GNU as encodings with the text of objdump -d -M intel and objdump -d, decoded in
+    /// sequence from address 0 so branch targets are as listed: legacy prefixes, REX, VEX
+    /// and EVEX forms, ModRM/SIB and RIP-relative operands.
+    const X86_64: &[(&[u8], &str, &str)] = &[
+        (&[0x55],
+         "push rbp", "push %rbp"),
+        (&[0x48, 0x89, 0xe5],
+         "mov rbp,rsp", "mov %rsp,%rbp"),
+        (&[0x48, 0x83, 0xec, 0x20],
+         "sub rsp,0x20", "sub $0x20,%rsp"),
+        (&[0xb8, 0x01, 0x00, 0x00, 0x00],
+         "mov eax,0x1", "mov $0x1,%eax"),
+        (&[0x48, 0xb8, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11],
+         "movabs rax,0x1122334455667788", "movabs $0x1122334455667788,%rax"),
+        (&[0x01, 0xd8],
+         "add eax,ebx", "add %ebx,%eax"),
+        (&[0x45, 0x31, 0xc8],
+         "xor r8d,r9d", "xor %r9d,%r8d"),
+        (&[0x4d, 0x8b, 0x53, 0x08],
+         "mov r10,QWORD PTR [r11+0x8]", "mov 0x8(%r11),%r10"),
+        (&[0xc7, 0x44, 0x24, 0x04, 0x07, 0x00, 0x00, 0x00],
+         "mov DWORD PTR [rsp+0x4],0x7", "movl $0x7,0x4(%rsp)"),
+        (&[0x48, 0x8d, 0x05, 0x00, 0x01, 0x00, 0x00],
+         "lea rax,[rip+0x100]", "lea 0x100(%rip),%rax"),
+        (&[0x48, 0x8b, 0x05, 0x00, 0x02, 0x00, 0x00],
+         "mov rax,QWORD PTR [rip+0x200]", "mov 0x200(%rip),%rax"),
+        (&[0x48, 0x8d, 0x4c, 0x98, 0x10],
+         "lea rcx,[rax+rbx*4+0x10]", "lea 0x10(%rax,%rbx,4),%rcx"),
+        (&[0x43, 0x8b, 0x44, 0xec, 0xf8],
+         "mov eax,DWORD PTR [r12+r13*8-0x8]", "mov -0x8(%r12,%r13,8),%eax"),
+        (&[0x8a, 0x45, 0xff],
+         "mov al,BYTE PTR [rbp-0x1]", "mov -0x1(%rbp),%al"),
+        (&[0x0f, 0xb7, 0x07],
+         "movzx eax,WORD PTR [rdi]", "movzwl (%rdi),%eax"),
+        (&[0x48, 0x63, 0x04, 0x8e],
+         "movsxd rax,DWORD PTR [rsi+rcx*4]", "movslq (%rsi,%rcx,4),%rax"),
+        (&[0x80, 0x3f, 0x00],
+         "cmp BYTE PTR [rdi],0x0", "cmpb $0x0,(%rdi)"),
+        (&[0x85, 0xc0],
+         "test eax,eax", "test %eax,%eax"),
+        (&[0x6b, 0xc1, 0x64],
+         "imul eax,ecx,0x64", "imul $0x64,%ecx,%eax"),
+        (&[0x48, 0xc1, 0xe0, 0x03],
+         "shl rax,0x3", "shl $0x3,%rax"),
+        (&[0xd3, 0xfa],
+         "sar edx,cl", "sar %cl,%edx"),
+        (&[0xeb, 0x1e],
+         "jmp 78", "jmp 78"),
+        (&[0x74, 0x0e],
+         "je 6a", "je 6a"),
+        (&[0x75, 0xee],
+         "jne 4c", "jne 4c"),
+        (&[0xe8, 0xfb, 0x00, 0x00, 0x00],
+         "call 15e", "call 15e"),
+        (&[0xff, 0x15, 0x00, 0x03, 0x00, 0x00],
+         "call QWORD PTR [rip+0x300]", "call *0x300(%rip)"),
+        (&[0xff, 0xe0],
+         "jmp rax", "jmp *%rax"),
+        (&[0xc3],
+         "ret", "ret"),
+        (&[0x90],
+         "nop", "nop"),
+        (&[0x0f, 0x1f, 0x04, 0x00],
+         "nop DWORD PTR [rax+rax*1]", "nopl (%rax,%rax,1)"),
+        (&[0x0f, 0x45, 0xc1],
+         "cmovne eax,ecx", "cmovne %ecx,%eax"),
+        (&[0x0f, 0x94, 0xc0],
+         "sete al", "sete %al"),
+        (&[0xf3, 0x48, 0xab],
+         "rep stos QWORD PTR es:[rdi],rax", "rep stos %rax,%es:(%rdi)"),
+        (&[0xf0, 0x0f, 0xb1, 0x0f],
+         "lock cmpxchg DWORD PTR [rdi],ecx", "lock cmpxchg %ecx,(%rdi)"),
+        (&[0x0f, 0x05],
+         "syscall", "syscall"),
+        (&[0xf3, 0x0f, 0x1e, 0xfa],
+         "endbr64", "endbr64"),
+        (&[0x0f, 0x28, 0x44, 0x24, 0x10],
+         "movaps xmm0,XMMWORD PTR [rsp+0x10]", "movaps 0x10(%rsp),%xmm0"),
+        (&[0x66, 0x48, 0x0f, 0x6e, 0xc8],
+         "movq xmm1,rax", "movq %rax,%xmm1"),
+        (&[0x66, 0x0f, 0xef, 0xd2],
+         "pxor xmm2,xmm2", "pxor %xmm2,%xmm2"),
+        (&[0xf2, 0x0f, 0x58, 0x05, 0x40, 0x00, 0x00, 0x00],
+         "addsd xmm0,QWORD PTR [rip+0x40]", "addsd 0x40(%rip),%xmm0"),
+        (&[0xf2, 0x0f, 0x2a, 0xc0],
+         "cvtsi2sd xmm0,eax", "cvtsi2sd %eax,%xmm0"),
+        (&[0xc5, 0xfe, 0x6f, 0x07],
+         "vmovdqu ymm0,YMMWORD PTR [rdi]", "vmovdqu (%rdi),%ymm0"),
+        (&[0xc5, 0xed, 0xfe, 0xcb],
+         "vpaddd ymm1,ymm2,ymm3", "vpaddd %ymm3,%ymm2,%ymm1"),
+        (&[0xc4, 0x41, 0x30, 0x57, 0xc2],
+         "vxorps xmm8,xmm9,xmm10", "vxorps %xmm10,%xmm9,%xmm8"),
+        (&[0xc4, 0xe2, 0x75, 0xb8, 0xc2],
+         "vfmadd231ps ymm0,ymm1,ymm2", "vfmadd231ps %ymm2,%ymm1,%ymm0"),
+        (&[0x62, 0xf1, 0xfe, 0x48, 0x6f, 0x07],
+         "vmovdqu64 zmm0,ZMMWORD PTR [rdi]", "vmovdqu64 (%rdi),%zmm0"),
+        (&[0x62, 0xf1, 0x6d, 0x48, 0xfe, 0xcb],
+         "vpaddd zmm1,zmm2,zmm3", "vpaddd %zmm3,%zmm2,%zmm1"),
+        (&[0x62, 0xf1, 0x74, 0xc9, 0x58, 0xc2],
+         "vaddps zmm0{k1}{z},zmm1,zmm2", "vaddps %zmm2,%zmm1,%zmm0{%k1}{z}"),
+        (&[0x62, 0x81, 0xf5, 0x40, 0xef, 0xc7],
+         "vpxorq zmm16,zmm17,zmm31", "vpxorq %zmm31,%zmm17,%zmm16"),
+    ];
+
+    /// 32-bit code, including 16-bit addressing through the 0x67 prefix.
+    const I386: &[(&[u8], &str, &str)] = &[
+        (&[0x55],
+         "push ebp", "push %ebp"),
+        (&[0x89, 0xe5],
+         "mov ebp,esp", "mov %esp,%ebp"),
+        (&[0x8b, 0x45, 0x08],
+         "mov eax,DWORD PTR [ebp+0x8]", "mov 0x8(%ebp),%eax"),
+        (&[0x8b, 0x4c, 0x50, 0x10],
+         "mov ecx,DWORD PTR [eax+edx*2+0x10]", "mov 0x10(%eax,%edx,2),%ecx"),
+        (&[0xa3, 0x00, 0xa0, 0x04, 0x08],
+         "mov ds:0x804a000,eax", "mov %eax,0x804a000"),
+        (&[0x8d, 0x36],
+         "lea esi,[esi]", "lea (%esi),%esi"),
+        (&[0x40],
+         "inc eax", "inc %eax"),
+        (&[0x49],
+         "dec ecx", "dec %ecx"),
+        (&[0x68, 0x78, 0x56, 0x34, 0x12],
+         "push 0x12345678", "push $0x12345678"),
+        (&[0xe8, 0x1b, 0x00, 0x00, 0x00],
+         "call 38", "call 38"),
+        (&[0x67, 0x8b, 0x40, 0x04],
+         "mov eax,DWORD PTR [bx+si+0x4]", "mov 0x4(%bx,%si),%eax"),
+        (&[0x67, 0x8a, 0x46, 0xfe],
+         "mov al,BYTE PTR [bp-0x2]", "mov -0x2(%bp),%al"),
+        (&[0x67, 0x66, 0x8d, 0x11],
+         "lea dx,[bx+di]", "lea (%bx,%di),%dx"),
+        (&[0x66, 0x8b, 0x03],
+         "mov ax,WORD PTR [ebx]", "mov (%ebx),%ax"),
+        (&[0x0f, 0xb6, 0x04, 0x0e],
+         "movzx eax,BYTE PTR [esi+ecx*1]", "movzbl (%esi,%ecx,1),%eax"),
+        (&[0xcd, 0x80],
+         "int 0x80", "int $0x80"),
+        (&[0xc9],
+         "leave", "leave"),
+        (&[0xc2, 0x08, 0x00],
+         "ret 0x8", "ret $0x8"),
+    ];
+
+    fn text(insn: &Instruction) -> String {
+        if insn.operands.is_empty() { insn.mnemonic.clone() } else { format!("{} {}", insn.mnemonic, insn.operands) }
+    }
+
+    fn check(cases: &[(&[u8], &str, &str)], mode64: bool) {
+        let mut addr = 0;
+        for &(bytes, intel, att) in cases.iter() {
+            for (syntax, expected) in [(Syntax::Intel, intel), (Syntax::Att, att)] {
+                let insn = decode(bytes, addr, mode64, syntax).unwrap_or_else(|| panic!("{:02x?} did not decode", bytes));
+                assert_eq!(insn.len, bytes.len(), "{:02x?}", bytes);
+                assert_eq!(text(&insn), expected, "{:02x?}", bytes);
+            }
+            addr += bytes.len() as u64;
+        }
+    }
+
+    #[test]
+    fn x86_64_encodings() {
+        check(X86_64, true);
+    }
+
+    #[test]
+    fn i386_encodings() {
+        check(I386, false);
+    }
+
+    #[test]
+    fn memory_references() {
+        // lea rax, [rip+0x100] at 0x1000 and mov ds:0x804a000, eax
+        let lea = decode(&[0x48, 0x8d, 0x05, 0x00, 0x01, 0x00, 0x00], 0x1000, true, Syntax::Intel).unwrap();
+        assert_eq!(lea.mem_ref, Some(0x1107));
+        let mov = decode(&[0xa3, 0x00, 0xa0, 0x04, 0x08], 0, false, Syntax::Intel).unwrap();
+        assert_eq!(mov.mem_ref, Some(0x804a000));
+    }
+}
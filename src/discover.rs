@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use crate::disasm;
+use crate::disasm::{Decoder, Flow, Mapping, Syntax};
+use crate::dwarf;
+use crate::elf;
+use crate::elf::ElfFile;
+use crate::plt;
+
+const MAX_START_INSNS: usize = 64;  // instructions of _start searched for the main argument
+
+/// True when the file has no .symtab and function starts have to be recovered.
+pub fn is_stripped(elf: &ElfFile) -> bool {
+    elf.symbols.is_empty() && elf.e_type != elf::ET_REL
+}
+
+/// Function pointers stored in .preinit_array, .init_array and .fini_array.
+pub fn array_entries(elf: &ElfFile) -> Vec<u64> {
+    let size = if elf.is_64() { 8 } else { 4 };
+    let mut addrs = Vec::new();
+    for shdr in elf.section_headers.iter() {
+        if ![elf::SHT_INIT_ARRAY, elf::SHT_FINI_ARRAY, elf::SHT_PREINIT_ARRAY].contains(&shdr.sh_type) {
+            continue;
+        }
+        let mut c = dwarf::Cursor::new(elf.section_data(shdr), 0, elf.is_little_endian());
+        while let Some(addr) = c.sized(size) {
+            addrs.push(addr);
+        }
+    }
+    addrs
+}
+
+/// Reads the pointer stored at `addr`. PIE GOT entries are zero in the file and filled in
+/// by a relative relocation, whose addend is used instead.
+fn pointer_at(elf: &ElfFile, addr: u64) -> Option<u64> {
+    let size = if elf.is_64() { 8 } else { 4 };
+    let value = dwarf::Cursor::new(elf.read_vaddr(addr, size)?, 0, elf.is_little_endian()).sized(size)?;
+    if value != 0 {
+        return Some(value);
+    }
+    elf.section_headers.iter()
+        .filter(|s| s.sh_type == elf::SHT_RELA)
+        .flat_map(|s| elf.relocations(s))
+        .find(|r| r.r_offset == addr && r.r_sym == 0)
+        .map(|r| r.r_addend as u64)
+}
+
+fn is_code(elf: &ElfFile, addr: u64) -> bool {
+    elf.section_for_vaddr(addr).is_some_and(|s| s.is_exec() && !s.name.starts_with(".plt"))
+}
+
+/// Finds main in a stripped executable from the first argument _start hands to
+/// __libc_start_main: "lea rdi, [rip+main]", "adrp x0 / add x0", "lla a0", a literal pool
+/// load into r0, or the last push before the call on i386.
+pub fn find_main(elf: &ElfFile) -> Option<u64> {
+    let mut decoder = Decoder::for_elf(elf, Syntax::Intel)?;
+    let arg: &[&str] = match elf.e_machine {
+        elf::EM_X86_64 => &["rdi", "edi"],
+        elf::EM_AARCH64 => &["x0", "w0"],
+        elf::EM_ARM => &["r0"],
+        elf::EM_RISCV => &["a0"],
+        elf::EM_386 => &[],
+        _ => return None,
+    };
+    let mut pc = elf.e_entry;
+    if elf.e_machine == elf::EM_ARM {
+        if pc & 1 != 0 {
+            decoder.set_mapping(Mapping::Thumb);
+        }
+        pc &= !1;
+    }
+    let end = elf.section_for_vaddr(pc)?;
+    let end = end.sh_addr + end.sh_size;
+    let mut regs: HashMap<String, u64> = HashMap::new();
+    let mut pushed = None;
+    for _ in 0..MAX_START_INSNS {
+        let bytes = elf.read_vaddr(pc, (end.saturating_sub(pc)).min(disasm::MAX_INSN_BYTES as u64) as usize)?;
+        let insn = decoder.decode(bytes, pc)?;
+        pc += insn.len as u64;
+        match insn.flow {
+            Flow::Call(_) | Flow::IndirectCall => {
+                // RISC-V _start calls load_gp before setting up the arguments.
+                let main = if arg.is_empty() { pushed } else { regs.get(arg[0]).cloned() };
+                match main.map(|m| if elf.e_machine == elf::EM_ARM { m & !1 } else { m }) {
+                    Some(main) if is_code(elf, main) => return Some(main),
+                    _ => continue,
+                }
+            }
+            Flow::Next => {}
+            _ => return None,
+        }
+        let ops: Vec<&str> = insn.operands.split(',').map(|o| o.trim()).collect();
+        let value = if let Some(addr) = insn.mem_ref {
+            match insn.mnemonic.as_str() {
+                "lea" | "adr" | "adrp" | "add" | "addi" | "auipc" => Some(addr),
+                _ => pointer_at(elf, addr),
+            }
+        } else if insn.operands.contains('[') {
+            plt::memory_operand(&insn.operands)
+                .and_then(|(base, offset)| regs.get(&base).map(|v| v.wrapping_add(offset)))
+                .and_then(|slot| pointer_at(elf, slot))
+        } else if insn.mnemonic == "add" && ops.len() == 3 {
+            regs.get(ops[1]).zip(plt::immediate(ops[2])).map(|(v, imm)| v.wrapping_add(imm))
+        } else if ops.len() == 2 && ["mov", "movabs", "movw", "li"].contains(&insn.mnemonic.as_str()) {
+            plt::immediate(ops[1])
+        } else {
+            None
+        };
+        if insn.mnemonic == "push" {
+            pushed = value.or_else(|| plt::immediate(ops[0]));
+            continue;
+        }
+        let dest = match ops.first() {
+            Some(&d) if arg.contains(&d) => arg[0],
+            Some(&d) => d,
+            None => continue,
+        };
+        match value {
+            Some(v) => regs.insert(dest.to_string(), v),
+            None => regs.remove(dest),
+        };
+    }
+    None
+}
+
+/// Recognizes a function prologue at `data[i..]`: endbr and "push rbp; mov rbp, rsp" on x86,
+/// paciasp/bti c and "stp x29, x30, [sp, #-N]!" on AArch64, "push {.., lr}" on ARM and
+/// "addi sp, sp, -N" on RISC-V.
+fn is_prologue(elf: &ElfFile, data: &[u8], i: usize) -> bool {
+    let rest = &data[i..];
+    let word = |n: usize| -> Option<u32> {
+        let b = rest.get(n..n + 4)?;
+        Some(if elf.is_little_endian() { u32::from_le_bytes([b[0], b[1], b[2], b[3]]) }
+             else { u32::from_be_bytes([b[0], b[1], b[2], b[3]]) })
+    };
+    let half = |n: usize| -> Option<u16> {
+        let b = rest.get(n..n + 2)?;
+        Some(if elf.is_little_endian() { u16::from_le_bytes([b[0], b[1]]) } else { u16::from_be_bytes([b[0], b[1]]) })
+    };
+    match elf.e_machine {
+        elf::EM_X86_64 | elf::EM_386 => {
+            // Code is entered right after a ret, int3/nop padding or on an aligned address.
+            let boundary = i == 0 || [0xc3, 0xcc, 0x90].contains(&data[i - 1]) || i.is_multiple_of(16);
+            boundary && (rest.starts_with(&[0xf3, 0x0f, 0x1e, 0xfa]) || rest.starts_with(&[0xf3, 0x0f, 0x1e, 0xfb])
+                         || rest.starts_with(&[0x55, 0x48, 0x89, 0xe5]) || rest.starts_with(&[0x55, 0x89, 0xe5]))
+        }
+        elf::EM_AARCH64 => i.is_multiple_of(4) && word(0).is_some_and(|w| w == 0xd503233f || w == 0xd503245f
+                                                               || w & 0xffc07fff == 0xa9807bfd),
+        elf::EM_ARM if elf.e_entry & 1 != 0 => i.is_multiple_of(2) && half(0).is_some_and(|h| h & 0xff00 == 0xb500),
+        elf::EM_ARM => i.is_multiple_of(4) && word(0).is_some_and(|w| w & 0xffffc000 == 0xe92d4000),
+        elf::EM_RISCV => i.is_multiple_of(2) && match half(0) {
+            // c.addi16sp with a negative immediate
+            Some(h) if h & 3 != 3 => h & 0xef83 == 0x6101 && h & 0x1000 != 0,
+            Some(_) => word(0).is_some_and(|w| w & 0x800fffff == 0x80010113),
+            None => false,
+        },
+        _ => false,
+    }
+}
+
+/// Direct call targets and prologue matches found by a linear sweep of the code sections.
+fn sweep(elf: &ElfFile) -> (Vec<u64>, Vec<u64>) {
+    let mut calls = Vec::new();
+    let mut prologues = Vec::new();
+    let mut decoder = match Decoder::for_elf(elf, Syntax::Intel) {
+        Some(decoder) => decoder,
+        None => return (calls, prologues),
+    };
+    let arm = elf.e_machine == elf::EM_ARM;
+    if arm && elf.e_entry & 1 != 0 {
+        decoder.set_mapping(Mapping::Thumb);
+    }
+    for shdr in elf.section_headers.iter().filter(|s| s.is_exec() && !s.name.starts_with(".plt")) {
+        let data = elf.section_data(shdr);
+        for i in 0..data.len() {
+            if is_prologue(elf, data, i) {
+                prologues.push(shdr.sh_addr + i as u64);
+            }
+        }
+        let mut pos = 0;
+        while pos < data.len() {
+            let addr = shdr.sh_addr + pos as u64;
+            match decoder.decode(&data[pos..], addr) {
+                Some(insn) => {
+                    pos += insn.len;
+                    // "call next; pop reg" fetches the pc on i386 and is not a function.
+                    if let Flow::Call(t) = insn.flow {
+                        if t != addr + insn.len as u64 {
+                            calls.push(if arm { t & !1 } else { t });
+                        }
+                    }
+                }
+                None => pos += decoder.skip_len(&data[pos..]).max(1),
+            }
+        }
+    }
+    (calls, prologues)
+}
+
+/// Recovers function starts of a binary without .symtab from .eh_frame FDEs, the entry point,
+/// init/fini array entries, main, direct call targets and prologue signatures. Addresses
+/// inside an FDE range are only taken from the FDE itself.
+pub fn functions(elf: &ElfFile) -> BTreeMap<u64, String> {
+    let arm = elf.e_machine == elf::EM_ARM;
+    let mut ranges = dwarf::eh_frame_ranges(elf);
+    ranges.sort();
+    let inside_fde = |addr: u64| {
+        let i = ranges.partition_point(|r| r.0 < addr);
+        i > 0 && addr < ranges[i - 1].1
+    };
+    let mut starts: Vec<u64> = ranges.iter().map(|r| r.0).collect();
+    starts.push(elf.e_entry);
+    starts.extend(array_entries(elf));
+    let (calls, prologues) = sweep(elf);
+    starts.extend(calls.into_iter().chain(prologues).filter(|&a| !inside_fde(a)));
+
+    let mut found = BTreeMap::new();
+    for addr in starts {
+        let addr = if arm { addr & !1 } else { addr };
+        if is_code(elf, addr) {
+            found.insert(addr, format!("sub_{:x}", addr));
+        }
+    }
+    let entry = if arm { elf.e_entry & !1 } else { elf.e_entry };
+    if let Some(name) = found.get_mut(&entry) {
+        *name = "_start".to_string();
+    }
+    if let Some(main) = find_main(elf) {
+        found.insert(main, "main".to_string());
+    }
+    found
+}
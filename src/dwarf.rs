@@ -0,0 +1,838 @@
+use crate::elf::ElfFile;
+
+// Tags
+pub const DW_TAG_ARRAY_TYPE: u16            = 0x01;
+pub const DW_TAG_CLASS_TYPE: u16            = 0x02;
+pub const DW_TAG_ENUMERATION_TYPE: u16      = 0x04;
+pub const DW_TAG_FORMAL_PARAMETER: u16      = 0x05;
+pub const DW_TAG_MEMBER: u16                = 0x0d;
+pub const DW_TAG_POINTER_TYPE: u16          = 0x0f;
+pub const DW_TAG_REFERENCE_TYPE: u16        = 0x10;
+pub const DW_TAG_STRUCTURE_TYPE: u16        = 0x13;
+pub const DW_TAG_SUBROUTINE_TYPE: u16       = 0x15;
+pub const DW_TAG_TYPEDEF: u16               = 0x16;
+pub const DW_TAG_UNION_TYPE: u16            = 0x17;
+pub const DW_TAG_UNSPECIFIED_PARAMETERS: u16 = 0x18;
+pub const DW_TAG_SUBRANGE_TYPE: u16         = 0x21;
+pub const DW_TAG_CONST_TYPE: u16            = 0x26;
+pub const DW_TAG_ENUMERATOR: u16            = 0x28;
+pub const DW_TAG_SUBPROGRAM: u16            = 0x2e;
+pub const DW_TAG_VARIABLE: u16              = 0x34;
+pub const DW_TAG_VOLATILE_TYPE: u16         = 0x35;
+pub const DW_TAG_RESTRICT_TYPE: u16         = 0x37;
+pub const DW_TAG_RVALUE_REFERENCE_TYPE: u16 = 0x42;
+pub const DW_TAG_ATOMIC_TYPE: u16           = 0x47;
+
+// Attributes
+pub const DW_AT_NAME: u16                   = 0x03;
+pub const DW_AT_BYTE_SIZE: u16              = 0x0b;
+pub const DW_AT_BIT_OFFSET: u16             = 0x0c;
+pub const DW_AT_BIT_SIZE: u16               = 0x0d;
+pub const DW_AT_LOW_PC: u16                 = 0x11;
+pub const DW_AT_HIGH_PC: u16                = 0x12;
+pub const DW_AT_CONST_VALUE: u16            = 0x1c;
+pub const DW_AT_UPPER_BOUND: u16            = 0x2f;
+pub const DW_AT_ABSTRACT_ORIGIN: u16        = 0x31;
+pub const DW_AT_COUNT: u16                  = 0x37;
+pub const DW_AT_DATA_MEMBER_LOCATION: u16   = 0x38;
+pub const DW_AT_DECLARATION: u16            = 0x3c;
+pub const DW_AT_EXTERNAL: u16               = 0x3f;
+pub const DW_AT_SPECIFICATION: u16          = 0x47;
+pub const DW_AT_TYPE: u16                   = 0x49;
+pub const DW_AT_RANGES: u16                 = 0x55;
+pub const DW_AT_DATA_BIT_OFFSET: u16        = 0x6b;
+pub const DW_AT_LINKAGE_NAME: u16           = 0x6e;
+pub const DW_AT_STR_OFFSETS_BASE: u16       = 0x72;
+pub const DW_AT_ADDR_BASE: u16              = 0x73;
+pub const DW_AT_RNGLISTS_BASE: u16          = 0x74;
+pub const DW_AT_MIPS_LINKAGE_NAME: u16      = 0x2007;
+
+// Forms
+const DW_FORM_ADDR: u16             = 0x01;
+const DW_FORM_BLOCK2: u16           = 0x03;
+const DW_FORM_BLOCK4: u16           = 0x04;
+const DW_FORM_DATA2: u16            = 0x05;
+const DW_FORM_DATA4: u16            = 0x06;
+const DW_FORM_DATA8: u16            = 0x07;
+const DW_FORM_STRING: u16           = 0x08;
+const DW_FORM_BLOCK: u16            = 0x09;
+const DW_FORM_BLOCK1: u16           = 0x0a;
+const DW_FORM_DATA1: u16            = 0x0b;
+const DW_FORM_FLAG: u16             = 0x0c;
+const DW_FORM_SDATA: u16            = 0x0d;
+const DW_FORM_STRP: u16             = 0x0e;
+const DW_FORM_UDATA: u16            = 0x0f;
+const DW_FORM_REF_ADDR: u16         = 0x10;
+const DW_FORM_REF1: u16             = 0x11;
+const DW_FORM_REF2: u16             = 0x12;
+const DW_FORM_REF4: u16             = 0x13;
+const DW_FORM_REF8: u16             = 0x14;
+const DW_FORM_REF_UDATA: u16        = 0x15;
+const DW_FORM_INDIRECT: u16         = 0x16;
+const DW_FORM_SEC_OFFSET: u16       = 0x17;
+const DW_FORM_EXPRLOC: u16          = 0x18;
+const DW_FORM_FLAG_PRESENT: u16     = 0x19;
+const DW_FORM_STRX: u16             = 0x1a;
+const DW_FORM_ADDRX: u16            = 0x1b;
+const DW_FORM_REF_SUP4: u16         = 0x1c;
+const DW_FORM_STRP_SUP: u16         = 0x1d;
+const DW_FORM_DATA16: u16           = 0x1e;
+const DW_FORM_LINE_STRP: u16        = 0x1f;
+const DW_FORM_REF_SIG8: u16         = 0x20;
+const DW_FORM_IMPLICIT_CONST: u16   = 0x21;
+const DW_FORM_LOCLISTX: u16         = 0x22;
+const DW_FORM_RNGLISTX: u16         = 0x23;
+const DW_FORM_REF_SUP8: u16         = 0x24;
+const DW_FORM_STRX1: u16            = 0x25;
+const DW_FORM_STRX2: u16            = 0x26;
+const DW_FORM_STRX3: u16            = 0x27;
+const DW_FORM_STRX4: u16            = 0x28;
+const DW_FORM_ADDRX1: u16           = 0x29;
+const DW_FORM_ADDRX2: u16           = 0x2a;
+const DW_FORM_ADDRX3: u16           = 0x2b;
+const DW_FORM_ADDRX4: u16           = 0x2c;
+const DW_FORM_GNU_ADDR_INDEX: u16   = 0x1f01;
+const DW_FORM_GNU_STR_INDEX: u16    = 0x1f02;
+const DW_FORM_GNU_REF_ALT: u16      = 0x1f20;
+const DW_FORM_GNU_STRP_ALT: u16     = 0x1f21;
+
+// Unit types (DWARF 5)
+const DW_UT_TYPE: u8            = 0x02;
+const DW_UT_SKELETON: u8        = 0x04;
+const DW_UT_SPLIT_COMPILE: u8   = 0x05;
+const DW_UT_SPLIT_TYPE: u8      = 0x06;
+
+// Pointer encodings (.eh_frame)
+const DW_EH_PE_OMIT: u8         = 0xff;
+const DW_EH_PE_ULEB128: u8      = 0x01;
+const DW_EH_PE_UDATA2: u8       = 0x02;
+const DW_EH_PE_UDATA4: u8       = 0x03;
+const DW_EH_PE_UDATA8: u8       = 0x04;
+const DW_EH_PE_SLEB128: u8      = 0x09;
+const DW_EH_PE_SDATA2: u8       = 0x0a;
+const DW_EH_PE_SDATA4: u8       = 0x0b;
+const DW_EH_PE_SDATA8: u8       = 0x0c;
+const DW_EH_PE_PCREL: u8        = 0x10;
+const DW_EH_PE_DATAREL: u8      = 0x30;
+
+// Range list entries (DWARF 5)
+const DW_RLE_END_OF_LIST: u8    = 0x00;
+const DW_RLE_BASE_ADDRESSX: u8  = 0x01;
+const DW_RLE_STARTX_ENDX: u8    = 0x02;
+const DW_RLE_STARTX_LENGTH: u8  = 0x03;
+const DW_RLE_OFFSET_PAIR: u8    = 0x04;
+const DW_RLE_BASE_ADDRESS: u8   = 0x05;
+const DW_RLE_START_END: u8      = 0x06;
+const DW_RLE_START_LENGTH: u8   = 0x07;
+
+/// Sequential reader over a DWARF section.
+#[derive(Clone)]
+pub struct Cursor<'a> {
+    bytes: &'a [u8],
+    pub pos: usize,
+    little_endian: bool,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(bytes: &'a [u8], pos: usize, little_endian: bool) -> Cursor<'a> {
+        Cursor{bytes, pos, little_endian}
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    pub fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(slice)
+    }
+
+    fn uint(&mut self, len: usize) -> Option<u64> {
+        let slice = self.take(len)?;
+        let mut value = 0u64;
+        for i in 0..len {
+            let b = if self.little_endian { slice[len - 1 - i] } else { slice[i] };
+            value = (value << 8) | b as u64;
+        }
+        Some(value)
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        self.uint(1).map(|v| v as u8)
+    }
+
+    pub fn u16(&mut self) -> Option<u16> {
+        self.uint(2).map(|v| v as u16)
+    }
+
+    pub fn u24(&mut self) -> Option<u32> {
+        self.uint(3).map(|v| v as u32)
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        self.uint(4).map(|v| v as u32)
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        self.uint(8)
+    }
+
+    pub fn sized(&mut self, len: usize) -> Option<u64> {
+        self.uint(len)
+    }
+
+    pub fn uleb(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            if shift < 64 {
+                value |= ((b & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if b & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+    }
+
+    pub fn sleb(&mut self) -> Option<i64> {
+        let mut value = 0i64;
+        let mut shift = 0;
+        loop {
+            let b = self.u8()?;
+            if shift < 64 {
+                value |= ((b & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if b & 0x80 == 0 {
+                if shift < 64 && b & 0x40 != 0 {
+                    value |= -1i64 << shift;
+                }
+                return Some(value);
+            }
+        }
+    }
+
+    pub fn cstr(&mut self) -> Option<String> {
+        let tail = self.bytes.get(self.pos..)?;
+        let len = tail.iter().position(|&b| b == 0)?;
+        self.pos += len + 1;
+        Some(String::from_utf8_lossy(&tail[..len]).into_owned())
+    }
+
+    /// Reads an initial length field, returning (length, is_dwarf64).
+    pub fn initial_length(&mut self) -> Option<(u64, bool)> {
+        let len = self.u32()?;
+        if len == 0xffff_ffff {
+            Some((self.u64()?, true))
+        } else {
+            Some((len as u64, false))
+        }
+    }
+
+    pub fn offset(&mut self, dwarf64: bool) -> Option<u64> {
+        if dwarf64 { self.u64() } else { self.u32().map(|v| v as u64) }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum AttrValue {
+    Addr(u64),
+    Udata(u64),
+    Sdata(i64),
+    Str(String),
+    Ref(u64),           // offset into .debug_info
+    SecOffset(u64),
+    Block(Vec<u8>),
+    Flag(bool),
+    StrIndex(u64),
+    AddrIndex(u64),
+    RangeListIndex(u64),
+    Signature,          // DW_FORM_REF_SIG8; type units are not followed
+}
+
+impl AttrValue {
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            AttrValue::Addr(v) | AttrValue::Udata(v) | AttrValue::SecOffset(v) => Some(v),
+            AttrValue::Sdata(v) => Some(v as u64),
+            AttrValue::Flag(f) => Some(f as u64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            AttrValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// Unit header fields needed to decode attribute forms.
+#[derive(Clone, Copy)]
+struct UnitHeader {
+    offset:     u64,
+    version:    u16,
+    addr_size:  u8,
+    dwarf64:    bool,
+}
+
+struct Abbrev {
+    tag: u16,
+    has_children: bool,
+    specs: Vec<(u16, u16, i64)>,    // (attribute, form, implicit const)
+}
+
+#[derive(Clone, Debug)]
+pub struct Die {
+    pub offset: u64,
+    pub tag:    u16,
+    pub depth:  usize,
+    pub attrs:  Vec<(u16, AttrValue)>,
+}
+
+impl Die {
+    pub fn attr(&self, at: u16) -> Option<&AttrValue> {
+        self.attrs.iter().find(|a| a.0 == at).map(|a| &a.1)
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.attr(DW_AT_NAME).and_then(|v| v.as_str())
+    }
+
+    pub fn udata(&self, at: u16) -> Option<u64> {
+        self.attr(at).and_then(|v| v.as_u64())
+    }
+
+    pub fn type_ref(&self) -> Option<u64> {
+        match self.attr(DW_AT_TYPE) {
+            Some(AttrValue::Ref(r)) => Some(*r),
+            _ => None,
+        }
+    }
+}
+
+pub struct Unit {
+    pub offset:     u64,
+    pub version:    u16,
+    pub addr_size:  u8,
+    pub dwarf64:    bool,
+    pub dies:       Vec<Die>,
+}
+
+impl Unit {
+    pub fn root(&self) -> Option<&Die> {
+        self.dies.first()
+    }
+
+    pub fn name(&self) -> String {
+        self.root().and_then(|d| d.name()).unwrap_or("").to_string()
+    }
+
+    /// Indexes of the direct children of the DIE at `index`.
+    pub fn children(&self, index: usize) -> Vec<usize> {
+        let depth = self.dies[index].depth;
+        let mut children = Vec::new();
+        for (i, die) in self.dies.iter().enumerate().skip(index + 1) {
+            if die.depth <= depth {
+                break;
+            }
+            if die.depth == depth + 1 {
+                children.push(i);
+            }
+        }
+        children
+    }
+
+    pub fn die_index(&self, offset: u64) -> Option<usize> {
+        self.dies.binary_search_by_key(&offset, |d| d.offset).ok()
+    }
+}
+
+/// The DWARF debug sections of an ELF file.
+pub struct Dwarf<'a> {
+    little_endian:  bool,
+    info:           &'a [u8],
+    abbrev:         &'a [u8],
+    str:            &'a [u8],
+    line_str:       &'a [u8],
+    str_offsets:    &'a [u8],
+    addr:           &'a [u8],
+    ranges:         &'a [u8],
+    rnglists:       &'a [u8],
+    aranges:        &'a [u8],
+}
+
+impl<'a> Dwarf<'a> {
+    /// Returns None when the file carries no usable .debug_info.
+    pub fn load(elf: &'a ElfFile) -> Option<Dwarf<'a>> {
+        let section = |name: &str| -> &'a [u8] {
+            match elf.section_by_name(name) {
+                Some(shdr) if shdr.sh_flags & crate::elf::SHF_COMPRESSED == 0 => elf.section_data(shdr),
+                _ => &[],
+            }
+        };
+        let info = section(".debug_info");
+        if info.is_empty() {
+            return None;
+        }
+        Some(Dwarf {
+            little_endian: elf.is_little_endian(),
+            info,
+            abbrev: section(".debug_abbrev"),
+            str: section(".debug_str"),
+            line_str: section(".debug_line_str"),
+            str_offsets: section(".debug_str_offsets"),
+            addr: section(".debug_addr"),
+            ranges: section(".debug_ranges"),
+            rnglists: section(".debug_rnglists"),
+            aranges: section(".debug_aranges"),
+        })
+    }
+
+    fn cursor(&self, bytes: &'a [u8], pos: usize) -> Cursor<'a> {
+        Cursor::new(bytes, pos, self.little_endian)
+    }
+
+    fn parse_abbrevs(&self, offset: u64) -> Vec<(u64, Abbrev)> {
+        let mut abbrevs = Vec::new();
+        let mut c = self.cursor(self.abbrev, offset as usize);
+        loop {
+            let code = match c.uleb() {
+                Some(0) | None => break,
+                Some(code) => code,
+            };
+            let tag = c.uleb().unwrap_or(0) as u16;
+            let has_children = c.u8().unwrap_or(0) != 0;
+            let mut specs = Vec::new();
+            loop {
+                let at = c.uleb().unwrap_or(0) as u16;
+                let form = c.uleb().unwrap_or(0) as u16;
+                let implicit = if form == DW_FORM_IMPLICIT_CONST { c.sleb().unwrap_or(0) } else { 0 };
+                if at == 0 && form == 0 {
+                    break;
+                }
+                specs.push((at, form, implicit));
+            }
+            abbrevs.push((code, Abbrev{tag, has_children, specs}));
+        }
+        abbrevs.sort_by_key(|a| a.0);
+        abbrevs
+    }
+
+    fn read_attr(&self, c: &mut Cursor<'a>, form: u16, implicit: i64, hdr: UnitHeader) -> Option<AttrValue> {
+        let (unit_offset, addr_size, dwarf64) = (hdr.offset, hdr.addr_size, hdr.dwarf64);
+        let value = match form {
+            DW_FORM_ADDR => AttrValue::Addr(c.sized(addr_size as usize)?),
+            DW_FORM_BLOCK1 => { let len = c.u8()? as usize; AttrValue::Block(c.take(len)?.to_vec()) }
+            DW_FORM_BLOCK2 => { let len = c.u16()? as usize; AttrValue::Block(c.take(len)?.to_vec()) }
+            DW_FORM_BLOCK4 => { let len = c.u32()? as usize; AttrValue::Block(c.take(len)?.to_vec()) }
+            DW_FORM_BLOCK | DW_FORM_EXPRLOC => { let len = c.uleb()? as usize; AttrValue::Block(c.take(len)?.to_vec()) }
+            DW_FORM_DATA1 => AttrValue::Udata(c.u8()? as u64),
+            DW_FORM_DATA2 => AttrValue::Udata(c.u16()? as u64),
+            DW_FORM_DATA4 => AttrValue::Udata(c.u32()? as u64),
+            DW_FORM_DATA8 => AttrValue::Udata(c.u64()?),
+            DW_FORM_DATA16 => AttrValue::Block(c.take(16)?.to_vec()),
+            DW_FORM_SDATA => AttrValue::Sdata(c.sleb()?),
+            DW_FORM_UDATA => AttrValue::Udata(c.uleb()?),
+            DW_FORM_IMPLICIT_CONST => AttrValue::Sdata(implicit),
+            DW_FORM_STRING => AttrValue::Str(c.cstr()?),
+            DW_FORM_STRP => {
+                let off = c.offset(dwarf64)? as usize;
+                AttrValue::Str(self.cursor(self.str, off).cstr().unwrap_or_default())
+            }
+            DW_FORM_LINE_STRP => {
+                let off = c.offset(dwarf64)? as usize;
+                AttrValue::Str(self.cursor(self.line_str, off).cstr().unwrap_or_default())
+            }
+            DW_FORM_STRP_SUP | DW_FORM_GNU_STRP_ALT => { c.offset(dwarf64)?; AttrValue::Str(String::new()) }
+            DW_FORM_STRX | DW_FORM_GNU_STR_INDEX => AttrValue::StrIndex(c.uleb()?),
+            DW_FORM_STRX1 => AttrValue::StrIndex(c.u8()? as u64),
+            DW_FORM_STRX2 => AttrValue::StrIndex(c.u16()? as u64),
+            DW_FORM_STRX3 => AttrValue::StrIndex(c.u24()? as u64),
+            DW_FORM_STRX4 => AttrValue::StrIndex(c.u32()? as u64),
+            DW_FORM_ADDRX | DW_FORM_GNU_ADDR_INDEX => AttrValue::AddrIndex(c.uleb()?),
+            DW_FORM_ADDRX1 => AttrValue::AddrIndex(c.u8()? as u64),
+            DW_FORM_ADDRX2 => AttrValue::AddrIndex(c.u16()? as u64),
+            DW_FORM_ADDRX3 => AttrValue::AddrIndex(c.u24()? as u64),
+            DW_FORM_ADDRX4 => AttrValue::AddrIndex(c.u32()? as u64),
+            DW_FORM_FLAG => AttrValue::Flag(c.u8()? != 0),
+            DW_FORM_FLAG_PRESENT => AttrValue::Flag(true),
+            DW_FORM_REF1 => AttrValue::Ref(unit_offset + c.u8()? as u64),
+            DW_FORM_REF2 => AttrValue::Ref(unit_offset + c.u16()? as u64),
+            DW_FORM_REF4 => AttrValue::Ref(unit_offset + c.u32()? as u64),
+            DW_FORM_REF8 => AttrValue::Ref(unit_offset + c.u64()?),
+            DW_FORM_REF_UDATA => AttrValue::Ref(unit_offset + c.uleb()?),
+            DW_FORM_REF_ADDR => {
+                // DWARF 2 sized ref_addr like an address, later versions like an offset.
+                if hdr.version <= 2 { AttrValue::Ref(c.sized(addr_size as usize)?) } else { AttrValue::Ref(c.offset(dwarf64)?) }
+            }
+            DW_FORM_REF_SIG8 => { c.u64()?; AttrValue::Signature }
+            DW_FORM_REF_SUP4 => { c.u32()?; AttrValue::Udata(0) }
+            DW_FORM_REF_SUP8 => { c.u64()?; AttrValue::Udata(0) }
+            DW_FORM_GNU_REF_ALT => { c.offset(dwarf64)?; AttrValue::Udata(0) }
+            DW_FORM_SEC_OFFSET => AttrValue::SecOffset(c.offset(dwarf64)?),
+            DW_FORM_LOCLISTX => AttrValue::Udata(c.uleb()?),
+            DW_FORM_RNGLISTX => AttrValue::RangeListIndex(c.uleb()?),
+            DW_FORM_INDIRECT => {
+                let form = c.uleb()? as u16;
+                return self.read_attr(c, form, implicit, hdr);
+            }
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Parses every unit in .debug_info, with all DIEs materialized.
+    pub fn units(&self) -> Vec<Unit> {
+        let mut units = Vec::new();
+        let mut c = self.cursor(self.info, 0);
+        while !c.is_empty() {
+            let unit_offset = c.pos as u64;
+            let (length, dwarf64) = match c.initial_length() {
+                Some(v) => v,
+                None => break,
+            };
+            let end = c.pos.saturating_add(length as usize).min(self.info.len());
+            if let Some(unit) = self.parse_unit(c.clone(), unit_offset, end, dwarf64) {
+                units.push(unit);
+            }
+            c.pos = end;
+        }
+        units
+    }
+
+    fn parse_unit(&self, mut c: Cursor<'a>, unit_offset: u64, end: usize, dwarf64: bool) -> Option<Unit> {
+        let version = c.u16()?;
+        let (abbrev_offset, addr_size) = if version >= 5 {
+            let unit_type = c.u8()?;
+            let addr_size = c.u8()?;
+            let abbrev_offset = c.offset(dwarf64)?;
+            match unit_type {
+                DW_UT_SKELETON | DW_UT_SPLIT_COMPILE => { c.u64()?; }
+                DW_UT_TYPE | DW_UT_SPLIT_TYPE => { c.u64()?; c.offset(dwarf64)?; }
+                _ => {}
+            }
+            (abbrev_offset, addr_size)
+        } else {
+            let abbrev_offset = c.offset(dwarf64)?;
+            (abbrev_offset, c.u8()?)
+        };
+        let abbrevs = self.parse_abbrevs(abbrev_offset);
+        let hdr = UnitHeader{offset: unit_offset, version, addr_size, dwarf64};
+
+        let mut dies: Vec<Die> = Vec::new();
+        let mut parents: Vec<usize> = Vec::new();
+        while c.pos < end {
+            let offset = c.pos as u64;
+            let code = c.uleb()?;
+            if code == 0 {
+                parents.pop();
+                if parents.is_empty() {
+                    break;
+                }
+                continue;
+            }
+            let abbrev = match abbrevs.binary_search_by_key(&code, |a| a.0) {
+                Ok(i) => &abbrevs[i].1,
+                Err(_) => break,
+            };
+            let mut attrs = Vec::with_capacity(abbrev.specs.len());
+            for &(at, form, implicit) in abbrev.specs.iter() {
+                let value = self.read_attr(&mut c, form, implicit, hdr)?;
+                attrs.push((at, value));
+            }
+            let index = dies.len();
+            dies.push(Die{offset, tag: abbrev.tag, depth: parents.len(), attrs});
+            if abbrev.has_children {
+                parents.push(index);
+            } else if parents.is_empty() {
+                break;
+            }
+        }
+
+        let mut unit = Unit{offset: unit_offset, version, addr_size, dwarf64, dies};
+        self.resolve_indexed(&mut unit);
+        Some(unit)
+    }
+
+    /// Replaces DWARF 5 string/address indexes with the values they refer to.
+    fn resolve_indexed(&self, unit: &mut Unit) {
+        let root = match unit.root() {
+            Some(root) => root,
+            None => return,
+        };
+        let offset_size = if unit.dwarf64 { 8 } else { 4 };
+        let str_offsets_base = root.udata(DW_AT_STR_OFFSETS_BASE).unwrap_or(if unit.dwarf64 { 16 } else { 8 });
+        let addr_base = root.udata(DW_AT_ADDR_BASE).unwrap_or(8);
+        let addr_size = unit.addr_size as usize;
+        for die in unit.dies.iter_mut() {
+            for attr in die.attrs.iter_mut() {
+                match attr.1 {
+                    AttrValue::StrIndex(index) => {
+                        let pos = str_offsets_base as usize + index as usize * offset_size;
+                        let off = self.cursor(self.str_offsets, pos).sized(offset_size).unwrap_or(u64::MAX);
+                        attr.1 = AttrValue::Str(self.cursor(self.str, off as usize).cstr().unwrap_or_default());
+                    }
+                    AttrValue::AddrIndex(index) => {
+                        let pos = addr_base as usize + index as usize * addr_size;
+                        attr.1 = AttrValue::Addr(self.cursor(self.addr, pos).sized(addr_size).unwrap_or(0));
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Returns the address ranges covered by a DIE (low_pc/high_pc or DW_AT_RANGES).
+    pub fn die_ranges(&self, unit: &Unit, die: &Die) -> Vec<(u64, u64)> {
+        let low_pc = match die.attr(DW_AT_LOW_PC) {
+            Some(AttrValue::Addr(a)) => Some(*a),
+            _ => None,
+        };
+        if let (Some(low), Some(high)) = (low_pc, die.attr(DW_AT_HIGH_PC)) {
+            let high = match high {
+                AttrValue::Addr(a) => *a,
+                other => low.wrapping_add(other.as_u64().unwrap_or(0)),
+            };
+            return if high > low { vec![(low, high)] } else { Vec::new() };
+        }
+
+        let base = unit.root().and_then(|r| match r.attr(DW_AT_LOW_PC) {
+            Some(AttrValue::Addr(a)) => Some(*a),
+            _ => None,
+        }).unwrap_or(0);
+        match die.attr(DW_AT_RANGES) {
+            Some(AttrValue::SecOffset(off)) | Some(AttrValue::Udata(off)) => {
+                if unit.version >= 5 {
+                    self.rnglist(unit, *off, base)
+                } else {
+                    self.debug_ranges(unit, *off, base)
+                }
+            }
+            Some(AttrValue::RangeListIndex(index)) => {
+                let offset_size = if unit.dwarf64 { 8 } else { 4 };
+                let rnglists_base = unit.root().and_then(|r| r.udata(DW_AT_RNGLISTS_BASE))
+                    .unwrap_or(if unit.dwarf64 { 20 } else { 12 });
+                let pos = rnglists_base as usize + *index as usize * offset_size;
+                match self.cursor(self.rnglists, pos).sized(offset_size) {
+                    Some(off) => self.rnglist(unit, rnglists_base + off, base),
+                    None => Vec::new(),
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn debug_ranges(&self, unit: &Unit, offset: u64, mut base: u64) -> Vec<(u64, u64)> {
+        let addr_size = unit.addr_size as usize;
+        let max = if addr_size == 8 { u64::MAX } else { 0xffff_ffff };
+        let mut ranges = Vec::new();
+        let mut c = self.cursor(self.ranges, offset as usize);
+        while let (Some(start), Some(end)) = (c.sized(addr_size), c.sized(addr_size)) {
+            if start == 0 && end == 0 {
+                break;
+            }
+            if start == max {
+                base = end;
+                continue;
+            }
+            if end > start {
+                ranges.push((base.wrapping_add(start), base.wrapping_add(end)));
+            }
+        }
+        ranges
+    }
+
+    fn rnglist(&self, unit: &Unit, offset: u64, mut base: u64) -> Vec<(u64, u64)> {
+        let addr_size = unit.addr_size as usize;
+        let addr_base = unit.root().and_then(|r| r.udata(DW_AT_ADDR_BASE)).unwrap_or(8);
+        let addrx = |index: u64| -> u64 {
+            self.cursor(self.addr, addr_base as usize + index as usize * addr_size).sized(addr_size).unwrap_or(0)
+        };
+        let mut ranges = Vec::new();
+        let mut c = self.cursor(self.rnglists, offset as usize);
+        loop {
+            let (start, end) = match c.u8() {
+                None | Some(DW_RLE_END_OF_LIST) => break,
+                Some(DW_RLE_BASE_ADDRESSX) => { base = addrx(c.uleb().unwrap_or(0)); continue; }
+                Some(DW_RLE_BASE_ADDRESS) => { base = c.sized(addr_size).unwrap_or(0); continue; }
+                Some(DW_RLE_STARTX_ENDX) => (addrx(c.uleb().unwrap_or(0)), addrx(c.uleb().unwrap_or(0))),
+                Some(DW_RLE_STARTX_LENGTH) => {
+                    let start = addrx(c.uleb().unwrap_or(0));
+                    (start, start.wrapping_add(c.uleb().unwrap_or(0)))
+                }
+                Some(DW_RLE_OFFSET_PAIR) => {
+                    let start = c.uleb().unwrap_or(0);
+                    let end = c.uleb().unwrap_or(0);
+                    (base.wrapping_add(start), base.wrapping_add(end))
+                }
+                Some(DW_RLE_START_END) => (c.sized(addr_size).unwrap_or(0), c.sized(addr_size).unwrap_or(0)),
+                Some(DW_RLE_START_LENGTH) => {
+                    let start = c.sized(addr_size).unwrap_or(0);
+                    (start, start.wrapping_add(c.uleb().unwrap_or(0)))
+                }
+                Some(_) => break,
+            };
+            if end > start {
+                ranges.push((start, end));
+            }
+        }
+        ranges
+    }
+
+    /// Returns (unit offset, [(start, end)]) for every set in .debug_aranges.
+    pub fn aranges(&self) -> Vec<(u64, Vec<(u64, u64)>)> {
+        let mut sets = Vec::new();
+        let mut c = self.cursor(self.aranges, 0);
+        while !c.is_empty() {
+            let (length, dwarf64) = match c.initial_length() {
+                Some(v) => v,
+                None => break,
+            };
+            let end = c.pos.saturating_add(length as usize);
+            let header_start = c.pos;
+            let _version = c.u16();
+            let unit_offset = c.offset(dwarf64).unwrap_or(0);
+            let addr_size = c.u8().unwrap_or(0) as usize;
+            let _seg_size = c.u8();
+            // Tuples are aligned to twice the address size from the set start.
+            let tuple = 2 * addr_size.max(1);
+            let consumed = c.pos - header_start + if dwarf64 { 12 } else { 4 };
+            c.pos += (tuple - consumed % tuple) % tuple;
+            let mut ranges = Vec::new();
+            while c.pos + tuple <= end {
+                let start = c.sized(addr_size).unwrap_or(0);
+                let len = c.sized(addr_size).unwrap_or(0);
+                if start == 0 && len == 0 {
+                    break;
+                }
+                ranges.push((start, start.wrapping_add(len)));
+            }
+            sets.push((unit_offset, ranges));
+            c.pos = end;
+        }
+        sets
+    }
+}
+
+/// Returns (compilation unit name, address ranges) for every unit with code.
+pub fn compile_unit_ranges(elf: &ElfFile) -> Vec<(String, Vec<(u64, u64)>)> {
+    let dwarf = match Dwarf::load(elf) {
+        Some(dwarf) => dwarf,
+        None => return Vec::new(),
+    };
+    let units = dwarf.units();
+    let aranges = dwarf.aranges();
+    let mut result = Vec::new();
+    for unit in units.iter() {
+        let root = match unit.root() {
+            Some(root) => root,
+            None => continue,
+        };
+        let mut ranges = dwarf.die_ranges(unit, root);
+        if ranges.is_empty() {
+            if let Some(set) = aranges.iter().find(|s| s.0 == unit.offset) {
+                ranges = set.1.clone();
+            }
+        }
+        if !ranges.is_empty() {
+            result.push((unit.name(), ranges));
+        }
+    }
+    result
+}
+
+/// Reads a pointer stored with a DW_EH_PE_* encoding at `vaddr`, the address of the field.
+fn read_encoded(c: &mut Cursor, encoding: u8, addr_size: usize, vaddr: u64, data_base: u64) -> Option<u64> {
+    let value = match encoding & 0x0f {
+        0 => c.sized(addr_size)?,
+        DW_EH_PE_ULEB128 => c.uleb()?,
+        DW_EH_PE_UDATA2 => c.u16()? as u64,
+        DW_EH_PE_UDATA4 => c.u32()? as u64,
+        DW_EH_PE_UDATA8 => c.u64()?,
+        DW_EH_PE_SLEB128 => c.sleb()? as u64,
+        DW_EH_PE_SDATA2 => c.u16()? as i16 as u64,
+        DW_EH_PE_SDATA4 => c.u32()? as i32 as u64,
+        DW_EH_PE_SDATA8 => c.u64()?,
+        _ => return None,
+    };
+    let value = match encoding & 0x70 {
+        DW_EH_PE_PCREL => value.wrapping_add(vaddr),
+        DW_EH_PE_DATAREL => value.wrapping_add(data_base),
+        _ => value,
+    };
+    Some(if addr_size == 4 { value & 0xffff_ffff } else { value })
+}
+
+/// Returns the (start, end) address range of every FDE in .eh_frame.
+pub fn eh_frame_ranges(elf: &ElfFile) -> Vec<(u64, u64)> {
+    let shdr = match elf.section_by_name(".eh_frame") {
+        Some(shdr) if shdr.sh_type != crate::elf::SHT_NOBITS => shdr,
+        _ => return Vec::new(),
+    };
+    let data = elf.section_data(shdr);
+    let addr_size = if elf.is_64() { 8 } else { 4 };
+    let data_base = elf.section_by_name(".got").map_or(0, |s| s.sh_addr);
+    let mut fde_encodings: Vec<(usize, u8)> = Vec::new();   // CIE offset -> FDE pointer encoding
+    let mut ranges = Vec::new();
+    let mut c = Cursor::new(data, 0, elf.is_little_endian());
+    while !c.is_empty() {
+        let start = c.pos;
+        let (length, dwarf64) = match c.initial_length() {
+            Some((0, _)) | None => break,
+            Some(v) => v,
+        };
+        let body = c.pos;
+        let end = body.saturating_add(length as usize);
+        let id = c.offset(dwarf64).unwrap_or(0);
+        if id == 0 {
+            fde_encodings.push((start, cie_fde_encoding(&mut c, addr_size).unwrap_or(0)));
+        } else if let Some(cie) = body.checked_sub(id as usize) {
+            let encoding = fde_encodings.iter().find(|e| e.0 == cie).map_or(0, |e| e.1);
+            let field = shdr.sh_addr.wrapping_add(c.pos as u64);
+            let begin = read_encoded(&mut c, encoding, addr_size, field, data_base);
+            let len = read_encoded(&mut c, encoding & 0x0f, addr_size, 0, 0);
+            if let (Some(begin), Some(len)) = (begin, len) {
+                if len != 0 {
+                    ranges.push((begin, begin.wrapping_add(len)));
+                }
+            }
+        }
+        c.pos = end;
+    }
+    ranges
+}
+
+/// Parses a CIE body after its id and returns the encoding of FDE addresses ('R').
+fn cie_fde_encoding(c: &mut Cursor, addr_size: usize) -> Option<u8> {
+    let version = c.u8()?;
+    let augmentation = c.cstr()?;
+    if augmentation.contains("eh") {
+        c.sized(addr_size)?;
+    }
+    c.uleb()?;
+    c.sleb()?;
+    if version == 1 { c.u8()? as u64 } else { c.uleb()? };
+    if !augmentation.starts_with('z') {
+        return Some(0);
+    }
+    c.uleb()?;
+    for ch in augmentation.chars().skip(1) {
+        match ch {
+            'L' => { c.u8()?; }
+            'P' => {
+                let encoding = c.u8()?;
+                if encoding != DW_EH_PE_OMIT {
+                    read_encoded(c, encoding & 0x0f, addr_size, 0, 0)?;
+                }
+            }
+            'R' => return c.u8(),
+            _ => {}
+        }
+    }
+    Some(0)
+}
@@ -1,43 +1,43 @@
+
 use std::mem;
 
+use crate::export::Format;
+use crate::image;
+use crate::pe;
+use crate::process;
+
 type Elf32Half     = u16;
 type Elf32Word     = u32;
-type Elf32Sword    = i32;
 type Elf32Addr     = u32;
 type Elf32Off      = u32;
 
 type Elf64Half     = u16;
 type Elf64Word     = u32;
-type Elf64Sword    = i32;
 type Elf64Addr     = u64;
 type Elf64Off      = u64;
 
 const SIZE_ELF32_HALF: usize    = mem::size_of::<Elf32Half>();
 const SIZE_ELF32_WORD: usize    = mem::size_of::<Elf32Word>();
-const SIZE_ELF32_SWORD: usize   = mem::size_of::<Elf32Sword>();
 const SIZE_ELF32_ADDR: usize    = mem::size_of::<Elf32Addr>();
 const SIZE_ELF32_OFF: usize     = mem::size_of::<Elf32Off>();
 
 const SIZE_ELF64_HALF: usize    = mem::size_of::<Elf64Half>();
 const SIZE_ELF64_WORD: usize    = mem::size_of::<Elf64Word>();
-const SIZE_ELF64_SWORD: usize   = mem::size_of::<Elf64Sword>();
 const SIZE_ELF64_ADDR: usize    = mem::size_of::<Elf64Addr>();
 const SIZE_ELF64_OFF: usize     = mem::size_of::<Elf64Off>();
 
-const EI_NIDENT: usize      = 16;
-const EI_CLASS: usize       = 4;
-const EI_DATA: usize        = 5;
-const EI_VERSION: usize     = 6;
-const EI_OSABI: usize       = 7;
-const EI_ABIVERSION:usize   = 8;
+pub const EI_NIDENT: usize      = 16;
+pub const EI_CLASS: usize       = 4;
+pub const EI_DATA: usize        = 5;
+pub const EI_VERSION: usize     = 6;
+pub const EI_OSABI: usize       = 7;
+pub const EI_ABIVERSION:usize   = 8;
 
 // OSABI
 const ELFOSABI_NONE: u8         = 0;
-const ELFOSABI_SYSV: u8	        = 0;
 const ELFOSABI_HPUX: u8	        = 1;
 const ELFOSABI_NETBSD: u8       = 2;
 const ELFOSABI_GNU: u8          = 3;
-const ELFOSABI_LINUX: u8        = ELFOSABI_GNU;
 const ELFOSABI_SOLARIS: u8      = 6;
 const ELFOSABI_AIX: u8          = 7;
 const ELFOSABI_IRIX: u8		    = 8;
@@ -49,20 +49,21 @@ const ELFOSABI_ARM_AEABI: u8    = 64;
 const ELFOSABI_ARM: u8          = 97;
 const ELFOSABI_STANDALONE: u8   = 255;
 
-const ELFCLASSNONE: u8  = 0;
-const ELFCLASS32: u8    = 1;
-const ELFCLASS64: u8    = 2;
+pub const ELFCLASSNONE: u8  = 0;
+pub const ELFCLASS32: u8    = 1;
+pub const ELFCLASS64: u8    = 2;
 
 const ELFDATANONE: u8   = 0;
 const ELFDATA2LSB: u8   = 1;
 const ELFDATA2MSB: u8   = 2;
 
-const ET_NONE:u16   = 0;
-const ET_REL:u16	= 1;
-const ET_EXEC:u16	= 2;
-const ET_DYN:u16    = 3;
-const ET_CORE:u16   = 4;
-const ET_NUM:u16    = 5;
+const EV_CURRENT: u32   = 1;
+
+pub const ET_NONE:u16   = 0;
+pub const ET_REL:u16	= 1;
+pub const ET_EXEC:u16	= 2;
+pub const ET_DYN:u16    = 3;
+pub const ET_CORE:u16   = 4;
 
 const ELF32_OFFSET_E_TYPE: usize        = EI_NIDENT;
 const ELF32_OFFSET_E_MACHINE: usize     = ELF32_OFFSET_E_TYPE + SIZE_ELF32_HALF;
@@ -103,238 +104,1255 @@ const ELF_MACHINES: [(u16, &str); 7] = [
 ];
 
 
-pub struct Elf32Ehdr
-{
-    e_ident:        [u8; EI_NIDENT],    // Magic number and other info
-    e_type:         Elf32Half,          // Object file type
-    e_machine:      Elf32Half,          // Architecture
-    e_version:      Elf32Word,          // Object file version
-    e_entry:        Elf32Addr,          // Entry point virtual address
-    e_phoff:        Elf32Off,           // Program header table file offset
-    e_shoff:        Elf32Off,           // Section header table file offset
-    e_flags:        Elf32Word,          // Processor-specific flags
-    e_ehsize:       Elf32Half,          // ELF header size in bytes
-    e_phentsize:    Elf32Half,          // Program header table entry size
-    e_phnum:        Elf32Half,          // Program header table entry count
-    e_shentsize:    Elf32Half,          // Section header table entry size
-    e_shnum:        Elf32Half,          // Section header table entry count
-    e_shstrndx:     Elf32Half,          // Section header string table index
-}
-
-pub struct Elf64Ehdr
-{
-    e_ident:        [u8; EI_NIDENT],    // Magic number and other info
-    e_type:         Elf64Half,          // Object file type
-    e_machine:      Elf64Half,          // Architecture
-    e_version:      Elf64Word,          // Object file version
-    e_entry:        Elf64Addr,          // Entry point virtual address
-    e_phoff:        Elf64Off,           // Program header table file offset
-    e_shoff:        Elf64Off,           // Section header table file offset
-    e_flags:        Elf64Word,          // Processor-specific flags
-    e_ehsize:       Elf64Half,          // ELF header size in bytes
-    e_phentsize:    Elf64Half,          // Program header table entry size
-    e_phnum:        Elf64Half,          // Program header table entry count
-    e_shentsize:    Elf64Half,          // Section header table entry size
-    e_shnum:        Elf64Half,          // Section header table entry count
-    e_shstrndx:     Elf64Half,          // Section header string table index
-}
-
-impl Elf32Ehdr {
-    pub fn new(bytes: &[u8]) -> Elf32Ehdr {
-        let mut buf_half: [u8; 2] = Default::default();
-        let mut buf_word: [u8; 4] = Default::default();
-        let mut buf_dword: [u8; 8] = Default::default();
+/// Shows a header count like readelf: "0 (70000)" when the escape value defers to section 0.
+fn format_extended(value: u16, escape: u16, initial: Option<u64>) -> String {
+    match initial {
+        Some(real) if value == escape => format!("{} ({})", value, real),
+        _ => value.to_string(),
+    }
+}
 
-        let mut e_ident :[u8; EI_NIDENT] = Default::default();
-        e_ident.copy_from_slice(&bytes[0..EI_NIDENT]);
+pub fn is_elf(bytes: &[u8]) -> bool {
+    if bytes[0] != 0x7F {
+        return false;
+    }
+    if bytes[1] != 0x45 {
+        return false;
+    }
+    if bytes[2] != 0x4C {
+        return false;
+    }
+    if bytes[3] != 0x46 {
+        return false;
+    }
+    true
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FileFormat {
+    Elf,
+    Pe,
+    IntelHex,
+    SRecord,
+    Unknown,
+}
 
-        buf_half.copy_from_slice(&bytes[ELF32_OFFSET_E_TYPE..ELF32_OFFSET_E_TYPE + SIZE_ELF32_HALF]);
-        let e_type = Elf32Half::from_le_bytes(buf_half);
-
-        buf_half.copy_from_slice(&bytes[ELF32_OFFSET_E_MACHINE..ELF32_OFFSET_E_MACHINE + SIZE_ELF32_HALF]);
-        let e_machine = Elf32Half::from_le_bytes(buf_half);
-
-        buf_word.copy_from_slice(&bytes[ELF32_OFFSET_E_VERSION..ELF32_OFFSET_E_VERSION + SIZE_ELF32_WORD]);
-        let e_version = Elf32Word::from_le_bytes(buf_word);
-
-        buf_word.copy_from_slice(&bytes[ELF32_OFFSET_E_ENTRY..ELF32_OFFSET_E_ENTRY + SIZE_ELF32_ADDR]);
-        let e_entry = Elf32Addr::from_le_bytes(buf_word);
-
-        buf_word.copy_from_slice(&bytes[ELF32_OFFSET_E_PHOFF..ELF32_OFFSET_E_PHOFF + SIZE_ELF32_OFF]);
-        let e_phoff =  Elf32Off::from_le_bytes(buf_word);
-
-        buf_word.copy_from_slice(&bytes[ELF32_OFFSET_E_SHOFF..ELF32_OFFSET_E_SHOFF + SIZE_ELF32_OFF]);
-        let e_shoff =  Elf32Off::from_le_bytes(buf_word);
-
-        buf_word.copy_from_slice(&bytes[ELF32_OFFSET_E_FLAGS..ELF32_OFFSET_E_FLAGS + SIZE_ELF32_WORD]);
-        let e_flags = Elf32Word::from_le_bytes(buf_word);
-        buf_half.copy_from_slice(&bytes[ELF32_OFFSET_E_EHSIZE..ELF32_OFFSET_E_EHSIZE + SIZE_ELF32_HALF]);
-        let e_ehsize = Elf32Half::from_le_bytes(buf_half);
-        buf_half.copy_from_slice(&bytes[ELF32_OFFSET_E_PHENTSIZE..ELF32_OFFSET_E_PHENTSIZE + SIZE_ELF32_HALF]);
-        let e_phentsize = Elf32Half::from_le_bytes(buf_half);
-        buf_half.copy_from_slice(&bytes[ELF32_OFFSET_E_PHNUM..ELF32_OFFSET_E_PHNUM + SIZE_ELF32_HALF]);
-        let e_phnum = Elf32Half::from_le_bytes(buf_half);
-        buf_half.copy_from_slice(&bytes[ELF32_OFFSET_E_SHENTSIZE..ELF32_OFFSET_E_SHENTSIZE + SIZE_ELF32_HALF]);
-        let e_shentsize = Elf32Half::from_le_bytes(buf_half);
-        buf_half.copy_from_slice(&bytes[ELF32_OFFSET_E_SHNUM..ELF32_OFFSET_E_SHNUM + SIZE_ELF32_HALF]);
-        let e_shnum = Elf32Half::from_le_bytes(buf_half);
-        buf_half.copy_from_slice(&bytes[ELF32_OFFSET_E_SHSTRNDX..ELF32_OFFSET_E_SHSTRNDX + SIZE_ELF32_HALF]);
-        let e_shstrndx = Elf32Half::from_le_bytes(buf_half);
-        Elf32Ehdr {
-            e_ident: e_ident,
-            e_type: e_type,
-            e_machine: e_machine,
-            e_version: e_version,
-            e_entry: e_entry,
-            e_phoff: e_phoff,
-            e_shoff: e_shoff,
-            e_flags: e_flags,
-            e_ehsize: e_ehsize,
-            e_phentsize: e_phentsize,
-            e_phnum: e_phnum,
-            e_shentsize: e_shentsize,
-            e_shnum: e_shnum,
-            e_shstrndx: e_shstrndx,
+impl FileFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileFormat::Elf => "ELF",
+            FileFormat::Pe => "PE/COFF",
+            FileFormat::IntelHex => "Intel HEX",
+            FileFormat::SRecord => "S-record",
+            FileFormat::Unknown => "unknown",
         }
     }
+}
 
-    pub fn show_elf_header_info(&self) {
-        println!("ELF Header:");
-        print!("  Magic:{:3}", "");
-        for by in self.e_ident.iter() {
-            print!("{:02x} ", by);
+/// Identifies the container format of a file from its contents.
+pub fn detect_format(bytes: &[u8]) -> FileFormat {
+    if bytes.len() >= EI_NIDENT && is_elf(bytes) {
+        return FileFormat::Elf;
+    }
+    if pe::is_pe(bytes) {
+        return FileFormat::Pe;
+    }
+    match image::detect(bytes) {
+        Format::IntelHex => FileFormat::IntelHex,
+        Format::SRecord => FileFormat::SRecord,
+        Format::Binary => FileFormat::Unknown,
+    }
+}
+
+pub fn get_machine_name(e_machine:u16) -> String {
+    for machine in &ELF_MACHINES {
+        if e_machine == machine.0 {
+            return machine.1.to_string();
         }
-        println!("");
-    
-        match self.e_ident[EI_CLASS] {
-            ELFCLASSNONE => println!("  Class:{:29}ELF None", ""),
-            ELFCLASS32 => println!("  Class:{:29}ELF32", ""),
-            ELFCLASS64 => println!("  Class:{:29}ELF64", ""),
-            _ => println!("  Class: {:29}{}", "", self.e_ident[EI_CLASS])
+    }
+    format!("Unknown Machine:[{}]", e_machine)
+}
+
+const ELF32_EHDR_SIZE: usize    = ELF32_OFFSET_E_SHSTRNDX + SIZE_ELF32_HALF;
+const ELF64_EHDR_SIZE: usize    = ELF64_OFFSET_E_SHSTRNDX + SIZE_ELF64_HALF;
+const ELF32_PHDR_SIZE: usize    = 32;
+const ELF64_PHDR_SIZE: usize    = 56;
+const ELF32_SHDR_SIZE: usize    = 40;
+const ELF64_SHDR_SIZE: usize    = 64;
+const ELF32_SYM_SIZE: usize     = 16;
+const ELF64_SYM_SIZE: usize     = 24;
+const ELF32_DYN_SIZE: usize     = 8;
+const ELF64_DYN_SIZE: usize     = 16;
+
+// Segment types
+pub const PT_NULL: u32          = 0;
+pub const PT_LOAD: u32          = 1;
+pub const PT_DYNAMIC: u32       = 2;
+pub const PT_INTERP: u32        = 3;
+pub const PT_NOTE: u32          = 4;
+pub const PT_SHLIB: u32         = 5;
+pub const PT_PHDR: u32          = 6;
+pub const PT_TLS: u32           = 7;
+pub const PT_GNU_EH_FRAME: u32  = 0x6474_e550;
+pub const PT_GNU_STACK: u32     = 0x6474_e551;
+pub const PT_GNU_RELRO: u32     = 0x6474_e552;
+pub const PT_GNU_PROPERTY: u32  = 0x6474_e553;
+pub const PT_ARM_EXIDX: u32     = 0x7000_0001;
+pub const PT_RISCV_ATTRIBUTES: u32 = 0x7000_0003;
+
+// e_phnum escape: the real count is in sh_info of section 0
+pub const PN_XNUM: u16          = 0xffff;
+
+// Segment flags
+pub const PF_X: u32 = 1;
+pub const PF_W: u32 = 2;
+pub const PF_R: u32 = 4;
+
+// Section types
+pub const SHT_NULL: u32             = 0;
+pub const SHT_PROGBITS: u32         = 1;
+pub const SHT_SYMTAB: u32           = 2;
+pub const SHT_STRTAB: u32           = 3;
+pub const SHT_RELA: u32             = 4;
+pub const SHT_HASH: u32             = 5;
+pub const SHT_DYNAMIC: u32          = 6;
+pub const SHT_NOTE: u32             = 7;
+pub const SHT_NOBITS: u32           = 8;
+pub const SHT_REL: u32              = 9;
+pub const SHT_SHLIB: u32            = 10;
+pub const SHT_DYNSYM: u32           = 11;
+pub const SHT_INIT_ARRAY: u32       = 14;
+pub const SHT_FINI_ARRAY: u32       = 15;
+pub const SHT_PREINIT_ARRAY: u32    = 16;
+pub const SHT_GROUP: u32            = 17;
+pub const SHT_SYMTAB_SHNDX: u32     = 18;
+pub const SHT_GNU_ATTRIBUTES: u32   = 0x6fff_fff5;
+pub const SHT_GNU_HASH: u32         = 0x6fff_fff6;
+pub const SHT_GNU_VERDEF: u32       = 0x6fff_fffd;
+pub const SHT_GNU_VERNEED: u32      = 0x6fff_fffe;
+pub const SHT_GNU_VERSYM: u32       = 0x6fff_ffff;
+pub const SHT_ARM_EXIDX: u32        = 0x7000_0001;
+pub const SHT_ARM_ATTRIBUTES: u32   = 0x7000_0003;
+pub const SHT_RISCV_ATTRIBUTES: u32 = 0x7000_0003;
+
+// Section flags
+pub const SHF_WRITE: u64        = 0x1;
+pub const SHF_ALLOC: u64        = 0x2;
+pub const SHF_EXECINSTR: u64    = 0x4;
+pub const SHF_MERGE: u64        = 0x10;
+pub const SHF_STRINGS: u64      = 0x20;
+pub const SHF_INFO_LINK: u64    = 0x40;
+pub const SHF_LINK_ORDER: u64   = 0x80;
+pub const SHF_GROUP: u64        = 0x200;
+pub const SHF_TLS: u64          = 0x400;
+pub const SHF_COMPRESSED: u64   = 0x800;
+
+// Special section indexes
+pub const SHN_UNDEF: u16        = 0;
+pub const SHN_LORESERVE: u16    = 0xff00;
+pub const SHN_ABS: u16          = 0xfff1;
+pub const SHN_XINDEX: u16       = 0xffff;
+
+// Symbol binding
+pub const STB_LOCAL: u8     = 0;
+pub const STB_GLOBAL: u8    = 1;
+pub const STB_WEAK: u8      = 2;
+pub const STB_GNU_UNIQUE: u8 = 10;
+
+// Symbol types
+pub const STT_NOTYPE: u8    = 0;
+pub const STT_OBJECT: u8    = 1;
+pub const STT_FUNC: u8      = 2;
+pub const STT_SECTION: u8   = 3;
+pub const STT_FILE: u8      = 4;
+pub const STT_COMMON: u8    = 5;
+pub const STT_TLS: u8       = 6;
+pub const STT_GNU_IFUNC: u8 = 10;
+
+// Symbol visibility
+pub const STV_INTERNAL: u8  = 1;
+pub const STV_HIDDEN: u8    = 2;
+
+// Dynamic tags
+pub const DT_NULL: i64          = 0;
+pub const DT_NEEDED: i64        = 1;
+pub const DT_PLTRELSZ: i64      = 2;
+pub const DT_PLTGOT: i64        = 3;
+pub const DT_HASH: i64          = 4;
+pub const DT_STRTAB: i64        = 5;
+pub const DT_SYMTAB: i64        = 6;
+pub const DT_RELA: i64          = 7;
+pub const DT_RELASZ: i64        = 8;
+pub const DT_STRSZ: i64         = 10;
+pub const DT_INIT: i64          = 12;
+pub const DT_FINI: i64          = 13;
+pub const DT_SONAME: i64        = 14;
+pub const DT_RPATH: i64         = 15;
+pub const DT_SYMBOLIC: i64      = 16;
+pub const DT_REL: i64           = 17;
+pub const DT_RELSZ: i64         = 18;
+pub const DT_PLTREL: i64        = 20;
+pub const DT_JMPREL: i64        = 23;
+pub const DT_INIT_ARRAY: i64    = 25;
+pub const DT_FINI_ARRAY: i64    = 26;
+pub const DT_RUNPATH: i64       = 29;
+pub const DT_FLAGS: i64         = 30;
+pub const DT_PREINIT_ARRAY: i64 = 32;
+pub const DT_GNU_HASH: i64      = 0x6fff_fef5;
+pub const DT_VERSYM: i64        = 0x6fff_fff0;
+pub const DT_FLAGS_1: i64       = 0x6fff_fffb;
+pub const DT_VERDEF: i64        = 0x6fff_fffc;
+pub const DT_VERNEED: i64       = 0x6fff_fffe;
+
+// DT_FLAGS and DT_FLAGS_1 values
+pub const DF_SYMBOLIC: u64        = 0x2;      // resolve references within the object first
+pub const DF_1_NODEFLIB: u64      = 0x800;    // ignore ld.so.cache and the default paths
+
+// Symbol versioning
+pub const VER_NDX_LOCAL: u16      = 0;
+pub const VER_NDX_GLOBAL: u16     = 1;
+pub const VERSYM_HIDDEN: u16      = 0x8000;   // not the default version (name@VER, not name@@VER)
+pub const VER_FLG_BASE: u16       = 0x1;      // version definition of the file itself
+pub const VER_FLG_WEAK: u16       = 0x2;
+const VERDEF_SIZE: usize          = 20;
+const VERNEED_SIZE: usize         = 16;
+
+// Relocation types that fill GOT slots
+pub const R_386_GLOB_DAT: u32       = 6;
+pub const R_386_JMP_SLOT: u32       = 7;
+pub const R_X86_64_GLOB_DAT: u32    = 6;
+pub const R_X86_64_JUMP_SLOT: u32   = 7;
+pub const R_ARM_GLOB_DAT: u32       = 21;
+pub const R_ARM_JUMP_SLOT: u32      = 22;
+pub const R_AARCH64_GLOB_DAT: u32   = 1025;
+pub const R_AARCH64_JUMP_SLOT: u32  = 1026;
+pub const R_RISCV_JUMP_SLOT: u32    = 5;
+
+// Relocation types that copy a shared object's data into the executable
+pub const R_386_COPY: u32           = 5;
+pub const R_X86_64_COPY: u32        = 5;
+pub const R_ARM_COPY: u32           = 20;
+pub const R_AARCH64_COPY: u32       = 1024;
+pub const R_RISCV_COPY: u32         = 4;
+
+pub const EM_386: u16       = 3;
+pub const EM_ARM: u16       = 40;
+pub const EM_X86_64: u16    = 62;
+pub const EM_RX: u16        = 173;
+pub const EM_AARCH64: u16   = 183;
+pub const EM_RISCV: u16     = 243;
+
+pub const EF_ARM_BE8: u32   = 0x0080_0000;
+pub const EF_ARM_ABI_FLOAT_SOFT: u32 = 0x0000_0200;
+pub const EF_ARM_ABI_FLOAT_HARD: u32 = 0x0000_0400;
+pub const EF_ARM_EABIMASK: u32 = 0xff00_0000;
+pub const EF_ARM_EABI_VER5: u32 = 0x0500_0000;
+
+pub const EF_RISCV_RVC: u32             = 0x0001;
+pub const EF_RISCV_FLOAT_ABI: u32       = 0x0006;
+pub const EF_RISCV_FLOAT_ABI_SOFT: u32  = 0x0000;
+pub const EF_RISCV_FLOAT_ABI_SINGLE: u32 = 0x0002;
+pub const EF_RISCV_FLOAT_ABI_DOUBLE: u32 = 0x0004;
+pub const EF_RISCV_RVE: u32             = 0x0008;
+pub const EF_RISCV_TSO: u32             = 0x0010;
+
+const PT_TYPES: [(u32, &str); 14] = [
+  (PT_NULL              , "NULL"            ),
+  (PT_LOAD              , "LOAD"            ),
+  (PT_DYNAMIC           , "DYNAMIC"         ),
+  (PT_INTERP            , "INTERP"          ),
+  (PT_NOTE              , "NOTE"            ),
+  (PT_SHLIB             , "SHLIB"           ),
+  (PT_PHDR              , "PHDR"            ),
+  (PT_TLS               , "TLS"             ),
+  (PT_GNU_EH_FRAME      , "GNU_EH_FRAME"    ),
+  (PT_GNU_STACK         , "GNU_STACK"       ),
+  (PT_GNU_RELRO         , "GNU_RELRO"       ),
+  (PT_GNU_PROPERTY      , "GNU_PROPERTY"    ),
+  (PT_ARM_EXIDX         , "LOPROC+0x1"      ),
+  (PT_RISCV_ATTRIBUTES  , "LOPROC+0x3"      ),
+];
+
+const SHT_TYPES: [(u32, &str); 24] = [
+  (SHT_NULL             , "NULL"            ),
+  (SHT_PROGBITS         , "PROGBITS"        ),
+  (SHT_SYMTAB           , "SYMTAB"          ),
+  (SHT_STRTAB           , "STRTAB"          ),
+  (SHT_RELA             , "RELA"            ),
+  (SHT_HASH             , "HASH"            ),
+  (SHT_DYNAMIC          , "DYNAMIC"         ),
+  (SHT_NOTE             , "NOTE"            ),
+  (SHT_NOBITS           , "NOBITS"          ),
+  (SHT_REL              , "REL"             ),
+  (SHT_SHLIB            , "SHLIB"           ),
+  (SHT_DYNSYM           , "DYNSYM"          ),
+  (SHT_INIT_ARRAY       , "INIT_ARRAY"      ),
+  (SHT_FINI_ARRAY       , "FINI_ARRAY"      ),
+  (SHT_PREINIT_ARRAY    , "PREINIT_ARRAY"   ),
+  (SHT_GROUP            , "GROUP"           ),
+  (SHT_SYMTAB_SHNDX     , "SYMTAB SECTION INDICES"),
+  (SHT_GNU_ATTRIBUTES   , "GNU_ATTRIBUTES"  ),
+  (SHT_GNU_HASH         , "GNU_HASH"        ),
+  (SHT_GNU_VERDEF       , "VERDEF"          ),
+  (SHT_GNU_VERNEED      , "VERNEED"         ),
+  (SHT_GNU_VERSYM       , "VERSYM"          ),
+  (SHT_ARM_EXIDX        , "LOPROC+0x1"      ),
+  (SHT_ARM_ATTRIBUTES   , "LOPROC+0x3"      ),
+];
+
+pub fn get_segment_type_name(p_type: u32) -> String {
+    for pt in &PT_TYPES {
+        if p_type == pt.0 {
+            return pt.1.to_string();
         }
-    
-        match self.e_ident[EI_DATA] {
-            ELFDATANONE => println!("  Data:{:30}Invalid data encoding", ""),
-            ELFDATA2LSB => println!("  Data:{:30}2's complement, little endian", ""),
-            ELFDATA2MSB => println!("  Data:{:30}2's complement, big endian", ""),
-            _ => println!("  Data: {:30}{}", "", self.e_ident[EI_CLASS])
+    }
+    format!("0x{:x}", p_type)
+}
+
+pub fn get_section_type_name(sh_type: u32) -> String {
+    for sht in &SHT_TYPES {
+        if sh_type == sht.0 {
+            return sht.1.to_string();
         }
-    
-        println!("  Version:{:27}{} (current)", "", self.e_ident[EI_VERSION]);
-    
-        match self.e_ident[EI_OSABI] {
-            ELFOSABI_NONE => println!("  OS/ABI:{:28}UNIX - System V", ""),
-            ELFOSABI_SYSV => println!("  OS/ABI:{:28}Alias.", ""),
-            ELFOSABI_HPUX => println!("  OS/ABI:{:28}HP-UX", ""),
-            ELFOSABI_NETBSD => println!("  OS/ABI:{:28}NetBSD", ""),
-            ELFOSABI_GNU => println!("  OS/ABI:{:28}Object uses GNU ELF extensions.", ""),
-            ELFOSABI_LINUX => println!("  OS/ABI:{:28}Compatibility alias.", ""),
-            ELFOSABI_SOLARIS => println!("  OS/ABI:{:28}Sun Solaris.", ""),
-            ELFOSABI_AIX => println!("  OS/ABI:{:28}IBM AIX.", ""),
-            ELFOSABI_IRIX => println!("  OS/ABI:{:28}SGI Irix.", ""),
-            ELFOSABI_FREEBSD => println!("  OS/ABI:{:28}FreeBSD.", ""),
-            ELFOSABI_TRU64 => println!("  OS/ABI:{:28}Compaq TRU64 UNIX.", ""),
-            ELFOSABI_MODESTO => println!("  OS/ABI:{:28}Novell Modesto.", ""),
-            ELFOSABI_OPENBSD =>	println!("  OS/ABI:{:28}OpenBSD.", ""),
-            ELFOSABI_ARM_AEABI => println!("  OS/ABI:{:28}ARM EABI", ""),
-            ELFOSABI_ARM => println!("  OS/ABI:{:28}ARM", ""),
-            ELFOSABI_STANDALONE => println!("  OS/ABI:{:28}Standalone (embedded) application", ""),
-            _ => println!("  OS/ABI:{:28}{}", "", self.e_ident[EI_OSABI])
+    }
+    format!("0x{:x}", sh_type)
+}
+
+pub fn get_segment_flags_name(p_flags: u32) -> String {
+    format!("{}{}{}",
+        if p_flags & PF_R != 0 { "R" } else { " " },
+        if p_flags & PF_W != 0 { "W" } else { " " },
+        if p_flags & PF_X != 0 { "E" } else { " " })
+}
+
+pub fn get_section_flags_name(sh_flags: u64) -> String {
+    let mut name = String::new();
+    if sh_flags & SHF_WRITE != 0 { name.push('W'); }
+    if sh_flags & SHF_ALLOC != 0 { name.push('A'); }
+    if sh_flags & SHF_EXECINSTR != 0 { name.push('X'); }
+    if sh_flags & SHF_MERGE != 0 { name.push('M'); }
+    if sh_flags & SHF_STRINGS != 0 { name.push('S'); }
+    if sh_flags & SHF_INFO_LINK != 0 { name.push('I'); }
+    if sh_flags & SHF_LINK_ORDER != 0 { name.push('L'); }
+    if sh_flags & SHF_GROUP != 0 { name.push('G'); }
+    if sh_flags & SHF_TLS != 0 { name.push('T'); }
+    if sh_flags & SHF_COMPRESSED != 0 { name.push('C'); }
+    name
+}
+
+/// e_flags in hex followed by the ABI bits readelf decodes for ARM and RISC-V.
+pub fn get_flags_name(e_machine: u16, e_flags: u32) -> String {
+    let mut parts = vec![format!("0x{:x}", e_flags)];
+    match e_machine {
+        EM_ARM => {
+            if e_flags & EF_ARM_EABIMASK != 0 {
+                parts.push(format!("Version{} EABI", e_flags >> 24));
+            }
+            if e_flags & EF_ARM_ABI_FLOAT_HARD != 0 {
+                parts.push("hard-float ABI".to_string());
+            } else if e_flags & EF_ARM_ABI_FLOAT_SOFT != 0 {
+                parts.push("soft-float ABI".to_string());
+            }
+            if e_flags & EF_ARM_BE8 != 0 {
+                parts.push("BE8".to_string());
+            }
         }
-    
-        println!("  ABI Version:{:23}{}", "", self.e_ident[EI_ABIVERSION]);
-    
-        print!("  Type:{:30}", "");
-        match self.e_type {
-            ET_NONE => println!("None"),
-            ET_REL	=> println!("REL (Relocatable file)"),
-            ET_EXEC	=> println!("EXE (Executable file)"),
-            ET_DYN  => println!("DYN (Shared object file)"),
-            ET_CORE => println!("Core"),
-            _ => println!("{}", self.e_type)
+        EM_RISCV => {
+            if e_flags & EF_RISCV_RVC != 0 {
+                parts.push("RVC".to_string());
+            }
+            parts.push(get_riscv_float_abi_name(e_flags).to_string());
+            if e_flags & EF_RISCV_RVE != 0 {
+                parts.push("RVE".to_string());
+            }
+            if e_flags & EF_RISCV_TSO != 0 {
+                parts.push("TSO".to_string());
+            }
         }
-    
-        println!("  Machine:{:27}{}", "", get_machine_name(self.e_machine));
-        println!("  Version:{:27}0x{:x}", "", self.e_version);
-        println!("  Entry point address:{:15}0x{:x}", "", self.e_entry);
-        println!("  Start of program headers:{:10}{} (bytes into file)", "", self.e_phoff);
-        println!("  Start of section headers:{:10}{} (bytes into file)", "", self.e_shoff);
-        println!("  Flags:{:29}0x{:x}", "", self.e_flags);
-        println!("  Size of this header:{:15}{} (bytes)", "", self.e_ehsize);
-        println!("  Size of program headers:{:11}{} (bytes)", "", self.e_phentsize);
-        println!("  Number of program headers:{:9}{}", "", self.e_phnum);
-        println!("  Size of section headers:{:11}{} (bytes)", "", self.e_shentsize);
-        println!("  Number of section headers:{:9}{}", "", self.e_shnum);
-        println!("  Section header string table index:{:1}{}", "", self.e_shstrndx);
+        _ => {}
+    }
+    parts.join(", ")
+}
+
+pub fn get_riscv_float_abi_name(e_flags: u32) -> &'static str {
+    match e_flags & EF_RISCV_FLOAT_ABI {
+        EF_RISCV_FLOAT_ABI_SOFT => "soft-float ABI",
+        EF_RISCV_FLOAT_ABI_SINGLE => "single-float ABI",
+        EF_RISCV_FLOAT_ABI_DOUBLE => "double-float ABI",
+        _ => "quad-float ABI",
+    }
+}
+
+pub fn get_symbol_bind_name(bind: u8) -> String {
+    match bind {
+        STB_LOCAL => "LOCAL".to_string(),
+        STB_GLOBAL => "GLOBAL".to_string(),
+        STB_WEAK => "WEAK".to_string(),
+        STB_GNU_UNIQUE => "UNIQUE".to_string(),
+        _ => format!("{}", bind)
+    }
+}
+
+pub fn get_symbol_type_name(st_type: u8) -> String {
+    match st_type {
+        STT_NOTYPE => "NOTYPE".to_string(),
+        STT_OBJECT => "OBJECT".to_string(),
+        STT_FUNC => "FUNC".to_string(),
+        STT_SECTION => "SECTION".to_string(),
+        STT_FILE => "FILE".to_string(),
+        STT_COMMON => "COMMON".to_string(),
+        STT_TLS => "TLS".to_string(),
+        STT_GNU_IFUNC => "IFUNC".to_string(),
+        _ => format!("{}", st_type)
     }
 }
 
-impl Elf64Ehdr {
-    pub fn new(bytes: &[u8]) -> Elf64Ehdr{
+/// Reads fixed-size fields honoring the byte order and class of the file.
+#[derive(Clone, Copy)]
+pub struct ElfReader<'a> {
+    bytes: &'a [u8],
+    class: u8,
+    data: u8,
+}
+
+impl<'a> ElfReader<'a> {
+    pub fn new(bytes: &'a [u8], class: u8, data: u8) -> ElfReader<'a> {
+        ElfReader{bytes, class, data}
+    }
+
+    pub fn is_64(&self) -> bool {
+        self.class == ELFCLASS64
+    }
+
+    pub fn u8(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(offset).copied()
+    }
+
+    pub fn half(&self, offset: usize) -> Option<u16> {
         let mut buf_half: [u8; 2] = Default::default();
+        buf_half.copy_from_slice(self.bytes.get(offset..offset.checked_add(2)?)?);
+        if self.data == ELFDATA2MSB {
+            Some(u16::from_be_bytes(buf_half))
+        } else {
+            Some(u16::from_le_bytes(buf_half))
+        }
+    }
+
+    pub fn word(&self, offset: usize) -> Option<u32> {
         let mut buf_word: [u8; 4] = Default::default();
+        buf_word.copy_from_slice(self.bytes.get(offset..offset.checked_add(4)?)?);
+        if self.data == ELFDATA2MSB {
+            Some(u32::from_be_bytes(buf_word))
+        } else {
+            Some(u32::from_le_bytes(buf_word))
+        }
+    }
+
+    pub fn xword(&self, offset: usize) -> Option<u64> {
         let mut buf_dword: [u8; 8] = Default::default();
+        buf_dword.copy_from_slice(self.bytes.get(offset..offset.checked_add(8)?)?);
+        if self.data == ELFDATA2MSB {
+            Some(u64::from_be_bytes(buf_dword))
+        } else {
+            Some(u64::from_le_bytes(buf_dword))
+        }
+    }
 
-        let mut e_ident :[u8; EI_NIDENT] = Default::default();
+    /// Reads an address or offset sized for the file class.
+    pub fn addr(&self, offset: usize) -> Option<u64> {
+        if self.is_64() {
+            self.xword(offset)
+        } else {
+            self.word(offset).map(u64::from)
+        }
+    }
+
+    pub fn addr_size(&self) -> usize {
+        if self.is_64() { 8 } else { 4 }
+    }
+
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+/// Returns the NUL-terminated string starting at `offset`.
+pub fn read_cstr(bytes: &[u8], offset: usize) -> Option<String> {
+    let tail = bytes.get(offset..)?;
+    let len = tail.iter().position(|&b| b == 0)?;
+    Some(String::from_utf8_lossy(&tail[..len]).into_owned())
+}
+
+#[derive(Clone, Debug)]
+pub struct ProgramHeader {
+    pub p_type:     u32,    // Segment type
+    pub p_flags:    u32,    // Segment flags
+    pub p_offset:   u64,    // Segment file offset
+    pub p_vaddr:    u64,    // Segment virtual address
+    pub p_paddr:    u64,    // Segment physical address
+    pub p_filesz:   u64,    // Segment size in file
+    pub p_memsz:    u64,    // Segment size in memory
+    pub p_align:    u64,    // Segment alignment
+}
+
+impl ProgramHeader {
+    fn parse(r: &ElfReader, offset: usize) -> Option<ProgramHeader> {
+        if r.is_64() {
+            Some(ProgramHeader {
+                p_type: r.word(offset)?,
+                p_flags: r.word(offset + 4)?,
+                p_offset: r.xword(offset + 8)?,
+                p_vaddr: r.xword(offset + 16)?,
+                p_paddr: r.xword(offset + 24)?,
+                p_filesz: r.xword(offset + 32)?,
+                p_memsz: r.xword(offset + 40)?,
+                p_align: r.xword(offset + 48)?,
+            })
+        } else {
+            Some(ProgramHeader {
+                p_type: r.word(offset)?,
+                p_offset: r.word(offset + 4)? as u64,
+                p_vaddr: r.word(offset + 8)? as u64,
+                p_paddr: r.word(offset + 12)? as u64,
+                p_filesz: r.word(offset + 16)? as u64,
+                p_memsz: r.word(offset + 20)? as u64,
+                p_flags: r.word(offset + 24)?,
+                p_align: r.word(offset + 28)? as u64,
+            })
+        }
+    }
+
+    pub fn contains_vaddr(&self, addr: u64) -> bool {
+        self.p_vaddr <= addr && addr < self.p_vaddr.saturating_add(self.p_memsz)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SectionHeader {
+    pub name:           String, // Section name resolved from the string table
+    pub sh_name:        u32,    // Section name (string tbl index)
+    pub sh_type:        u32,    // Section type
+    pub sh_flags:       u64,    // Section flags
+    pub sh_addr:        u64,    // Section virtual addr at execution
+    pub sh_offset:      u64,    // Section file offset
+    pub sh_size:        u64,    // Section size in bytes
+    pub sh_link:        u32,    // Link to another section
+    pub sh_info:        u32,    // Additional section information
+    pub sh_addralign:   u64,    // Section alignment
+    pub sh_entsize:     u64,    // Entry size if section holds table
+}
+
+impl SectionHeader {
+    fn parse(r: &ElfReader, offset: usize) -> Option<SectionHeader> {
+        if r.is_64() {
+            Some(SectionHeader {
+                name: String::new(),
+                sh_name: r.word(offset)?,
+                sh_type: r.word(offset + 4)?,
+                sh_flags: r.xword(offset + 8)?,
+                sh_addr: r.xword(offset + 16)?,
+                sh_offset: r.xword(offset + 24)?,
+                sh_size: r.xword(offset + 32)?,
+                sh_link: r.word(offset + 40)?,
+                sh_info: r.word(offset + 44)?,
+                sh_addralign: r.xword(offset + 48)?,
+                sh_entsize: r.xword(offset + 56)?,
+            })
+        } else {
+            Some(SectionHeader {
+                name: String::new(),
+                sh_name: r.word(offset)?,
+                sh_type: r.word(offset + 4)?,
+                sh_flags: r.word(offset + 8)? as u64,
+                sh_addr: r.word(offset + 12)? as u64,
+                sh_offset: r.word(offset + 16)? as u64,
+                sh_size: r.word(offset + 20)? as u64,
+                sh_link: r.word(offset + 24)?,
+                sh_info: r.word(offset + 28)?,
+                sh_addralign: r.word(offset + 32)? as u64,
+                sh_entsize: r.word(offset + 36)? as u64,
+            })
+        }
+    }
+
+    pub fn is_alloc(&self) -> bool {
+        self.sh_flags & SHF_ALLOC != 0
+    }
+
+    pub fn is_exec(&self) -> bool {
+        self.sh_flags & SHF_EXECINSTR != 0
+    }
+
+    /// Number of bytes the section occupies in the file.
+    pub fn file_size(&self) -> u64 {
+        if self.sh_type == SHT_NOBITS { 0 } else { self.sh_size }
+    }
+
+    pub fn contains_vaddr(&self, addr: u64) -> bool {
+        self.is_alloc() && self.sh_addr <= addr && addr < self.sh_addr.saturating_add(self.sh_size)
+    }
+}
+
+/// One entry of a SHT_REL or SHT_RELA section.
+#[derive(Clone, Debug)]
+pub struct Relocation {
+    pub r_offset:   u64,    // Address the relocation applies to
+    pub r_type:     u32,    // Relocation type
+    pub r_sym:      u32,    // Symbol table index
+    pub r_addend:   i64,    // Addend (0 for SHT_REL)
+}
+
+#[derive(Clone, Debug)]
+pub struct Symbol {
+    pub name:       String, // Symbol name resolved from the string table
+    pub st_name:    u32,    // Symbol name (string tbl index)
+    pub st_info:    u8,     // Symbol type and binding
+    pub st_other:   u8,     // Symbol visibility
+    pub st_shndx:   u16,    // Section index
+    pub shndx:      u32,    // st_shndx, or its SHT_SYMTAB_SHNDX entry when it is SHN_XINDEX
+    pub st_value:   u64,    // Symbol value
+    pub st_size:    u64,    // Symbol size
+}
+
+impl Symbol {
+    fn parse(r: &ElfReader, offset: usize) -> Option<Symbol> {
+        if r.is_64() {
+            Some(Symbol {
+                name: String::new(),
+                st_name: r.word(offset)?,
+                st_info: r.u8(offset + 4)?,
+                st_other: r.u8(offset + 5)?,
+                st_shndx: r.half(offset + 6)?,
+                shndx: 0,
+                st_value: r.xword(offset + 8)?,
+                st_size: r.xword(offset + 16)?,
+            })
+        } else {
+            Some(Symbol {
+                name: String::new(),
+                st_name: r.word(offset)?,
+                st_value: r.word(offset + 4)? as u64,
+                st_size: r.word(offset + 8)? as u64,
+                st_info: r.u8(offset + 12)?,
+                st_other: r.u8(offset + 13)?,
+                st_shndx: r.half(offset + 14)?,
+                shndx: 0,
+            })
+        }
+    }
+
+    pub fn bind(&self) -> u8 {
+        self.st_info >> 4
+    }
+
+    pub fn st_type(&self) -> u8 {
+        self.st_info & 0xf
+    }
+
+    pub fn visibility(&self) -> u8 {
+        self.st_other & 0x3
+    }
+
+    pub fn is_undefined(&self) -> bool {
+        self.st_shndx == SHN_UNDEF
+    }
+
+    /// Index of the section defining the symbol. None for undefined, absolute and common
+    /// symbols and the other reserved indexes.
+    pub fn section_index(&self) -> Option<usize> {
+        if self.st_shndx == SHN_UNDEF || (self.st_shndx >= SHN_LORESERVE && self.st_shndx != SHN_XINDEX) {
+            return None;
+        }
+        Some(self.shndx as usize)
+    }
+
+    pub fn is_function(&self) -> bool {
+        self.st_type() == STT_FUNC || self.st_type() == STT_GNU_IFUNC
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DynamicEntry {
+    pub d_tag:  i64,    // Dynamic entry type
+    pub d_val:  u64,    // Integer or address value
+}
+
+/// A version definition (.gnu.version_d) or requirement (.gnu.version_r).
+#[derive(Clone, Debug)]
+pub struct VersionEntry {
+    pub index:  u16,            // value used in .gnu.version
+    pub flags:  u16,            // VER_FLG_BASE, VER_FLG_WEAK
+    pub name:   String,
+    pub file:   Option<String>, // library a requirement is expected from
+}
+
+/// The versioning tables of a file, for looking up the version of each dynamic symbol.
+pub struct SymbolVersions {
+    pub versym: Vec<u16>,
+    pub defs:   Vec<VersionEntry>,
+    pub needs:  Vec<VersionEntry>,
+}
+
+impl SymbolVersions {
+    /// Version of dynamic symbol `index` and whether it is hidden (a non-default version).
+    /// None for unversioned, local and base-version symbols.
+    pub fn get(&self, index: usize) -> Option<(&VersionEntry, bool)> {
+        let versym = *self.versym.get(index)?;
+        let ndx = versym & !VERSYM_HIDDEN;
+        if ndx == VER_NDX_LOCAL || ndx == VER_NDX_GLOBAL {
+            return None;
+        }
+        let entry = self.defs.iter().chain(self.needs.iter()).find(|v| v.index == ndx)?;
+        Some((entry, versym & VERSYM_HIDDEN != 0))
+    }
+}
+
+/// A parsed ELF image of either class and byte order.
+pub struct ElfFile {
+    pub e_ident:        [u8; EI_NIDENT],
+    pub e_type:         u16,
+    pub e_machine:      u16,
+    pub e_version:      u32,
+    pub e_entry:        u64,
+    pub e_phoff:        u64,
+    pub e_shoff:        u64,
+    pub e_flags:        u32,
+    pub e_ehsize:       u16,
+    pub e_phentsize:    u16,
+    pub e_phnum:        u16,
+    pub e_shentsize:    u16,
+    pub e_shnum:        u16,
+    pub e_shstrndx:     u16,
+    pub program_headers:    Vec<ProgramHeader>,
+    pub section_headers:    Vec<SectionHeader>,
+    pub symbols:            Vec<Symbol>,        // .symtab
+    pub dynamic_symbols:    Vec<Symbol>,        // .dynsym
+    pub dynamic:            Vec<DynamicEntry>,  // PT_DYNAMIC / .dynamic
+    bytes: Vec<u8>,
+}
+
+impl ElfFile {
+    pub fn open(path: &str) -> Result<ElfFile, String> {
+        // "pid:<pid>[:<image>]" reads an image out of a running process.
+        if let Some(spec) = path.strip_prefix("pid:") {
+            return process::open(spec);
+        }
+        // "image:<file>,machine=<name>,..." wraps an Intel HEX, S-record or raw image.
+        if let Some(spec) = path.strip_prefix("image:") {
+            return image::open(spec);
+        }
+        let bytes = std::fs::read(path).map_err(|e| format!("File open failed filepath:{} ({})", path, e))?;
+        match detect_format(&bytes) {
+            FileFormat::Elf => ElfFile::parse(bytes).map_err(|e| format!("{}: {}", path, e)),
+            // PE images are analyzed through an ELF model of their mapped sections.
+            FileFormat::Pe => pe::PeFile::parse(bytes).and_then(|pe| pe::to_elf(&pe)).map_err(|e| format!("{}: {}", path, e)),
+            FileFormat::IntelHex | FileFormat::SRecord => {
+                Err(format!("{} is an {} image: open it as image:{},machine=<name>", path, detect_format(&bytes).name(), path))
+            }
+            FileFormat::Unknown => {
+                Err(format!("{} is not ELF or PE format file (use image:{},machine=<name> for HEX, S-record and raw images)",
+                            path, path))
+            }
+        }
+    }
+
+    /// Opens `path` like `open`, but an ELF file whose header tables run off the end is still
+    /// loaded, with the problems listed next to it (see `parse_lenient`).
+    pub fn open_lenient(path: &str) -> Result<(ElfFile, Vec<String>), String> {
+        if path.starts_with("pid:") || path.starts_with("image:") {
+            return ElfFile::open(path).map(|elf| (elf, Vec::new()));
+        }
+        let bytes = std::fs::read(path).map_err(|e| format!("File open failed filepath:{} ({})", path, e))?;
+        match detect_format(&bytes) {
+            FileFormat::Elf => ElfFile::parse_lenient(bytes).map_err(|e| format!("{}: {}", path, e)),
+            _ => ElfFile::open(path).map(|elf| (elf, Vec::new())),
+        }
+    }
+
+    pub fn parse(bytes: Vec<u8>) -> Result<ElfFile, String> {
+        let (elf, problems) = ElfFile::parse_lenient(bytes)?;
+        match problems.into_iter().next() {
+            Some(problem) => Err(problem),
+            None => Ok(elf),
+        }
+    }
+
+    /// Parses as much of a damaged file as lies inside it: a short ELF header reads as zeros
+    /// past the end, and header tables stop at the first entry that runs off the file. The
+    /// problems found are returned next to the file; only a missing ELF identification or an
+    /// unknown class is an error.
+    pub fn parse_lenient(bytes: Vec<u8>) -> Result<(ElfFile, Vec<String>), String> {
+        if bytes.len() < EI_NIDENT || !is_elf(&bytes) {
+            return Err("not ELF format file".to_string());
+        }
+        let class = bytes[EI_CLASS];
+        let data = bytes[EI_DATA];
+        let ehdr_size = match class {
+            ELFCLASS32 => ELF32_EHDR_SIZE,
+            ELFCLASS64 => ELF64_EHDR_SIZE,
+            _ => return Err(format!("unsupported ELF class {}", class)),
+        };
+        let mut problems = Vec::new();
+        let mut header = bytes[..ehdr_size.min(bytes.len())].to_vec();
+        if header.len() < ehdr_size {
+            problems.push("truncated ELF header".to_string());
+            header.resize(ehdr_size, 0);
+        }
+
+        let mut e_ident: [u8; EI_NIDENT] = Default::default();
         e_ident.copy_from_slice(&bytes[0..EI_NIDENT]);
+        let r = ElfReader::new(&header, class, data);
+        let mut elf = if class == ELFCLASS64 {
+            ElfFile {
+                e_ident,
+                e_type: r.half(ELF64_OFFSET_E_TYPE).unwrap(),
+                e_machine: r.half(ELF64_OFFSET_E_MACHINE).unwrap(),
+                e_version: r.word(ELF64_OFFSET_E_VERSION).unwrap(),
+                e_entry: r.xword(ELF64_OFFSET_E_ENTRY).unwrap(),
+                e_phoff: r.xword(ELF64_OFFSET_E_PHOFF).unwrap(),
+                e_shoff: r.xword(ELF64_OFFSET_E_SHOFF).unwrap(),
+                e_flags: r.word(ELF64_OFFSET_E_FLAGS).unwrap(),
+                e_ehsize: r.half(ELF64_OFFSET_E_EHSIZE).unwrap(),
+                e_phentsize: r.half(ELF64_OFFSET_E_PHENTSIZE).unwrap(),
+                e_phnum: r.half(ELF64_OFFSET_E_PHNUM).unwrap(),
+                e_shentsize: r.half(ELF64_OFFSET_E_SHENTSIZE).unwrap(),
+                e_shnum: r.half(ELF64_OFFSET_E_SHNUM).unwrap(),
+                e_shstrndx: r.half(ELF64_OFFSET_E_SHSTRNDX).unwrap(),
+                program_headers: Vec::new(),
+                section_headers: Vec::new(),
+                symbols: Vec::new(),
+                dynamic_symbols: Vec::new(),
+                dynamic: Vec::new(),
+                bytes: Vec::new(),
+            }
+        } else {
+            ElfFile {
+                e_ident,
+                e_type: r.half(ELF32_OFFSET_E_TYPE).unwrap(),
+                e_machine: r.half(ELF32_OFFSET_E_MACHINE).unwrap(),
+                e_version: r.word(ELF32_OFFSET_E_VERSION).unwrap(),
+                e_entry: r.word(ELF32_OFFSET_E_ENTRY).unwrap() as u64,
+                e_phoff: r.word(ELF32_OFFSET_E_PHOFF).unwrap() as u64,
+                e_shoff: r.word(ELF32_OFFSET_E_SHOFF).unwrap() as u64,
+                e_flags: r.word(ELF32_OFFSET_E_FLAGS).unwrap(),
+                e_ehsize: r.half(ELF32_OFFSET_E_EHSIZE).unwrap(),
+                e_phentsize: r.half(ELF32_OFFSET_E_PHENTSIZE).unwrap(),
+                e_phnum: r.half(ELF32_OFFSET_E_PHNUM).unwrap(),
+                e_shentsize: r.half(ELF32_OFFSET_E_SHENTSIZE).unwrap(),
+                e_shnum: r.half(ELF32_OFFSET_E_SHNUM).unwrap(),
+                e_shstrndx: r.half(ELF32_OFFSET_E_SHSTRNDX).unwrap(),
+                program_headers: Vec::new(),
+                section_headers: Vec::new(),
+                symbols: Vec::new(),
+                dynamic_symbols: Vec::new(),
+                dynamic: Vec::new(),
+                bytes: Vec::new(),
+            }
+        };
+
+        // Section 0 may hold the program header count, so sections come first.
+        let r = ElfReader::new(&bytes, class, data);
+        elf.section_headers = elf.parse_section_headers(&r, &mut problems);
+        elf.program_headers = elf.parse_program_headers(&r, &mut problems);
+        elf.symbols = elf.parse_symbols(&r, SHT_SYMTAB);
+        elf.dynamic_symbols = elf.parse_symbols(&r, SHT_DYNSYM);
+        elf.dynamic = elf.parse_dynamic(&r);
+        elf.bytes = bytes;
+        Ok((elf, problems))
+    }
+
+    fn parse_program_headers(&self, r: &ElfReader, problems: &mut Vec<String>) -> Vec<ProgramHeader> {
+        let entsize = if r.is_64() { ELF64_PHDR_SIZE } else { ELF32_PHDR_SIZE };
+        let mut phdrs = Vec::new();
+        if self.e_phoff == 0 {
+            return phdrs;
+        }
+        for i in 0..self.phnum() {
+            let offset = (self.e_phoff as usize).saturating_add(i * entsize);
+            match ProgramHeader::parse(r, offset) {
+                Some(phdr) => phdrs.push(phdr),
+                None => {
+                    problems.push(format!("program header {} is out of file", i));
+                    break;
+                }
+            }
+        }
+        phdrs
+    }
+
+    fn parse_section_headers(&self, r: &ElfReader, problems: &mut Vec<String>) -> Vec<SectionHeader> {
+        let entsize = if r.is_64() { ELF64_SHDR_SIZE } else { ELF32_SHDR_SIZE };
+        let mut shdrs = Vec::new();
+        if self.e_shoff == 0 {
+            return shdrs;
+        }
+        // With SHN_LORESERVE or more sections, e_shnum is 0 and e_shstrndx is SHN_XINDEX; the
+        // real values are in sh_size and sh_link of section 0.
+        let initial = match SectionHeader::parse(r, self.e_shoff as usize) {
+            Some(initial) => initial,
+            None => {
+                problems.push("section header 0 is out of file".to_string());
+                return shdrs;
+            }
+        };
+        let count = if self.e_shnum == 0 { initial.sh_size as usize } else { self.e_shnum as usize };
+        let shstrndx = if self.e_shstrndx == SHN_XINDEX { initial.sh_link as usize } else { self.e_shstrndx as usize };
+        for i in 0..count {
+            let offset = (self.e_shoff as usize).saturating_add(i.saturating_mul(entsize));
+            match SectionHeader::parse(r, offset) {
+                Some(shdr) => shdrs.push(shdr),
+                None => {
+                    problems.push(format!("section header {} is out of file", i));
+                    break;
+                }
+            }
+        }
+
+        if let Some(strtab) = shdrs.get(shstrndx) {
+            let strtab = section_slice(r.bytes(), strtab).to_vec();
+            for shdr in shdrs.iter_mut() {
+                shdr.name = read_cstr(&strtab, shdr.sh_name as usize).unwrap_or_default();
+            }
+        }
+        shdrs
+    }
+
+    fn parse_symbols(&self, r: &ElfReader, sh_type: u32) -> Vec<Symbol> {
+        let entsize = if r.is_64() { ELF64_SYM_SIZE } else { ELF32_SYM_SIZE };
+        let mut syms = Vec::new();
+        let (index, symtab) = match self.section_headers.iter().enumerate().find(|(_, s)| s.sh_type == sh_type) {
+            Some(found) => found,
+            None => return syms,
+        };
+        // Section indexes that do not fit st_shndx are kept in a parallel table of words.
+        let xindex = self.section_headers.iter()
+            .find(|s| s.sh_type == SHT_SYMTAB_SHNDX && s.sh_link as usize == index)
+            .map(|s| s.sh_offset as usize);
+        let strtab = match self.section_headers.get(symtab.sh_link as usize) {
+            Some(strtab) => section_slice(r.bytes(), strtab),
+            None => &[],
+        };
+        let count = section_slice(r.bytes(), symtab).len() / entsize;
+        for i in 0..count {
+            let offset = symtab.sh_offset as usize + i * entsize;
+            if let Some(mut sym) = Symbol::parse(r, offset) {
+                sym.name = read_cstr(strtab, sym.st_name as usize).unwrap_or_default();
+                sym.shndx = match xindex {
+                    Some(table) if sym.st_shndx == SHN_XINDEX => r.word(table + i * 4).unwrap_or(0),
+                    _ => sym.st_shndx as u32,
+                };
+                syms.push(sym);
+            }
+        }
+        syms
+    }
+
+    fn parse_dynamic(&self, r: &ElfReader) -> Vec<DynamicEntry> {
+        let entsize = if r.is_64() { ELF64_DYN_SIZE } else { ELF32_DYN_SIZE };
+        let (offset, size) = if let Some(phdr) = self.program_headers.iter().find(|p| p.p_type == PT_DYNAMIC) {
+            (phdr.p_offset, phdr.p_filesz)
+        } else if let Some(shdr) = self.section_headers.iter().find(|s| s.sh_type == SHT_DYNAMIC) {
+            (shdr.sh_offset, shdr.sh_size)
+        } else {
+            return Vec::new();
+        };
 
-        buf_half.copy_from_slice(&bytes[ELF64_OFFSET_E_TYPE..ELF64_OFFSET_E_TYPE + SIZE_ELF64_HALF]);
-        let e_type = Elf64Half::from_le_bytes(buf_half);
-
-        buf_half.copy_from_slice(&bytes[ELF64_OFFSET_E_MACHINE..ELF64_OFFSET_E_MACHINE + SIZE_ELF64_HALF]);
-        let e_machine = Elf64Half::from_le_bytes(buf_half);
-
-        buf_word.copy_from_slice(&bytes[ELF64_OFFSET_E_VERSION..ELF64_OFFSET_E_VERSION + SIZE_ELF64_WORD]);
-        let e_version = Elf64Word::from_le_bytes(buf_word);
-
-        buf_dword.copy_from_slice(&bytes[ELF64_OFFSET_E_ENTRY..ELF64_OFFSET_E_ENTRY + SIZE_ELF64_ADDR]);
-        let e_entry = Elf64Addr::from_le_bytes(buf_dword);
-
-        buf_dword.copy_from_slice(&bytes[ELF64_OFFSET_E_PHOFF..ELF64_OFFSET_E_PHOFF + SIZE_ELF64_OFF]);
-        let e_phoff =  Elf64Off::from_le_bytes(buf_dword);
-
-        buf_dword.copy_from_slice(&bytes[ELF64_OFFSET_E_SHOFF..ELF64_OFFSET_E_SHOFF + SIZE_ELF64_OFF]);
-        let e_shoff =  Elf64Off::from_le_bytes(buf_dword);
-
-        buf_word.copy_from_slice(&bytes[ELF64_OFFSET_E_FLAGS..ELF64_OFFSET_E_FLAGS + SIZE_ELF64_WORD]);
-        let e_flags = Elf64Word::from_le_bytes(buf_word);
-        buf_half.copy_from_slice(&bytes[ELF64_OFFSET_E_EHSIZE..ELF64_OFFSET_E_EHSIZE + SIZE_ELF64_HALF]);
-        let e_ehsize = Elf64Half::from_le_bytes(buf_half);
-        buf_half.copy_from_slice(&bytes[ELF64_OFFSET_E_PHENTSIZE..ELF64_OFFSET_E_PHENTSIZE + SIZE_ELF64_HALF]);
-        let e_phentsize = Elf64Half::from_le_bytes(buf_half);
-        buf_half.copy_from_slice(&bytes[ELF64_OFFSET_E_PHNUM..ELF64_OFFSET_E_PHNUM + SIZE_ELF64_HALF]);
-        let e_phnum = Elf64Half::from_le_bytes(buf_half);
-        buf_half.copy_from_slice(&bytes[ELF64_OFFSET_E_SHENTSIZE..ELF64_OFFSET_E_SHENTSIZE + SIZE_ELF64_HALF]);
-        let e_shentsize = Elf64Half::from_le_bytes(buf_half);
-        buf_half.copy_from_slice(&bytes[ELF64_OFFSET_E_SHNUM..ELF64_OFFSET_E_SHNUM + SIZE_ELF64_HALF]);
-        let e_shnum = Elf64Half::from_le_bytes(buf_half);
-        buf_half.copy_from_slice(&bytes[ELF64_OFFSET_E_SHSTRNDX..ELF64_OFFSET_E_SHSTRNDX + SIZE_ELF64_HALF]);
-        let e_shstrndx = Elf64Half::from_le_bytes(buf_half);
-        Elf64Ehdr {
-            e_ident: e_ident,
-            e_type: e_type,
-            e_machine: e_machine,
-            e_version: e_version,
-            e_entry: e_entry,
-            e_phoff: e_phoff,
-            e_shoff: e_shoff,
-            e_flags: e_flags,
-            e_ehsize: e_ehsize,
-            e_phentsize: e_phentsize,
-            e_phnum: e_phnum,
-            e_shentsize: e_shentsize,
-            e_shnum: e_shnum,
-            e_shstrndx: e_shstrndx,
+        let mut entries = Vec::new();
+        for i in 0..(size as usize / entsize) {
+            let pos = offset as usize + i * entsize;
+            let entry = if r.is_64() {
+                r.xword(pos).and_then(|tag| r.xword(pos + 8).map(|val| DynamicEntry{d_tag: tag as i64, d_val: val}))
+            } else {
+                r.word(pos).and_then(|tag| r.word(pos + 4).map(|val| DynamicEntry{d_tag: tag as i32 as i64, d_val: val as u64}))
+            };
+            match entry {
+                Some(entry) if entry.d_tag == DT_NULL => break,
+                Some(entry) => entries.push(entry),
+                None => break,
+            }
         }
+        entries
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn reader(&self) -> ElfReader<'_> {
+        ElfReader::new(&self.bytes, self.e_ident[EI_CLASS], self.e_ident[EI_DATA])
+    }
+
+    pub fn is_64(&self) -> bool {
+        self.e_ident[EI_CLASS] == ELFCLASS64
+    }
+
+    /// Number of program headers, taken from section 0 when e_phnum is PN_XNUM.
+    pub fn phnum(&self) -> usize {
+        match self.section_headers.first() {
+            Some(initial) if self.e_phnum == PN_XNUM => initial.sh_info as usize,
+            _ => self.e_phnum as usize,
+        }
+    }
+
+    /// Number of section headers, taken from section 0 when e_shnum is 0.
+    pub fn shnum(&self) -> usize {
+        self.section_headers.len()
+    }
+
+    /// Index of the section name string table, taken from section 0 when e_shstrndx is
+    /// SHN_XINDEX.
+    pub fn shstrndx(&self) -> usize {
+        match self.section_headers.first() {
+            Some(initial) if self.e_shstrndx == SHN_XINDEX => initial.sh_link as usize,
+            _ => self.e_shstrndx as usize,
+        }
+    }
+
+    pub fn is_little_endian(&self) -> bool {
+        self.e_ident[EI_DATA] != ELFDATA2MSB
+    }
+
+    pub fn section_by_name(&self, name: &str) -> Option<&SectionHeader> {
+        self.section_headers.iter().find(|s| s.name == name)
+    }
+
+    /// Returns the file contents of a section (empty for SHT_NOBITS).
+    pub fn section_data(&self, shdr: &SectionHeader) -> &[u8] {
+        section_slice(&self.bytes, shdr)
+    }
+
+    /// Returns the file contents of a segment.
+    pub fn segment_data(&self, phdr: &ProgramHeader) -> &[u8] {
+        let start = phdr.p_offset as usize;
+        let end = start.saturating_add(phdr.p_filesz as usize);
+        self.bytes.get(start..end).unwrap_or(&[])
     }
 
+    /// Translates a virtual address into a file offset via the PT_LOAD segments,
+    /// falling back to section headers for relocatable objects.
+    pub fn vaddr_to_offset(&self, addr: u64) -> Option<u64> {
+        for phdr in self.program_headers.iter().filter(|p| p.p_type == PT_LOAD) {
+            if phdr.p_vaddr <= addr && addr < phdr.p_vaddr.saturating_add(phdr.p_filesz) {
+                return Some(phdr.p_offset + (addr - phdr.p_vaddr));
+            }
+        }
+        for shdr in self.section_headers.iter().filter(|s| s.sh_type != SHT_NOBITS) {
+            if shdr.contains_vaddr(addr) {
+                return Some(shdr.sh_offset + (addr - shdr.sh_addr));
+            }
+        }
+        None
+    }
+
+    /// Returns the file-backed bytes starting at a virtual address.
+    pub fn read_vaddr(&self, addr: u64, len: usize) -> Option<&[u8]> {
+        let offset = self.vaddr_to_offset(addr)? as usize;
+        self.bytes.get(offset..offset.checked_add(len)?)
+    }
+
+    pub fn section_for_vaddr(&self, addr: u64) -> Option<&SectionHeader> {
+        self.section_headers.iter().find(|s| s.contains_vaddr(addr))
+    }
+
+    /// Returns the symbol table to use for name lookups: .symtab if present, .dynsym otherwise.
+    pub fn best_symbols(&self) -> &[Symbol] {
+        if self.symbols.is_empty() { &self.dynamic_symbols } else { &self.symbols }
+    }
+
+    /// Parses the entries of a SHT_REL or SHT_RELA section.
+    pub fn relocations(&self, shdr: &SectionHeader) -> Vec<Relocation> {
+        let r = self.reader();
+        let rela = shdr.sh_type == SHT_RELA;
+        let entsize = match (self.is_64(), rela) {
+            (true, true) => 24,
+            (true, false) => 16,
+            (false, true) => 12,
+            (false, false) => 8,
+        };
+        let mut relocs = Vec::new();
+        for i in 0..(self.section_data(shdr).len() / entsize) {
+            let pos = shdr.sh_offset as usize + i * entsize;
+            let reloc = if self.is_64() {
+                r.xword(pos).and_then(|offset| r.xword(pos + 8).map(|info| Relocation {
+                    r_offset: offset,
+                    r_type: info as u32,
+                    r_sym: (info >> 32) as u32,
+                    r_addend: if rela { r.xword(pos + 16).unwrap_or(0) as i64 } else { 0 },
+                }))
+            } else {
+                r.word(pos).and_then(|offset| r.word(pos + 4).map(|info| Relocation {
+                    r_offset: offset as u64,
+                    r_type: info & 0xff,
+                    r_sym: info >> 8,
+                    r_addend: if rela { r.word(pos + 8).unwrap_or(0) as i32 as i64 } else { 0 },
+                }))
+            };
+            match reloc {
+                Some(reloc) => relocs.push(reloc),
+                None => break,
+            }
+        }
+        relocs
+    }
+
+    /// Relocation type of the GOT slots behind PLT entries on this machine.
+    pub fn jump_slot_type(&self) -> Option<u32> {
+        match self.e_machine {
+            EM_386 => Some(R_386_JMP_SLOT),
+            EM_X86_64 => Some(R_X86_64_JUMP_SLOT),
+            EM_ARM => Some(R_ARM_JUMP_SLOT),
+            EM_AARCH64 => Some(R_AARCH64_JUMP_SLOT),
+            EM_RISCV => Some(R_RISCV_JUMP_SLOT),
+            _ => None,
+        }
+    }
+
+    /// Relocation type of GOT slots holding the address of a symbol, if the machine has one.
+    pub fn glob_dat_type(&self) -> Option<u32> {
+        match self.e_machine {
+            EM_386 => Some(R_386_GLOB_DAT),
+            EM_X86_64 => Some(R_X86_64_GLOB_DAT),
+            EM_ARM => Some(R_ARM_GLOB_DAT),
+            EM_AARCH64 => Some(R_AARCH64_GLOB_DAT),
+            _ => None,
+        }
+    }
+
+    /// Relocation type of copy relocations, if the machine has one.
+    pub fn copy_type(&self) -> Option<u32> {
+        match self.e_machine {
+            EM_386 => Some(R_386_COPY),
+            EM_X86_64 => Some(R_X86_64_COPY),
+            EM_ARM => Some(R_ARM_COPY),
+            EM_AARCH64 => Some(R_AARCH64_COPY),
+            EM_RISCV => Some(R_RISCV_COPY),
+            _ => None,
+        }
+    }
+
+    /// File offset of a versioning table, from its section or else its dynamic tag.
+    fn version_table(&self, sh_type: u32, tag: i64) -> Option<usize> {
+        match self.section_headers.iter().find(|s| s.sh_type == sh_type) {
+            Some(shdr) => Some(shdr.sh_offset as usize),
+            None => self.vaddr_to_offset(self.dynamic_value(tag)?).map(|o| o as usize),
+        }
+    }
+
+    /// .gnu.version: the version index of each dynamic symbol, VERSYM_HIDDEN included.
+    /// Empty when the file is not versioned.
+    pub fn symbol_versions(&self) -> Vec<u16> {
+        let r = self.reader();
+        match self.version_table(SHT_GNU_VERSYM, DT_VERSYM) {
+            Some(offset) => (0..self.dynamic_symbols.len()).map_while(|i| r.half(offset + i * 2)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// .gnu.version_d: the versions this file defines.
+    pub fn version_definitions(&self) -> Vec<VersionEntry> {
+        let r = self.reader();
+        let mut result = Vec::new();
+        let mut offset = match self.version_table(SHT_GNU_VERDEF, DT_VERDEF) {
+            Some(offset) => offset,
+            None => return result,
+        };
+        // vd_version, vd_flags, vd_ndx, vd_cnt, vd_hash, vd_aux, vd_next; the first aux entry names it
+        while let (Some(flags), Some(index), Some(aux), Some(next)) =
+                (r.half(offset + 2), r.half(offset + 4), r.word(offset + 12), r.word(offset + 16)) {
+            if let Some(name) = r.word(offset + aux as usize).and_then(|n| self.dynamic_string(n as u64)) {
+                result.push(VersionEntry{index, flags, name, file: None});
+            }
+            if next == 0 || result.len() > 0xffff || (next as usize) < VERDEF_SIZE {
+                break;
+            }
+            offset += next as usize;
+        }
+        result
+    }
+
+    /// .gnu.version_r: the versions this file needs, with the library each is expected from.
+    pub fn version_requirements(&self) -> Vec<VersionEntry> {
+        let r = self.reader();
+        let mut result = Vec::new();
+        let mut offset = match self.version_table(SHT_GNU_VERNEED, DT_VERNEED) {
+            Some(offset) => offset,
+            None => return result,
+        };
+        // vn_version, vn_cnt, vn_file, vn_aux, vn_next; then vna_hash, vna_flags, vna_other, vna_name, vna_next
+        while let (Some(count), Some(file), Some(aux), Some(next)) =
+                (r.half(offset + 2), r.word(offset + 4), r.word(offset + 8), r.word(offset + 12)) {
+            let file = self.dynamic_string(file as u64);
+            let mut at = offset + aux as usize;
+            for _ in 0..count {
+                let (flags, index, name, next_aux) = match (r.half(at + 4), r.half(at + 6), r.word(at + 8), r.word(at + 12)) {
+                    (Some(f), Some(i), Some(n), Some(x)) => (f, i, n, x),
+                    _ => break,
+                };
+                if let Some(name) = self.dynamic_string(name as u64) {
+                    result.push(VersionEntry{index, flags, name, file: file.clone()});
+                }
+                if next_aux == 0 {
+                    break;
+                }
+                at += next_aux as usize;
+            }
+            if next == 0 || result.len() > 0xffff || (next as usize) < VERNEED_SIZE {
+                break;
+            }
+            offset += next as usize;
+        }
+        result
+    }
+
+    pub fn versions(&self) -> SymbolVersions {
+        SymbolVersions{versym: self.symbol_versions(), defs: self.version_definitions(), needs: self.version_requirements()}
+    }
+
+    pub fn dynamic_value(&self, tag: i64) -> Option<u64> {
+        self.dynamic.iter().find(|d| d.d_tag == tag).map(|d| d.d_val)
+    }
+
+    /// Reads a string out of the dynamic string table (DT_STRTAB).
+    pub fn dynamic_string(&self, offset: u64) -> Option<String> {
+        let strtab_offset = match self.dynamic_value(DT_STRTAB).and_then(|a| self.vaddr_to_offset(a)) {
+            Some(off) => off,
+            None => self.section_by_name(".dynstr")?.sh_offset,
+        };
+        read_cstr(&self.bytes, strtab_offset.checked_add(offset)? as usize)
+    }
+
+    pub fn needed(&self) -> Vec<String> {
+        self.dynamic.iter()
+            .filter(|d| d.d_tag == DT_NEEDED)
+            .filter_map(|d| self.dynamic_string(d.d_val))
+            .collect()
+    }
+
+    pub fn soname(&self) -> Option<String> {
+        self.dynamic_string(self.dynamic_value(DT_SONAME)?)
+    }
+
+    pub fn rpath(&self) -> Option<String> {
+        self.dynamic_string(self.dynamic_value(DT_RPATH)?)
+    }
+
+    pub fn runpath(&self) -> Option<String> {
+        self.dynamic_string(self.dynamic_value(DT_RUNPATH)?)
+    }
+
+    pub fn interpreter(&self) -> Option<String> {
+        let phdr = self.program_headers.iter().find(|p| p.p_type == PT_INTERP)?;
+        read_cstr(self.segment_data(phdr), 0)
+    }
+
+    /// Prints the header like readelf -h, from the fields decoded in the file's byte order.
     pub fn show_elf_header_info(&self) {
         println!("ELF Header:");
         print!("  Magic:{:3}", "");
         for by in self.e_ident.iter() {
             print!("{:02x} ", by);
         }
-        println!("");
+        println!();
     
         match self.e_ident[EI_CLASS] {
             ELFCLASSNONE => println!("  Class:{:29}ELF None", ""),
@@ -354,11 +1372,9 @@ impl Elf64Ehdr {
     
         match self.e_ident[EI_OSABI] {
             ELFOSABI_NONE => println!("  OS/ABI:{:28}UNIX - System V", ""),
-            ELFOSABI_SYSV => println!("  OS/ABI:{:28}Alias.", ""),
             ELFOSABI_HPUX => println!("  OS/ABI:{:28}HP-UX", ""),
             ELFOSABI_NETBSD => println!("  OS/ABI:{:28}NetBSD", ""),
             ELFOSABI_GNU => println!("  OS/ABI:{:28}Object uses GNU ELF extensions.", ""),
-            ELFOSABI_LINUX => println!("  OS/ABI:{:28}Compatibility alias.", ""),
             ELFOSABI_SOLARIS => println!("  OS/ABI:{:28}Sun Solaris.", ""),
             ELFOSABI_AIX => println!("  OS/ABI:{:28}IBM AIX.", ""),
             ELFOSABI_IRIX => println!("  OS/ABI:{:28}SGI Irix.", ""),
@@ -389,45 +1405,215 @@ impl Elf64Ehdr {
         println!("  Entry point address:{:15}0x{:x}", "", self.e_entry);
         println!("  Start of program headers:{:10}{} (bytes into file)", "", self.e_phoff);
         println!("  Start of section headers:{:10}{} (bytes into file)", "", self.e_shoff);
-        println!("  Flags:{:29}0x{:x}", "", self.e_flags);
+        println!("  Flags:{:29}{}", "", get_flags_name(self.e_machine, self.e_flags));
         println!("  Size of this header:{:15}{} (bytes)", "", self.e_ehsize);
         println!("  Size of program headers:{:11}{} (bytes)", "", self.e_phentsize);
-        println!("  Number of program headers:{:9}{}", "", self.e_phnum);
+        let initial = self.section_headers.first();
+        println!("  Number of program headers:{:9}{}", "",
+                 format_extended(self.e_phnum, PN_XNUM, initial.map(|s| s.sh_info as u64)));
         println!("  Size of section headers:{:11}{} (bytes)", "", self.e_shentsize);
-        println!("  Number of section headers:{:9}{}", "", self.e_shnum);
-        println!("  Section header string table index:{:1}{}", "", self.e_shstrndx);
+        println!("  Number of section headers:{:9}{}", "", format_extended(self.e_shnum, 0, initial.map(|s| s.sh_size)));
+        println!("  Section header string table index:{:1}{}", "",
+                 format_extended(self.e_shstrndx, SHN_XINDEX, initial.map(|s| s.sh_link as u64)));
     }
 }
 
-pub fn is_elf(bytes: &[u8]) -> bool {
-    if bytes[0] != 0x7F {
-        return false;
+/// Stores a `size`-byte integer at `offset` in the given byte order.
+pub fn write_value(bytes: &mut [u8], offset: usize, size: usize, value: u64, little_endian: bool) {
+    let encoded = if little_endian { value.to_le_bytes() } else { value.to_be_bytes() };
+    let field = if little_endian { &encoded[..size] } else { &encoded[8 - size..] };
+    if let Some(dest) = bytes.get_mut(offset..offset + size) {
+        dest.copy_from_slice(field);
     }
-    if bytes[1] != 0x45 {
-        return false;
+}
+
+/// Appends a section header table to an ELF image and points the ELF header at it. An empty
+/// list removes the table.
+pub fn write_section_headers(bytes: &mut Vec<u8>, shdrs: &[SectionHeader], shstrndx: usize) {
+    let is_64 = bytes[EI_CLASS] == ELFCLASS64;
+    let le = bytes[EI_DATA] != ELFDATA2MSB;
+    let addr = if is_64 { 8 } else { 4 };
+    let shoff = if shdrs.is_empty() { 0 } else { bytes.len().next_multiple_of(addr) };
+    bytes.resize(shoff.max(bytes.len()), 0);
+    for shdr in shdrs.iter() {
+        let fields = [(4, shdr.sh_name as u64), (4, shdr.sh_type as u64), (addr, shdr.sh_flags), (addr, shdr.sh_addr),
+                      (addr, shdr.sh_offset), (addr, shdr.sh_size), (4, shdr.sh_link as u64), (4, shdr.sh_info as u64),
+                      (addr, shdr.sh_addralign), (addr, shdr.sh_entsize)];
+        for (size, value) in fields {
+            let at = bytes.len();
+            bytes.resize(at + size, 0);
+            write_value(bytes, at, size, value, le);
+        }
     }
-    if bytes[2] != 0x4C {
-        return false;
+    // Counts that do not fit the header go to section 0.
+    let (shnum, shstrndx) = (shdrs.len(), shstrndx);
+    let e_shnum = if shnum >= SHN_LORESERVE as usize { 0 } else { shnum as u64 };
+    let e_shstrndx = if shstrndx >= SHN_LORESERVE as usize { SHN_XINDEX as u64 } else { shstrndx as u64 };
+    if shoff != 0 && e_shnum == 0 {
+        write_value(bytes, shoff + if is_64 { 32 } else { 20 }, addr, shnum as u64, le);
     }
-    if bytes[3] != 0x46 {
-        return false;
+    if shoff != 0 && e_shstrndx == SHN_XINDEX as u64 {
+        write_value(bytes, shoff + if is_64 { 40 } else { 24 }, 4, shstrndx as u64, le);
     }
-    true
+    let (e_shoff, e_shentsize, e_shnum_at, e_shstrndx_at, shdr_size) = if is_64 {
+        (ELF64_OFFSET_E_SHOFF, ELF64_OFFSET_E_SHENTSIZE, ELF64_OFFSET_E_SHNUM, ELF64_OFFSET_E_SHSTRNDX, ELF64_SHDR_SIZE)
+    } else {
+        (ELF32_OFFSET_E_SHOFF, ELF32_OFFSET_E_SHENTSIZE, ELF32_OFFSET_E_SHNUM, ELF32_OFFSET_E_SHSTRNDX, ELF32_SHDR_SIZE)
+    };
+    write_value(bytes, e_shoff, addr, shoff as u64, le);
+    write_value(bytes, e_shentsize, 2, shdr_size as u64, le);
+    write_value(bytes, e_shnum_at, 2, e_shnum, le);
+    write_value(bytes, e_shstrndx_at, 2, e_shstrndx, le);
 }
 
-pub fn is_elf32(bytes: &[u8]) -> bool {
-    bytes[EI_CLASS] == ELFCLASS32
+/// Builds the ELF header of a new file. Program and section header fields are filled in
+/// by write_program_headers and write_section_headers.
+pub fn write_file_header(is_64: bool, little_endian: bool, e_type: u16, e_machine: u16, e_flags: u32, e_entry: u64) -> Vec<u8> {
+    let mut bytes = vec![0u8; if is_64 { ELF64_EHDR_SIZE } else { ELF32_EHDR_SIZE }];
+    bytes[..4].copy_from_slice(&[0x7f, b'E', b'L', b'F']);
+    bytes[EI_CLASS] = if is_64 { ELFCLASS64 } else { ELFCLASS32 };
+    bytes[EI_DATA] = if little_endian { ELFDATA2LSB } else { ELFDATA2MSB };
+    bytes[EI_VERSION] = EV_CURRENT as u8;
+    let ehsize = bytes.len() as u64;
+    let (fields, addr) = if is_64 {
+        ([ELF64_OFFSET_E_TYPE, ELF64_OFFSET_E_MACHINE, ELF64_OFFSET_E_VERSION, ELF64_OFFSET_E_ENTRY,
+          ELF64_OFFSET_E_FLAGS, ELF64_OFFSET_E_EHSIZE], 8)
+    } else {
+        ([ELF32_OFFSET_E_TYPE, ELF32_OFFSET_E_MACHINE, ELF32_OFFSET_E_VERSION, ELF32_OFFSET_E_ENTRY,
+          ELF32_OFFSET_E_FLAGS, ELF32_OFFSET_E_EHSIZE], 4)
+    };
+    let values = [(2, e_type as u64), (2, e_machine as u64), (4, EV_CURRENT as u64), (addr, e_entry),
+                  (4, e_flags as u64), (2, ehsize)];
+    for (at, (size, value)) in fields.iter().zip(values) {
+        write_value(&mut bytes, *at, size, value, little_endian);
+    }
+    bytes
 }
 
-pub fn is_elf64(bytes: &[u8]) -> bool {
-    bytes[EI_CLASS] == ELFCLASS64
+/// Appends the program headers and points the ELF header at them.
+pub fn write_program_headers(bytes: &mut Vec<u8>, phdrs: &[ProgramHeader]) {
+    let is_64 = bytes[EI_CLASS] == ELFCLASS64;
+    let le = bytes[EI_DATA] != ELFDATA2MSB;
+    let addr = if is_64 { 8 } else { 4 };
+    let phoff = bytes.len().next_multiple_of(addr);
+    bytes.resize(phoff, 0);
+    for phdr in phdrs.iter() {
+        let fields = if is_64 {
+            [(4, phdr.p_type as u64), (4, phdr.p_flags as u64), (8, phdr.p_offset), (8, phdr.p_vaddr), (8, phdr.p_paddr),
+             (8, phdr.p_filesz), (8, phdr.p_memsz), (8, phdr.p_align)]
+        } else {
+            [(4, phdr.p_type as u64), (4, phdr.p_offset), (4, phdr.p_vaddr), (4, phdr.p_paddr), (4, phdr.p_filesz),
+             (4, phdr.p_memsz), (4, phdr.p_flags as u64), (4, phdr.p_align)]
+        };
+        for (size, value) in fields {
+            let at = bytes.len();
+            bytes.resize(at + size, 0);
+            write_value(bytes, at, size, value, le);
+        }
+    }
+    let (e_phoff, e_phentsize, e_phnum, phdr_size) = if is_64 {
+        (ELF64_OFFSET_E_PHOFF, ELF64_OFFSET_E_PHENTSIZE, ELF64_OFFSET_E_PHNUM, ELF64_PHDR_SIZE)
+    } else {
+        (ELF32_OFFSET_E_PHOFF, ELF32_OFFSET_E_PHENTSIZE, ELF32_OFFSET_E_PHNUM, ELF32_PHDR_SIZE)
+    };
+    write_value(bytes, e_phoff, addr, phoff as u64, le);
+    write_value(bytes, e_phentsize, 2, phdr_size as u64, le);
+    write_value(bytes, e_phnum, 2, phdrs.len() as u64, le);
 }
 
-pub fn get_machine_name(e_machine:u16) -> String {
-    for machine in &ELF_MACHINES {
-        if e_machine == machine.0 {
-            return machine.1.to_string();
-        }
+/// Size of the ELF file header.
+pub fn file_header_size(is_64: bool) -> usize {
+    if is_64 { ELF64_EHDR_SIZE } else { ELF32_EHDR_SIZE }
+}
+
+/// Size of one program header entry.
+pub fn program_header_size(is_64: bool) -> usize {
+    if is_64 { ELF64_PHDR_SIZE } else { ELF32_PHDR_SIZE }
+}
+
+/// Size of one section header entry.
+pub fn section_header_size(is_64: bool) -> usize {
+    if is_64 { ELF64_SHDR_SIZE } else { ELF32_SHDR_SIZE }
+}
+
+/// Appends one symbol table entry. `sym.st_shndx` is written as is.
+pub fn write_symbol(bytes: &mut Vec<u8>, sym: &Symbol, is_64: bool, little_endian: bool) {
+    let fields = if is_64 {
+        [(4, sym.st_name as u64), (1, sym.st_info as u64), (1, sym.st_other as u64), (2, sym.st_shndx as u64),
+         (8, sym.st_value), (8, sym.st_size)]
+    } else {
+        [(4, sym.st_name as u64), (4, sym.st_value), (4, sym.st_size), (1, sym.st_info as u64),
+         (1, sym.st_other as u64), (2, sym.st_shndx as u64)]
+    };
+    for (size, value) in fields {
+        let at = bytes.len();
+        bytes.resize(at + size, 0);
+        write_value(bytes, at, size, value, little_endian);
+    }
+}
+
+/// Size of one symbol table entry.
+pub fn symbol_size(is_64: bool) -> usize {
+    if is_64 { ELF64_SYM_SIZE } else { ELF32_SYM_SIZE }
+}
+
+fn section_slice<'a>(bytes: &'a [u8], shdr: &SectionHeader) -> &'a [u8] {
+    if shdr.sh_type == SHT_NOBITS {
+        return &[];
+    }
+    let start = shdr.sh_offset as usize;
+    let end = start.saturating_add(shdr.sh_size as usize);
+    bytes.get(start..end).unwrap_or(&[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testelf;
+    use crate::testelf::Builder;
+
+    #[test]
+    fn extended_numbering() {
+        let mut b = Builder::new(EM_X86_64);
+        let text = b.section(".text", SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, 0x401000, &[0xc3]);
+        let strtab = b.section(".strtab", SHT_STRTAB, 0, 0, b"\0f\0");
+        let mut sym = vec![0u8; 48];
+        sym[24] = 1;                                    // st_name "f"
+        sym[28] = 0x12;                                 // STB_GLOBAL, STT_FUNC
+        sym[30..32].copy_from_slice(&SHN_XINDEX.to_le_bytes());
+        sym[32..40].copy_from_slice(&0x401000u64.to_le_bytes());
+        let symtab = b.section(".symtab", SHT_SYMTAB, 0, 0, &sym);
+        let mut xindex = vec![0u8; 8];
+        xindex[4..].copy_from_slice(&(text as u32).to_le_bytes());
+        let shndx = b.section(".symtab_shndx", SHT_SYMTAB_SHNDX, 0, 0, &xindex);
+        b.sections[symtab - 1].sh_link = strtab as u32;
+        b.sections[shndx - 1].sh_link = symtab as u32;
+        b.load(text, text, PF_R | PF_X);
+        let mut bytes = b.build();
+
+        // Move the counts to section 0 as if they did not fit the header.
+        let (shnum, shstrndx) = (b.sections.len() as u64 + 2, b.sections.len() as u64 + 1);
+        let initial = testelf::shdr(&bytes, 0);
+        testelf::put(&mut bytes, initial + 32, shnum, 8);
+        testelf::put(&mut bytes, initial + 40, shstrndx, 4);
+        testelf::put(&mut bytes, initial + 44, 1, 4);
+        testelf::put(&mut bytes, 56, PN_XNUM as u64, 2);
+        testelf::put(&mut bytes, 60, 0, 2);
+        testelf::put(&mut bytes, 62, SHN_XINDEX as u64, 2);
+
+        let elf = ElfFile::parse(bytes).unwrap();
+        assert_eq!((elf.e_phnum, elf.e_shnum, elf.e_shstrndx), (PN_XNUM, 0, SHN_XINDEX));
+        assert_eq!(elf.phnum(), 1);
+        assert_eq!(elf.shnum(), shnum as usize);
+        assert_eq!(elf.shstrndx(), shstrndx as usize);
+        assert_eq!(elf.program_headers.len(), 1);
+        assert_eq!(elf.section_headers[text].name, ".text");
+        let f = elf.symbols.iter().find(|s| s.name == "f").unwrap();
+        assert_eq!(f.section_index(), Some(text));
+
+        let initial = elf.section_headers.first();
+        assert_eq!(format_extended(elf.e_shnum, 0, initial.map(|s| s.sh_size)), format!("0 ({})", shnum));
+        assert_eq!(format_extended(elf.e_shstrndx, SHN_XINDEX, initial.map(|s| s.sh_link as u64)),
+                   format!("65535 ({})", shstrndx));
     }
-    return format!("Unknown Machine:[{}]", e_machine);
 }
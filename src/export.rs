@@ -0,0 +1,277 @@
+use std::fs;
+
+use crate::elf;
+use crate::elf::ElfFile;
+
+const RECORD_BYTES: usize = 16;     // data bytes per HEX/SREC line, as objcopy writes them
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Binary,
+    IntelHex,
+    SRecord,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "binary" | "bin" => Some(Format::Binary),
+            "ihex" | "hex" => Some(Format::IntelHex),
+            "srec" | "s19" | "s28" | "s37" | "mot" => Some(Format::SRecord),
+            _ => None,
+        }
+    }
+}
+
+pub struct ExportOptions {
+    pub format:     Format,
+    pub range:      Option<(u64, u64)>,     // [start, end) of load addresses to keep
+    pub gap_fill:   Option<u8>,             // fill between segments (binary always fills, with 0 by default)
+    pub srec_type:  Option<u8>,             // 1, 2 or 3 for S19/S28/S37; chosen from the addresses when None
+}
+
+/// A contiguous run of bytes at a load address.
+pub struct Chunk {
+    pub addr:   u64,
+    pub data:   Vec<u8>,
+}
+
+/// Parses "0x..." as hex and anything else as decimal.
+pub fn parse_number(text: &str) -> Option<u64> {
+    match text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse::<u64>().ok(),
+    }
+}
+
+/// Parses "<start>-<end>" or "<start>+<length>".
+pub fn parse_range(text: &str) -> Option<(u64, u64)> {
+    if let Some((start, len)) = text.split_once('+') {
+        let start = parse_number(start)?;
+        return Some((start, start.checked_add(parse_number(len)?)?));
+    }
+    let (start, end) = text.split_once('-')?;
+    let (start, end) = (parse_number(start)?, parse_number(end)?);
+    if start < end { Some((start, end)) } else { None }
+}
+
+/// The file contents of the PT_LOAD segments at their load addresses (p_paddr), clipped to
+/// `range` and merged where they touch. Without program headers the SHF_ALLOC sections are used.
+pub fn load_image(elf: &ElfFile, range: Option<(u64, u64)>) -> Result<Vec<Chunk>, String> {
+    let mut pieces: Vec<(u64, &[u8])> = Vec::new();
+    for phdr in elf.program_headers.iter().filter(|p| p.p_type == elf::PT_LOAD && p.p_filesz > 0) {
+        pieces.push((phdr.p_paddr, elf.segment_data(phdr)));
+    }
+    if elf.program_headers.is_empty() {
+        for shdr in elf.section_headers.iter().filter(|s| s.is_alloc() && s.sh_type != elf::SHT_NOBITS && s.sh_size > 0) {
+            pieces.push((shdr.sh_addr, elf.section_data(shdr)));
+        }
+    }
+    pieces.sort_by_key(|p| p.0);
+
+    let (lo, hi) = range.unwrap_or((0, u64::MAX));
+    let mut chunks: Vec<Chunk> = Vec::new();
+    for (addr, data) in pieces {
+        let end = addr.saturating_add(data.len() as u64);
+        let (s, e) = (addr.max(lo), end.min(hi));
+        if s >= e {
+            continue;
+        }
+        let data = &data[(s - addr) as usize..(e - addr) as usize];
+        match chunks.last_mut() {
+            Some(last) if last.addr + last.data.len() as u64 > s => {
+                return Err(format!("load segments overlap at {:#x}", s));
+            }
+            Some(last) if last.addr + last.data.len() as u64 == s => last.data.extend_from_slice(data),
+            _ => chunks.push(Chunk{addr: s, data: data.to_vec()}),
+        }
+    }
+    if chunks.is_empty() {
+        return Err("nothing to export: no loadable contents in the range".to_string());
+    }
+    Ok(chunks)
+}
+
+/// Joins the chunks into one, filling the gaps with `fill`.
+fn fill_gaps(chunks: &[Chunk], fill: u8) -> Chunk {
+    let start = chunks[0].addr;
+    let mut data = Vec::new();
+    for chunk in chunks {
+        data.resize((chunk.addr - start) as usize, fill);
+        data.extend_from_slice(&chunk.data);
+    }
+    Chunk{addr: start, data}
+}
+
+fn checksum_ihex(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)).wrapping_neg()
+}
+
+fn ihex_record(out: &mut String, addr: u16, record_type: u8, data: &[u8]) {
+    let mut bytes = vec![data.len() as u8, (addr >> 8) as u8, addr as u8, record_type];
+    bytes.extend_from_slice(data);
+    out.push(':');
+    for b in bytes.iter() {
+        out.push_str(&format!("{:02X}", b));
+    }
+    out.push_str(&format!("{:02X}\n", checksum_ihex(&bytes)));
+}
+
+/// Intel HEX with type 04 (extended linear address) records and a type 05 start address.
+pub fn to_ihex(chunks: &[Chunk], entry: u64) -> Result<String, String> {
+    let mut out = String::new();
+    let mut upper = 0u64;
+    for chunk in chunks {
+        if chunk.addr + chunk.data.len() as u64 > 0x1_0000_0000 {
+            return Err(format!("address {:#x} does not fit Intel HEX (32-bit)", chunk.addr + chunk.data.len() as u64 - 1));
+        }
+        let mut addr = chunk.addr;
+        let mut rest = &chunk.data[..];
+        while !rest.is_empty() {
+            if addr >> 16 != upper {
+                upper = addr >> 16;
+                ihex_record(&mut out, 0, 0x04, &[(upper >> 8) as u8, upper as u8]);
+            }
+            // A record must not cross a 64 KiB boundary.
+            let len = rest.len().min(RECORD_BYTES).min((0x1_0000 - (addr & 0xffff)) as usize);
+            ihex_record(&mut out, addr as u16, 0x00, &rest[..len]);
+            addr += len as u64;
+            rest = &rest[len..];
+        }
+    }
+    if entry != 0 && entry <= u32::MAX as u64 {
+        ihex_record(&mut out, 0, 0x05, &(entry as u32).to_be_bytes());
+    }
+    ihex_record(&mut out, 0, 0x01, &[]);
+    Ok(out)
+}
+
+fn srec_record(out: &mut String, record_type: u8, addr: u64, addr_len: usize, data: &[u8]) {
+    let mut bytes = vec![(addr_len + data.len() + 1) as u8];
+    bytes.extend_from_slice(&addr.to_be_bytes()[8 - addr_len..]);
+    bytes.extend_from_slice(data);
+    out.push_str(&format!("S{}", record_type));
+    for b in bytes.iter() {
+        out.push_str(&format!("{:02X}", b));
+    }
+    out.push_str(&format!("{:02X}\n", !bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b))));
+}
+
+/// Motorola S-records: S0 header, S1/S2/S3 data, S5 count and S9/S8/S7 start address.
+pub fn to_srec(chunks: &[Chunk], entry: u64, header: &str, srec_type: Option<u8>) -> Result<String, String> {
+    let top = chunks.iter().map(|c| c.addr + c.data.len() as u64 - 1).max().unwrap_or(0).max(entry);
+    let srec_type = match srec_type {
+        Some(t) => t,
+        None if top <= 0xffff => 1,
+        None if top <= 0xff_ffff => 2,
+        None => 3,
+    };
+    let addr_len = srec_type as usize + 1;
+    if top >> (addr_len * 8) != 0 {
+        return Err(format!("address {:#x} does not fit S{}{} records", top, srec_type, 10 - srec_type));
+    }
+    let mut out = String::new();
+    srec_record(&mut out, 0, 0, 2, &header.as_bytes()[..header.len().min(64)]);
+    let mut count = 0;
+    for chunk in chunks {
+        for (i, data) in chunk.data.chunks(RECORD_BYTES).enumerate() {
+            srec_record(&mut out, srec_type, chunk.addr + (i * RECORD_BYTES) as u64, addr_len, data);
+            count += 1;
+        }
+    }
+    if count <= 0xffff {
+        srec_record(&mut out, 5, count, 2, &[]);
+    }
+    srec_record(&mut out, 10 - srec_type, entry, addr_len, &[]);
+    Ok(out)
+}
+
+/// Writes the loadable image of `elf` to `output` and returns the chunks written.
+pub fn export(elf: &ElfFile, output: &str, options: &ExportOptions) -> Result<Vec<Chunk>, String> {
+    let mut chunks = load_image(elf, options.range)?;
+    let fill = if options.format == Format::Binary { Some(options.gap_fill.unwrap_or(0)) } else { options.gap_fill };
+    if let Some(fill) = fill {
+        chunks = vec![fill_gaps(&chunks, fill)];
+    }
+    let header = output.rsplit('/').next().unwrap_or(output);
+    let bytes = match options.format {
+        Format::Binary => chunks[0].data.clone(),
+        Format::IntelHex => to_ihex(&chunks, elf.e_entry)?.into_bytes(),
+        Format::SRecord => to_srec(&chunks, elf.e_entry, header, options.srec_type)?.into_bytes(),
+    };
+    fs::write(output, bytes).map_err(|e| format!("{}: {}", output, e))?;
+    Ok(chunks)
+}
+
+pub fn show_export(output: &str, chunks: &[Chunk]) {
+    println!("Wrote {}:", output);
+    println!("  {:<18}  {:<18}  {:>10}", "Start", "End", "Size");
+    for chunk in chunks {
+        println!("  {:#018x}  {:#018x}  {:>10}", chunk.addr, chunk.addr + chunk.data.len() as u64, chunk.data.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(addr: u64, data: &[u8]) -> Chunk {
+        Chunk{addr, data: data.to_vec()}
+    }
+
+    #[test]
+    fn ihex_checksum() {
+        let data = [0x21, 0x46, 0x01, 0x36, 0x01, 0x21, 0x47, 0x01, 0x36, 0x00, 0x7e, 0xfe, 0x09, 0xd2, 0x19, 0x01];
+        let hex = to_ihex(&[chunk(0x100, &data)], 0).unwrap();
+        assert_eq!(hex, ":10010000214601360121470136007EFE09D2190140\n:00000001FF\n");
+    }
+
+    #[test]
+    fn ihex_extended_linear_address() {
+        // Sixteen bytes straddling the 64 KiB boundary at 0x20000 split into two records.
+        let data: Vec<u8> = (0..16).collect();
+        let hex = to_ihex(&[chunk(0x1fff8, &data)], 0x20008).unwrap();
+        assert_eq!(hex, concat!(
+            ":020000040001F9\n",
+            ":08FFF8000001020304050607E5\n",
+            ":020000040002F8\n",
+            ":0800000008090A0B0C0D0E0F9C\n",
+            ":0400000500020008ED\n",
+            ":00000001FF\n"));
+    }
+
+    #[test]
+    fn ihex_address_too_large() {
+        assert!(to_ihex(&[chunk(0xffff_fff0, &[0; 32])], 0).is_err());
+    }
+
+    #[test]
+    fn srec_checksum_and_count() {
+        let mut data = vec![0x0a, 0x0a, 0x0d];
+        data.resize(18, 0);
+        data[16..].copy_from_slice(&[0x01, 0x02]);
+        let srec = to_srec(&[chunk(0x7af0, &data)], 0x7af0, "HDR", None).unwrap();
+        assert_eq!(srec, concat!(
+            "S00600004844521B\n",
+            "S1137AF00A0A0D0000000000000000000000000061\n",
+            "S1057B0001027C\n",
+            "S5030002FA\n",
+            "S9037AF092\n"));
+    }
+
+    #[test]
+    fn srec_type_from_addresses() {
+        let srec = to_srec(&[chunk(0x10000, &[0xaa])], 0x10000, "HDR", None).unwrap();
+        assert_eq!(srec, "S00600004844521B\nS205010000AA4F\nS5030001FB\nS804010000FA\n");
+        let srec = to_srec(&[chunk(0x100_0000, &[0xaa])], 0, "HDR", None).unwrap();
+        assert!(srec.lines().nth(1).unwrap().starts_with("S30601000000AA"));
+    }
+
+    #[test]
+    fn srec_address_too_large() {
+        let err = to_srec(&[chunk(0xfff0, &[0; 32])], 0, "HDR", Some(1)).unwrap_err();
+        assert_eq!(err, "address 0x1000f does not fit S19 records");
+        let err = to_srec(&[chunk(0x100, &[0])], 0x100_0000, "HDR", Some(2)).unwrap_err();
+        assert_eq!(err, "address 0x1000000 does not fit S28 records");
+    }
+}
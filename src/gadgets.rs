@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+use crate::disasm::Decoder;
+use crate::disasm::Flow;
+use crate::disasm::Instruction;
+use crate::disasm::MAX_INSN_BYTES;
+use crate::elf;
+use crate::elf::ElfFile;
+
+/// Default number of instructions in a gadget, the terminator included.
+pub const DEFAULT_DEPTH: usize = 5;
+
+/// How a gadget hands control on.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    Rop,    // ret
+    Jop,    // jmp reg
+    Cop,    // call reg
+}
+
+pub struct Gadget {
+    pub addr:   u64,                // first occurrence
+    pub count:  usize,              // occurrences of the same instruction sequence
+    pub kind:   Kind,
+    pub class:  &'static str,
+    pub insns:  Vec<String>,
+}
+
+impl Gadget {
+    pub fn text(&self) -> String {
+        self.insns.join(" ; ")
+    }
+}
+
+pub fn get_kind_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Rop => "rop",
+        Kind::Jop => "jop",
+        Kind::Cop => "cop",
+    }
+}
+
+pub fn parse_kind(name: &str) -> Option<Kind> {
+    match name {
+        "rop" => Some(Kind::Rop),
+        "jop" => Some(Kind::Jop),
+        "cop" => Some(Kind::Cop),
+        _ => None,
+    }
+}
+
+fn is_x86(machine: u16) -> bool {
+    machine == elf::EM_386 || machine == elf::EM_X86_64
+}
+
+/// Alignment of instruction starts, and the longest encoding, for the machines gadgets are
+/// searched on.
+fn insn_sizes(machine: u16) -> Option<(usize, usize)> {
+    match machine {
+        elf::EM_386 | elf::EM_X86_64 => Some((1, MAX_INSN_BYTES)),
+        elf::EM_AARCH64 => Some((4, 4)),
+        elf::EM_RISCV => Some((2, 4)),
+        _ => None,
+    }
+}
+
+/// The kind of gadget `insn` ends, if it is a return or a jump or call through a register.
+fn terminator(machine: u16, insn: &Instruction) -> Option<Kind> {
+    // x86 can also jump through memory; those are PLT stubs and jump tables, not gadgets.
+    if is_x86(machine) && insn.operands.contains('[') {
+        return None;
+    }
+    match insn.flow {
+        Flow::Return => Some(Kind::Rop),
+        Flow::IndirectJump => Some(Kind::Jop),
+        Flow::IndirectCall => Some(Kind::Cop),
+        _ => None,
+    }
+}
+
+fn format_insn(insn: &Instruction) -> String {
+    if insn.operands.is_empty() {
+        insn.mnemonic.clone()
+    } else {
+        format!("{} {}", insn.mnemonic, insn.operands)
+    }
+}
+
+// Gadget classes, the most useful effect first
+const CLASS_SYSCALL: usize  = 0;
+const CLASS_PIVOT: usize    = 1;    // moves the stack pointer to a register value
+const CLASS_STORE: usize    = 2;
+const CLASS_POP: usize      = 3;    // loads a register from the stack
+const CLASS_LOAD: usize     = 4;
+const CLASS_REG: usize      = 5;    // register moves and arithmetic
+const CLASS_NONE: usize     = 6;
+const CLASS_NAMES: [&str; 7] = ["syscall", "pivot", "store", "pop", "load", "reg", "none"];
+
+const SYSCALL_MNEMONICS: [&str; 5] = ["syscall", "sysenter", "int", "svc", "ecall"];
+const RISCV_STORES: [&str; 8] = ["sb", "sh", "sw", "sd", "c.sw", "c.sd", "c.swsp", "c.sdsp"];
+const RISCV_LOADS: [&str; 11] = ["lb", "lh", "lw", "ld", "lbu", "lhu", "lwu", "c.lw", "c.ld", "c.lwsp", "c.ldsp"];
+
+fn is_stack_pointer(operand: &str) -> bool {
+    ["rsp", "esp", "sp"].contains(&operand)
+}
+
+fn is_immediate(operand: &str) -> bool {
+    let operand = operand.trim_start_matches('#').trim_start_matches('-');
+    operand.starts_with("0x") || operand.parse::<u64>().is_ok()
+}
+
+/// The class of a single instruction in front of the terminator.
+fn insn_class(machine: u16, insn: &Instruction) -> usize {
+    let m = insn.mnemonic.as_str();
+    let operands: Vec<&str> = insn.operands.split(',').map(|o| o.trim()).collect();
+    let dest = operands[0];
+    let source = operands.get(1).cloned().unwrap_or("");
+    let reads_stack = operands[1..].iter().any(|o| o.contains("sp"));
+    if SYSCALL_MNEMONICS.contains(&m) {
+        return CLASS_SYSCALL;
+    }
+    if is_x86(machine) {
+        match m {
+            "leave" => CLASS_PIVOT,
+            "xchg" if operands.iter().any(|o| is_stack_pointer(o)) => CLASS_PIVOT,
+            "mov" | "add" | "sub" | "lea" if is_stack_pointer(dest) && !is_immediate(source) => CLASS_PIVOT,
+            "pop" => CLASS_POP,
+            "nop" => CLASS_NONE,
+            "push" | "lea" => CLASS_REG,
+            _ if dest.contains('[') => CLASS_STORE,
+            _ if source.contains('[') => CLASS_LOAD,
+            _ => CLASS_REG,
+        }
+    } else if (m == "mov" || m == "mv" || m == "c.mv") && is_stack_pointer(dest) && !is_immediate(source) {
+        CLASS_PIVOT
+    } else if (machine == elf::EM_AARCH64 && m.starts_with("st")) || RISCV_STORES.contains(&m) {
+        CLASS_STORE
+    } else if (machine == elf::EM_AARCH64 && m.starts_with("ld")) || RISCV_LOADS.contains(&m) {
+        if reads_stack { CLASS_POP } else { CLASS_LOAD }
+    } else if m == "nop" || m == "c.nop" {
+        CLASS_NONE
+    } else {
+        CLASS_REG
+    }
+}
+
+/// What the instructions in front of the terminator do: the class of the most useful one.
+fn classify(machine: u16, insns: &[Instruction]) -> &'static str {
+    let body = &insns[..insns.len() - 1];
+    CLASS_NAMES[body.iter().map(|i| insn_class(machine, i)).min().unwrap_or(CLASS_NONE)]
+}
+
+/// The instructions decoded from `start` up to and including the terminator at `end`, when
+/// they land on it exactly within `depth` instructions and none of them transfers control.
+fn decode_run(decoder: &Decoder, bytes: &[u8], base: u64, start: usize, end: usize, depth: usize) -> Option<Vec<Instruction>> {
+    let mut decoder = *decoder;
+    let mut insns = Vec::new();
+    let mut pos = start;
+    while pos < end && insns.len() + 1 < depth {
+        let insn = decoder.decode(&bytes[pos..], base + pos as u64)?;
+        if insn.flow != Flow::Next {
+            return None;
+        }
+        pos += insn.len;
+        insns.push(insn);
+    }
+    if pos != end {
+        return None;
+    }
+    let insn = decoder.decode(&bytes[pos..], base + pos as u64)?;
+    insns.push(insn);
+    Some(insns)
+}
+
+/// Gadgets of up to `depth` instructions in the executable PT_LOAD segments, one per distinct
+/// instruction sequence, by address.
+pub fn find(elf: &ElfFile, decoder: &Decoder, depth: usize) -> Result<Vec<Gadget>, String> {
+    let (align, max_len) = match insn_sizes(elf.e_machine) {
+        Some(sizes) => sizes,
+        None => return Err(format!("gadget search supports x86, x86-64, AArch64 and RISC-V, not machine {}", elf.e_machine)),
+    };
+    let mut gadgets: Vec<Gadget> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for phdr in elf.program_headers.iter().filter(|p| p.p_type == elf::PT_LOAD && p.p_flags & elf::PF_X != 0) {
+        let bytes = elf.segment_data(phdr);
+        let base = phdr.p_vaddr;
+        for end in (0..bytes.len()).step_by(align) {
+            let mut probe = *decoder;
+            let kind = match probe.decode(&bytes[end..], base + end as u64) {
+                Some(insn) => match terminator(elf.e_machine, &insn) {
+                    Some(kind) => kind,
+                    None => continue,
+                },
+                None => continue,
+            };
+            let window = (depth - 1) * max_len;
+            let first = end.saturating_sub(window) / align * align;
+            for start in (first..=end).step_by(align) {
+                let insns = match decode_run(decoder, bytes, base, start, end, depth) {
+                    Some(insns) => insns,
+                    None => continue,
+                };
+                let texts: Vec<String> = insns.iter().map(format_insn).collect();
+                let key = texts.join(" ; ");
+                if let Some(&index) = seen.get(&key) {
+                    gadgets[index].count += 1;
+                    continue;
+                }
+                seen.insert(key, gadgets.len());
+                gadgets.push(Gadget{addr: base + start as u64, count: 1, kind, class: classify(elf.e_machine, &insns),
+                                    insns: texts});
+            }
+        }
+    }
+    gadgets.sort_by_key(|g| g.addr);
+    Ok(gadgets)
+}
+
+/// Matches `text` against a pattern in which `*` stands for any run of characters and `?`
+/// for any one character.
+fn wildcard(pattern: &[u8], text: &[u8]) -> bool {
+    match (pattern.first(), text.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => wildcard(&pattern[1..], text) || (!text.is_empty() && wildcard(pattern, &text[1..])),
+        (Some(b'?'), Some(_)) => wildcard(&pattern[1..], &text[1..]),
+        (Some(p), Some(t)) if p.eq_ignore_ascii_case(t) => wildcard(&pattern[1..], &text[1..]),
+        _ => false,
+    }
+}
+
+/// Whether consecutive instructions of the gadget match the `;`-separated instruction
+/// patterns, e.g. "pop r?i ; ret" or "mov * ; jmp *".
+pub fn matches(gadget: &Gadget, pattern: &str) -> bool {
+    let parts: Vec<String> = pattern.split(';').map(|p| p.split_whitespace().collect::<Vec<&str>>().join(" ")).collect();
+    let insns: Vec<String> = gadget.insns.iter().map(|i| i.split_whitespace().collect::<Vec<&str>>().join(" ")).collect();
+    insns.windows(parts.len()).any(|w| w.iter().zip(parts.iter()).all(|(i, p)| wildcard(p.as_bytes(), i.as_bytes())))
+}
+
+pub fn show_gadgets(gadgets: &[&Gadget]) {
+    println!("  {:<16}  Kind  {:<7}  Count  Gadget", "Address", "Class");
+    for g in gadgets.iter() {
+        println!("  {:016x}  {:<4}  {:<7}  {:>5}  {}", g.addr, get_kind_name(g.kind), g.class, g.count, g.text());
+    }
+    println!();
+    println!("{} gadgets", gadgets.len());
+}
@@ -1,68 +1,196 @@
 use std::process::Command;
 use std::process::Stdio;
 use std::process::Child;
-use std::io::Write;
-use mio::{Events, Poll, Token};
+use std::process::ChildStdin;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
 
 pub struct GdbMi {
     target: String,
     timeout: u32,
     proc: Option<Child>,
+    stdin: Option<ChildStdin>,
+    output: Option<Receiver<String>>,
 }
 
 impl GdbMi {
     pub fn new(target: &str, timeout:u32) -> GdbMi {
 
-        GdbMi{target: target.to_string(), timeout: timeout, proc: None}
+        GdbMi{target: target.to_string(), timeout, proc: None, stdin: None, output: None}
     }
 
     pub fn start(&mut self) {
         let mut proc = Command::new("gdb").
-            args(&["-q", "-mi", &self.target]).
+            args(["-q", "-nx", "--interpreter=mi", &self.target]).
             stdin(Stdio::piped()).
-            //stdout(Stdio::piped()).
+            stdout(Stdio::piped()).
             spawn().expect("gdb command failed");
+        self.stdin = proc.stdin.take();
+
+        // gdb output is read on its own thread so that reads can time out.
+        let stdout = proc.stdout.take().expect("Failed to open stdout");
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => if tx.send(line).is_err() { break },
+                    Err(_) => break,
+                }
+            }
+        });
+        self.output = Some(rx);
         self.proc = Some(proc);
-        /*
-        let mut stdin = proc.stdin.take().expect("Failed to open stdin");
-        stdin.write_all("b main".as_bytes()).expect("Failed to write to stdin");
-        */
+        self.check_status();
     }
 
-    pub fn run(&mut self) {
+    /// Runs the target and returns the pc where it stopped, or None if it exited.
+    pub fn run(&mut self) -> Option<u64> {
         self.exec_cmd("-exec-run");
+        self.wait_stopped()
+    }
+
+    /// Starts the target stopped at its first instruction, before the dynamic loader runs.
+    pub fn start_at_first_insn(&mut self) -> Option<u64> {
+        self.exec_cmd("-interpreter-exec console starti");
+        self.wait_stopped()
+    }
+
+    /// Resumes the target and returns the pc where it stopped, or None if it exited.
+    pub fn cont(&mut self) -> Option<u64> {
+        self.exec_cmd("-exec-continue");
+        self.wait_stopped()
+    }
+
+    /// Connects to a gdb stub (gdbserver, QEMU, OpenOCD) and returns the pc the target is stopped at.
+    pub fn connect_remote(&mut self, address: &str) -> Option<u64> {
+        self.exec_cmd(&format!("-target-select remote {}", address));
+        self.pc()
+    }
+
+    /// Returns the pc of the stopped target.
+    pub fn pc(&mut self) -> Option<u64> {
+        let records = self.exec_cmd("-data-evaluate-expression \"(unsigned long)$pc\"");
+        let result = records.iter().find(|r| r.starts_with("^done"))?;
+        field(result, "value")?.parse().ok()
+    }
+
+    /// Returns the runtime entry point (AT_ENTRY) of a started target.
+    pub fn entry_address(&mut self) -> Option<u64> {
+        let records = self.exec_cmd("-interpreter-exec console \"info auxv\"");
+        let line = records.iter().find(|r| r.starts_with("~\"") && r.contains("AT_ENTRY"))?;
+        let value = line.split_whitespace().rev().find(|t| t.starts_with("0x"))?;
+        u64::from_str_radix(value.trim_start_matches("0x").trim_end_matches("\"").trim_end_matches("\\n"), 16).ok()
     }
 
     pub fn set_break_point(&mut self, func_name: &str) {
         self.exec_cmd(& format!("-break-insert {}", &func_name));
     }
 
-    pub fn stepi(&mut self) {
+    /// Executes one machine instruction and returns the new pc, or None if the target exited.
+    pub fn stepi(&mut self) -> Option<u64> {
         self.exec_cmd("-exec-step-instruction");
+        self.wait_stopped()
+    }
+
+    /// Returns the runtime address of a symbol in the inferior.
+    pub fn symbol_address(&mut self, name: &str) -> Option<u64> {
+        let records = self.exec_cmd(&format!("-data-evaluate-expression \"(unsigned long)&{}\"", name));
+        let result = records.iter().find(|r| r.starts_with("^done"))?;
+        field(result, "value")?.parse().ok()
+    }
+
+    pub fn read_memory(&mut self, addr: u64, len: usize) -> Option<Vec<u8>> {
+        let records = self.exec_cmd(&format!("-data-read-memory-bytes 0x{:x} {}", addr, len));
+        let result = records.iter().find(|r| r.starts_with("^done"))?;
+        let contents = field(result, "contents")?;
+        (0..contents.len() / 2).map(|i| u8::from_str_radix(&contents[i * 2..i * 2 + 2], 16).ok()).collect()
     }
 
     fn write_cmd(&mut self, cmd: &str) {
-        let mut stdin = self.proc.take().unwrap().stdin.take().unwrap();
+        let stdin = self.stdin.as_mut().expect("gdb is not started");
         stdin.write_all(cmd.as_bytes()).expect("Failed to write to stdin");
+        stdin.write_all(b"\n").expect("Failed to write to stdin");
+        stdin.flush().expect("Failed to write to stdin");
     }
 
-    fn wait_until_writable(&self) {
-        //poll.register(&server, SERVER, Ready::readable(), PollOpt::edge()).unwrap();
-        loop {
-            // TODO
-            //ret = IO::select(nil, [@stdin], nil, @timeout)
-            //break if 0 < ret[1].length
-            println!("stdin waiting...");
+    fn read_line(&self) -> Result<String, RecvTimeoutError> {
+        let output = self.output.as_ref().ok_or(RecvTimeoutError::Disconnected)?;
+        output.recv_timeout(Duration::from_millis(self.timeout as u64))
+    }
+
+    /// Reads output records up to the next "(gdb)" prompt.
+    fn check_status(&mut self) -> Vec<String> {
+        let mut records = Vec::new();
+        while let Ok(line) = self.read_line() {
+            if line.trim_end() == "(gdb)" {
+                break;
+            }
+            if line.starts_with("^error") {
+                eprintln!("gdb: {}", field(&line, "msg").unwrap_or_default());
+            }
+            records.push(line);
         }
+        records
     }
 
-    fn check_status(&self) {
-        println!("TODO");
+    /// Waits for the *stopped record that follows an execution command.
+    fn wait_stopped(&mut self) -> Option<u64> {
+        loop {
+            // The target may run for any length of time before it stops; only gdb going away
+            // ends the wait.
+            let line = match self.read_line() {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return None,
+            };
+            if !line.starts_with("*stopped") {
+                continue;
+            }
+            self.check_status();
+            if field(&line, "reason").map(|r| r.starts_with("exited")).unwrap_or(false) {
+                return None;
+            }
+            let addr = field(&line, "addr")?;
+            return u64::from_str_radix(addr.trim_start_matches("0x"), 16).ok();
+        }
     }
 
-    fn exec_cmd(&mut self, cmd: &str) {
-        self.wait_until_writable();
+    fn exec_cmd(&mut self, cmd: &str) -> Vec<String> {
         self.write_cmd(cmd);
-        self.check_status();
+        self.check_status()
+    }
+}
+
+impl Drop for GdbMi {
+    fn drop(&mut self) {
+        if let Some(stdin) = self.stdin.as_mut() {
+            let _ = stdin.write_all(b"-gdb-exit\n");
+        }
+        if let Some(mut proc) = self.proc.take() {
+            let _ = proc.wait();
+        }
+    }
+}
+
+/// Returns the value of the first `name="..."` pair in an MI record.
+fn field(record: &str, name: &str) -> Option<String> {
+    let key = format!("{}=\"", name);
+    let start = record.match_indices(&key)
+        .find(|(i, _)| *i == 0 || matches!(record.as_bytes()[i - 1], b',' | b'{' | b'['))?.0 + key.len();
+    let mut value = String::new();
+    let mut chars = record[start..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                't' => value.push('\t'),
+                c => value.push(c),
+            },
+            c => value.push(c),
+        }
     }
+    None
 }
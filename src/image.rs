@@ -0,0 +1,371 @@
+use std::fs;
+
+use crate::elf;
+use crate::elf::ElfFile;
+use crate::elf::ProgramHeader;
+use crate::elf::SectionHeader;
+use crate::export;
+use crate::export::Chunk;
+use crate::export::Format;
+
+// Machines for images without an ELF header: name, e_machine, 64-bit, e_flags
+const MACHINES: [(&str, u16, bool, u32); 11] = [
+  ("i386"       , elf::EM_386       , false , 0                     ),
+  ("x86"        , elf::EM_386       , false , 0                     ),
+  ("x86_64"     , elf::EM_X86_64    , true  , 0                     ),
+  ("x86-64"     , elf::EM_X86_64    , true  , 0                     ),
+  ("arm"        , elf::EM_ARM       , false , elf::EF_ARM_EABI_VER5 ),
+  ("thumb"      , elf::EM_ARM       , false , elf::EF_ARM_EABI_VER5 ),
+  ("aarch64"    , elf::EM_AARCH64   , true  , 0                     ),
+  ("arm64"      , elf::EM_AARCH64   , true  , 0                     ),
+  ("riscv32"    , elf::EM_RISCV     , false , elf::EF_RISCV_RVC     ),
+  ("riscv64"    , elf::EM_RISCV     , true  , elf::EF_RISCV_RVC     ),
+  ("rx"         , elf::EM_RX        , false , 0                     ),
+];
+
+/// "image:<file>[,machine=<name>][,base=<addr>][,entry=<addr>][,symbols=<elf>][,format=ihex|srec|binary][,endian=big]"
+pub struct ImageSpec {
+    pub path:       String,
+    pub format:     Option<Format>,     // detected from the contents when None
+    pub machine:    Option<String>,
+    pub base:       u64,                // load address of a raw binary
+    pub entry:      Option<u64>,
+    pub symbols:    Option<String>,     // side ELF with the symbols (and DWARF) of the image
+    pub big_endian: bool,
+}
+
+pub fn parse_spec(spec: &str) -> Result<ImageSpec, String> {
+    let mut items = spec.split(',');
+    let path = items.next().filter(|p| !p.is_empty()).ok_or("image: needs a file name")?;
+    let mut image = ImageSpec{path: path.to_string(), format: None, machine: None, base: 0, entry: None, symbols: None,
+                              big_endian: false};
+    for item in items {
+        let (key, value) = item.split_once('=').ok_or(format!("image: expected key=value, got \"{}\"", item))?;
+        let number = || export::parse_number(value).ok_or(format!("image: bad address \"{}\"", value));
+        match key {
+            "machine" => image.machine = Some(value.to_string()),
+            "base" => image.base = number()?,
+            "entry" => image.entry = Some(number()?),
+            "symbols" => image.symbols = Some(value.to_string()),
+            "format" => image.format = Some(Format::from_name(value).ok_or(format!("image: unknown format {}", value))?),
+            "endian" => image.big_endian = match value {
+                "big" => true,
+                "little" => false,
+                _ => return Err(format!("image: unknown endian {}", value)),
+            },
+            _ => return Err(format!("image: unknown option {}", key)),
+        }
+    }
+    Ok(image)
+}
+
+/// Intel HEX lines start with ':' and S-records with 'S' and a record type digit.
+pub fn detect(bytes: &[u8]) -> Format {
+    let text = bytes.iter().position(|b| !b.is_ascii_whitespace()).map(|i| &bytes[i..]).unwrap_or(&[]);
+    let printable = bytes.iter().take(4096).all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace());
+    match text {
+        [b':', ..] if printable => Format::IntelHex,
+        [b'S', b'0'..=b'9', ..] if printable => Format::SRecord,
+        _ => Format::Binary,
+    }
+}
+
+fn hex_bytes(digits: &str, line: usize) -> Result<Vec<u8>, String> {
+    if !digits.len().is_multiple_of(2) || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("line {}: bad hex digits", line));
+    }
+    Ok((0..digits.len() / 2).map(|i| u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).unwrap_or(0)).collect())
+}
+
+fn be_value(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |value, b| value << 8 | *b as u64)
+}
+
+type Records = (Vec<(u64, Vec<u8>)>, Option<u64>);
+
+/// Returns the data records at their absolute addresses and the start address, if any.
+pub fn parse_ihex(text: &str) -> Result<Records, String> {
+    let mut records = Vec::new();
+    let (mut upper, mut entry) = (0u64, None);
+    for (i, line) in text.lines().enumerate().map(|(i, l)| (i + 1, l.trim())).filter(|(_, l)| !l.is_empty()) {
+        let bytes = hex_bytes(line.strip_prefix(':').ok_or(format!("line {}: missing ':'", i))?, i)?;
+        if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+            return Err(format!("line {}: bad record length", i));
+        }
+        if bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+            return Err(format!("line {}: checksum mismatch", i));
+        }
+        let (addr, data) = (be_value(&bytes[1..3]), &bytes[4..bytes.len() - 1]);
+        match (bytes[3], data.len()) {
+            (0x00, _) => records.push((upper + addr, data.to_vec())),
+            (0x01, _) => break,
+            (0x02, 2) => upper = be_value(data) << 4,
+            (0x03, 4) => entry = Some((be_value(&data[..2]) << 4) + be_value(&data[2..])),
+            (0x04, 2) => upper = be_value(data) << 16,
+            (0x05, 4) => entry = Some(be_value(data)),
+            (t, _) => return Err(format!("line {}: bad record type {:02X}", i, t)),
+        }
+    }
+    Ok((records, entry))
+}
+
+pub fn parse_srec(text: &str) -> Result<Records, String> {
+    let mut records = Vec::new();
+    let mut entry = None;
+    for (i, line) in text.lines().enumerate().map(|(i, l)| (i + 1, l.trim())).filter(|(_, l)| !l.is_empty()) {
+        let record_type = line.strip_prefix('S').and_then(|l| l.chars().next()).and_then(|c| c.to_digit(10))
+            .ok_or(format!("line {}: not an S-record", i))?;
+        let bytes = hex_bytes(&line[2..], i)?;
+        if bytes.is_empty() || bytes.len() != bytes[0] as usize + 1 {
+            return Err(format!("line {}: bad record length", i));
+        }
+        // The checksum is the ones' complement of the sum of the other bytes.
+        if bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0xff {
+            return Err(format!("line {}: checksum mismatch", i));
+        }
+        let addr_len = match record_type {
+            1 | 9 => 2,
+            2 | 8 => 3,
+            3 | 7 => 4,
+            _ => continue,      // S0 header, S5/S6 record counts
+        };
+        let body = &bytes[1..bytes.len() - 1];
+        if body.len() < addr_len {
+            return Err(format!("line {}: bad record length", i));
+        }
+        let addr = be_value(&body[..addr_len]);
+        if record_type <= 3 {
+            records.push((addr, body[addr_len..].to_vec()));
+        } else {
+            entry = Some(addr);
+        }
+    }
+    Ok((records, entry))
+}
+
+/// Sorts the records and joins the ones that touch into chunks.
+fn coalesce(mut records: Vec<(u64, Vec<u8>)>) -> Result<Vec<Chunk>, String> {
+    records.sort_by_key(|r| r.0);
+    let mut chunks: Vec<Chunk> = Vec::new();
+    for (addr, data) in records.into_iter().filter(|r| !r.1.is_empty()) {
+        match chunks.last_mut() {
+            Some(last) if last.addr + last.data.len() as u64 > addr => return Err(format!("records overlap at {:#x}", addr)),
+            Some(last) if last.addr + last.data.len() as u64 == addr => last.data.extend_from_slice(&data),
+            _ => chunks.push(Chunk{addr, data}),
+        }
+    }
+    if chunks.is_empty() {
+        return Err("image has no data".to_string());
+    }
+    Ok(chunks)
+}
+
+pub fn section_header(name: &str, names: &mut Vec<u8>, sh_type: u32, sh_flags: u64, sh_addr: u64, sh_offset: u64,
+                      sh_size: u64) -> SectionHeader {
+    let sh_name = names.len() as u32;
+    names.extend_from_slice(name.as_bytes());
+    names.push(0);
+    SectionHeader{name: name.to_string(), sh_name, sh_type, sh_flags, sh_addr, sh_offset, sh_size, sh_link: 0, sh_info: 0,
+                  sh_addralign: 1, sh_entsize: 0}
+}
+
+/// Copies the side ELF's symbols, moving each defined one to the image section that holds
+/// its address (SHN_ABS when no section does). Returns the .symtab and .strtab contents and
+/// the index of the first global symbol.
+fn copy_symbols(side: &ElfFile, shdrs: &[SectionHeader], is_64: bool, le: bool) -> (Vec<u8>, Vec<u8>, u32) {
+    let mut symtab = Vec::new();
+    let mut strtab = vec![0u8];
+    let null = elf::Symbol{name: String::new(), st_name: 0, st_info: 0, st_other: 0, st_shndx: 0, shndx: 0, st_value: 0, st_size: 0};
+    elf::write_symbol(&mut symtab, &null, is_64, le);
+    let symbols: Vec<&elf::Symbol> = side.symbols.iter().skip(1).filter(|s| s.st_type() != elf::STT_SECTION).collect();
+    let (locals, globals): (Vec<&elf::Symbol>, Vec<&elf::Symbol>) = symbols.iter().partition(|s| s.bind() == elf::STB_LOCAL);
+    for sym in locals.iter().chain(globals.iter()) {
+        let mut sym = (*sym).clone();
+        let addr = if side.e_machine == elf::EM_ARM && sym.is_function() { sym.st_value & !1 } else { sym.st_value };
+        if sym.section_index().is_some() || sym.st_shndx == elf::SHN_ABS {
+            sym.st_shndx = shdrs.iter().position(|s| s.is_alloc() && s.contains_vaddr(addr)).map_or(elf::SHN_ABS, |i| i as u16);
+        }
+        sym.st_name = strtab.len() as u32;
+        strtab.extend_from_slice(sym.name.as_bytes());
+        strtab.push(0);
+        elf::write_symbol(&mut symtab, &sym, is_64, le);
+    }
+    (symtab, strtab, locals.len() as u32 + 1)
+}
+
+/// Builds an ET_EXEC image with one PT_LOAD segment and one load<i> section per chunk, plus
+/// the symbols, debug sections and out-of-image sections of the side ELF.
+fn build(chunks: &[Chunk], e_machine: u16, is_64: bool, le: bool, e_flags: u32, entry: u64, side: Option<&ElfFile>) -> Vec<u8> {
+    let mut bytes = elf::write_file_header(is_64, le, elf::ET_EXEC, e_machine, e_flags, entry);
+    let mut offset = (bytes.len() + chunks.len() * elf::program_header_size(is_64)).next_multiple_of(16) as u64;
+    let mut phdrs = Vec::new();
+    for chunk in chunks {
+        let size = chunk.data.len() as u64;
+        phdrs.push(ProgramHeader{p_type: elf::PT_LOAD, p_flags: elf::PF_R | elf::PF_X, p_offset: offset, p_vaddr: chunk.addr,
+                                 p_paddr: chunk.addr, p_filesz: size, p_memsz: size, p_align: 1});
+        offset = (offset + size).next_multiple_of(16);
+    }
+    elf::write_program_headers(&mut bytes, &phdrs);
+
+    let mut names = vec![0u8];
+    let mut shdrs = vec![section_header("", &mut names, elf::SHT_NULL, 0, 0, 0, 0)];
+    for (i, (chunk, phdr)) in chunks.iter().zip(phdrs.iter()).enumerate() {
+        bytes.resize(phdr.p_offset as usize, 0);
+        bytes.extend_from_slice(&chunk.data);
+        shdrs.push(section_header(&format!("load{}", i), &mut names, elf::SHT_PROGBITS, elf::SHF_ALLOC | elf::SHF_EXECINSTR,
+                                  chunk.addr, phdr.p_offset, phdr.p_filesz));
+    }
+    if let Some(side) = side {
+        // Memory the image does not hold (RAM, peripherals) keeps the side ELF's sections without contents.
+        for shdr in side.section_headers.iter().filter(|s| s.is_alloc() && s.sh_size > 0) {
+            let end = shdr.sh_addr.saturating_add(shdr.sh_size);
+            if chunks.iter().all(|c| end <= c.addr || c.addr + c.data.len() as u64 <= shdr.sh_addr) {
+                shdrs.push(section_header(&shdr.name, &mut names, elf::SHT_NOBITS, shdr.sh_flags, shdr.sh_addr,
+                                          bytes.len() as u64, shdr.sh_size));
+            }
+        }
+        let (symtab, strtab, first_global) = copy_symbols(side, &shdrs, is_64, le);
+        let mut add = |name: &str, sh_type: u32, data: &[u8], align: usize, shdrs: &mut Vec<SectionHeader>| {
+            bytes.resize(bytes.len().next_multiple_of(align), 0);
+            let shdr = section_header(name, &mut names, sh_type, 0, 0, bytes.len() as u64, data.len() as u64);
+            bytes.extend_from_slice(data);
+            shdrs.push(shdr);
+            shdrs.len() - 1
+        };
+        for shdr in side.section_headers.iter().filter(|s| s.name.starts_with(".debug_")) {
+            let index = add(&shdr.name, shdr.sh_type, side.section_data(shdr), 1, &mut shdrs);
+            shdrs[index].sh_flags = shdr.sh_flags;
+        }
+        let strtab = add(".strtab", elf::SHT_STRTAB, &strtab, 1, &mut shdrs);
+        let symtab = add(".symtab", elf::SHT_SYMTAB, &symtab, 8, &mut shdrs);
+        shdrs[symtab].sh_link = strtab as u32;
+        shdrs[symtab].sh_info = first_global;
+        shdrs[symtab].sh_entsize = elf::symbol_size(is_64) as u64;
+    }
+    let shstrndx = shdrs.len();
+    let shstrtab = section_header(".shstrtab", &mut names, elf::SHT_STRTAB, 0, 0, bytes.len() as u64, 0);
+    shdrs.push(SectionHeader{sh_size: names.len() as u64, ..shstrtab});
+    bytes.extend_from_slice(&names);
+    elf::write_section_headers(&mut bytes, &shdrs, shstrndx);
+    bytes
+}
+
+/// Number of code bytes of the side ELF that differ from the image at the same load address,
+/// out of the number of code bytes the image covers.
+fn compare_code(side: &ElfFile, image: &ElfFile) -> (usize, usize) {
+    let (mut differ, mut total) = (0, 0);
+    for shdr in side.section_headers.iter().filter(|s| s.is_exec() && s.sh_type == elf::SHT_PROGBITS) {
+        let lma = side.program_headers.iter()
+            .find(|p| p.p_type == elf::PT_LOAD && p.contains_vaddr(shdr.sh_addr))
+            .map_or(shdr.sh_addr, |p| shdr.sh_addr - p.p_vaddr + p.p_paddr);
+        for (i, b) in side.section_data(shdr).iter().enumerate() {
+            if let Some(&[x]) = image.read_vaddr(lma + i as u64, 1) {
+                total += 1;
+                differ += (x != *b) as usize;
+            }
+        }
+    }
+    (differ, total)
+}
+
+/// Opens an Intel HEX, S-record or raw image as an ELF file (see ImageSpec).
+pub fn open(spec: &str) -> Result<ElfFile, String> {
+    let spec = parse_spec(spec)?;
+    let raw = fs::read(&spec.path).map_err(|e| format!("File open failed filepath:{} ({})", spec.path, e))?;
+    let side = match spec.symbols.as_deref() {
+        Some(path) => Some(ElfFile::open(path)?),
+        None => None,
+    };
+    let format = spec.format.unwrap_or(detect(&raw));
+    let text = || String::from_utf8_lossy(&raw).to_string();
+    let (records, start) = match format {
+        Format::IntelHex => parse_ihex(&text()),
+        Format::SRecord => parse_srec(&text()),
+        Format::Binary => Ok((vec![(spec.base, raw.clone())], None)),
+    }.map_err(|e| format!("{}: {}", spec.path, e))?;
+    let chunks = coalesce(records).map_err(|e| format!("{}: {}", spec.path, e))?;
+
+    let machine = spec.machine.as_deref().map(|name| MACHINES.iter().find(|m| m.0 == name)
+        .ok_or(format!("image: unknown machine {} (one of: {})", name,
+                       MACHINES.iter().map(|m| m.0).collect::<Vec<_>>().join(", "))));
+    let (e_machine, is_64, e_flags, le) = match (machine.transpose()?, side.as_ref()) {
+        (Some(m), Some(side)) if m.1 != side.e_machine => {
+            return Err(format!("image: machine {} does not match {}", m.0, elf::get_machine_name(side.e_machine)));
+        }
+        (Some(m), _) => (m.1, m.2, m.3, !spec.big_endian),
+        (None, Some(side)) => (side.e_machine, side.is_64(), side.e_flags, side.is_little_endian()),
+        (None, None) => return Err("image: machine=<name> or symbols=<elf> is required".to_string()),
+    };
+    let thumb = spec.machine.as_deref() == Some("thumb");
+    let entry = spec.entry.or(start).or(side.as_ref().map(|s| s.e_entry)).unwrap_or(chunks[0].addr | thumb as u64);
+
+    let elf = ElfFile::parse(build(&chunks, e_machine, is_64, le, e_flags, entry, side.as_ref()))?;
+    if let Some(side) = side.as_ref() {
+        let (differ, total) = compare_code(side, &elf);
+        if total == 0 {
+            eprintln!("warning: {} holds none of the code of {}", spec.path, spec.symbols.unwrap_or_default());
+        } else if differ > 0 {
+            eprintln!("warning: {} differs from {} in {} of {} code bytes", spec.path, spec.symbols.unwrap_or_default(),
+                      differ, total);
+        }
+    }
+    Ok(elf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks() -> Vec<Chunk> {
+        vec![Chunk{addr: 0x1fff8, data: (0..40).collect()}, Chunk{addr: 0x30000, data: vec![0xaa; 5]}]
+    }
+
+    fn flatten(chunks: &[Chunk]) -> Vec<(u64, Vec<u8>)> {
+        chunks.iter().map(|c| (c.addr, c.data.clone())).collect()
+    }
+
+    #[test]
+    fn ihex_round_trip() {
+        let (records, entry) = parse_ihex(&export::to_ihex(&chunks(), 0x20008).unwrap()).unwrap();
+        assert_eq!(flatten(&coalesce(records).unwrap()), flatten(&chunks()));
+        assert_eq!(entry, Some(0x20008));
+    }
+
+    #[test]
+    fn srec_round_trip() {
+        let (records, entry) = parse_srec(&export::to_srec(&chunks(), 0x20008, "HDR", None).unwrap()).unwrap();
+        assert_eq!(flatten(&coalesce(records).unwrap()), flatten(&chunks()));
+        assert_eq!(entry, Some(0x20008));
+    }
+
+    #[test]
+    fn ihex_segment_records() {
+        // Type 02 sets a segment base of 0x1000 paragraphs, type 03 the start CS:IP 0012:0034.
+        let (records, entry) = parse_ihex(":020000021000EC\n:0100040055A6\n:0400000300120034B3\n:00000001FF\n").unwrap();
+        assert_eq!(records, vec![(0x10004, vec![0x55])]);
+        assert_eq!(entry, Some(0x154));
+        assert_eq!(parse_ihex(":03000002100000EB\n").unwrap_err(), "line 1: bad record type 02");
+    }
+
+    #[test]
+    fn ihex_rejects_bad_records() {
+        assert_eq!(parse_ihex(":0100000000FE\n").unwrap_err(), "line 1: checksum mismatch");
+        assert_eq!(parse_ihex(":0100000000FF\n:02000000AAFE\n").unwrap_err(), "line 2: bad record length");
+        assert_eq!(parse_ihex("0100000000FF\n").unwrap_err(), "line 1: missing ':'");
+        assert_eq!(parse_ihex(":01000000G0FF\n").unwrap_err(), "line 1: bad hex digits");
+    }
+
+    #[test]
+    fn srec_rejects_bad_records() {
+        assert_eq!(parse_srec("S1057B0001027D\n").unwrap_err(), "line 1: checksum mismatch");
+        assert_eq!(parse_srec("S1067B0001027B\n").unwrap_err(), "line 1: bad record length");
+        assert_eq!(parse_srec("S101FE\n").unwrap_err(), "line 1: bad record length");
+        assert_eq!(parse_srec("X1057B0001027C\n").unwrap_err(), "line 1: not an S-record");
+    }
+
+    #[test]
+    fn overlapping_records() {
+        let (records, _) = parse_ihex(":0400000001020304F2\n:02000200AABB97\n").unwrap();
+        assert_eq!(coalesce(records).err().unwrap(), "records overlap at 0x2");
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+
+use crate::elf;
+use crate::elf::ElfFile;
+
+// Size of struct modversion_info: an unsigned long CRC and the rest of the 64 bytes for the name.
+const MODVERSION_INFO_SIZE: usize = 64;
+
+// Module licenses the kernel treats as GPL compatible (license_is_gpl_compatible)
+const GPL_COMPATIBLE: [&str; 6] = ["GPL", "GPL v2", "GPL and additional rights", "Dual BSD/GPL", "Dual MIT/GPL",
+                                   "Dual MPL/GPL"];
+
+// Relocation types the module loaders accept (apply_relocate/apply_relocate_add)
+const X86_64_MODULE_RELOCS: [u32; 7] = [
+    0,      // R_X86_64_NONE
+    1,      // R_X86_64_64
+    2,      // R_X86_64_PC32
+    4,      // R_X86_64_PLT32
+    10,     // R_X86_64_32
+    11,     // R_X86_64_32S
+    24,     // R_X86_64_PC64
+];
+const I386_MODULE_RELOCS: [u32; 3] = [
+    1,      // R_386_32
+    2,      // R_386_PC32
+    4,      // R_386_PLT32
+];
+const AARCH64_MODULE_RELOCS: [(u32, u32); 4] = [
+    (0, 0),         // R_AARCH64_NONE
+    (256, 280),     // R_AARCH64_NONE .. R_AARCH64_CONDBR19
+    (282, 293),     // R_AARCH64_JUMP26 .. R_AARCH64_MOVW_PREL_G3
+    (299, 299),     // R_AARCH64_LDST128_ABS_LO12_NC
+];
+
+pub struct Export {
+    pub name:   String,
+    pub kind:   String,     // EXPORT_SYMBOL, EXPORT_SYMBOL_GPL, ...
+}
+
+pub struct Module {
+    pub info:       Vec<(String, String)>,  // .modinfo key=value pairs in file order
+    pub versions:   Vec<(String, u32)>,     // __versions: imported symbol and its CRC
+    pub exports:    Vec<Export>,            // __ksymtab*
+}
+
+impl Module {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.info.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        self.info.iter().filter(|(k, _)| k == key).map(|(_, v)| v.as_str()).collect()
+    }
+
+    pub fn depends(&self) -> Vec<&str> {
+        self.get("depends").map(|d| d.split(',').filter(|s| !s.is_empty()).collect()).unwrap_or_default()
+    }
+}
+
+/// One line of Module.symvers: "<crc>\t<symbol>\t<module>\t<export type>[\t<namespace>]".
+pub struct SymVer {
+    pub crc:        u32,
+    pub name:       String,
+    pub module:     String,     // "vmlinux" for the kernel itself
+    pub kind:       String,
+    pub namespace:  String,
+}
+
+pub fn is_module(elf: &ElfFile) -> bool {
+    elf.e_type == elf::ET_REL && elf.section_by_name(".modinfo").is_some()
+}
+
+fn parse_modinfo(data: &[u8]) -> Vec<(String, String)> {
+    data.split(|&b| b == 0)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let s = String::from_utf8_lossy(s);
+            match s.split_once('=') {
+                Some((k, v)) => (k.to_string(), v.to_string()),
+                None => (s.to_string(), String::new()),
+            }
+        })
+        .collect()
+}
+
+fn parse_versions(elf: &ElfFile) -> Vec<(String, u32)> {
+    let mut versions = Vec::new();
+    if let Some(shdr) = elf.section_by_name("__versions") {
+        let data = elf.section_data(shdr);
+        let r = elf::ElfReader::new(data, elf.e_ident[elf::EI_CLASS], elf.e_ident[elf::EI_DATA]);
+        let word = r.addr_size();
+        for entry in 0..data.len() / MODVERSION_INFO_SIZE {
+            let pos = entry * MODVERSION_INFO_SIZE;
+            let crc = r.addr(pos).unwrap_or(0) as u32;
+            let name = elf::read_cstr(&data[..pos + MODVERSION_INFO_SIZE], pos + word).unwrap_or_default();
+            versions.push((name, crc));
+        }
+    }
+    // CONFIG_EXTENDED_MODVERSIONS keeps names that do not fit modversion_info in two parallel tables.
+    if let (Some(names), Some(crcs)) = (elf.section_by_name("__version_ext_names"), elf.section_by_name("__version_ext_crcs")) {
+        let crcs = elf.section_data(crcs);
+        let r = elf::ElfReader::new(crcs, elf.e_ident[elf::EI_CLASS], elf.e_ident[elf::EI_DATA]);
+        let names = elf.section_data(names).split(|&b| b == 0).filter(|s| !s.is_empty());
+        for (i, name) in names.enumerate() {
+            versions.push((String::from_utf8_lossy(name).to_string(), r.word(i * 4).unwrap_or(0)));
+        }
+    }
+    versions
+}
+
+fn export_kind(section: &str) -> Option<&'static str> {
+    match section {
+        "__ksymtab" => Some("EXPORT_SYMBOL"),
+        "__ksymtab_gpl" => Some("EXPORT_SYMBOL_GPL"),
+        "__ksymtab_gpl_future" => Some("EXPORT_SYMBOL_GPL_FUTURE"),
+        "__ksymtab_unused" => Some("EXPORT_UNUSED_SYMBOL"),
+        "__ksymtab_unused_gpl" => Some("EXPORT_UNUSED_SYMBOL_GPL"),
+        _ => None,
+    }
+}
+
+/// Every EXPORT_SYMBOL leaves a __ksymtab_<name> symbol on its kernel_symbol entry.
+fn parse_exports(elf: &ElfFile) -> Vec<Export> {
+    let mut exports = Vec::new();
+    for sym in elf.symbols.iter() {
+        let name = match sym.name.strip_prefix("__ksymtab_") {
+            Some(name) => name,
+            None => continue,
+        };
+        let section = sym.section_index().and_then(|i| elf.section_headers.get(i));
+        if let Some(kind) = section.and_then(|s| export_kind(&s.name)) {
+            exports.push(Export{name: name.to_string(), kind: kind.to_string()});
+        }
+    }
+    exports
+}
+
+pub fn parse(elf: &ElfFile) -> Result<Module, String> {
+    if !is_module(elf) {
+        return Err("not a kernel module (no .modinfo in a relocatable file)".to_string());
+    }
+    Ok(Module {
+        info: parse_modinfo(elf.section_by_name(".modinfo").map(|s| elf.section_data(s)).unwrap_or(&[])),
+        versions: parse_versions(elf),
+        exports: parse_exports(elf),
+    })
+}
+
+pub fn read_symvers(path: &str) -> Result<Vec<SymVer>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut symvers = Vec::new();
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 4 {
+            continue;
+        }
+        let crc = u64::from_str_radix(fields[0].trim_start_matches("0x"), 16)
+            .map_err(|_| format!("{}: bad CRC in line \"{}\"", path, line))?;
+        symvers.push(SymVer {
+            crc: crc as u32,
+            name: fields[1].to_string(),
+            module: fields[2].to_string(),
+            kind: fields[3].to_string(),
+            namespace: fields.get(4).unwrap_or(&"").to_string(),
+        });
+    }
+    Ok(symvers)
+}
+
+fn is_gpl_compatible(license: &str) -> bool {
+    GPL_COMPATIBLE.contains(&license)
+}
+
+fn module_reloc_supported(e_machine: u16, r_type: u32) -> Option<bool> {
+    match e_machine {
+        elf::EM_X86_64 => Some(X86_64_MODULE_RELOCS.contains(&r_type)),
+        elf::EM_386 => Some(I386_MODULE_RELOCS.contains(&r_type)),
+        elf::EM_AARCH64 => Some(AARCH64_MODULE_RELOCS.iter().any(|&(lo, hi)| lo <= r_type && r_type <= hi)),
+        _ => None,
+    }
+}
+
+/// Undefined symbols the relocations of loaded (SHF_ALLOC) sections refer to, and whether each is
+/// weak. Unsupported relocation types and bad symbol indexes are reported as errors.
+fn imports(elf: &ElfFile, errors: &mut Vec<String>) -> Vec<(String, bool)> {
+    let mut seen = HashSet::new();
+    let mut imports = Vec::new();
+    for shdr in elf.section_headers.iter() {
+        if shdr.sh_type != elf::SHT_RELA && shdr.sh_type != elf::SHT_REL {
+            continue;
+        }
+        let target = match elf.section_headers.get(shdr.sh_info as usize) {
+            Some(target) if target.is_alloc() => target,
+            _ => continue,
+        };
+        for reloc in elf.relocations(shdr) {
+            if module_reloc_supported(elf.e_machine, reloc.r_type) == Some(false) {
+                errors.push(format!("{}+{:#x}: relocation type {} is not supported by the module loader",
+                                    target.name, reloc.r_offset, reloc.r_type));
+            }
+            let sym = match elf.symbols.get(reloc.r_sym as usize) {
+                Some(sym) => sym,
+                None => {
+                    errors.push(format!("{}+{:#x}: relocation refers to symbol index {} beyond .symtab",
+                                        target.name, reloc.r_offset, reloc.r_sym));
+                    continue;
+                }
+            };
+            if reloc.r_sym != 0 && sym.is_undefined() && seen.insert(sym.name.clone()) {
+                imports.push((sym.name.clone(), sym.bind() == elf::STB_WEAK));
+            }
+        }
+    }
+    imports
+}
+
+/// Checks what the kernel checks on load: metadata it requires, symbol versions for every
+/// import and relocations it can apply. With Module.symvers also checks CRCs, GPL-only
+/// symbols, symbol namespaces and missing dependencies. Returns (errors, warnings).
+pub fn check(elf: &ElfFile, module: &Module, symvers: Option<&[SymVer]>) -> (Vec<String>, Vec<String>) {
+    let mut errors = Vec::new();
+    let mut warnings = Vec::new();
+    if elf.section_by_name(".gnu.linkonce.this_module").is_none() {
+        errors.push("no .gnu.linkonce.this_module section (struct module); run modpost".to_string());
+    }
+    if module.get("vermagic").is_none() {
+        errors.push("no vermagic in .modinfo".to_string());
+    }
+    let license = module.get("license");
+    match license {
+        None => warnings.push("no license in .modinfo; loading taints the kernel".to_string()),
+        Some(l) if !is_gpl_compatible(l) => warnings.push(format!("license \"{}\" taints the kernel", l)),
+        _ => {}
+    }
+
+    let imports = imports(elf, &mut errors);
+    let versions: HashMap<&str, u32> = module.versions.iter().map(|(n, c)| (n.as_str(), *c)).collect();
+    if versions.is_empty() {
+        warnings.push("no __versions; symbol CRCs cannot be checked by the kernel".to_string());
+    } else {
+        for (name, _) in imports.iter().filter(|(name, _)| !versions.contains_key(name.as_str())) {
+            errors.push(format!("no symbol version for {}", name));
+        }
+    }
+
+    let symvers = match symvers {
+        Some(symvers) => symvers,
+        None => return (errors, warnings),
+    };
+    let known: HashMap<&str, &SymVer> = symvers.iter().map(|s| (s.name.as_str(), s)).collect();
+    let namespaces = module.get_all("import_ns");
+    let depends = module.depends();
+    let mut needed = Vec::new();
+    for (name, weak) in imports.iter() {
+        let sym = match known.get(name.as_str()) {
+            Some(sym) => sym,
+            None => {
+                if !weak {
+                    errors.push(format!("unknown symbol {}", name));
+                }
+                continue;
+            }
+        };
+        if let Some(&crc) = versions.get(name.as_str()) {
+            if crc != sym.crc {
+                errors.push(format!("disagrees about version of symbol {} (module {:#010x}, {} {:#010x})",
+                                    name, crc, sym.module, sym.crc));
+            }
+        }
+        if sym.kind.contains("_GPL") && !license.is_some_and(is_gpl_compatible) {
+            errors.push(format!("GPL-incompatible module uses GPL-only symbol {}", name));
+        }
+        if !sym.namespace.is_empty() && !namespaces.contains(&sym.namespace.as_str()) {
+            errors.push(format!("module uses symbol {} from namespace {}, but does not import it", name, sym.namespace));
+        }
+        if sym.module != "vmlinux" && !depends.contains(&module_name(&sym.module)) && !needed.contains(&sym.module) {
+            needed.push(sym.module.clone());
+        }
+    }
+    for owner in needed {
+        warnings.push(format!("depends does not list {}, which exports symbols this module uses", module_name(&owner)));
+    }
+    (errors, warnings)
+}
+
+/// "drivers/net/foo" in Module.symvers is the module "foo".
+fn module_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+pub fn show_module(path: &str, module: &Module) {
+    println!("{:<16}{}", "filename:", path);
+    for (key, value) in module.info.iter() {
+        println!("{:<16}{}", format!("{}:", key), value);
+    }
+    println!();
+    println!("Symbol versions (__versions) contain {} entries:", module.versions.len());
+    println!("  CRC         Symbol");
+    for (name, crc) in module.versions.iter() {
+        println!("  {:#010x}  {}", crc, name);
+    }
+    println!();
+    println!("Exported symbols (__ksymtab) contain {} entries:", module.exports.len());
+    println!("  Type                      Symbol");
+    for export in module.exports.iter() {
+        println!("  {:<24}  {}", export.kind, export.name);
+    }
+}
@@ -0,0 +1,415 @@
+use crate::elf;
+use crate::elf::ElfFile;
+use crate::elf::SectionHeader;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,      // loaders or tools reject or misread the file
+    Warning,    // violates the ELF specification, but is commonly tolerated
+    Info,       // legal, but unusual in a linked artifact
+}
+
+pub struct Violation {
+    pub severity:   Severity,
+    pub place:      String,     // "header", "segment <i>" or "section <i> (<name>)"
+    pub message:    String,
+}
+
+pub fn get_severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "info",
+    }
+}
+
+struct Checker {
+    violations: Vec<Violation>,
+}
+
+impl Checker {
+    fn report(&mut self, severity: Severity, place: &str, message: String) {
+        self.violations.push(Violation{severity, place: place.to_string(), message});
+    }
+}
+
+fn section_place(index: usize, shdr: &SectionHeader) -> String {
+    format!("section {} ({})", index, shdr.name)
+}
+
+/// Whether `[offset, offset + size)` lies inside a file of `len` bytes.
+fn in_file(offset: u64, size: u64, len: usize) -> bool {
+    offset.checked_add(size).is_some_and(|end| end <= len as u64)
+}
+
+fn check_header(elf: &ElfFile, c: &mut Checker) {
+    let is_64 = elf.is_64();
+    if elf.e_ehsize as usize != elf::file_header_size(is_64) {
+        c.report(Severity::Warning, "header", format!("e_ehsize is {}, expected {}", elf.e_ehsize, elf::file_header_size(is_64)));
+    }
+    if elf.phnum() > 0 && elf.e_phentsize as usize != elf::program_header_size(is_64) {
+        c.report(Severity::Error, "header", format!("e_phentsize is {}, expected {}", elf.e_phentsize,
+                                                      elf::program_header_size(is_64)));
+    }
+    if elf.shnum() > 0 && elf.e_shentsize as usize != elf::section_header_size(is_64) {
+        c.report(Severity::Error, "header", format!("e_shentsize is {}, expected {}", elf.e_shentsize,
+                                                      elf::section_header_size(is_64)));
+    }
+    if elf.e_version != 1 {
+        c.report(Severity::Warning, "header", format!("e_version is {}, expected 1", elf.e_version));
+    }
+    if elf.shnum() > 0 {
+        match elf.section_headers.get(elf.shstrndx()) {
+            Some(shdr) if shdr.sh_type == elf::SHT_STRTAB => {}
+            Some(_) => c.report(Severity::Error, "header", format!("e_shstrndx {} is not a string table", elf.shstrndx())),
+            None => c.report(Severity::Error, "header", format!("e_shstrndx {} is beyond the {} sections", elf.shstrndx(),
+                                                                  elf.shnum())),
+        }
+    } else if elf.e_type != elf::ET_CORE && (elf.e_shoff == 0 || elf.e_shnum == 0) {
+        c.report(Severity::Info, "header", "no section headers".to_string());
+    }
+    if (elf.e_type == elf::ET_EXEC || elf.e_type == elf::ET_DYN) && elf.program_headers.is_empty() {
+        let kind = if elf.e_type == elf::ET_EXEC { "executable" } else { "shared object" };
+        c.report(Severity::Error, "header", format!("{} without program headers", kind));
+    }
+}
+
+fn check_segments(elf: &ElfFile, c: &mut Checker) {
+    let len = elf.bytes().len();
+    for (i, phdr) in elf.program_headers.iter().enumerate() {
+        let place = format!("segment {} ({})", i, elf::get_segment_type_name(phdr.p_type));
+        if phdr.p_filesz > phdr.p_memsz {
+            c.report(Severity::Error, &place, format!("p_filesz {:#x} is larger than p_memsz {:#x}", phdr.p_filesz, phdr.p_memsz));
+        }
+        if phdr.p_filesz > 0 && !in_file(phdr.p_offset, phdr.p_filesz, len) {
+            c.report(Severity::Error, &place, format!("file range {:#x}+{:#x} extends beyond the end of the file ({:#x})",
+                                                       phdr.p_offset, phdr.p_filesz, len));
+        }
+        if phdr.p_type != elf::PT_LOAD {
+            continue;
+        }
+        if phdr.p_align > 1 && !phdr.p_align.is_power_of_two() {
+            c.report(Severity::Error, &place, format!("p_align {:#x} is not a power of two", phdr.p_align));
+        } else if phdr.p_align > 1 && phdr.p_vaddr % phdr.p_align != phdr.p_offset % phdr.p_align {
+            c.report(Severity::Error, &place, format!("p_vaddr {:#x} and p_offset {:#x} are not congruent modulo p_align {:#x}",
+                                                       phdr.p_vaddr, phdr.p_offset, phdr.p_align));
+        }
+    }
+
+    // PT_LOAD entries must be sorted by address and must not share memory.
+    let loads: Vec<(usize, &elf::ProgramHeader)> = elf.program_headers.iter().enumerate()
+        .filter(|(_, p)| p.p_type == elf::PT_LOAD)
+        .collect();
+    for pair in loads.windows(2) {
+        let ((_, prev), (i, phdr)) = (pair[0], pair[1]);
+        if phdr.p_vaddr < prev.p_vaddr {
+            c.report(Severity::Warning, &format!("segment {} (LOAD)", i),
+                     format!("p_vaddr {:#x} is below the previous PT_LOAD at {:#x}", phdr.p_vaddr, prev.p_vaddr));
+        }
+    }
+    for (n, (i, a)) in loads.iter().enumerate() {
+        for (j, b) in loads[n + 1..].iter() {
+            let a_end = a.p_vaddr.saturating_add(a.p_memsz);
+            let b_end = b.p_vaddr.saturating_add(b.p_memsz);
+            if a.p_memsz > 0 && b.p_memsz > 0 && a.p_vaddr < b_end && b.p_vaddr < a_end {
+                c.report(Severity::Error, &format!("segment {} (LOAD)", j),
+                         format!("[{:#x}, {:#x}) overlaps segment {} at [{:#x}, {:#x})", b.p_vaddr, b_end, i, a.p_vaddr, a_end));
+            }
+        }
+    }
+}
+
+/// Section types whose sh_link must name a section of one of the given types.
+const LINK_TYPES: [(u32, &[u32]); 10] = [
+    (elf::SHT_SYMTAB,        &[elf::SHT_STRTAB]),
+    (elf::SHT_DYNSYM,        &[elf::SHT_STRTAB]),
+    (elf::SHT_DYNAMIC,       &[elf::SHT_STRTAB]),
+    (elf::SHT_HASH,          &[elf::SHT_SYMTAB, elf::SHT_DYNSYM]),
+    (elf::SHT_GNU_HASH,      &[elf::SHT_SYMTAB, elf::SHT_DYNSYM]),
+    (elf::SHT_GROUP,         &[elf::SHT_SYMTAB]),
+    (elf::SHT_SYMTAB_SHNDX,  &[elf::SHT_SYMTAB]),
+    (elf::SHT_GNU_VERSYM,    &[elf::SHT_DYNSYM]),
+    (elf::SHT_GNU_VERDEF,    &[elf::SHT_STRTAB]),
+    (elf::SHT_GNU_VERNEED,   &[elf::SHT_STRTAB]),
+];
+
+fn check_links(elf: &ElfFile, index: usize, shdr: &SectionHeader, c: &mut Checker) {
+    let place = section_place(index, shdr);
+    let shnum = elf.shnum();
+    if shdr.sh_link as usize >= shnum {
+        c.report(Severity::Error, &place, format!("sh_link {} is beyond the {} sections", shdr.sh_link, shnum));
+        return;
+    }
+    let linked = &elf.section_headers[shdr.sh_link as usize];
+    let expected = LINK_TYPES.iter().find(|(t, _)| *t == shdr.sh_type).map(|(_, e)| *e);
+    if let Some(expected) = expected {
+        if !expected.contains(&linked.sh_type) {
+            c.report(Severity::Error, &place, format!("sh_link {} ({}) is a {}, expected {}", shdr.sh_link, linked.name,
+                     elf::get_section_type_name(linked.sh_type),
+                     expected.iter().map(|t| elf::get_section_type_name(*t)).collect::<Vec<String>>().join(" or ")));
+        }
+    }
+    match shdr.sh_type {
+        // Relocations name the symbol table in sh_link (0 for dynamic relocations without one)
+        // and the section they apply to in sh_info.
+        elf::SHT_REL | elf::SHT_RELA => {
+            if shdr.sh_link != 0 && ![elf::SHT_SYMTAB, elf::SHT_DYNSYM].contains(&linked.sh_type) {
+                c.report(Severity::Error, &place, format!("sh_link {} ({}) is not a symbol table", shdr.sh_link, linked.name));
+            }
+            if shdr.sh_info as usize >= shnum {
+                c.report(Severity::Error, &place, format!("sh_info {} is beyond the {} sections", shdr.sh_info, shnum));
+            } else if shdr.sh_info == 0 && shdr.sh_flags & elf::SHF_INFO_LINK != 0 {
+                c.report(Severity::Warning, &place, "SHF_INFO_LINK is set, but sh_info is 0".to_string());
+            }
+        }
+        // sh_info is one greater than the index of the last local symbol.
+        elf::SHT_SYMTAB | elf::SHT_DYNSYM => {
+            if shdr.sh_entsize as usize != elf::symbol_size(elf.is_64()) {
+                c.report(Severity::Error, &place, format!("sh_entsize is {}, expected {}", shdr.sh_entsize,
+                                                           elf::symbol_size(elf.is_64())));
+            } else if shdr.sh_info as u64 > shdr.sh_size / shdr.sh_entsize {
+                c.report(Severity::Error, &place, format!("sh_info {} is beyond the {} symbols", shdr.sh_info,
+                                                           shdr.sh_size / shdr.sh_entsize));
+            }
+        }
+        _ => {
+            if shdr.sh_flags & elf::SHF_INFO_LINK != 0 && shdr.sh_info as usize >= shnum {
+                c.report(Severity::Error, &place, format!("sh_info {} is beyond the {} sections", shdr.sh_info, shnum));
+            }
+        }
+    }
+}
+
+fn check_sections(elf: &ElfFile, c: &mut Checker) {
+    let len = elf.bytes().len();
+    for (index, shdr) in elf.section_headers.iter().enumerate().skip(1) {
+        let place = section_place(index, shdr);
+        if shdr.sh_type != elf::SHT_NOBITS && shdr.sh_type != elf::SHT_NULL && !in_file(shdr.sh_offset, shdr.sh_size, len) {
+            c.report(Severity::Error, &place, format!("file range {:#x}+{:#x} extends beyond the end of the file ({:#x})",
+                                                       shdr.sh_offset, shdr.sh_size, len));
+        }
+        if shdr.sh_addralign > 1 && !shdr.sh_addralign.is_power_of_two() {
+            c.report(Severity::Warning, &place, format!("sh_addralign {:#x} is not a power of two", shdr.sh_addralign));
+        } else if shdr.sh_addralign > 1 && shdr.sh_addr % shdr.sh_addralign != 0 {
+            c.report(Severity::Warning, &place, format!("sh_addr {:#x} is not aligned to sh_addralign {:#x}", shdr.sh_addr,
+                                                         shdr.sh_addralign));
+        }
+        check_links(elf, index, shdr, c);
+        if shdr.sh_type == elf::SHT_STRTAB && shdr.sh_size > 0 {
+            // A table cut off by the end of the file is checked on the bytes that are there.
+            let start = shdr.sh_offset.min(len as u64) as usize;
+            let end = shdr.sh_offset.saturating_add(shdr.sh_size).min(len as u64) as usize;
+            let data = &elf.bytes()[start..end];
+            if !in_file(shdr.sh_offset, shdr.sh_size, len) {
+                c.report(Severity::Error, &place, format!("string table is not NUL-terminated: only {:#x} of its {:#x} bytes \
+                                                           are in the file", data.len(), shdr.sh_size));
+            } else if data.last() != Some(&0) {
+                c.report(Severity::Error, &place, "string table is not NUL-terminated".to_string());
+            }
+            if data.first() != Some(&0) {
+                c.report(Severity::Warning, &place, "string table does not start with an empty string".to_string());
+            }
+        }
+    }
+
+    // Sections with contents do not share file bytes, including sections that run off the end
+    // of the file. Each start is compared with the furthest end so far, so that a section
+    // inside a long one is caught even when another section lies between them.
+    let mut ranges: Vec<(u64, u64, usize)> = elf.section_headers.iter().enumerate()
+        .filter(|(_, s)| s.sh_type != elf::SHT_NOBITS && s.sh_type != elf::SHT_NULL && s.sh_size > 0)
+        .map(|(i, s)| (s.sh_offset, s.sh_offset.saturating_add(s.sh_size), i))
+        .collect();
+    ranges.sort();
+    let mut furthest: Option<(u64, usize)> = None;
+    for &(start, end, j) in ranges.iter() {
+        if let Some((reach, i)) = furthest {
+            if start < reach {
+                c.report(Severity::Warning, &section_place(j, &elf.section_headers[j]),
+                         format!("file offset {:#x} overlaps section {} ({})", start, i, elf.section_headers[i].name));
+            }
+        }
+        if furthest.is_none_or(|(reach, _)| end > reach) {
+            furthest = Some((end, j));
+        }
+    }
+}
+
+fn check_entry(elf: &ElfFile, c: &mut Checker) {
+    if elf.e_type != elf::ET_EXEC && elf.e_type != elf::ET_DYN {
+        return;
+    }
+    // Shared libraries without an entry point leave it 0.
+    if elf.e_entry == 0 && elf.e_type == elf::ET_DYN {
+        return;
+    }
+    let loads: Vec<&elf::ProgramHeader> = elf.program_headers.iter().filter(|p| p.p_type == elf::PT_LOAD).collect();
+    if loads.is_empty() {
+        return;
+    }
+    match loads.iter().find(|p| p.contains_vaddr(elf.e_entry)) {
+        Some(phdr) if phdr.p_flags & elf::PF_X != 0 => {}
+        Some(phdr) => c.report(Severity::Error, "header", format!("e_entry {:#x} is in a non-executable segment ({})",
+                                                                  elf.e_entry, elf::get_segment_flags_name(phdr.p_flags).trim())),
+        None => c.report(Severity::Error, "header", format!("e_entry {:#x} is outside every PT_LOAD segment", elf.e_entry)),
+    }
+}
+
+/// Checks the structural invariants of an ELF file, in header, segment, section order.
+/// `problems` are the header tables `ElfFile::parse_lenient` could not read from the file.
+pub fn check(elf: &ElfFile, problems: &[String]) -> Vec<Violation> {
+    let mut c = Checker{violations: Vec::new()};
+    for problem in problems.iter() {
+        c.report(Severity::Error, "header", problem.clone());
+    }
+    check_header(elf, &mut c);
+    check_entry(elf, &mut c);
+    check_segments(elf, &mut c);
+    check_sections(elf, &mut c);
+    c.violations
+}
+
+pub fn show_violations(path: &str, violations: &[Violation]) {
+    for v in violations.iter() {
+        println!("{}: {}: {}: {}", path, get_severity_name(v.severity), v.place, v.message);
+    }
+    let count = |severity| violations.iter().filter(|v| v.severity == severity).count();
+    println!("{} errors, {} warnings, {} info", count(Severity::Error), count(Severity::Warning), count(Severity::Info));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testelf;
+    use crate::testelf::Builder;
+
+    const SYMTAB: usize = 3;    // section indices in minimal()
+    const STRTAB: usize = 4;
+
+    /// A valid x86-64 executable: .text and .data in two PT_LOADs, a .symtab of two symbols
+    /// and its .strtab.
+    fn minimal() -> Vec<u8> {
+        let mut b = Builder::new(elf::EM_X86_64);
+        b.entry = 0x401000;
+        let text = b.section(".text", elf::SHT_PROGBITS, elf::SHF_ALLOC | elf::SHF_EXECINSTR, 0x401000, &[0xc3]);
+        let data = b.section(".data", elf::SHT_PROGBITS, elf::SHF_ALLOC | elf::SHF_WRITE, 0x402000, &[0; 8]);
+        let symtab = b.section(".symtab", elf::SHT_SYMTAB, 0, 0, &[0; 48]);
+        let strtab = b.section(".strtab", elf::SHT_STRTAB, 0, 0, b"\0main\0");
+        b.sections[symtab - 1].sh_link = strtab as u32;
+        b.sections[symtab - 1].sh_info = 1;
+        b.sections[symtab - 1].sh_entsize = 24;
+        b.load(text, text, elf::PF_R | elf::PF_X);
+        b.load(data, data, elf::PF_R | elf::PF_W);
+        assert_eq!((symtab, strtab), (SYMTAB, STRTAB));
+        b.build()
+    }
+
+    /// Reads the little-endian u64 at `at`.
+    fn get(bytes: &[u8], at: usize) -> u64 {
+        let mut value = [0u8; 8];
+        value.copy_from_slice(&bytes[at..at + 8]);
+        u64::from_le_bytes(value)
+    }
+
+    fn violations(bytes: Vec<u8>) -> Vec<(&'static str, String, String)> {
+        let (elf, problems) = ElfFile::parse_lenient(bytes).unwrap();
+        check(&elf, &problems).into_iter()
+            .map(|v| (get_severity_name(v.severity), v.place, v.message))
+            .collect()
+    }
+
+    fn error(place: &str, message: &str) -> Vec<(&'static str, String, String)> {
+        vec![("error", place.to_string(), message.to_string())]
+    }
+
+    #[test]
+    fn minimal_file_is_clean() {
+        assert_eq!(violations(minimal()), []);
+    }
+
+    #[test]
+    fn overlapping_loads() {
+        let mut bytes = minimal();
+        let at = testelf::phdr(&bytes, 1) + 16;
+        testelf::put(&mut bytes, at, 0x401000, 8);
+        assert_eq!(violations(bytes),
+                   error("segment 1 (LOAD)", "[0x401000, 0x401008) overlaps segment 0 at [0x401000, 0x401001)"));
+    }
+
+    #[test]
+    fn filesz_larger_than_memsz() {
+        let mut bytes = minimal();
+        let at = testelf::phdr(&bytes, 1) + 40;
+        testelf::put(&mut bytes, at, 4, 8);
+        assert_eq!(violations(bytes), error("segment 1 (LOAD)", "p_filesz 0x8 is larger than p_memsz 0x4"));
+    }
+
+    #[test]
+    fn offset_not_congruent_with_address() {
+        let mut bytes = minimal();
+        let at = testelf::phdr(&bytes, 1) + 8;
+        let offset = get(&bytes, at) + 8;
+        testelf::put(&mut bytes, at, offset, 8);
+        assert_eq!(violations(bytes),
+                   error("segment 1 (LOAD)", &format!("p_vaddr 0x402000 and p_offset {:#x} are not congruent modulo \
+                                                       p_align 0x1000", offset)));
+    }
+
+    #[test]
+    fn bad_symtab_link() {
+        let mut bytes = minimal();
+        let at = testelf::shdr(&bytes, SYMTAB) + 40;
+        testelf::put(&mut bytes, at, 1, 4);
+        assert_eq!(violations(bytes),
+                   error("section 3 (.symtab)", &format!("sh_link 1 (.text) is a {}, expected {}",
+                                                         elf::get_section_type_name(elf::SHT_PROGBITS),
+                                                         elf::get_section_type_name(elf::SHT_STRTAB))));
+
+        let mut bytes = minimal();
+        let at = testelf::shdr(&bytes, SYMTAB) + 40;
+        testelf::put(&mut bytes, at, 9, 4);
+        assert_eq!(violations(bytes), error("section 3 (.symtab)", "sh_link 9 is beyond the 6 sections"));
+    }
+
+    #[test]
+    fn bad_symtab_info() {
+        let mut bytes = minimal();
+        let at = testelf::shdr(&bytes, SYMTAB) + 44;
+        testelf::put(&mut bytes, at, 3, 4);
+        assert_eq!(violations(bytes), error("section 3 (.symtab)", "sh_info 3 is beyond the 2 symbols"));
+    }
+
+    #[test]
+    fn unterminated_string_table() {
+        let mut bytes = minimal();
+        let h = testelf::shdr(&bytes, STRTAB);
+        let end = get(&bytes, h + 24) + get(&bytes, h + 32);
+        bytes[end as usize - 1] = b'x';
+        assert_eq!(violations(bytes), error("section 4 (.strtab)", "string table is not NUL-terminated"));
+    }
+
+    #[test]
+    fn entry_outside_executable_segments() {
+        let mut bytes = minimal();
+        testelf::put(&mut bytes, 24, 0x402000, 8);
+        assert_eq!(violations(bytes),
+                   error("header", &format!("e_entry 0x402000 is in a non-executable segment ({})",
+                                            elf::get_segment_flags_name(elf::PF_R | elf::PF_W).trim())));
+
+        let mut bytes = minimal();
+        testelf::put(&mut bytes, 24, 0x500000, 8);
+        assert_eq!(violations(bytes), error("header", "e_entry 0x500000 is outside every PT_LOAD segment"));
+    }
+
+    #[test]
+    fn bogus_header_sizes() {
+        let mut bytes = minimal();
+        testelf::put(&mut bytes, 52, 52, 2);
+        assert_eq!(violations(bytes),
+                   vec![("warning", "header".to_string(), "e_ehsize is 52, expected 64".to_string())]);
+
+        let mut bytes = minimal();
+        testelf::put(&mut bytes, 54, 32, 2);
+        let found = violations(bytes);
+        assert_eq!(found[0], error("header", "e_phentsize is 32, expected 56")[0]);
+    }
+}
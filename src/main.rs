@@ -1,50 +1,780 @@
 extern crate mio;
 
 use std::env;
-use std::fs::File;
-use std::io::Read;
-use std::process::Command;
-use std::process::Stdio;
-use std::io::Write;
+use std::fs;
+use std::path::Path;
 
+mod abi;
+mod attributes;
+mod bind;
+mod callgraph;
+mod cfg;
+mod deps;
+mod diff;
+mod disasm;
+mod discover;
+mod dwarf;
 mod elf;
+mod export;
+mod gadgets;
 mod gdbmi;
+mod image;
+mod kmod;
+mod lint;
+mod memmap;
+mod pe;
+mod plt;
+mod process;
+mod size;
+mod stack;
+mod strings;
+#[cfg(test)]
+mod testelf;
+mod triage;
+
+fn usage() -> ! {
+    eprintln!("usage: binary_tracer <target> [--remote <host:port>]  trace target with gdb");
+    eprintln!("       binary_tracer --diff <old> <new>       compare two ELF files");
+    eprintln!("       binary_tracer --size <file> [-d sections|segments|symbols|compileunits]");
+    eprintln!("                     [-n <count>] [-s file|vm] [--base <old file>]");
+    eprintln!("       binary_tracer --disasm <file> [--att] [-j <section>] [--symbol <name>]");
+    eprintln!("       binary_tracer --cfg <file> [-f dot|json|coverage] [--function <name>] [--trace <log>]");
+    eprintln!("       binary_tracer --callgraph <file> [-f text|dot|graphml] [--callers <name>]");
+    eprintln!("                     [--reachable <name>] [--dead]");
+    eprintln!("       binary_tracer --deps <file> [--sysroot <dir>]");
+    eprintln!("       binary_tracer --bindings <file> [--sysroot <dir>] [--symbol <name>] [--all]");
+    eprintln!("       binary_tracer --abi <old.so> <new.so>  exits 1 on breaking changes, 2 if a file cannot be opened");
+    eprintln!("       binary_tracer --proc <pid> [--dump <dir>]");
+    eprintln!("       binary_tracer --attributes <file> [<file>...]  exits 1 when the files are incompatible");
+    eprintln!("       binary_tracer --kmod <file.ko> [--symvers <Module.symvers>]");
+    eprintln!("       binary_tracer --regions <file> <linker script or memory map>");
+    eprintln!("       binary_tracer --export <file> -o <output> [-O binary|ihex|srec] [--range <start>-<end>]");
+    eprintln!("                     [--gap-fill <byte>] [--srec-type 1|2|3]");
+    eprintln!("       binary_tracer --pe <file>");
+    eprintln!("       binary_tracer --triage <file>");
+    eprintln!("       binary_tracer --strings <file> [-n <min length>] [--referenced] [--function <name>]");
+    eprintln!("       binary_tracer --gadgets <file> [--depth <n>] [--type rop|jop|cop] [--class <class>]");
+    eprintln!("                     [--search <pattern>]");
+    eprintln!("       binary_tracer --lint <file> [--errors]  exits 1 on errors, 2 if <file> cannot be opened");
+    eprintln!("       binary_tracer --stack <file> [--root <name>[,<name>...]] [--frames]");
+    eprintln!("       <file> may also be pid:<pid>[:<base>|:<name>] to analyze an image of a running process");
+    eprintln!("       or image:<file>,machine=<name>[,base=<addr>][,entry=<addr>][,symbols=<elf>][,format=ihex|srec|binary]");
+    eprintln!("       [,endian=big] for Intel HEX, S-record and raw images");
+    std::process::exit(-1);
+}
+
+fn open_elf(path: &str) -> elf::ElfFile {
+    match elf::ElfFile::open(path) {
+        Ok(elf) => elf,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        }
+    }
+}
+
+/// Returns the value following `name` in `args`, if present.
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).map(|s| s.as_str())
+}
+
+fn size_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let source = match size::DataSource::from_name(option_value(args, "-d").unwrap_or("sections")) {
+        Some(source) => source,
+        None => usage(),
+    };
+    let limit = match option_value(args, "-n").map(|n| n.parse::<usize>()) {
+        Some(Ok(n)) => n,
+        Some(Err(_)) => usage(),
+        None => 20,
+    };
+    let sort = match option_value(args, "-s").unwrap_or("vm") {
+        "file" => size::SortBy::File,
+        "vm" => size::SortBy::Vm,
+        _ => usage(),
+    };
+
+    // Without DWARF every byte would fall back to its section and the report would look valid.
+    let open = |path: &str| {
+        let elf = open_elf(path);
+        if source == size::DataSource::CompileUnits && dwarf::Dwarf::load(&elf).is_none() {
+            eprintln!("{}: no DWARF debug information for -d compileunits", path);
+            std::process::exit(-1);
+        }
+        elf
+    };
+    let elf = open(path);
+    let mut rows = size::size_report(&elf, source);
+    match option_value(args, "--base") {
+        Some(base_path) => {
+            let base = size::size_report(&open(base_path), source);
+            let mut delta = size::diff_rows(&base, &rows);
+            size::sort_rows(&mut delta, sort);
+            size::show_size_report(&delta, limit, Some(&base));
+        }
+        None => {
+            size::sort_rows(&mut rows, sort);
+            size::show_size_report(&rows, limit, None);
+        }
+    }
+}
+
+fn disasm_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let syntax = if args.iter().any(|a| a == "--att") { disasm::Syntax::Att } else { disasm::Syntax::Intel };
+    let elf = open_elf(path);
+    let decoder = match disasm::Decoder::for_elf(&elf, syntax) {
+        Some(decoder) => decoder,
+        None => {
+            eprintln!("{}: unsupported machine {}", path, elf.e_machine);
+            std::process::exit(-1);
+        }
+    };
+    let symbols = disasm::Symbolizer::new(&elf);
+    println!();
+    println!("{}:     file format elf{}", path, if elf.is_64() { 64 } else { 32 });
+    match option_value(args, "--symbol") {
+        Some(name) => {
+            if !disasm::show_function(&elf, &decoder, &symbols, name) {
+                eprintln!("{}: no symbol {}", path, name);
+                std::process::exit(-1);
+            }
+        }
+        None => disasm::show_disassembly(&elf, &decoder, &symbols, option_value(args, "-j")),
+    }
+}
+
+fn cfg_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let format = option_value(args, "-f").unwrap_or("dot");
+    if !["dot", "json", "coverage"].contains(&format) {
+        usage();
+    }
+    let trace = match option_value(args, "--trace").map(cfg::load_trace) {
+        Some(Ok(trace)) => Some(trace),
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        }
+        None if format == "coverage" => usage(),
+        None => None,
+    };
+    let elf = open_elf(path);
+    let decoder = match disasm::Decoder::for_elf(&elf, disasm::Syntax::Intel) {
+        Some(decoder) => decoder,
+        None => {
+            eprintln!("{}: unsupported machine {}", path, elf.e_machine);
+            std::process::exit(-1);
+        }
+    };
+    let symbols = disasm::Symbolizer::new(&elf);
+    let only = option_value(args, "--function");
+    let functions = cfg::build_all(&elf, &decoder, &symbols, only);
+    if let (Some(name), true) = (only, functions.is_empty()) {
+        eprintln!("{}: no function {}", path, name);
+        std::process::exit(-1);
+    }
+    match format {
+        "json" => cfg::show_json(&functions, trace.as_ref()),
+        "coverage" => cfg::show_coverage(&functions, trace.as_ref().unwrap()),
+        _ => cfg::show_dot(&functions, &decoder, trace.as_ref()),
+    }
+}
+
+fn callgraph_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let elf = open_elf(path);
+    let decoder = match disasm::Decoder::for_elf(&elf, disasm::Syntax::Intel) {
+        Some(decoder) => decoder,
+        None => {
+            eprintln!("{}: unsupported machine {}", path, elf.e_machine);
+            std::process::exit(-1);
+        }
+    };
+    let symbols = disasm::Symbolizer::new(&elf);
+    let functions = cfg::build_all(&elf, &decoder, &symbols, None);
+    let graph = callgraph::CallGraph::build(&elf, &functions);
+    let lookup = |name: &str| match graph.find(name) {
+        Some(node) => node,
+        None => {
+            eprintln!("{}: no function {}", path, name);
+            std::process::exit(-1);
+        }
+    };
+    if let Some(name) = option_value(args, "--callers") {
+        for &(caller, kind) in graph.callers(lookup(name)) {
+            println!("{:016x} {} ({})", graph.nodes[caller].addr, graph.nodes[caller].name, kind.name());
+        }
+    } else if let Some(name) = option_value(args, "--reachable") {
+        let nodes: Vec<usize> = graph.reachable(&[lookup(name)]).into_iter().collect();
+        callgraph::show_nodes(&graph, &nodes);
+    } else if args.iter().any(|a| a == "--dead") {
+        callgraph::show_nodes(&graph, &graph.dead(&elf));
+    } else {
+        match option_value(args, "-f").unwrap_or("text") {
+            "text" => callgraph::show_text(&graph),
+            "dot" => callgraph::show_dot(&graph),
+            "graphml" => callgraph::show_graphml(&graph),
+            _ => usage(),
+        }
+    }
+}
+
+fn deps_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let elf = open_elf(path);
+    let mut resolver = deps::Resolver::new(&elf, option_value(args, "--sysroot").unwrap_or(""));
+    let deps = resolver.resolve(path, &elf);
+    let interpreter = resolver.interpreter(&elf);
+    deps::show_tree(path, interpreter.clone(), &deps);
+    if !deps::problems(&deps).is_empty() || interpreter.is_some_and(|(_, exists)| !exists) {
+        std::process::exit(-1);
+    }
+}
+
+fn bindings_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let elf = open_elf(path);
+    let mut resolver = deps::Resolver::new(&elf, option_value(args, "--sysroot").unwrap_or(""));
+    for problem in deps::problems(&resolver.resolve(path, &elf)) {
+        eprintln!("warning: {}", problem);
+    }
+    let objects: Vec<bind::Object> = resolver.load_order().iter().map(|p| match bind::Object::open(p) {
+        Ok(object) => object,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        }
+    }).collect();
+    let bindings = bind::bind(&objects);
+    bind::show_bindings(&objects, &bindings, option_value(args, "--symbol"), args.iter().any(|a| a == "--all"));
+    if bindings.iter().any(|b| b.outcome == bind::Outcome::Unresolved) || !bind::version_errors(&objects).is_empty() {
+        std::process::exit(-1);
+    }
+}
+
+fn abi_mode(args: &[String]) {
+    if args.len() < 2 {
+        usage();
+    }
+    let open = |path: &str| match bind::Object::open(path) {
+        Ok(object) => object,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let report = abi::compare(&open(&args[0]), &open(&args[1]));
+    abi::show_report(&args[0], &args[1], &report);
+    if report.is_breaking() {
+        std::process::exit(1);
+    }
+}
+
+fn attributes_mode(args: &[String]) {
+    if args.is_empty() {
+        usage();
+    }
+    let files: Vec<elf::ElfFile> = args.iter().map(|path| open_elf(path)).collect();
+    for (path, elf) in args.iter().zip(files.iter()) {
+        println!("{}:", path);
+        println!("  Flags: {}", elf::get_flags_name(elf.e_machine, elf.e_flags));
+        attributes::show_attributes(elf);
+        println!();
+    }
+    let mut incompatible = false;
+    for i in 0..files.len() {
+        for j in i + 1..files.len() {
+            for reason in attributes::conflicts(&files[i], &files[j]) {
+                eprintln!("warning: {} and {} are incompatible: {}", args[i], args[j], reason);
+                incompatible = true;
+            }
+        }
+    }
+    if incompatible {
+        std::process::exit(1);
+    }
+}
+
+fn kmod_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let elf = open_elf(path);
+    let fail = |e: String| -> ! {
+        eprintln!("{}", e);
+        std::process::exit(-1);
+    };
+    let module = kmod::parse(&elf).unwrap_or_else(|e| fail(format!("{}: {}", path, e)));
+    let symvers = option_value(args, "--symvers").map(|p| kmod::read_symvers(p).unwrap_or_else(|e| fail(e)));
+    kmod::show_module(path, &module);
+    let (errors, warnings) = kmod::check(&elf, &module, symvers.as_deref());
+    for warning in warnings.iter() {
+        eprintln!("warning: {}", warning);
+    }
+    for error in errors.iter() {
+        eprintln!("error: {}", error);
+    }
+    if !errors.is_empty() {
+        std::process::exit(-1);
+    }
+}
+
+fn regions_mode(args: &[String]) {
+    if args.len() < 2 {
+        usage();
+    }
+    let elf = open_elf(&args[0]);
+    let regions = match memmap::read_memory(&args[1]) {
+        Ok(regions) => regions,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        }
+    };
+    let report = memmap::report(&elf, &regions);
+    memmap::show_report(&elf, &regions, &report);
+    for error in report.errors.iter() {
+        eprintln!("error: {}", error);
+    }
+    if !report.errors.is_empty() {
+        std::process::exit(-1);
+    }
+}
+
+fn export_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let output = match option_value(args, "-o") {
+        Some(output) => output,
+        None => usage(),
+    };
+    // Without -O the format follows the output extension (.bin, .hex, .srec, .s19, .s28, .s37, .mot).
+    let format = option_value(args, "-O").or(output.rsplit('.').next()).and_then(export::Format::from_name);
+    let format = match format {
+        Some(format) => format,
+        None => usage(),
+    };
+    let range = match option_value(args, "--range").map(export::parse_range) {
+        Some(None) => usage(),
+        Some(range) => range,
+        None => None,
+    };
+    let gap_fill = match option_value(args, "--gap-fill").map(|b| export::parse_number(b).filter(|b| *b <= 0xff)) {
+        Some(None) => usage(),
+        Some(fill) => fill.map(|b| b as u8),
+        None => None,
+    };
+    let srec_type = match option_value(args, "--srec-type").map(|t| t.parse::<u8>()) {
+        Some(Ok(t)) if (1..=3).contains(&t) => Some(t),
+        Some(_) => usage(),
+        None => match output.rsplit('.').next() {
+            Some("s19") => Some(1),
+            Some("s28") => Some(2),
+            Some("s37") => Some(3),
+            _ => None,
+        },
+    };
+    let elf = open_elf(path);
+    let options = export::ExportOptions{format, range, gap_fill, srec_type};
+    match export::export(&elf, output, &options) {
+        Ok(chunks) => export::show_export(output, &chunks),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        }
+    }
+}
+
+fn pe_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    match pe::open(path) {
+        Ok(pe) => pe::show_pe(path, &pe),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        }
+    }
+}
+
+fn triage_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let elf = open_elf(path);
+    // Signatures are searched in the file itself: a PE file is opened as an ELF model.
+    let file = if Path::new(path).is_file() { fs::read(path).unwrap_or_default() } else { elf.bytes().to_vec() };
+    let report = triage::analyze(&elf, &file);
+    triage::show_report(path, &file, &report);
+}
+
+fn strings_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let min = match option_value(args, "-n").map(|n| n.parse::<usize>()) {
+        Some(Ok(min)) if min > 0 => min,
+        None => 4,
+        _ => usage(),
+    };
+    let only = option_value(args, "--function");
+    let referenced = args.iter().any(|a| a == "--referenced");
+    let elf = open_elf(path);
+    let mut strings = strings::extract(&elf, min);
+    match disasm::Decoder::for_elf(&elf, disasm::Syntax::Intel) {
+        Some(decoder) => {
+            let symbols = disasm::Symbolizer::new(&elf);
+            let functions = cfg::build_all(&elf, &decoder, &symbols, None);
+            strings::cross_reference(&elf, &functions, &mut strings);
+        }
+        None if referenced || only.is_some() => {
+            eprintln!("{}: unsupported machine {}", path, elf.e_machine);
+            std::process::exit(-1);
+        }
+        None => eprintln!("warning: {}: unsupported machine {}, no cross-references", path, elf.e_machine),
+    }
+    let shown: Vec<&strings::FoundString> = strings.iter()
+        .filter(|s| !referenced || !s.refs.is_empty())
+        .filter(|s| only.is_none_or(|name| s.refs.iter().any(|r| r.function == name)))
+        .collect();
+    strings::show_strings(&shown);
+}
+
+fn gadgets_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let depth = match option_value(args, "--depth").map(|n| n.parse::<usize>()) {
+        Some(Ok(depth)) if depth > 0 => depth,
+        None => gadgets::DEFAULT_DEPTH,
+        _ => usage(),
+    };
+    let kind = match option_value(args, "--type").map(gadgets::parse_kind) {
+        Some(Some(kind)) => Some(kind),
+        None => None,
+        _ => usage(),
+    };
+    let class = option_value(args, "--class");
+    let pattern = option_value(args, "--search");
+    let elf = open_elf(path);
+    let decoder = match disasm::Decoder::for_elf(&elf, disasm::Syntax::Intel) {
+        Some(decoder) => decoder,
+        None => {
+            eprintln!("{}: unsupported machine {}", path, elf.e_machine);
+            std::process::exit(-1);
+        }
+    };
+    let found = match gadgets::find(&elf, &decoder, depth) {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            std::process::exit(-1);
+        }
+    };
+    let shown: Vec<&gadgets::Gadget> = found.iter()
+        .filter(|g| kind.is_none_or(|k| g.kind == k))
+        .filter(|g| class.is_none_or(|c| g.class == c))
+        .filter(|g| pattern.is_none_or(|p| gadgets::matches(g, p)))
+        .collect();
+    gadgets::show_gadgets(&shown);
+}
+
+fn lint_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    // Damaged files are linted rather than rejected: the tables that run off the end of the
+    // file are reported as errors.
+    let (elf, problems) = match elf::ElfFile::open_lenient(path) {
+        Ok(opened) => opened,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let mut violations = lint::check(&elf, &problems);
+    if args.iter().any(|a| a == "--errors") {
+        violations.retain(|v| v.severity == lint::Severity::Error);
+    }
+    lint::show_violations(path, &violations);
+    if violations.iter().any(|v| v.severity == lint::Severity::Error) {
+        std::process::exit(1);
+    }
+}
+
+fn stack_mode(args: &[String]) {
+    let path = match args.first() {
+        Some(path) => path,
+        None => usage(),
+    };
+    let elf = open_elf(path);
+    let decoder = match disasm::Decoder::for_elf(&elf, disasm::Syntax::Intel) {
+        Some(decoder) => decoder,
+        None => {
+            eprintln!("{}: unsupported machine {}", path, elf.e_machine);
+            std::process::exit(-1);
+        }
+    };
+    let symbols = disasm::Symbolizer::new(&elf);
+    let functions = cfg::build_all(&elf, &decoder, &symbols, None);
+    let graph = callgraph::CallGraph::build(&elf, &functions);
+    let frames = stack::frames(&elf, &functions, &graph);
+    let lookup = |name: &str| match graph.find(name) {
+        Some(node) => node,
+        None => {
+            eprintln!("{}: no function {}", path, name);
+            std::process::exit(-1);
+        }
+    };
+    let roots: Vec<usize> = option_value(args, "--root").map(|r| r.split(',').map(lookup).collect()).unwrap_or_default();
+    // Stripped binaries: main is found from the startup code.
+    let main = graph.find("main").or_else(|| discover::find_main(&elf).and_then(|a| graph.containing(a)));
+    let interrupts = stack::interrupt_entries(&elf, &graph);
+    if args.iter().any(|a| a == "--frames") {
+        stack::show_frames(&graph, &frames);
+        println!();
+    }
+    if main.is_none() && roots.is_empty() && interrupts.is_empty() {
+        eprintln!("{}: no main or interrupt handlers; name the entry points with --root", path);
+        std::process::exit(-1);
+    }
+    let mut analysis = stack::Analysis::new(&elf, &frames);
+    stack::show_depths(&graph, &mut analysis, main, &roots, &interrupts);
+}
+
+fn proc_mode(args: &[String]) {
+    let pid = match args.first().map(|p| p.parse::<u32>()) {
+        Some(Ok(pid)) => pid,
+        _ => usage(),
+    };
+    let images = match process::images(pid) {
+        Ok(images) => images,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        }
+    };
+    process::show_images(pid, &images);
+    if let Some(dir) = option_value(args, "--dump") {
+        if let Err(e) = process::dump_images(dir, &images) {
+            eprintln!("{}", e);
+            std::process::exit(-1);
+        }
+    }
+}
 
 fn main() {
 
     let argv:Vec<String> = env::args().collect();
     if argv.len() < 2 {
         eprintln!("input debug target");
-        std::process::exit(-1);
+        usage();
     }
 
-    let msg = format!("File open failed filepath:{}", &argv[1]);
-    let mut f = File::open(&argv[1]).expect(&msg);
-    let mut buf = Vec::new();
-    let _ret = f.read_to_end(&mut buf);
-    if elf::is_elf(&buf) != true {
-        eprintln!("{} is not ELF format file", argv[1]);
-        std::process::exit(-1);
+    match argv[1].as_str() {
+        "--diff" => {
+            if argv.len() < 4 {
+                usage();
+            }
+            let old = open_elf(&argv[2]);
+            let new = open_elf(&argv[3]);
+            let d = diff::diff(&old, &new);
+            diff::show_diff(&argv[2], &argv[3], &d);
+            return;
+        }
+        "--abi" => {
+            abi_mode(&argv[2..]);
+            return;
+        }
+        "--proc" => {
+            proc_mode(&argv[2..]);
+            return;
+        }
+        "--attributes" => {
+            attributes_mode(&argv[2..]);
+            return;
+        }
+        "--kmod" => {
+            kmod_mode(&argv[2..]);
+            return;
+        }
+        "--regions" => {
+            regions_mode(&argv[2..]);
+            return;
+        }
+        "--export" => {
+            export_mode(&argv[2..]);
+            return;
+        }
+        "--pe" => {
+            pe_mode(&argv[2..]);
+            return;
+        }
+        "--triage" => {
+            triage_mode(&argv[2..]);
+            return;
+        }
+        "--strings" => {
+            strings_mode(&argv[2..]);
+            return;
+        }
+        "--gadgets" => {
+            gadgets_mode(&argv[2..]);
+            return;
+        }
+        "--lint" => {
+            lint_mode(&argv[2..]);
+            return;
+        }
+        "--stack" => {
+            stack_mode(&argv[2..]);
+            return;
+        }
+        "--size" => {
+            size_mode(&argv[2..]);
+            return;
+        }
+        "--disasm" => {
+            disasm_mode(&argv[2..]);
+            return;
+        }
+        "--cfg" => {
+            cfg_mode(&argv[2..]);
+            return;
+        }
+        "--callgraph" => {
+            callgraph_mode(&argv[2..]);
+            return;
+        }
+        "--deps" => {
+            deps_mode(&argv[2..]);
+            return;
+        }
+        "--bindings" => {
+            bindings_mode(&argv[2..]);
+            return;
+        }
+        "--help" | "-h" => usage(),
+        _ => {}
     }
 
-    if elf::is_elf32(&buf) {
-        let elf32ehdr: elf::Elf32Ehdr = elf::Elf32Ehdr::new(&buf);
-        elf32ehdr.show_elf_header_info();
-    } else if elf::is_elf64(&buf) {
-        let elf64ehdr: elf::Elf64Ehdr = elf::Elf64Ehdr::new(&buf);
-        elf64ehdr.show_elf_header_info();
-    } else {
-        eprintln!("{} is Unsupported ELF format file", argv[1]);
-        std::process::exit(-1);
+    let elf = open_elf(&argv[1]);
+    elf.show_elf_header_info();
+    attributes::show_attributes(&elf);
+    // Check the dependencies up front; the loader would only fail once gdb runs the target.
+    let mut resolver = deps::Resolver::new(&elf, "");
+    for problem in deps::problems(&resolver.resolve(&argv[1], &elf)) {
+        eprintln!("warning: {}", problem);
     }
+    let mut decoder = disasm::Decoder::for_elf(&elf, disasm::Syntax::Intel);
+    let symbols = disasm::Symbolizer::new(&elf);
+
+    // gdb needs a file: images built from HEX, S-records or process memory are written out first.
+    let target = if Path::new(&argv[1]).is_file() {
+        argv[1].clone()
+    } else {
+        let path = env::temp_dir().join(format!("binary_tracer-{}.elf", std::process::id()));
+        if let Err(e) = fs::write(&path, elf.bytes()) {
+            eprintln!("{}: {}", path.display(), e);
+            std::process::exit(-1);
+        }
+        path.to_string_lossy().to_string()
+    };
 
     // start gdb
-    let mut gdbmi = gdbmi::GdbMi::new(&argv[1], 1000);
+    let mut gdbmi = gdbmi::GdbMi::new(&target, 1000);
     gdbmi.start();
-    gdbmi.set_break_point("main");
-    gdbmi.run();
-    loop {
-        gdbmi.stepi();
+    let has_main = elf.symbols.iter().chain(elf.dynamic_symbols.iter()).any(|s| s.name == "main");
+    let (mut pc, bias) = if let Some(address) = option_value(&argv[2..], "--remote") {
+        // The stub's target already holds the image at its link-time addresses: run it to
+        // main (or the entry point) unless it is stopped there.
+        let start = symbols.addr_of("main").unwrap_or(elf.e_entry);
+        let start = if elf.e_machine == elf::EM_ARM { start & !1 } else { start };
+        match gdbmi.connect_remote(address) {
+            Some(pc) if pc == start => (Some(pc), 0),
+            _ => {
+                gdbmi.set_break_point(&format!("*0x{:x}", start));
+                (gdbmi.cont(), 0)
+            }
+        }
+    } else if has_main {
+        gdbmi.set_break_point("main");
+        let pc = gdbmi.run();
+        // PIE targets run at a load bias from their link-time addresses.
+        let bias = match (gdbmi.symbol_address("main"), symbols.addr_of("main")) {
+            (Some(runtime), Some(linked)) => runtime.wrapping_sub(linked),
+            _ => 0,
+        };
+        (pc, bias)
+    } else {
+        // gdb cannot resolve main in a stripped target: stop at the first instruction to
+        // learn the load bias, then break on the recovered main (or the entry point).
+        let start = symbols.addr_of("main").unwrap_or(elf.e_entry);
+        let start = if elf.e_machine == elf::EM_ARM { start & !1 } else { start };
+        gdbmi.start_at_first_insn();
+        let bias = gdbmi.entry_address().map_or(0, |entry| entry.wrapping_sub(elf.e_entry));
+        gdbmi.set_break_point(&format!("*0x{:x}", start.wrapping_add(bias)));
+        (gdbmi.cont(), bias)
+    };
+    while let Some(addr) = pc {
+        match (decoder.as_mut(), gdbmi.read_memory(addr, disasm::MAX_INSN_BYTES)) {
+            (Some(decoder), Some(bytes)) => trace_step(&elf, decoder, &symbols, addr, bias, &bytes),
+            _ => println!("{:16x}", addr),
+        }
+        pc = gdbmi.stepi();
+    }
+    if target != argv[1] {
+        let _ = fs::remove_file(&target);
     }
 }
 
+/// Prints one traced instruction. Code inside the target is shown at its link-time address
+/// so that it lines up with the --disasm listing; anything else (shared libraries, the
+/// dynamic loader) is shown at its runtime address.
+fn trace_step(elf: &elf::ElfFile, decoder: &mut disasm::Decoder, symbols: &disasm::Symbolizer,
+              addr: u64, bias: u64, bytes: &[u8]) {
+    let linked = addr.wrapping_sub(bias);
+    let in_target = elf.section_for_vaddr(linked).map(|s| s.is_exec()).unwrap_or(false);
+    let at = if in_target { linked } else { addr };
+    if let Some(mapping) = disasm::section_index(elf, at).and_then(|i| symbols.mapping_at(i, at)).filter(|_| in_target) {
+        decoder.set_mapping(mapping);
+    }
+    // Label function and PLT stub entries so calls into imports read like the listing.
+    if let Some(name) = symbols.symbol_at(at).filter(|_| in_target) {
+        println!("{:016x} <{}>:", at, name);
+    }
+    match decoder.decode(bytes, at) {
+        Some(insn) if in_target => println!("{}", disasm::format_line(decoder, &insn, bytes, symbols)),
+        Some(insn) => println!("{:8x}:\t{}", addr, decoder.text(&insn)),
+        None => println!("{:8x}:\t(bad)", addr),
+    }
+}
@@ -0,0 +1,386 @@
+use std::fs;
+
+use crate::elf;
+use crate::elf::ElfFile;
+
+/// A MEMORY region of a linker script.
+pub struct Region {
+    pub name:   String,
+    pub attrs:  String,     // "rx", "rwx", ... (empty when not given)
+    pub origin: u64,
+    pub length: u64,
+}
+
+impl Region {
+    fn end(&self) -> u64 {
+        self.origin.saturating_add(self.length)
+    }
+
+    fn contains(&self, addr: u64) -> bool {
+        self.origin <= addr && addr < self.end()
+    }
+}
+
+/// Where one PT_LOAD segment runs (VMA) and where its file contents are stored (LMA).
+pub struct Placement {
+    pub index:      usize,          // program header index
+    pub vma:        (u64, u64),     // [start, end) of p_memsz at p_vaddr
+    pub lma:        (u64, u64),     // [start, end) of p_filesz at p_paddr
+    pub vma_region: Option<usize>,
+    pub lma_region: Option<usize>,
+}
+
+pub struct RegionUsage {
+    pub used:       u64,            // high-water mark: highest end placed in the region minus its origin
+}
+
+pub struct MemoryReport {
+    pub placements: Vec<Placement>,
+    pub usage:      Vec<RegionUsage>,   // parallel to the regions
+    pub errors:     Vec<String>,
+}
+
+/// Evaluates the expressions allowed in ORIGIN and LENGTH: numbers with K/M suffixes,
+/// + - * / and parentheses, and ORIGIN(region)/LENGTH(region) of regions defined earlier.
+struct Expr<'a> {
+    s:          &'a [u8],
+    pos:        usize,
+    regions:    &'a [Region],
+}
+
+impl<'a> Expr<'a> {
+    fn skip_space(&mut self) {
+        while self.pos < self.s.len() && self.s[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_space();
+        self.s.get(self.pos).copied()
+    }
+
+    fn word(&mut self) -> String {
+        self.skip_space();
+        let start = self.pos;
+        while self.pos < self.s.len() && (self.s[self.pos].is_ascii_alphanumeric() || b"_.$".contains(&self.s[self.pos])) {
+            self.pos += 1;
+        }
+        String::from_utf8_lossy(&self.s[start..self.pos]).to_string()
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), String> {
+        if self.peek() != Some(c) {
+            return Err(format!("expected '{}' in \"{}\"", c as char, String::from_utf8_lossy(self.s)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn number(text: &str) -> Option<u64> {
+        let lower = text.to_ascii_lowercase();
+        let (digits, scale) = match lower.as_bytes().last() {
+            Some(b'k') => (&lower[..lower.len() - 1], 1024),
+            Some(b'm') => (&lower[..lower.len() - 1], 1024 * 1024),
+            Some(b'g') => (&lower[..lower.len() - 1], 1024 * 1024 * 1024),
+            _ => (&lower[..], 1),
+        };
+        let value = match digits.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+            None => digits.parse::<u64>().ok()?,
+        };
+        value.checked_mul(scale)
+    }
+
+    fn primary(&mut self) -> Result<u64, String> {
+        if self.peek() == Some(b'(') {
+            self.pos += 1;
+            let value = self.sum()?;
+            self.expect(b')')?;
+            return Ok(value);
+        }
+        let word = self.word();
+        if let Some(value) = Expr::number(&word) {
+            return Ok(value);
+        }
+        let function = word.to_ascii_uppercase();
+        if function == "ORIGIN" || function == "LENGTH" {
+            self.expect(b'(')?;
+            let name = self.word();
+            self.expect(b')')?;
+            let region = self.regions.iter().find(|r| r.name == name)
+                .ok_or(format!("{}({}) refers to an unknown region", function, name))?;
+            return Ok(if function == "ORIGIN" { region.origin } else { region.length });
+        }
+        Err(format!("cannot evaluate \"{}\"", String::from_utf8_lossy(self.s).trim()))
+    }
+
+    fn product(&mut self) -> Result<u64, String> {
+        let mut value = self.primary()?;
+        while let Some(op) = self.peek().filter(|c| *c == b'*' || *c == b'/') {
+            self.pos += 1;
+            let rhs = self.primary()?;
+            value = if op == b'*' { value.wrapping_mul(rhs) } else { value.checked_div(rhs).ok_or("division by zero")? };
+        }
+        Ok(value)
+    }
+
+    fn sum(&mut self) -> Result<u64, String> {
+        let mut value = self.product()?;
+        while let Some(op) = self.peek().filter(|c| *c == b'+' || *c == b'-') {
+            self.pos += 1;
+            let rhs = self.product()?;
+            value = if op == b'+' { value.wrapping_add(rhs) } else { value.wrapping_sub(rhs) };
+        }
+        Ok(value)
+    }
+}
+
+fn strip_comments(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("/*") {
+        out.push_str(&rest[..start]);
+        rest = rest[start..].find("*/").map(|end| &rest[start + end + 2..]).unwrap_or("");
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Splits `KEY = expr` into the key and the expression.
+fn assignment(item: &str) -> Option<(String, &str)> {
+    let (key, value) = item.split_once('=')?;
+    Some((key.trim().to_ascii_lowercase(), value))
+}
+
+/// Parses the MEMORY command of a linker script, or a file that holds just its region lines:
+///   FLASH (rx)  : ORIGIN = 0x08000000, LENGTH = 512K
+///   RAM (rwx)   : ORIGIN = 0x20000000, LENGTH = 128K
+pub fn parse_memory(text: &str) -> Result<Vec<Region>, String> {
+    let text = strip_comments(text);
+    let body = match text.find("MEMORY") {
+        Some(at) => {
+            let open = text[at..].find('{').ok_or("MEMORY without '{'")? + at + 1;
+            let close = text[open..].find('}').ok_or("MEMORY without '}'")? + open;
+            &text[open..close]
+        }
+        None => &text[..],
+    };
+    let mut regions: Vec<Region> = Vec::new();
+    for line in body.lines().map(|l| l.trim()).filter(|l| !l.is_empty()) {
+        let (head, spec) = line.split_once(':').ok_or(format!("bad memory region \"{}\"", line))?;
+        let (name, attrs) = match head.split_once('(') {
+            Some((name, attrs)) => (name.trim(), attrs.trim_end().trim_end_matches(')').trim()),
+            None => (head.trim(), ""),
+        };
+        let (mut origin, mut length) = (None, None);
+        for item in spec.split(',').filter(|i| !i.trim().is_empty()) {
+            let (key, value) = assignment(item).ok_or(format!("bad memory region \"{}\"", line))?;
+            let mut expr = Expr{s: value.as_bytes(), pos: 0, regions: &regions};
+            let value = expr.sum()?;
+            if expr.peek().is_some() {
+                return Err(format!("trailing text in \"{}\"", item.trim()));
+            }
+            match key.as_str() {
+                "origin" | "org" | "o" => origin = Some(value),
+                "length" | "len" | "l" => length = Some(value),
+                _ => return Err(format!("unknown memory attribute \"{}\" in \"{}\"", key, line)),
+            }
+        }
+        match (origin, length) {
+            (Some(origin), Some(length)) => regions.push(Region{name: name.to_string(), attrs: attrs.to_string(), origin, length}),
+            _ => return Err(format!("memory region {} needs ORIGIN and LENGTH", name)),
+        }
+    }
+    if regions.is_empty() {
+        return Err("no memory regions".to_string());
+    }
+    Ok(regions)
+}
+
+pub fn read_memory(path: &str) -> Result<Vec<Region>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse_memory(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn region_of(regions: &[Region], start: u64) -> Option<usize> {
+    regions.iter().position(|r| r.contains(start))
+}
+
+/// Places every PT_LOAD segment in the regions by VMA and LMA and finds how far each region is
+/// filled. Gaps below the highest segment count as used, as they do for the linker.
+pub fn report(elf: &ElfFile, regions: &[Region]) -> MemoryReport {
+    let mut placements = Vec::new();
+    let mut errors = Vec::new();
+    for (index, phdr) in elf.program_headers.iter().enumerate().filter(|(_, p)| p.p_type == elf::PT_LOAD) {
+        let vma = (phdr.p_vaddr, phdr.p_vaddr.saturating_add(phdr.p_memsz));
+        let lma = (phdr.p_paddr, phdr.p_paddr.saturating_add(phdr.p_filesz));
+        let vma_region = if vma.0 < vma.1 { region_of(regions, vma.0) } else { None };
+        let lma_region = if lma.0 < lma.1 { region_of(regions, lma.0) } else { None };
+        if vma.0 < vma.1 && vma_region.is_none() {
+            errors.push(format!("LOAD #{} VMA {:#x}-{:#x} is outside every memory region", index, vma.0, vma.1));
+        }
+        if lma.0 < lma.1 && lma_region.is_none() {
+            errors.push(format!("LOAD #{} LMA {:#x}-{:#x} is outside every memory region", index, lma.0, lma.1));
+        }
+        placements.push(Placement{index, vma, lma, vma_region, lma_region});
+    }
+
+    let mut usage = Vec::new();
+    for (i, region) in regions.iter().enumerate() {
+        let mut top = region.origin;
+        for p in placements.iter() {
+            for (range, placed) in [(p.vma, p.vma_region), (p.lma, p.lma_region)] {
+                if placed == Some(i) {
+                    top = top.max(range.1);
+                }
+            }
+        }
+        let overflow = top.saturating_sub(region.end());
+        if overflow > 0 {
+            errors.push(format!("region `{}' overflowed by {} bytes", region.name, overflow));
+        }
+        usage.push(RegionUsage{used: top - region.origin});
+    }
+    MemoryReport{placements, usage, errors}
+}
+
+/// Load address of an allocated section: its address moved by the p_paddr - p_vaddr of its segment.
+fn section_lma(elf: &ElfFile, shdr: &elf::SectionHeader) -> Option<u64> {
+    if shdr.sh_type == elf::SHT_NOBITS {
+        return None;
+    }
+    elf.program_headers.iter()
+        .filter(|p| p.p_type == elf::PT_LOAD)
+        .find(|p| p.p_vaddr <= shdr.sh_addr && shdr.sh_addr < p.p_vaddr.saturating_add(p.p_filesz.max(1)))
+        .map(|p| shdr.sh_addr - p.p_vaddr + p.p_paddr)
+}
+
+fn region_name(regions: &[Region], index: Option<usize>) -> &str {
+    index.map(|i| regions[i].name.as_str()).unwrap_or("-")
+}
+
+pub fn show_report(elf: &ElfFile, regions: &[Region], report: &MemoryReport) {
+    let width = regions.iter().map(|r| r.name.len()).max().unwrap_or(0).max(6);
+    println!("Memory regions:");
+    println!("  {:<width$}  {:<6}  {:<10}  {:>10}  {:>10}  {:>10}  {:>7}",
+             "Region", "Attr", "Origin", "Length", "Used", "Free", "Used%");
+    for (region, usage) in regions.iter().zip(report.usage.iter()) {
+        let percent = if region.length == 0 { 0.0 } else { usage.used as f64 * 100.0 / region.length as f64 };
+        println!("  {:<width$}  {:<6}  {:#010x}  {:>10}  {:>10}  {:>10}  {:>6.2}%",
+                 region.name, region.attrs, region.origin, region.length, usage.used,
+                 region.length.saturating_sub(usage.used), percent);
+    }
+    println!();
+    println!("Segments:");
+    println!("  {:<8}  {:<21}  {:<width$}  {:<21}  {:<width$}  {:>10}  {:>10}",
+             "Segment", "VMA", "Region", "LMA", "Region", "FileSiz", "MemSiz");
+    for p in report.placements.iter() {
+        println!("  LOAD #{:<2}  {:#010x}-{:#010x}  {:<width$}  {:#010x}-{:#010x}  {:<width$}  {:>10}  {:>10}",
+                 p.index, p.vma.0, p.vma.1, region_name(regions, p.vma_region), p.lma.0, p.lma.1,
+                 region_name(regions, p.lma_region), p.lma.1 - p.lma.0, p.vma.1 - p.vma.0);
+    }
+    println!();
+    println!("Sections:");
+    println!("  {:<24}  {:<10}  {:>10}  {:<width$}  {:<10}  Region", "Section", "VMA", "Size", "Region", "LMA");
+    for shdr in elf.section_headers.iter().filter(|s| s.is_alloc() && s.sh_size > 0) {
+        let lma = section_lma(elf, shdr);
+        println!("  {:<24}  {:#010x}  {:>10}  {:<width$}  {:<10}  {}",
+                 shdr.name, shdr.sh_addr, shdr.sh_size, region_name(regions, region_of(regions, shdr.sh_addr)),
+                 lma.map(|l| format!("{:#010x}", l)).unwrap_or("-".to_string()),
+                 region_name(regions, lma.and_then(|l| region_of(regions, l))));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testelf::Builder;
+
+    fn regions(text: &str) -> Vec<(String, String, u64, u64)> {
+        parse_memory(text).unwrap().into_iter().map(|r| (r.name, r.attrs, r.origin, r.length)).collect()
+    }
+
+    fn region(name: &str, attrs: &str, origin: u64, length: u64) -> (String, String, u64, u64) {
+        (name.to_string(), attrs.to_string(), origin, length)
+    }
+
+    #[test]
+    fn cortex_m_script() {
+        let script = "
+            /* STM32F407 */
+            ENTRY(Reset_Handler)
+            MEMORY
+            {
+              FLASH (rx)      : ORIGIN = 0x08000000, LENGTH = 1M   /* main flash */
+              RAM (xrw)       : ORIGIN = 0x20000000, LENGTH = 128K
+              CCMRAM (rw)     : ORIGIN = 0x10000000, LENGTH = 64K
+            }
+            SECTIONS
+            {
+              .text : { *(.text*) } > FLASH
+            }";
+        assert_eq!(regions(script), [
+            region("FLASH", "rx", 0x0800_0000, 0x10_0000),
+            region("RAM", "xrw", 0x2000_0000, 0x2_0000),
+            region("CCMRAM", "rw", 0x1000_0000, 0x1_0000),
+        ]);
+    }
+
+    #[test]
+    fn expressions() {
+        let lines = "
+            BOOT (rx) : ORIGIN = 0x08000000, LENGTH = 16k
+            APP (rx)  : ORIGIN = ORIGIN(BOOT) + LENGTH(BOOT), LENGTH = 512K - LENGTH(BOOT)
+            RAM       : org = 0x20000000, len = 2M / 16 * (1 + 1)";
+        assert_eq!(regions(lines), [
+            region("BOOT", "rx", 0x0800_0000, 0x4000),
+            region("APP", "rx", 0x0800_4000, 0x7_c000),
+            region("RAM", "", 0x2000_0000, 0x4_0000),
+        ]);
+    }
+
+    #[test]
+    fn bad_regions() {
+        let err = |text: &str| parse_memory(text).err().unwrap();
+        assert_eq!(err("APP : ORIGIN = ORIGIN(BOOT), LENGTH = 1K"), "ORIGIN(BOOT) refers to an unknown region");
+        assert_eq!(err("RAM : ORIGIN = 0x20000000"), "memory region RAM needs ORIGIN and LENGTH");
+        assert_eq!(err("RAM : ORIGIN = 0x20000000 4, LENGTH = 1K"), "trailing text in \"ORIGIN = 0x20000000 4\"");
+        assert_eq!(err("RAM : START = 0, LENGTH = 1K"), "unknown memory attribute \"start\" in \"RAM : START = 0, LENGTH = 1K\"");
+        assert_eq!(err("MEMORY { /* none */ }"), "no memory regions");
+    }
+
+    /// .text in FLASH and .data run from RAM but loaded from FLASH right after .text, with
+    /// .bss after .data.
+    fn firmware() -> ElfFile {
+        let mut b = Builder::new(elf::EM_ARM);
+        b.entry = 0x0800_0001;
+        let text = b.section(".text", elf::SHT_PROGBITS, elf::SHF_ALLOC | elf::SHF_EXECINSTR, 0x0800_0000, &[0; 0xf8]);
+        let data = b.section(".data", elf::SHT_PROGBITS, elf::SHF_ALLOC | elf::SHF_WRITE, 0x2000_0000, &[1; 0x10]);
+        let bss = b.section(".bss", elf::SHT_NOBITS, elf::SHF_ALLOC | elf::SHF_WRITE, 0x2000_0010, &[0; 0x100]);
+        b.load(text, text, elf::PF_R | elf::PF_X);
+        b.load(data, bss, elf::PF_R | elf::PF_W).paddr = Some(0x0800_00f8);
+        ElfFile::parse(b.build()).unwrap()
+    }
+
+    #[test]
+    fn data_loaded_from_flash() {
+        let regions = parse_memory("FLASH (rx) : ORIGIN = 0x08000000, LENGTH = 1K\nRAM (rwx) : ORIGIN = 0x20000000, LENGTH = 1K").unwrap();
+        let report = report(&firmware(), &regions);
+        let placed: Vec<_> = report.placements.iter().map(|p| (p.index, p.vma, p.lma, p.vma_region, p.lma_region)).collect();
+        assert_eq!(placed, [
+            (0, (0x0800_0000, 0x0800_00f8), (0x0800_0000, 0x0800_00f8), Some(0), Some(0)),
+            (1, (0x2000_0000, 0x2000_0110), (0x0800_00f8, 0x0800_0108), Some(1), Some(0)),
+        ]);
+        assert_eq!(report.usage.iter().map(|u| u.used).collect::<Vec<_>>(), [0x108, 0x110]);
+        assert!(report.errors.is_empty());
+    }
+
+    #[test]
+    fn overflow() {
+        let regions = parse_memory("FLASH : ORIGIN = 0x08000000, LENGTH = 0x100\nRAM : ORIGIN = 0x20000000, LENGTH = 0x100").unwrap();
+        let report = report(&firmware(), &regions);
+        assert_eq!(report.usage.iter().map(|u| u.used).collect::<Vec<_>>(), [0x108, 0x110]);
+        assert_eq!(report.errors, ["region `FLASH' overflowed by 8 bytes", "region `RAM' overflowed by 16 bytes"]);
+    }
+}
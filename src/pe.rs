@@ -0,0 +1,792 @@
+use std::collections::HashMap;
+
+use crate::dwarf::Cursor;
+use crate::elf;
+use crate::elf::ElfFile;
+use crate::elf::ProgramHeader;
+use crate::elf::SectionHeader;
+use crate::image;
+
+const E_LFANEW: usize           = 0x3c;     // offset of the PE header offset in the MS-DOS header
+const COFF_HEADER_SIZE: usize   = 20;
+const SECTION_HEADER_SIZE: usize = 40;
+const COFF_SYMBOL_SIZE: usize   = 18;
+const IMPORT_DESCRIPTOR_SIZE: usize = 20;
+
+// Optional header magic
+pub const PE32_MAGIC: u16       = 0x10b;
+pub const PE32_PLUS_MAGIC: u16  = 0x20b;
+
+// Machine types: IMAGE_FILE_MACHINE_*, name, e_machine
+const MACHINES: [(u16, &str, u16); 8] = [
+  (0x014c   , "i386"            , elf::EM_386       ),
+  (0x8664   , "x86-64"          , elf::EM_X86_64    ),
+  (0x01c0   , "ARM"             , elf::EM_ARM       ),
+  (0x01c2   , "ARM Thumb"       , elf::EM_ARM       ),
+  (0x01c4   , "ARM Thumb-2"     , elf::EM_ARM       ),
+  (0xaa64   , "ARM64"           , elf::EM_AARCH64   ),
+  (0x5032   , "RISC-V 32-bit"   , elf::EM_RISCV     ),
+  (0x5064   , "RISC-V 64-bit"   , elf::EM_RISCV     ),
+];
+const MACHINE_ARMNT: u16 = 0x01c4;
+
+// File characteristics
+pub const IMAGE_FILE_DLL: u16 = 0x2000;
+const FILE_CHARACTERISTICS: [(u16, &str); 9] = [
+  (0x0001   , "RELOCS_STRIPPED"         ),
+  (0x0002   , "EXECUTABLE_IMAGE"        ),
+  (0x0004   , "LINE_NUMS_STRIPPED"      ),
+  (0x0008   , "LOCAL_SYMS_STRIPPED"     ),
+  (0x0020   , "LARGE_ADDRESS_AWARE"     ),
+  (0x0100   , "32BIT_MACHINE"           ),
+  (0x0200   , "DEBUG_STRIPPED"          ),
+  (0x1000   , "SYSTEM"                  ),
+  (0x2000   , "DLL"                     ),
+];
+
+// DLL characteristics
+const DLL_CHARACTERISTICS: [(u16, &str); 10] = [
+  (0x0020   , "HIGH_ENTROPY_VA"         ),
+  (0x0040   , "DYNAMIC_BASE"            ),
+  (0x0080   , "FORCE_INTEGRITY"         ),
+  (0x0100   , "NX_COMPAT"               ),
+  (0x0200   , "NO_ISOLATION"            ),
+  (0x0400   , "NO_SEH"                  ),
+  (0x0800   , "NO_BIND"                 ),
+  (0x1000   , "APPCONTAINER"            ),
+  (0x4000   , "GUARD_CF"                ),
+  (0x8000   , "TERMINAL_SERVER_AWARE"   ),
+];
+
+// Subsystems
+const SUBSYSTEMS: [(u16, &str); 9] = [
+  (1        , "Native"                  ),
+  (2        , "Windows GUI"             ),
+  (3        , "Windows CUI"             ),
+  (7        , "POSIX CUI"               ),
+  (9        , "Windows CE GUI"          ),
+  (10       , "EFI application"         ),
+  (11       , "EFI boot service driver" ),
+  (12       , "EFI runtime driver"      ),
+  (14       , "Xbox"                    ),
+];
+
+// Data directory indexes
+pub const DIRECTORY_EXPORT: usize       = 0;
+pub const DIRECTORY_IMPORT: usize       = 1;
+pub const DIRECTORY_BASERELOC: usize    = 5;
+const DIRECTORY_NAMES: [&str; 16] = [
+  "Export", "Import", "Resource", "Exception", "Security", "Base relocation", "Debug", "Architecture",
+  "Global pointer", "TLS", "Load config", "Bound import", "IAT", "Delay import", "CLR runtime", "Reserved",
+];
+
+// Section characteristics
+pub const IMAGE_SCN_CNT_CODE: u32       = 0x0000_0020;
+pub const IMAGE_SCN_MEM_EXECUTE: u32    = 0x2000_0000;
+pub const IMAGE_SCN_MEM_READ: u32       = 0x4000_0000;
+pub const IMAGE_SCN_MEM_WRITE: u32      = 0x8000_0000;
+
+// Base relocation types
+const RELOCATION_TYPES: [(u8, &str); 8] = [
+  (0        , "ABSOLUTE"                ),
+  (1        , "HIGH"                    ),
+  (2        , "LOW"                     ),
+  (3        , "HIGHLOW"                 ),
+  (4        , "HIGHADJ"                 ),
+  (5        , "ARM_MOV32"               ),
+  (7        , "THUMB_MOV32"             ),
+  (10       , "DIR64"                   ),
+];
+const IMAGE_REL_BASED_ABSOLUTE: u8 = 0;
+
+// COFF symbol storage classes
+const IMAGE_SYM_CLASS_EXTERNAL: u8  = 2;
+const IMAGE_SYM_CLASS_STATIC: u8    = 3;
+const IMAGE_SYM_CLASS_LABEL: u8     = 6;
+const IMAGE_SYM_DTYPE_FUNCTION: u16 = 0x20;
+
+pub struct CoffHeader {
+    pub machine:                    u16,
+    pub number_of_sections:         u16,
+    pub time_date_stamp:            u32,
+    pub pointer_to_symbol_table:    u32,
+    pub number_of_symbols:          u32,
+    pub size_of_optional_header:    u16,
+    pub characteristics:            u16,
+}
+
+pub struct OptionalHeader {
+    pub magic:                  u16,    // PE32_MAGIC or PE32_PLUS_MAGIC
+    pub address_of_entry_point: u32,    // RVA, 0 for DLLs without an entry point
+    pub image_base:             u64,
+    pub section_alignment:      u32,
+    pub file_alignment:         u32,
+    pub size_of_image:          u32,
+    pub size_of_headers:        u32,
+    pub checksum:               u32,
+    pub subsystem:              u16,
+    pub dll_characteristics:    u16,
+    pub data_directories:       Vec<(u32, u32)>,    // (RVA, size)
+}
+
+pub struct Section {
+    pub name:                   String, // long names ("/<offset>") resolved from the COFF string table
+    pub virtual_size:           u32,
+    pub virtual_address:        u32,    // RVA
+    pub size_of_raw_data:       u32,
+    pub pointer_to_raw_data:    u32,
+    pub characteristics:        u32,
+}
+
+impl Section {
+    /// Bytes the section occupies in memory.
+    pub fn memory_size(&self) -> u32 {
+        if self.virtual_size == 0 { self.size_of_raw_data } else { self.virtual_size }
+    }
+
+    /// Bytes of the section backed by the file; the rest of memory_size() is zero-filled.
+    pub fn file_size(&self) -> u32 {
+        self.size_of_raw_data.min(self.memory_size())
+    }
+
+    pub fn contains_rva(&self, rva: u32) -> bool {
+        rva >= self.virtual_address && rva - self.virtual_address < self.memory_size()
+    }
+
+    pub fn is_exec(&self) -> bool {
+        self.characteristics & (IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_CNT_CODE) != 0
+    }
+}
+
+pub struct Import {
+    pub dll:        String,
+    pub name:       Option<String>, // None when imported by ordinal
+    pub hint:       u16,            // hint, or the ordinal for imports by ordinal
+    pub iat_rva:    u32,            // import address table slot the loader fills in
+}
+
+pub struct Export {
+    pub ordinal:    u32,
+    pub name:       Option<String>,
+    pub rva:        u32,
+    pub forwarder:  Option<String>, // "DLL.Name" when the export forwards to another DLL
+}
+
+pub struct Relocation {
+    pub rva:        u32,
+    pub r_type:     u8,
+}
+
+pub struct CoffSymbol {
+    pub name:           String,
+    pub value:          u32,    // offset in the section for section symbols
+    pub section_number: i16,    // 1-based; 0 undefined, -1 absolute, -2 debug
+    pub sym_type:       u16,
+    pub storage_class:  u8,
+}
+
+struct ExportDirectory {
+    name:           u32,    // RVA of the DLL name
+    base:           u32,    // ordinal of the first export address table entry
+    functions:      u32,
+    names:          u32,
+    address_table:  u32,
+    name_table:     u32,
+    ordinal_table:  u32,
+}
+
+pub struct PeFile {
+    pub coff:           CoffHeader,
+    pub optional:       OptionalHeader,
+    pub sections:       Vec<Section>,
+    pub imports:        Vec<Import>,
+    pub exports:        Vec<Export>,
+    pub export_name:    Option<String>,     // DLL name recorded in the export directory
+    pub relocations:    Vec<Relocation>,
+    pub symbols:        Vec<CoffSymbol>,    // COFF symbol table (MinGW keeps it, MSVC does not)
+    bytes: Vec<u8>,
+}
+
+/// "MZ" MS-DOS stub whose e_lfanew points at a "PE\0\0" signature.
+pub fn is_pe(bytes: &[u8]) -> bool {
+    if !bytes.starts_with(b"MZ") {
+        return false;
+    }
+    let offset = Cursor::new(bytes, E_LFANEW, true).u32().unwrap_or(0) as usize;
+    bytes.get(offset..offset.saturating_add(4)) == Some(b"PE\0\0")
+}
+
+fn lookup<T: PartialEq + Copy>(table: &[(T, &'static str)], value: T) -> Option<&'static str> {
+    table.iter().find(|e| e.0 == value).map(|e| e.1)
+}
+
+fn flag_names(table: &[(u16, &str)], flags: u16) -> String {
+    table.iter().filter(|e| flags & e.0 != 0).map(|e| e.1).collect::<Vec<_>>().join(", ")
+}
+
+pub fn get_machine_name(machine: u16) -> String {
+    MACHINES.iter().find(|m| m.0 == machine).map_or(format!("Unknown Machine:[{:#06x}]", machine), |m| m.1.to_string())
+}
+
+pub fn get_relocation_type_name(r_type: u8) -> String {
+    lookup(&RELOCATION_TYPES, r_type).map_or(format!("<unknown: {}>", r_type), |n| n.to_string())
+}
+
+/// "R-X"-style permissions of a section.
+pub fn get_section_flags(characteristics: u32) -> String {
+    let flag = |mask: u32, c: char| if characteristics & mask != 0 { c } else { '-' };
+    [flag(IMAGE_SCN_MEM_READ, 'R'), flag(IMAGE_SCN_MEM_WRITE, 'W'), flag(IMAGE_SCN_MEM_EXECUTE, 'X')].iter().collect()
+}
+
+/// Reads a NUL-terminated (or full-width) fixed-size name.
+fn fixed_name(bytes: &[u8]) -> String {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..len]).into_owned()
+}
+
+impl PeFile {
+    pub fn parse(bytes: Vec<u8>) -> Result<PeFile, String> {
+        if !is_pe(&bytes) {
+            return Err("not PE format file".to_string());
+        }
+        let pe_offset = Cursor::new(&bytes, E_LFANEW, true).u32().unwrap_or(0) as usize + 4;
+        let coff = parse_coff_header(&bytes, pe_offset).ok_or("truncated COFF header")?;
+
+        let optional_offset = pe_offset + COFF_HEADER_SIZE;
+        let optional = parse_optional_header(&bytes, optional_offset).ok_or("truncated optional header")?;
+        // Every address is image base + a 32-bit RVA.
+        if optional.image_base > u64::MAX - u32::MAX as u64 {
+            return Err(format!("image base {:#x} out of range", optional.image_base));
+        }
+
+        let string_table = (coff.pointer_to_symbol_table as usize)
+            .saturating_add(coff.number_of_symbols as usize * COFF_SYMBOL_SIZE);
+        let mut sections = Vec::new();
+        let section_offset = optional_offset + coff.size_of_optional_header as usize;
+        for i in 0..coff.number_of_sections as usize {
+            let mut section = parse_section_header(&bytes, section_offset + i * SECTION_HEADER_SIZE)
+                .ok_or(format!("truncated section header {}", i))?;
+            // Names longer than 8 bytes are "/<decimal offset>" into the COFF string table.
+            if let Some(offset) = section.name.strip_prefix('/').and_then(|n| n.parse::<usize>().ok()) {
+                if coff.pointer_to_symbol_table != 0 {
+                    section.name = elf::read_cstr(&bytes, string_table + offset).unwrap_or(section.name);
+                }
+            }
+            sections.push(section);
+        }
+
+        let mut pe = PeFile{coff, optional, sections, imports: Vec::new(), exports: Vec::new(), export_name: None,
+                            relocations: Vec::new(), symbols: Vec::new(), bytes};
+        pe.imports = pe.parse_imports();
+        (pe.exports, pe.export_name) = pe.parse_exports();
+        pe.relocations = pe.parse_relocations();
+        pe.symbols = pe.parse_symbols(string_table);
+        Ok(pe)
+    }
+
+    pub fn is_64(&self) -> bool {
+        self.optional.magic == PE32_PLUS_MAGIC
+    }
+
+    pub fn is_dll(&self) -> bool {
+        self.coff.characteristics & IMAGE_FILE_DLL != 0
+    }
+
+    fn pointer_size(&self) -> usize {
+        if self.is_64() { 8 } else { 4 }
+    }
+
+    pub fn directory(&self, index: usize) -> Option<(u32, u32)> {
+        self.optional.data_directories.get(index).cloned().filter(|d| d.0 != 0 && d.1 != 0)
+    }
+
+    pub fn section_for_rva(&self, rva: u32) -> Option<&Section> {
+        self.sections.iter().find(|s| s.contains_rva(rva))
+    }
+
+    /// File offset of an RVA, if the file holds it (headers or section contents).
+    pub fn rva_to_offset(&self, rva: u32) -> Option<usize> {
+        if rva < self.optional.size_of_headers {
+            return Some(rva as usize);
+        }
+        let section = self.section_for_rva(rva)?;
+        let delta = rva - section.virtual_address;
+        if delta >= section.file_size() {
+            return None;
+        }
+        let offset = section.pointer_to_raw_data as usize + delta as usize;
+        if offset < self.bytes.len() { Some(offset) } else { None }
+    }
+
+    fn read_pointer(&self, c: &mut Cursor) -> Option<u64> {
+        if self.is_64() { c.u64() } else { c.u32().map(|v| v as u64) }
+    }
+
+    fn cursor(&self, rva: u32) -> Option<Cursor<'_>> {
+        Some(Cursor::new(&self.bytes, self.rva_to_offset(rva)?, true))
+    }
+
+    fn read_string(&self, rva: u32) -> Option<String> {
+        elf::read_cstr(&self.bytes, self.rva_to_offset(rva)?)
+    }
+
+    pub fn section_data(&self, section: &Section) -> &[u8] {
+        let start = (section.pointer_to_raw_data as usize).min(self.bytes.len());
+        let end = start.saturating_add(section.file_size() as usize).min(self.bytes.len());
+        &self.bytes[start..end]
+    }
+
+    fn parse_imports(&self) -> Vec<Import> {
+        let mut imports = Vec::new();
+        let (rva, _) = match self.directory(DIRECTORY_IMPORT) {
+            Some(dir) => dir,
+            None => return imports,
+        };
+        let pointer_size = self.pointer_size();
+        let ordinal_flag = 1u64 << (pointer_size * 8 - 1);
+        for i in 0.. {
+            let descriptor = rva.checked_add((i * IMPORT_DESCRIPTOR_SIZE) as u32).and_then(|d| self.import_descriptor(d));
+            let (lookup_table, name, iat) = match descriptor {
+                Some(d) if d != (0, 0, 0) => d,
+                _ => break,
+            };
+            let dll = self.read_string(name).unwrap_or_default();
+            // The lookup table is optional: old binders only have the IAT.
+            let table = if lookup_table != 0 { lookup_table } else { iat };
+            for j in 0.. {
+                let slot = (j * pointer_size) as u32;
+                let entry = match self.cursor(table.wrapping_add(slot)).and_then(|mut c| self.read_pointer(&mut c)) {
+                    Some(entry) if entry != 0 => entry,
+                    _ => break,
+                };
+                let iat_rva = iat.wrapping_add(slot);
+                if entry & ordinal_flag != 0 {
+                    imports.push(Import{dll: dll.clone(), name: None, hint: entry as u16, iat_rva});
+                } else {
+                    let hint = self.cursor(entry as u32).and_then(|mut c| c.u16()).unwrap_or(0);
+                    let name = self.read_string((entry as u32).wrapping_add(2));
+                    imports.push(Import{dll: dll.clone(), name, hint, iat_rva});
+                }
+            }
+        }
+        imports
+    }
+
+    fn parse_exports(&self) -> (Vec<Export>, Option<String>) {
+        let mut exports = Vec::new();
+        let (rva, size) = match self.directory(DIRECTORY_EXPORT) {
+            Some(dir) => dir,
+            None => return (exports, None),
+        };
+        let dir = match self.export_directory(rva) {
+            Some(dir) => dir,
+            None => return (exports, None),
+        };
+        let mut names_by_index = HashMap::new();
+        for i in 0..dir.names {
+            match self.export_name(&dir, i) {
+                Some((index, name)) => names_by_index.insert(index, name),
+                None => break,
+            };
+        }
+        for i in 0..dir.functions {
+            let function = match dir.address_table.checked_add(i * 4).and_then(|a| self.cursor(a)).and_then(|mut c| c.u32()) {
+                Some(function) => function,
+                None => break,
+            };
+            if function == 0 {
+                continue;
+            }
+            // An address inside the export directory is a "DLL.Name" forwarder string.
+            let forwarder = if function >= rva && function - rva < size { self.read_string(function) } else { None };
+            exports.push(Export{ordinal: dir.base.wrapping_add(i), name: names_by_index.remove(&i), rva: function, forwarder});
+        }
+        (exports, self.read_string(dir.name))
+    }
+
+    /// (import lookup table, DLL name, import address table) of an import descriptor.
+    fn import_descriptor(&self, rva: u32) -> Option<(u32, u32, u32)> {
+        let mut c = self.cursor(rva)?;
+        let lookup_table = c.u32()?;
+        c.take(8)?;                 // TimeDateStamp, ForwarderChain
+        Some((lookup_table, c.u32()?, c.u32()?))
+    }
+
+    fn export_directory(&self, rva: u32) -> Option<ExportDirectory> {
+        let mut c = self.cursor(rva)?;
+        c.take(12)?;                // Characteristics, TimeDateStamp, MajorVersion, MinorVersion
+        Some(ExportDirectory{name: c.u32()?, base: c.u32()?, functions: c.u32()?, names: c.u32()?, address_table: c.u32()?,
+                             name_table: c.u32()?, ordinal_table: c.u32()?})
+    }
+
+    /// The export address table index and name of the i-th name pointer.
+    fn export_name(&self, dir: &ExportDirectory, i: u32) -> Option<(u32, String)> {
+        let index = self.cursor(dir.ordinal_table.checked_add(i * 2)?)?.u16()?;
+        let name = self.cursor(dir.name_table.checked_add(i * 4)?)?.u32()?;
+        Some((index as u32, self.read_string(name)?))
+    }
+
+    fn parse_relocations(&self) -> Vec<Relocation> {
+        let mut relocations = Vec::new();
+        let (rva, size) = match self.directory(DIRECTORY_BASERELOC) {
+            Some(dir) => dir,
+            None => return relocations,
+        };
+        let mut offset: u32 = 0;
+        while offset.checked_add(8).is_some_and(|end| end <= size) {
+            let mut c = match rva.checked_add(offset).and_then(|a| self.cursor(a)) {
+                Some(c) => c,
+                None => break,
+            };
+            let (page, block_size) = match (c.u32(), c.u32()) {
+                (Some(page), Some(block_size)) if block_size >= 8 => (page, block_size),
+                _ => break,
+            };
+            for _ in 0..(block_size - 8) / 2 {
+                let entry = match c.u16() {
+                    Some(entry) => entry,
+                    None => break,
+                };
+                let r_type = (entry >> 12) as u8;
+                // ABSOLUTE entries only pad a block to a 32-bit boundary.
+                if r_type != IMAGE_REL_BASED_ABSOLUTE {
+                    relocations.push(Relocation{rva: page.wrapping_add((entry & 0xfff) as u32), r_type});
+                }
+            }
+            offset = match offset.checked_add(block_size) {
+                Some(offset) => offset,
+                None => break,
+            };
+        }
+        relocations
+    }
+
+    fn parse_symbols(&self, string_table: usize) -> Vec<CoffSymbol> {
+        let mut symbols = Vec::new();
+        if self.coff.pointer_to_symbol_table == 0 {
+            return symbols;
+        }
+        let mut i = 0;
+        while i < self.coff.number_of_symbols as usize {
+            let offset = self.coff.pointer_to_symbol_table as usize + i * COFF_SYMBOL_SIZE;
+            let (symbol, aux) = match self.coff_symbol(offset, string_table) {
+                Some(symbol) => symbol,
+                None => break,
+            };
+            symbols.push(symbol);
+            i += 1 + aux as usize;
+        }
+        symbols
+    }
+
+    /// A COFF symbol record and the number of auxiliary records that follow it.
+    fn coff_symbol(&self, offset: usize, string_table: usize) -> Option<(CoffSymbol, u8)> {
+        let mut c = Cursor::new(&self.bytes, offset, true);
+        let raw = c.take(8)?;
+        // A name longer than 8 bytes is a zero word followed by a string table offset.
+        let name = if raw[..4] == [0, 0, 0, 0] {
+            let offset = u32::from_le_bytes([raw[4], raw[5], raw[6], raw[7]]) as usize;
+            elf::read_cstr(&self.bytes, string_table + offset)?
+        } else {
+            fixed_name(raw)
+        };
+        Some((CoffSymbol{name, value: c.u32()?, section_number: c.u16()? as i16, sym_type: c.u16()?, storage_class: c.u8()?},
+              c.u8()?))
+    }
+}
+
+fn parse_coff_header(bytes: &[u8], offset: usize) -> Option<CoffHeader> {
+    let mut c = Cursor::new(bytes, offset, true);
+    Some(CoffHeader{
+        machine: c.u16()?,
+        number_of_sections: c.u16()?,
+        time_date_stamp: c.u32()?,
+        pointer_to_symbol_table: c.u32()?,
+        number_of_symbols: c.u32()?,
+        size_of_optional_header: c.u16()?,
+        characteristics: c.u16()?,
+    })
+}
+
+fn parse_section_header(bytes: &[u8], offset: usize) -> Option<Section> {
+    let mut c = Cursor::new(bytes, offset, true);
+    let name = fixed_name(c.take(8)?);
+    let virtual_size = c.u32()?;
+    let virtual_address = c.u32()?;
+    let size_of_raw_data = c.u32()?;
+    let pointer_to_raw_data = c.u32()?;
+    c.take(12)?;                    // PointerToRelocations, PointerToLinenumbers and their counts
+    Some(Section{name, virtual_size, virtual_address, size_of_raw_data, pointer_to_raw_data, characteristics: c.u32()?})
+}
+
+fn parse_optional_header(bytes: &[u8], offset: usize) -> Option<OptionalHeader> {
+    let mut c = Cursor::new(bytes, offset, true);
+    let magic = c.u16()?;
+    let is_64 = match magic {
+        PE32_MAGIC => false,
+        PE32_PLUS_MAGIC => true,
+        _ => return None,
+    };
+    c.take(14)?;                    // linker version, SizeOfCode, SizeOf(Un)InitializedData
+    let address_of_entry_point = c.u32()?;
+    c.take(4)?;                     // BaseOfCode
+    let image_base = if is_64 { c.u64()? } else { c.take(4)?; c.u32()? as u64 };
+    let section_alignment = c.u32()?;
+    let file_alignment = c.u32()?;
+    c.take(16)?;                    // OS, image and subsystem versions, Win32VersionValue
+    let size_of_image = c.u32()?;
+    let size_of_headers = c.u32()?;
+    let checksum = c.u32()?;
+    let subsystem = c.u16()?;
+    let dll_characteristics = c.u16()?;
+    c.take(if is_64 { 32 } else { 16 })?;   // stack and heap reserve/commit
+    c.take(4)?;                     // LoaderFlags
+    let count = c.u32()?.min(DIRECTORY_NAMES.len() as u32);
+    let mut data_directories = Vec::new();
+    for _ in 0..count {
+        data_directories.push((c.u32()?, c.u32()?));
+    }
+    Some(OptionalHeader{magic, address_of_entry_point, image_base, section_alignment, file_alignment, size_of_image,
+                        size_of_headers, checksum, subsystem, dll_characteristics, data_directories})
+}
+
+pub fn open(path: &str) -> Result<PeFile, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("File open failed filepath:{} ({})", path, e))?;
+    PeFile::parse(bytes).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Symbol name of an import: its name, or "<dll>#<ordinal>" for imports by ordinal.
+pub fn import_name(import: &Import) -> String {
+    match import.name.as_ref() {
+        Some(name) => name.clone(),
+        None => format!("{}#{}", import.dll.trim_end_matches(".dll").trim_end_matches(".DLL"), import.hint),
+    }
+}
+
+struct ModelSymbol {
+    addr:       u64,
+    name:       String,
+    st_type:    u8,
+    global:     bool,
+    size:       u64,
+}
+
+/// The symbols of the ELF model: COFF symbols, exports, and "__imp_<name>" for each import
+/// address table slot, locals first. Functions without a size extend to the next symbol in
+/// their section.
+fn model_symbols(pe: &PeFile) -> Vec<ModelSymbol> {
+    let base = pe.optional.image_base;
+    let in_code = |rva: u32| pe.section_for_rva(rva).is_some_and(|s| s.is_exec());
+    let mut symbols = Vec::new();
+    for sym in pe.symbols.iter() {
+        let section = match sym.section_number {
+            n if n > 0 && (n as usize) <= pe.sections.len() => &pe.sections[n as usize - 1],
+            _ => continue,
+        };
+        // Section and .file symbols are named like ".text" and ".file".
+        if sym.name.is_empty() || sym.name.starts_with('.') {
+            continue;
+        }
+        let global = match sym.storage_class {
+            IMAGE_SYM_CLASS_EXTERNAL => true,
+            IMAGE_SYM_CLASS_STATIC | IMAGE_SYM_CLASS_LABEL => false,
+            _ => continue,
+        };
+        let rva = section.virtual_address.wrapping_add(sym.value);
+        // Linkers leave function symbols untyped; their own markers (__CTOR_LIST__,
+        // __rt_psrelocs_end) in code sections start with "__".
+        let st_type = if sym.sym_type & 0xf0 == IMAGE_SYM_DTYPE_FUNCTION || (in_code(rva) && !sym.name.starts_with("__")) {
+            elf::STT_FUNC
+        } else if in_code(rva) {
+            elf::STT_NOTYPE
+        } else {
+            elf::STT_OBJECT
+        };
+        symbols.push(ModelSymbol{addr: base + rva as u64, name: sym.name.clone(), st_type, global, size: 0});
+    }
+    for export in pe.exports.iter().filter(|e| e.forwarder.is_none()) {
+        let name = export.name.clone().unwrap_or(format!("ordinal_{}", export.ordinal));
+        let st_type = if in_code(export.rva) { elf::STT_FUNC } else { elf::STT_OBJECT };
+        symbols.push(ModelSymbol{addr: base + export.rva as u64, name, st_type, global: true, size: 0});
+    }
+    for import in pe.imports.iter() {
+        symbols.push(ModelSymbol{addr: base + import.iat_rva as u64, name: format!("__imp_{}", import_name(import)),
+                                 st_type: elf::STT_OBJECT, global: true, size: pe.pointer_size() as u64});
+    }
+    // An export usually repeats a COFF symbol: keep one, preferring the typed global.
+    symbols.sort_by(|a, b| (a.addr, &a.name, !a.global, a.st_type != elf::STT_FUNC)
+        .cmp(&(b.addr, &b.name, !b.global, b.st_type != elf::STT_FUNC)));
+    symbols.dedup_by(|a, b| a.addr == b.addr && a.name == b.name);
+
+    // Symbols are sorted by address, so the next start is a binary search away.
+    let starts: Vec<u64> = symbols.iter().map(|s| s.addr).collect();
+    for sym in symbols.iter_mut().filter(|s| s.st_type == elf::STT_FUNC) {
+        let section_end = pe.section_for_rva((sym.addr - base) as u32)
+            .map_or(sym.addr, |s| base + s.virtual_address as u64 + s.memory_size() as u64);
+        let next = starts.get(starts.partition_point(|&a| a <= sym.addr)).map_or(section_end, |&a| a.min(section_end));
+        sym.size = next - sym.addr;
+    }
+    symbols.sort_by_key(|s| s.global);
+    symbols
+}
+
+/// Builds an ELF model of the mapped image: one PT_LOAD segment and one section per PE
+/// section at image base + RVA, and a .symtab from model_symbols().
+fn build(pe: &PeFile, e_machine: u16, e_flags: u32) -> Vec<u8> {
+    let is_64 = pe.is_64();
+    let base = pe.optional.image_base;
+    let e_type = if pe.is_dll() { elf::ET_DYN } else { elf::ET_EXEC };
+    let mut entry = match pe.optional.address_of_entry_point {
+        0 => 0,
+        rva => base + rva as u64,
+    };
+    // ARMNT images are Thumb-2 only.
+    if pe.coff.machine == MACHINE_ARMNT && entry != 0 {
+        entry |= 1;
+    }
+    let mut bytes = elf::write_file_header(is_64, true, e_type, e_machine, e_flags, entry);
+    let mut offset = (bytes.len() + pe.sections.len() * elf::program_header_size(is_64)).next_multiple_of(16) as u64;
+    let mut phdrs = Vec::new();
+    for section in pe.sections.iter() {
+        let c = section.characteristics;
+        let p_flags = [(IMAGE_SCN_MEM_READ, elf::PF_R), (IMAGE_SCN_MEM_WRITE, elf::PF_W), (IMAGE_SCN_MEM_EXECUTE, elf::PF_X)]
+            .iter().filter(|f| c & f.0 != 0).fold(0, |flags, f| flags | f.1);
+        let filesz = pe.section_data(section).len() as u64;
+        phdrs.push(ProgramHeader{p_type: elf::PT_LOAD, p_flags, p_offset: offset, p_vaddr: base + section.virtual_address as u64,
+                                 p_paddr: base + section.virtual_address as u64, p_filesz: filesz,
+                                 p_memsz: (section.memory_size() as u64).max(filesz), p_align: pe.optional.section_alignment as u64});
+        offset = (offset + filesz).next_multiple_of(16);
+    }
+    elf::write_program_headers(&mut bytes, &phdrs);
+
+    let mut names = vec![0u8];
+    let mut shdrs = vec![image::section_header("", &mut names, elf::SHT_NULL, 0, 0, 0, 0)];
+    for (section, phdr) in pe.sections.iter().zip(phdrs.iter()) {
+        bytes.resize(phdr.p_offset as usize, 0);
+        bytes.extend_from_slice(pe.section_data(section));
+        let c = section.characteristics;
+        let mut sh_flags = elf::SHF_ALLOC;
+        if c & IMAGE_SCN_MEM_WRITE != 0 {
+            sh_flags |= elf::SHF_WRITE;
+        }
+        if section.is_exec() {
+            sh_flags |= elf::SHF_EXECINSTR;
+        }
+        let sh_type = if phdr.p_filesz == 0 { elf::SHT_NOBITS } else { elf::SHT_PROGBITS };
+        shdrs.push(image::section_header(&section.name, &mut names, sh_type, sh_flags, phdr.p_vaddr, phdr.p_offset, phdr.p_memsz));
+    }
+
+    let symbols = model_symbols(pe);
+    let mut symtab = Vec::new();
+    let mut strtab = vec![0u8];
+    let null = elf::Symbol{name: String::new(), st_name: 0, st_info: 0, st_other: 0, st_shndx: 0, shndx: 0, st_value: 0, st_size: 0};
+    elf::write_symbol(&mut symtab, &null, is_64, true);
+    for model in symbols.iter() {
+        let st_shndx = shdrs.iter().position(|s| s.is_alloc() && s.contains_vaddr(model.addr)).map_or(elf::SHN_ABS, |i| i as u16);
+        let bind = if model.global { elf::STB_GLOBAL } else { elf::STB_LOCAL };
+        let sym = elf::Symbol{name: model.name.clone(), st_name: strtab.len() as u32, st_info: bind << 4 | model.st_type,
+                              st_other: 0, st_shndx, shndx: st_shndx as u32, st_value: model.addr, st_size: model.size};
+        strtab.extend_from_slice(model.name.as_bytes());
+        strtab.push(0);
+        elf::write_symbol(&mut symtab, &sym, is_64, true);
+    }
+    let first_global = symbols.iter().position(|s| s.global).unwrap_or(symbols.len()) as u32 + 1;
+
+    let strtab_index = shdrs.len();
+    shdrs.push(image::section_header(".strtab", &mut names, elf::SHT_STRTAB, 0, 0, bytes.len() as u64, strtab.len() as u64));
+    bytes.extend_from_slice(&strtab);
+    bytes.resize(bytes.len().next_multiple_of(8), 0);
+    let symtab_shdr = image::section_header(".symtab", &mut names, elf::SHT_SYMTAB, 0, 0, bytes.len() as u64, symtab.len() as u64);
+    shdrs.push(SectionHeader{sh_link: strtab_index as u32, sh_info: first_global, sh_addralign: 8,
+                             sh_entsize: elf::symbol_size(is_64) as u64, ..symtab_shdr});
+    bytes.extend_from_slice(&symtab);
+
+    let shstrndx = shdrs.len();
+    let shstrtab = image::section_header(".shstrtab", &mut names, elf::SHT_STRTAB, 0, 0, bytes.len() as u64, 0);
+    shdrs.push(SectionHeader{sh_size: names.len() as u64, ..shstrtab});
+    bytes.extend_from_slice(&names);
+    elf::write_section_headers(&mut bytes, &shdrs, shstrndx);
+    bytes
+}
+
+/// Wraps a PE image in an ELF model so that the ELF analyses (disassembly, sizes, call
+/// graphs, diffs) work on it.
+pub fn to_elf(pe: &PeFile) -> Result<ElfFile, String> {
+    let e_machine = MACHINES.iter().find(|m| m.0 == pe.coff.machine).map(|m| m.2)
+        .ok_or(format!("unsupported PE machine {}", get_machine_name(pe.coff.machine)))?;
+    let e_flags = match e_machine {
+        elf::EM_ARM => elf::EF_ARM_EABI_VER5,
+        elf::EM_RISCV => elf::EF_RISCV_RVC,
+        _ => 0,
+    };
+    ElfFile::parse(build(pe, e_machine, e_flags))
+}
+
+pub fn show_pe(path: &str, pe: &PeFile) {
+    let o = &pe.optional;
+    println!("PE Header:");
+    println!("  {:<34} {}", "Format:", if pe.is_64() { "PE32+" } else { "PE32" });
+    println!("  {:<34} {:#06x} ({})", "Machine:", pe.coff.machine, get_machine_name(pe.coff.machine));
+    println!("  {:<34} {:#06x} ({})", "Characteristics:", pe.coff.characteristics,
+             flag_names(&FILE_CHARACTERISTICS, pe.coff.characteristics));
+    println!("  {:<34} {:#010x}", "Time/Date stamp:", pe.coff.time_date_stamp);
+    match o.address_of_entry_point {
+        0 => println!("  {:<34} none", "Entry point:"),
+        rva => println!("  {:<34} {:#x} (RVA {:#x})", "Entry point:", o.image_base + rva as u64, rva),
+    }
+    println!("  {:<34} {:#x}", "Image base:", o.image_base);
+    println!("  {:<34} {:#x} / {:#x}", "Section / file alignment:", o.section_alignment, o.file_alignment);
+    println!("  {:<34} {:#x} / {:#x}", "Size of image / headers:", o.size_of_image, o.size_of_headers);
+    println!("  {:<34} {:#x}", "Checksum:", o.checksum);
+    println!("  {:<34} {} ({})", "Subsystem:", o.subsystem, lookup(&SUBSYSTEMS, o.subsystem).unwrap_or("unknown"));
+    println!("  {:<34} {:#06x} ({})", "DLL characteristics:", o.dll_characteristics,
+             flag_names(&DLL_CHARACTERISTICS, o.dll_characteristics));
+    println!("  {:<34} {}", "Number of COFF symbols:", pe.coff.number_of_symbols);
+    println!();
+
+    println!("Data directories:");
+    println!("  [Nr] {:<16} {:<10} Size", "Name", "RVA");
+    for (i, (rva, size)) in o.data_directories.iter().enumerate().filter(|d| d.1 .1 != 0) {
+        println!("  [{:2}] {:<16} {:#010x} {:#x}", i, DIRECTORY_NAMES[i], rva, size);
+    }
+    println!();
+
+    println!("Sections:");
+    println!("  [Nr] {:<16} {:<10} {:<10} {:<10} {:<10} Flg Characteristics", "Name", "RVA", "VirtSize", "RawOffset", "RawSize");
+    for (i, s) in pe.sections.iter().enumerate() {
+        println!("  [{:2}] {:<16} {:#010x} {:#010x} {:#010x} {:#010x} {} {:#010x}", i + 1, s.name, s.virtual_address,
+                 s.virtual_size, s.pointer_to_raw_data, s.size_of_raw_data, get_section_flags(s.characteristics),
+                 s.characteristics);
+    }
+    println!();
+
+    let mut dlls: Vec<&str> = pe.imports.iter().map(|i| i.dll.as_str()).collect();
+    dlls.dedup();
+    println!("Imports from {} DLLs ({} symbols):", dlls.len(), pe.imports.len());
+    for dll in dlls {
+        println!("  {}", dll);
+        for import in pe.imports.iter().filter(|i| i.dll == dll) {
+            match import.name.as_ref() {
+                Some(name) => println!("    {:#018x}  {:>5}  {}", o.image_base + import.iat_rva as u64, import.hint, name),
+                None => println!("    {:#018x}  {:>5}  (ordinal)", o.image_base + import.iat_rva as u64, import.hint),
+            }
+        }
+    }
+    println!();
+
+    println!("Exports of {} ({} entries):", pe.export_name.as_deref().unwrap_or(path), pe.exports.len());
+    println!("  {:>7}  {:<10}  Name", "Ordinal", "RVA");
+    for export in pe.exports.iter() {
+        let name = export.name.as_deref().unwrap_or("(none)");
+        match export.forwarder.as_ref() {
+            Some(target) => println!("  {:>7}  {:#010x}  {} -> {}", export.ordinal, export.rva, name, target),
+            None => println!("  {:>7}  {:#010x}  {}", export.ordinal, export.rva, name),
+        }
+    }
+    println!();
+
+    println!("Base relocations ({} entries):", pe.relocations.len());
+    for relocation in pe.relocations.iter() {
+        println!("  {:#018x}  {}", o.image_base + relocation.rva as u64, get_relocation_type_name(relocation.r_type));
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use crate::disasm::{Decoder, Flow, Instruction, Mapping, Syntax};
+use crate::elf;
+use crate::elf::ElfFile;
+
+/// A PLT stub and the imported symbol whose GOT slot it jumps through.
+#[derive(Clone, Debug)]
+pub struct PltStub {
+    pub addr:   u64,
+    pub size:   u64,
+    pub name:   String, // dynamic symbol name, without version
+}
+
+/// Maps GOT slot addresses to the dynamic symbols their JUMP_SLOT and GLOB_DAT
+/// relocations bind.
+pub fn got_slots(elf: &ElfFile) -> HashMap<u64, String> {
+    let types = [elf.jump_slot_type(), elf.glob_dat_type()];
+    let mut slots = HashMap::new();
+    for shdr in elf.section_headers.iter() {
+        if shdr.sh_type != elf::SHT_RELA && shdr.sh_type != elf::SHT_REL {
+            continue;
+        }
+        let dynsym = elf.section_headers.get(shdr.sh_link as usize).map(|s| s.sh_type) == Some(elf::SHT_DYNSYM);
+        if !dynsym {
+            continue;
+        }
+        for r in elf.relocations(shdr).iter().filter(|r| types.contains(&Some(r.r_type))) {
+            match elf.dynamic_symbols.get(r.r_sym as usize) {
+                Some(sym) if !sym.name.is_empty() => {
+                    slots.insert(r.r_offset, sym.name.split('@').next().unwrap_or("").to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+    slots
+}
+
+/// Parses "#123", "#0x7b" or "0x7b".
+pub fn immediate(text: &str) -> Option<u64> {
+    let text = text.trim().trim_start_matches('#');
+    match text.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse::<u64>().ok(),
+    }
+}
+
+/// Splits the memory operand "[base, #imm]" or "[base+0x10]" into base and offset.
+pub fn memory_operand(operands: &str) -> Option<(String, u64)> {
+    let inner = &operands[operands.find('[')? + 1..operands.find(']')?];
+    let mut parts = inner.splitn(2, [',', '+']);
+    let base = parts.next()?.trim().to_string();
+    let offset = match parts.next() {
+        Some(imm) => immediate(imm)?,
+        None => 0,
+    };
+    Some((base, offset))
+}
+
+/// Follows the address arithmetic of a stub: the GOT slot is either the memory operand of
+/// a load or jump, or a register base set up by adr/adrp/auipc plus add and an offset.
+struct SlotTracker {
+    regs:       HashMap<String, u64>,
+    got_base:   u64,    // i386 PIC stubs address the GOT through ebx
+    slot:       Option<u64>,
+}
+
+impl SlotTracker {
+    fn step(&mut self, insn: &Instruction) {
+        let ops: Vec<&str> = insn.operands.split(',').map(|o| o.trim()).collect();
+        let load = insn.mnemonic.starts_with("ld") || insn.mnemonic == "jmp" || insn.mnemonic == "bnd jmp";
+        if let Some(addr) = insn.mem_ref {
+            if load || insn.flow == Flow::IndirectJump {
+                self.slot = Some(addr);
+            } else if let Some(rd) = ops.first() {
+                self.regs.insert(rd.to_string(), addr);
+            }
+            return;
+        }
+        if insn.operands.contains('[') {
+            if let Some((base, offset)) = memory_operand(&insn.operands) {
+                if let Some(&value) = self.regs.get(&base) {
+                    self.slot = Some(value.wrapping_add(offset));
+                } else if base == "ebx" {
+                    self.slot = Some(self.got_base.wrapping_add(offset));
+                }
+            }
+            return;
+        }
+        if insn.mnemonic == "add" && ops.len() == 3 {
+            if let (Some(&value), Some(imm)) = (self.regs.get(ops[1]), immediate(ops[2])) {
+                self.regs.insert(ops[0].to_string(), value.wrapping_add(imm));
+            }
+        }
+    }
+}
+
+/// Decodes the stubs in .plt, .plt.sec and .plt.got and names each after the symbol bound
+/// to the GOT slot it jumps through. Stubs whose slot has no such relocation (the PLT0
+/// resolver entry, lazy-binding trampolines) are left out.
+pub fn stubs(elf: &ElfFile) -> Vec<PltStub> {
+    let mut decoder = match Decoder::for_elf(elf, Syntax::Intel) {
+        Some(decoder) => decoder,
+        None => return Vec::new(),
+    };
+    if elf.e_machine == elf::EM_ARM {
+        decoder.set_mapping(Mapping::Arm);
+    }
+    let slots = got_slots(elf);
+    if slots.is_empty() {
+        return Vec::new();
+    }
+    let got_base = elf.section_by_name(".got.plt").or_else(|| elf.section_by_name(".got")).map_or(0, |s| s.sh_addr);
+    let mut result = Vec::new();
+    for shdr in elf.section_headers.iter().filter(|s| [".plt", ".plt.sec", ".plt.got"].contains(&s.name.as_str())) {
+        let data = elf.section_data(shdr);
+        let end = shdr.sh_addr + data.len() as u64;
+        let mut found: Vec<PltStub> = Vec::new();
+        let mut pos = 0;
+        let mut start = shdr.sh_addr;
+        let mut tracker = SlotTracker{regs: HashMap::new(), got_base, slot: None};
+        let mut dec = decoder;
+        while pos < data.len() {
+            let addr = shdr.sh_addr + pos as u64;
+            let insn = match dec.decode(&data[pos..], addr) {
+                Some(insn) => insn,
+                None => {
+                    pos += dec.skip_len(&data[pos..]).max(1);
+                    start = shdr.sh_addr + pos as u64;
+                    continue;
+                }
+            };
+            pos += insn.len;
+            // Padding after the previous stub's jump belongs to neither stub.
+            if insn.mnemonic == "nop" && addr == start {
+                start = shdr.sh_addr + pos as u64;
+                continue;
+            }
+            tracker.step(&insn);
+            // RISC-V stubs end in "jalr t1, t3", which links.
+            if matches!(insn.flow, Flow::IndirectJump | Flow::IndirectCall | Flow::Jump(_)) {
+                if let Some(name) = tracker.slot.and_then(|slot| slots.get(&slot)) {
+                    found.push(PltStub{addr: start, size: 0, name: name.clone()});
+                }
+                start = shdr.sh_addr + pos as u64;
+                tracker = SlotTracker{regs: HashMap::new(), got_base, slot: None};
+            }
+        }
+        // A stub runs up to the next one; lazy-binding code after the jump belongs to it.
+        for i in 0..found.len() {
+            let next = found.get(i + 1).map_or(end, |s| s.addr);
+            found[i].size = next - found[i].addr;
+        }
+        result.extend(found);
+    }
+    result
+}
@@ -0,0 +1,429 @@
+use std::fs;
+use std::fs::File;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+use crate::dwarf;
+use crate::elf;
+use crate::elf::{ElfFile, ProgramHeader, SectionHeader};
+
+const PAGE_SIZE: u64 = 0x1000;
+const SCAN_CHUNK: usize = 1 << 20;  // bytes read at a time when searching anonymous memory
+const MAX_IMAGE_SIZE: u64 = 1 << 30;    // larger sizes come from a corrupt or fake header
+
+// Dynamic tags holding addresses, which ld.so relocates in place in writable .dynamic
+const POINTER_TAGS: [i64; 16] = [
+    elf::DT_PLTGOT, elf::DT_HASH, elf::DT_STRTAB, elf::DT_SYMTAB, elf::DT_RELA, elf::DT_INIT, elf::DT_FINI,
+    elf::DT_REL, elf::DT_JMPREL, elf::DT_INIT_ARRAY, elf::DT_FINI_ARRAY, elf::DT_PREINIT_ARRAY,
+    elf::DT_GNU_HASH, elf::DT_VERSYM, elf::DT_VERDEF, elf::DT_VERNEED,
+];
+
+/// One line of /proc/<pid>/maps.
+pub struct Mapping {
+    pub start:  u64,
+    pub end:    u64,
+    pub perms:  String,     // "r-xp"
+    pub offset: u64,        // file offset of the mapping
+    pub path:   String,     // file, "[heap]", "/memfd:name (deleted)" or empty for anonymous memory
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Layout {
+    Loaded,     // segments placed at their virtual addresses by the kernel, ld.so or a custom loader
+    File,       // the file copied into memory unchanged
+}
+
+/// An ELF image found in the address space of a process.
+pub struct Image {
+    pub base:   u64,            // address of the ELF header
+    pub end:    u64,
+    pub path:   String,         // pathname of the mapping holding the header
+    pub origin: &'static str,   // file, deleted, memfd or anonymous
+    pub main:   bool,           // the executable of the process
+    pub layout: Layout,
+    pub bias:   u64,            // added to link-time addresses
+    pub bytes:  Vec<u8>,        // reconstructed file
+}
+
+/// Readable memory of a process through /proc/<pid>/mem.
+struct Memory {
+    file: File,
+}
+
+impl Memory {
+    fn read(&self, addr: u64, len: usize) -> Option<Vec<u8>> {
+        let mut buf = vec![0; len];
+        self.file.read_exact_at(&mut buf, addr).ok()?;
+        Some(buf)
+    }
+}
+
+pub fn read_maps(pid: u32) -> Result<Vec<Mapping>, String> {
+    let path = format!("/proc/{}/maps", pid);
+    let text = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut maps = Vec::new();
+    for line in text.lines() {
+        // start-end perms offset dev inode pathname; the pathname may contain spaces.
+        let fields: Vec<&str> = line.splitn(6, ' ').collect();
+        if fields.len() < 5 {
+            continue;
+        }
+        let (start, end) = match fields[0].split_once('-') {
+            Some(range) => range,
+            None => continue,
+        };
+        let parse = |s: &str| u64::from_str_radix(s, 16).ok();
+        if let (Some(start), Some(end), Some(offset)) = (parse(start), parse(end), parse(fields[2])) {
+            maps.push(Mapping{start, end, perms: fields[1].to_string(), offset,
+                              path: fields.get(5).map(|p| p.trim_start().to_string()).unwrap_or_default()});
+        }
+    }
+    Ok(maps)
+}
+
+fn origin(path: &str) -> &'static str {
+    if path.starts_with("/memfd:") {
+        "memfd"
+    } else if path.ends_with(" (deleted)") {
+        "deleted"
+    } else if path.starts_with('/') {
+        "file"
+    } else {
+        "anonymous"
+    }
+}
+
+/// A plausible ELF header: magic, class, byte order, version and object type.
+fn is_header(bytes: &[u8]) -> bool {
+    if bytes.len() < 20 || !elf::is_elf(bytes) || ![1, 2].contains(&bytes[elf::EI_CLASS]) || ![1, 2].contains(&bytes[elf::EI_DATA])
+        || bytes[6] != 1 {
+        return false;
+    }
+    let e_type = if bytes[elf::EI_DATA] == 1 { u16::from_le_bytes([bytes[16], bytes[17]]) }
+                 else { u16::from_be_bytes([bytes[16], bytes[17]]) };
+    [elf::ET_REL, elf::ET_EXEC, elf::ET_DYN, elf::ET_CORE].contains(&e_type)
+}
+
+/// Addresses of ELF headers: the start of each file mapping at offset 0, and anywhere in
+/// anonymous and memfd memory, where unpackers and reflective loaders put their images.
+fn find_headers(mem: &Memory, maps: &[Mapping]) -> Vec<u64> {
+    let mut found = Vec::new();
+    for m in maps.iter().filter(|m| m.perms.starts_with('r')) {
+        if matches!(origin(&m.path), "file" | "deleted") {
+            if m.offset == 0 && mem.read(m.start, 64).is_some_and(|h| is_header(&h)) {
+                found.push(m.start);
+            }
+            continue;
+        }
+        let mut addr = m.start;
+        while addr < m.end {
+            let len = ((m.end - addr) as usize).min(SCAN_CHUNK);
+            if let Some(chunk) = mem.read(addr, len) {
+                for pos in 0..chunk.len().saturating_sub(3) {
+                    if chunk[pos..].starts_with(b"\x7fELF") {
+                        let at = addr + pos as u64;
+                        if mem.read(at, 64).is_some_and(|h| is_header(&h)) {
+                            found.push(at);
+                        }
+                    }
+                }
+            }
+            // Overlap chunks so a magic number split between them is still seen.
+            addr += (len as u64).saturating_sub(3).max(1);
+        }
+    }
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// The ELF header and program headers at `base`, parsed with the section table dropped.
+fn read_headers(mem: &Memory, base: u64) -> Option<(Vec<u8>, ElfFile)> {
+    let ident = mem.read(base, 64)?;
+    let is_64 = ident[elf::EI_CLASS] == elf::ELFCLASS64;
+    let le = ident[elf::EI_DATA] != 2;
+    let value = |offset: usize, size: usize| dwarf::Cursor::new(&ident, offset, le).sized(size);
+    let (phoff, phentsize, phnum) = if is_64 {
+        (value(0x20, 8)?, value(0x36, 2)?, value(0x38, 2)?)
+    } else {
+        (value(0x1c, 4)?, value(0x2a, 2)?, value(0x2c, 2)?)
+    };
+    let len = (if is_64 { 64 } else { 52 } as u64).max(phoff.checked_add(phentsize.checked_mul(phnum)?)?);
+    let mut head = mem.read(base, image_len(len)?)?;
+    elf::write_section_headers(&mut head, &[], 0);
+    let elf = ElfFile::parse(head.clone()).ok()?;
+    Some((head, elf))
+}
+
+fn image_len(size: u64) -> Option<usize> {
+    (size <= MAX_IMAGE_SIZE).then_some(size as usize)
+}
+
+fn loads(elf: &ElfFile) -> Vec<&ProgramHeader> {
+    elf.program_headers.iter().filter(|p| p.p_type == elf::PT_LOAD).collect()
+}
+
+/// Rebuilds the file from segments mapped at their load addresses.
+fn read_loaded(mem: &Memory, base: u64, head: &[u8], elf: &ElfFile) -> Option<(u64, Vec<u8>)> {
+    let loads = loads(elf);
+    let first = loads.first()?;
+    let start = (first.p_vaddr & !(PAGE_SIZE - 1)).wrapping_sub(first.p_offset & !(PAGE_SIZE - 1));
+    let bias = base.wrapping_sub(start);
+    if elf.e_type == elf::ET_EXEC && bias != 0 {
+        return None;
+    }
+    let size = loads.iter().map(|p| p.p_offset.saturating_add(p.p_filesz)).max()?.max(head.len() as u64);
+    let mut bytes = vec![0; image_len(size)?];
+    for p in loads.iter() {
+        let data = mem.read(bias.wrapping_add(p.p_vaddr), image_len(p.p_filesz)?)?;
+        bytes[p.p_offset as usize..][..data.len()].copy_from_slice(&data);
+    }
+    bytes[..head.len()].copy_from_slice(head);
+    Some((bias, bytes))
+}
+
+/// Reads the file copied into memory as is, with its section table if that is there too.
+fn read_file(mem: &Memory, base: u64, head: &[u8], elf: &ElfFile) -> Option<Vec<u8>> {
+    let size = loads(elf).iter().map(|p| p.p_offset.saturating_add(p.p_filesz)).max().unwrap_or(0).max(head.len() as u64);
+    let ident = mem.read(base, 64)?;
+    let le = ident[elf::EI_DATA] != 2;
+    let value = |offset: usize, size: usize| dwarf::Cursor::new(&ident, offset, le).sized(size);
+    let (shoff, shentsize, shnum) = if elf.is_64() {
+        (value(0x28, 8)?, value(0x3a, 2)?, value(0x3c, 2)?)
+    } else {
+        (value(0x20, 4)?, value(0x2e, 2)?, value(0x30, 2)?)
+    };
+    let table_end = shoff.checked_add(shentsize.checked_mul(shnum)?)?;
+    if shoff != 0 {
+        let with_table = mem.read(base, image_len(table_end.max(size))?)
+            .filter(|bytes| ElfFile::parse(bytes.clone()).is_ok_and(|e| !e.section_headers.is_empty()));
+        if with_table.is_some() {
+            return with_table;
+        }
+    }
+    let mut bytes = mem.read(base, image_len(size)?)?;
+    elf::write_section_headers(&mut bytes, &[], 0);
+    Some(bytes)
+}
+
+/// Converts an address found in memory back to its link-time value.
+fn unrelocate(elf: &ElfFile, bias: u64, value: u64) -> u64 {
+    let link = value.wrapping_sub(bias);
+    if bias != 0 && elf.vaddr_to_offset(value).is_none() && elf.vaddr_to_offset(link).is_some() { link } else { value }
+}
+
+/// The dynamic symbol table is usable: DT_SYMTAB and DT_STRTAB point into the image.
+fn has_dynamic(bytes: &[u8], bias: u64) -> bool {
+    ElfFile::parse(bytes.to_vec()).is_ok_and(|elf| {
+        [elf::DT_SYMTAB, elf::DT_STRTAB].iter().all(|&tag| {
+            elf.dynamic_value(tag).is_some_and(|v| elf.vaddr_to_offset(unrelocate(&elf, bias, v)).is_some())
+        })
+    })
+}
+
+/// Number of .dynsym entries, from DT_HASH or by walking the DT_GNU_HASH chains.
+fn dynamic_symbol_count(elf: &ElfFile) -> Option<u64> {
+    let le = elf.is_little_endian();
+    let word = |addr: u64| elf.read_vaddr(addr, 4).and_then(|b| dwarf::Cursor::new(b, 0, le).u32()).map(|w| w as u64);
+    if let Some(hash) = elf.dynamic_value(elf::DT_HASH) {
+        return word(hash + 4);
+    }
+    if let Some(hash) = elf.dynamic_value(elf::DT_GNU_HASH) {
+        let (nbuckets, symoffset, bloom_size) = (word(hash)?, word(hash + 4)?, word(hash + 8)?);
+        let buckets = hash + 16 + bloom_size * if elf.is_64() { 8 } else { 4 };
+        let last = (0..nbuckets).filter_map(|i| word(buckets + i * 4)).max().unwrap_or(0);
+        if last < symoffset {
+            return Some(symoffset);
+        }
+        let chains = buckets + nbuckets * 4;
+        let mut index = last;
+        while word(chains + (index - symoffset) * 4)? & 1 == 0 {
+            index += 1;
+        }
+        return Some(index + 1);
+    }
+    // Without a hash table, .dynstr usually follows .dynsym directly.
+    let (symtab, strtab) = (elf.dynamic_value(elf::DT_SYMTAB)?, elf.dynamic_value(elf::DT_STRTAB)?);
+    let entsize = if elf.is_64() { 24 } else { 16 };
+    (strtab > symtab).then(|| (strtab - symtab) / entsize)
+}
+
+/// Undoes ld.so's relocation of .dynamic and describes the segments and dynamic tables with
+/// synthetic sections, so the section-based analyses work on a loaded image.
+fn add_sections(bytes: &mut Vec<u8>, bias: u64) -> Option<()> {
+    let elf = ElfFile::parse(bytes.clone()).ok()?;
+    let le = elf.is_little_endian();
+    let word = if elf.is_64() { 8 } else { 4 };
+    if let Some(dynamic) = elf.program_headers.iter().find(|p| p.p_type == elf::PT_DYNAMIC) {
+        for (i, entry) in elf.dynamic.iter().enumerate() {
+            if POINTER_TAGS.contains(&entry.d_tag) {
+                let at = dynamic.p_offset as usize + i * word * 2 + word;
+                elf::write_value(bytes, at, word, unrelocate(&elf, bias, entry.d_val), le);
+            }
+        }
+    }
+    let elf = ElfFile::parse(bytes.clone()).ok()?;
+
+    let mut names = vec![0u8];
+    let mut shdrs: Vec<SectionHeader> = Vec::new();
+    let mut add = |name: &str, sh_type: u32, sh_flags: u64, sh_addr: u64, sh_size: u64, sh_link: u32, sh_entsize: u64| {
+        let sh_offset = if sh_addr == 0 { 0 } else { elf.vaddr_to_offset(sh_addr).unwrap_or(0) };
+        shdrs.push(SectionHeader{name: name.to_string(), sh_name: names.len() as u32, sh_type, sh_flags, sh_addr, sh_offset,
+                                 sh_size, sh_link, sh_info: 0, sh_addralign: 1, sh_entsize});
+        names.extend_from_slice(name.as_bytes());
+        names.push(0);
+        shdrs.len() as u32 - 1
+    };
+    add("", elf::SHT_NULL, 0, 0, 0, 0, 0);
+    for (i, p) in loads(&elf).iter().enumerate() {
+        let flags = elf::SHF_ALLOC | if p.p_flags & elf::PF_X != 0 { elf::SHF_EXECINSTR } else { 0 }
+                    | if p.p_flags & elf::PF_W != 0 { elf::SHF_WRITE } else { 0 };
+        add(&format!("load{}", i), elf::SHT_PROGBITS, flags, p.p_vaddr, p.p_filesz, 0, 0);
+    }
+    let dynamic = |tag| elf.dynamic_value(tag);
+    if let (Some(strtab), Some(symtab)) = (dynamic(elf::DT_STRTAB), dynamic(elf::DT_SYMTAB)) {
+        let entsize = if elf.is_64() { 24 } else { 16 };
+        let dynstr = add(".dynstr", elf::SHT_STRTAB, elf::SHF_ALLOC, strtab, dynamic(elf::DT_STRSZ).unwrap_or(0), 0, 0);
+        let count = dynamic_symbol_count(&elf).unwrap_or(0);
+        let dynsym = add(".dynsym", elf::SHT_DYNSYM, elf::SHF_ALLOC, symtab, count * entsize, dynstr, entsize);
+        if let Some(p) = elf.program_headers.iter().find(|p| p.p_type == elf::PT_DYNAMIC) {
+            add(".dynamic", elf::SHT_DYNAMIC, elf::SHF_ALLOC | elf::SHF_WRITE, p.p_vaddr, p.p_filesz, dynstr, word as u64 * 2);
+        }
+        let rela = |is_rela: bool| if is_rela { (elf::SHT_RELA, 3 * word as u64) } else { (elf::SHT_REL, 2 * word as u64) };
+        if let (Some(addr), Some(size)) = (dynamic(elf::DT_RELA), dynamic(elf::DT_RELASZ)) {
+            add(".rela.dyn", elf::SHT_RELA, elf::SHF_ALLOC, addr, size, dynsym, rela(true).1);
+        }
+        if let (Some(addr), Some(size)) = (dynamic(elf::DT_REL), dynamic(elf::DT_RELSZ)) {
+            add(".rel.dyn", elf::SHT_REL, elf::SHF_ALLOC, addr, size, dynsym, rela(false).1);
+        }
+        if let (Some(addr), Some(size)) = (dynamic(elf::DT_JMPREL), dynamic(elf::DT_PLTRELSZ)) {
+            let (sh_type, entsize) = rela(dynamic(elf::DT_PLTREL) == Some(elf::DT_RELA as u64));
+            let name = if sh_type == elf::SHT_RELA { ".rela.plt" } else { ".rel.plt" };
+            add(name, sh_type, elf::SHF_ALLOC, addr, size, dynsym, entsize);
+        }
+    }
+    let shstrndx = add(".shstrtab", elf::SHT_STRTAB, 0, 0, 0, 0, 0) as usize;
+    // The first global symbol is not known; index 1 keeps readers from rejecting the table.
+    if let Some(dynsym) = shdrs.iter_mut().find(|s| s.sh_type == elf::SHT_DYNSYM) {
+        dynsym.sh_info = 1;
+    }
+    shdrs[shstrndx].sh_offset = bytes.len() as u64;
+    shdrs[shstrndx].sh_size = names.len() as u64;
+    bytes.extend_from_slice(&names);
+    elf::write_section_headers(bytes, &shdrs, shstrndx);
+    Some(())
+}
+
+/// Reconstructs the image whose ELF header is at `base`.
+fn reconstruct(mem: &Memory, base: u64) -> Option<(Layout, u64, u64, Vec<u8>)> {
+    let (head, elf) = read_headers(mem, base)?;
+    let loaded = read_loaded(mem, base, &head, &elf);
+    let file = read_file(mem, base, &head, &elf);
+    let layout = match (&loaded, &file) {
+        (Some(_), None) => Layout::Loaded,
+        (None, Some(_)) => Layout::File,
+        (None, None) => return None,
+        // Both read: the one whose .dynamic makes sense wins, ld.so's layout otherwise.
+        (Some((bias, l)), Some(f)) => {
+            if !has_dynamic(l, *bias) && has_dynamic(f, 0) { Layout::File } else { Layout::Loaded }
+        }
+    };
+    let (bias, mut bytes) = match layout {
+        Layout::Loaded => loaded?,
+        Layout::File => (0, file?),
+    };
+    let end = match layout {
+        Layout::Loaded => loads(&elf).iter().map(|p| bias.wrapping_add(p.p_vaddr).wrapping_add(p.p_memsz)).max().unwrap_or(base),
+        Layout::File => base + bytes.len() as u64,
+    };
+    if ElfFile::parse(bytes.clone()).is_ok_and(|e| e.section_headers.is_empty()) {
+        add_sections(&mut bytes, bias);
+    }
+    Some((layout, bias, end, bytes))
+}
+
+/// Finds and reconstructs every ELF image mapped in process `pid`: the executable, shared
+/// libraries, deleted and memfd files, and images that exist only in anonymous memory.
+pub fn images(pid: u32) -> Result<Vec<Image>, String> {
+    let maps = read_maps(pid)?;
+    let mem_path = format!("/proc/{}/mem", pid);
+    let mem = Memory{file: File::open(&mem_path).map_err(|e| format!("{}: {}", mem_path, e))?};
+    let exe = fs::read_link(format!("/proc/{}/exe", pid)).map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+    let mut images = Vec::new();
+    for base in find_headers(&mem, &maps) {
+        let (layout, bias, end, bytes) = match reconstruct(&mem, base) {
+            Some(image) => image,
+            None => continue,
+        };
+        let mapping = maps.iter().find(|m| m.start <= base && base < m.end);
+        let path = mapping.map(|m| m.path.clone()).unwrap_or_default();
+        let main = !exe.is_empty() && path == exe && mapping.is_some_and(|m| m.start == base)
+                   && !images.iter().any(|i: &Image| i.main);
+        images.push(Image{base, end, origin: origin(&path), path, main, layout, bias, bytes});
+    }
+    Ok(images)
+}
+
+/// Opens an image of a running process for the ELF analyses. `spec` is "<pid>" for the
+/// executable, or "<pid>:<base address>" or "<pid>:<name>" for another image.
+pub fn open(spec: &str) -> Result<ElfFile, String> {
+    let (pid, select) = match spec.split_once(':') {
+        Some((pid, select)) => (pid, Some(select)),
+        None => (spec, None),
+    };
+    let pid: u32 = pid.parse().map_err(|_| format!("invalid process id {}", pid))?;
+    let images = images(pid)?;
+    let image = match select {
+        None => images.into_iter().find(|i| i.main),
+        Some(select) => match select.strip_prefix("0x").map(|hex| u64::from_str_radix(hex, 16)) {
+            Some(Ok(base)) => images.into_iter().find(|i| i.base == base),
+            _ => images.into_iter().find(|i| i.path.contains(select)),
+        },
+    };
+    let image = image.ok_or(format!("pid {}: no ELF image {}", pid, select.unwrap_or("for the executable")))?;
+    let base = image.base;
+    ElfFile::parse(image.bytes).map_err(|e| format!("pid {} image at 0x{:x}: {}", pid, base, e))
+}
+
+fn file_name(image: &Image) -> String {
+    let path = image.path.trim_end_matches(" (deleted)");
+    match Path::new(path).file_name() {
+        Some(name) if image.origin != "anonymous" => name.to_string_lossy().replace(':', "_"),
+        _ if path.starts_with('[') => path.trim_matches(|c| c == '[' || c == ']').replace([':', '/'], "_"),
+        _ => "anonymous".to_string(),
+    }
+}
+
+pub fn show_images(pid: u32, images: &[Image]) {
+    println!("ELF images in process {}:", pid);
+    println!("  {:18} {:18} {:18} {:7} {:10} {:5} {:6} Path", "Base", "End", "Bias", "Layout", "Origin", "Type", "Class");
+    for image in images.iter() {
+        let elf = ElfFile::parse(image.bytes.clone()).ok();
+        let e_type = match elf.as_ref().map(|e| e.e_type) {
+            Some(elf::ET_EXEC) => "EXEC",
+            Some(elf::ET_DYN) => "DYN",
+            Some(elf::ET_REL) => "REL",
+            Some(elf::ET_CORE) => "CORE",
+            _ => "?",
+        };
+        let class = if elf.as_ref().is_some_and(|e| e.is_64()) { "ELF64" } else { "ELF32" };
+        let layout = match image.layout {
+            Layout::Loaded => "loaded",
+            Layout::File => "file",
+        };
+        let path = if image.path.is_empty() { "[anonymous]" } else { &image.path };
+        println!("  0x{:016x} 0x{:016x} 0x{:016x} {:7} {:10} {:5} {:6} {}{}", image.base, image.end, image.bias, layout,
+                 image.origin, e_type, class, path, if image.main { "  (executable)" } else { "" });
+    }
+}
+
+/// Writes each reconstructed image to `dir` as "<base>-<name>".
+pub fn dump_images(dir: &str, images: &[Image]) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir, e))?;
+    for image in images.iter() {
+        let path = Path::new(dir).join(format!("{:x}-{}", image.base, file_name(image)));
+        fs::write(&path, &image.bytes).map_err(|e| format!("{}: {}", path.display(), e))?;
+        println!("wrote {}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,377 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use crate::dwarf;
+use crate::elf;
+use crate::elf::ElfFile;
+
+type Ranges = Vec<(u64, u64)>;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DataSource {
+    Sections,
+    Segments,
+    Symbols,
+    CompileUnits,
+}
+
+impl DataSource {
+    pub fn from_name(name: &str) -> Option<DataSource> {
+        match name {
+            "sections" => Some(DataSource::Sections),
+            "segments" => Some(DataSource::Segments),
+            "symbols" => Some(DataSource::Symbols),
+            "compileunits" => Some(DataSource::CompileUnits),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SortBy {
+    File,
+    Vm,
+}
+
+#[derive(Clone)]
+pub struct SizeRow {
+    pub label:      String,
+    pub file_size:  i64,
+    pub vm_size:    i64,
+}
+
+/// Non-overlapping address ranges, each owned by the first label that claimed it.
+struct RangeMap {
+    ranges: BTreeMap<u64, (u64, usize)>,    // start -> (end, label index)
+    labels: Vec<String>,
+    index: HashMap<String, usize>,
+}
+
+impl RangeMap {
+    fn new() -> RangeMap {
+        RangeMap{ranges: BTreeMap::new(), labels: Vec::new(), index: HashMap::new()}
+    }
+
+    fn label_index(&mut self, label: &str) -> usize {
+        if let Some(&i) = self.index.get(label) {
+            return i;
+        }
+        self.labels.push(label.to_string());
+        self.index.insert(label.to_string(), self.labels.len() - 1);
+        self.labels.len() - 1
+    }
+
+    /// Claims the parts of [start, end) not already owned by another label.
+    fn insert(&mut self, start: u64, end: u64, label: &str) {
+        if end <= start {
+            return;
+        }
+        let index = self.label_index(label);
+        let mut gaps = Vec::new();
+        let mut pos = start;
+        if let Some((_, &(prev_end, _))) = self.ranges.range(..start).next_back() {
+            pos = pos.max(prev_end);
+        }
+        for (&s, &(e, _)) in self.ranges.range(start..end) {
+            if s > pos {
+                gaps.push((pos, s));
+            }
+            pos = pos.max(e);
+        }
+        if pos < end {
+            gaps.push((pos, end));
+        }
+        for (s, e) in gaps {
+            self.ranges.insert(s, (e, index));
+        }
+    }
+
+    fn totals(&self) -> HashMap<String, u64> {
+        let mut totals = HashMap::new();
+        for (&start, &(end, index)) in self.ranges.iter() {
+            *totals.entry(self.labels[index].clone()).or_insert(0) += end - start;
+        }
+        totals
+    }
+}
+
+fn is_tbss(shdr: &elf::SectionHeader) -> bool {
+    shdr.sh_type == elf::SHT_NOBITS && shdr.sh_flags & elf::SHF_TLS != 0
+}
+
+fn segment_label(index: usize, p_flags: u32) -> String {
+    format!("LOAD #{} [{}]", index, elf::get_segment_flags_name(p_flags).replace(' ', ""))
+}
+
+/// Translates a VM range to the file ranges that back it.
+fn vm_to_file(elf: &ElfFile, start: u64, end: u64) -> Vec<(u64, u64)> {
+    let mut ranges = Vec::new();
+    for phdr in elf.program_headers.iter().filter(|p| p.p_type == elf::PT_LOAD) {
+        let s = start.max(phdr.p_vaddr);
+        let e = end.min(phdr.p_vaddr.saturating_add(phdr.p_filesz));
+        if s < e {
+            ranges.push((phdr.p_offset + (s - phdr.p_vaddr), phdr.p_offset + (e - phdr.p_vaddr)));
+        }
+    }
+    if elf.program_headers.is_empty() {
+        for shdr in elf.section_headers.iter().filter(|s| s.is_alloc() && s.sh_type != elf::SHT_NOBITS) {
+            let s = start.max(shdr.sh_addr);
+            let e = end.min(shdr.sh_addr.saturating_add(shdr.sh_size));
+            if s < e {
+                ranges.push((shdr.sh_offset + (s - shdr.sh_addr), shdr.sh_offset + (e - shdr.sh_addr)));
+            }
+        }
+    }
+    ranges
+}
+
+/// Translates a file range to the VM ranges PT_LOAD segments map it to.
+fn file_to_vm(elf: &ElfFile, start: u64, end: u64) -> Vec<(u64, u64)> {
+    let mut ranges = Vec::new();
+    for phdr in elf.program_headers.iter().filter(|p| p.p_type == elf::PT_LOAD) {
+        let s = start.max(phdr.p_offset);
+        let e = end.min(phdr.p_offset.saturating_add(phdr.p_filesz));
+        if s < e {
+            ranges.push((phdr.p_vaddr + (s - phdr.p_offset), phdr.p_vaddr + (e - phdr.p_offset)));
+        }
+    }
+    ranges
+}
+
+/// Returns the primary labels of a data source as (label, vm ranges, file ranges).
+fn source_labels(elf: &ElfFile, source: DataSource) -> Vec<(String, Ranges, Ranges)> {
+    let is_rel = elf.e_type == elf::ET_REL;
+    let mut labels = Vec::new();
+    match source {
+        DataSource::Sections => {
+            for shdr in elf.section_headers.iter().filter(|s| s.sh_type != elf::SHT_NULL) {
+                let vm = if shdr.is_alloc() && !is_tbss(shdr) && !is_rel { vec![(shdr.sh_addr, shdr.sh_addr.saturating_add(shdr.sh_size))] } else { Vec::new() };
+                let file = vec![(shdr.sh_offset, shdr.sh_offset.saturating_add(shdr.file_size()))];
+                labels.push((shdr.name.clone(), vm, file));
+            }
+        }
+        DataSource::Segments => {
+            for (i, phdr) in elf.program_headers.iter().enumerate().filter(|(_, p)| p.p_type == elf::PT_LOAD) {
+                labels.push((segment_label(i, phdr.p_flags),
+                    vec![(phdr.p_vaddr, phdr.p_vaddr.saturating_add(phdr.p_memsz))],
+                    vec![(phdr.p_offset, phdr.p_offset.saturating_add(phdr.p_filesz))]));
+            }
+        }
+        DataSource::Symbols => {
+            for sym in elf.best_symbols().iter() {
+                let st_type = sym.st_type();
+                if sym.name.is_empty() || sym.st_size == 0 || sym.section_index().is_none()
+                    || st_type == elf::STT_SECTION || st_type == elf::STT_FILE || st_type == elf::STT_TLS {
+                    continue;
+                }
+                // Thumb function addresses carry the mode in bit 0.
+                let start = if elf.e_machine == elf::EM_ARM && sym.is_function() { sym.st_value & !1 } else { sym.st_value };
+                let end = start.saturating_add(sym.st_size);
+                if is_rel {
+                    // Symbol values in relocatable objects are section offsets.
+                    if let Some(shdr) = sym.section_index().and_then(|i| elf.section_headers.get(i)) {
+                        if shdr.sh_type != elf::SHT_NOBITS {
+                            labels.push((sym.name.clone(), Vec::new(), vec![(shdr.sh_offset.saturating_add(start), shdr.sh_offset.saturating_add(end))]));
+                        }
+                    }
+                } else {
+                    labels.push((sym.name.clone(), vec![(start, end)], vm_to_file(elf, start, end)));
+                }
+            }
+        }
+        DataSource::CompileUnits => {
+            for (name, ranges) in dwarf::compile_unit_ranges(elf) {
+                let file = ranges.iter().flat_map(|&(s, e)| vm_to_file(elf, s, e)).collect();
+                labels.push((name, ranges, file));
+            }
+        }
+    }
+    labels
+}
+
+/// Attributes every file byte and every VM byte of `elf` to a label of `source`.
+/// Bytes not covered by the source fall back to their section, segment or header.
+pub fn size_report(elf: &ElfFile, source: DataSource) -> Vec<SizeRow> {
+    let mut vm_map = RangeMap::new();
+    let mut file_map = RangeMap::new();
+    // The headers usually lie inside the first PT_LOAD; claim them before anything else does.
+    let phdr_size = elf.e_phentsize as u64 * elf.program_headers.len() as u64;
+    let shdr_size = elf.e_shentsize as u64 * elf.section_headers.len() as u64;
+    let headers = [(0, elf.e_ehsize as u64), (elf.e_phoff, elf.e_phoff.saturating_add(phdr_size)),
+                   (elf.e_shoff, elf.e_shoff.saturating_add(shdr_size))];
+    for &(s, e) in headers.iter() {
+        file_map.insert(s, e, "[ELF Headers]");
+        for (vs, ve) in file_to_vm(elf, s, e) {
+            vm_map.insert(vs, ve, "[ELF Headers]");
+        }
+    }
+    for (label, vm, file) in source_labels(elf, source) {
+        for (s, e) in vm {
+            vm_map.insert(s, e, &label);
+        }
+        for (s, e) in file {
+            file_map.insert(s, e, &label);
+        }
+    }
+
+    if source != DataSource::Sections {
+        for shdr in elf.section_headers.iter().filter(|s| s.sh_type != elf::SHT_NULL) {
+            let label = format!("[section {}]", shdr.name);
+            // .tbss occupies no VM of its own; it overlaps the sections after it.
+            if shdr.is_alloc() && !is_tbss(shdr) && elf.e_type != elf::ET_REL {
+                vm_map.insert(shdr.sh_addr, shdr.sh_addr.saturating_add(shdr.sh_size), &label);
+            }
+            file_map.insert(shdr.sh_offset, shdr.sh_offset.saturating_add(shdr.file_size()), &label);
+        }
+    }
+    for (i, phdr) in elf.program_headers.iter().enumerate().filter(|(_, p)| p.p_type == elf::PT_LOAD) {
+        let label = if source == DataSource::Segments {
+            segment_label(i, phdr.p_flags)
+        } else {
+            format!("[LOAD #{} {}]", i, elf::get_segment_flags_name(phdr.p_flags).replace(' ', ""))
+        };
+        vm_map.insert(phdr.p_vaddr, phdr.p_vaddr.saturating_add(phdr.p_memsz), &label);
+        file_map.insert(phdr.p_offset, phdr.p_offset.saturating_add(phdr.p_filesz), &label);
+    }
+
+    file_map.insert(0, elf.bytes().len() as u64, "[Unmapped]");
+
+    let vm_totals = vm_map.totals();
+    let file_totals = file_map.totals();
+    let mut rows: Vec<SizeRow> = Vec::new();
+    let mut seen = HashMap::new();
+    for label in file_map.labels.iter().chain(vm_map.labels.iter()) {
+        if seen.insert(label, ()).is_some() {
+            continue;
+        }
+        let file_size = *file_totals.get(label).unwrap_or(&0) as i64;
+        let vm_size = *vm_totals.get(label).unwrap_or(&0) as i64;
+        if file_size != 0 || vm_size != 0 {
+            rows.push(SizeRow{label: label.clone(), file_size, vm_size});
+        }
+    }
+    rows
+}
+
+/// Returns rows holding `new - old` for every label present in either report.
+pub fn diff_rows(old: &[SizeRow], new: &[SizeRow]) -> Vec<SizeRow> {
+    let mut rows: Vec<SizeRow> = new.to_vec();
+    let index: HashMap<String, usize> = rows.iter().enumerate().map(|(i, r)| (r.label.clone(), i)).collect();
+    for row in old.iter() {
+        match index.get(&row.label) {
+            Some(&i) => {
+                rows[i].file_size -= row.file_size;
+                rows[i].vm_size -= row.vm_size;
+            }
+            None => rows.push(SizeRow{label: row.label.clone(), file_size: -row.file_size, vm_size: -row.vm_size}),
+        }
+    }
+    rows.retain(|r| r.file_size != 0 || r.vm_size != 0);
+    rows
+}
+
+pub fn sort_rows(rows: &mut [SizeRow], sort: SortBy) {
+    rows.sort_by(|a, b| {
+        let (ka, kb) = match sort {
+            SortBy::File => ((a.file_size.abs(), a.vm_size.abs()), (b.file_size.abs(), b.vm_size.abs())),
+            SortBy::Vm => ((a.vm_size.abs(), a.file_size.abs()), (b.vm_size.abs(), b.file_size.abs())),
+        };
+        kb.cmp(&ka).then(a.label.cmp(&b.label))
+    });
+}
+
+fn human_size(size: i64, signed: bool) -> String {
+    let sign = if size < 0 { "-" } else if signed { "+" } else { "" };
+    let abs = size.unsigned_abs() as f64;
+    if abs < 1024.0 {
+        format!("{}{}", sign, size.unsigned_abs())
+    } else if abs < 1024.0 * 1024.0 {
+        format!("{}{:.2}Ki", sign, abs / 1024.0)
+    } else if abs < 1024.0 * 1024.0 * 1024.0 {
+        format!("{}{:.2}Mi", sign, abs / (1024.0 * 1024.0))
+    } else {
+        format!("{}{:.2}Gi", sign, abs / (1024.0 * 1024.0 * 1024.0))
+    }
+}
+
+fn percent(part: i64, whole: i64, signed: bool) -> String {
+    if whole == 0 {
+        return if part == 0 { "0.0%".to_string() } else { "[NEW]".to_string() };
+    }
+    let p = part as f64 * 100.0 / whole as f64;
+    if signed { format!("{:+.1}%", p) } else { format!("{:.1}%", p) }
+}
+
+/// Prints the `limit` largest rows, folding the rest into an "[N Others]" row.
+/// With `base`, `rows` are deltas and percentages are relative to the base sizes.
+pub fn show_size_report(rows: &[SizeRow], limit: usize, base: Option<&[SizeRow]>) {
+    let signed = base.is_some();
+    let base_sizes: HashMap<&str, (i64, i64)> = base.unwrap_or(&[]).iter()
+        .map(|r| (r.label.as_str(), (r.file_size, r.vm_size)))
+        .collect();
+    let base_size = |labels: &[SizeRow]| -> (i64, i64) {
+        labels.iter().filter_map(|l| base_sizes.get(l.label.as_str())).fold((0, 0), |sum, s| (sum.0 + s.0, sum.1 + s.1))
+    };
+
+    let mut shown: Vec<(SizeRow, (i64, i64))> = rows.iter().take(limit)
+        .map(|r| (r.clone(), base_size(std::slice::from_ref(r))))
+        .collect();
+    if rows.len() > limit {
+        let others = &rows[limit..];
+        shown.push((SizeRow {
+            label: format!("[{} Others]", others.len()),
+            file_size: others.iter().map(|r| r.file_size).sum(),
+            vm_size: others.iter().map(|r| r.vm_size).sum(),
+        }, base_size(others)));
+    }
+    let total = SizeRow {
+        label: "TOTAL".to_string(),
+        file_size: rows.iter().map(|r| r.file_size).sum(),
+        vm_size: rows.iter().map(|r| r.vm_size).sum(),
+    };
+    let total_base = match base {
+        Some(base) => (base.iter().map(|r| r.file_size).sum(), base.iter().map(|r| r.vm_size).sum()),
+        None => (0, 0),
+    };
+
+    println!("     FILE SIZE          VM SIZE    ");
+    println!(" ---------------- ---------------- ");
+    for (row, (base_file, base_vm)) in shown.iter().chain(std::iter::once(&(total.clone(), total_base))) {
+        let (file_pct, vm_pct) = if signed {
+            (percent(row.file_size, *base_file, true), percent(row.vm_size, *base_vm, true))
+        } else {
+            (percent(row.file_size, total.file_size, false), percent(row.vm_size, total.vm_size, false))
+        };
+        println!(" {:>8} {:>7} {:>8} {:>7}    {}", file_pct, human_size(row.file_size, signed),
+            vm_pct, human_size(row.vm_size, signed), row.label);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testelf::Builder;
+
+    #[test]
+    fn thumb_function_starts_at_even_address() {
+        let mut b = Builder::new(elf::EM_ARM);
+        let text = b.section(".text", elf::SHT_PROGBITS, elf::SHF_ALLOC | elf::SHF_EXECINSTR, 0x8000,
+                             &[0x00, 0x20, 0x70, 0x47]);
+        let strtab = b.section(".strtab", elf::SHT_STRTAB, 0, 0, b"\0f\0");
+        let mut sym = vec![0u8; 32];
+        sym[16] = 1;                                    // st_name "f"
+        sym[20..24].copy_from_slice(&0x8001u32.to_le_bytes());
+        sym[24] = 4;                                    // st_size
+        sym[28] = 0x12;                                 // STB_GLOBAL, STT_FUNC
+        sym[30..32].copy_from_slice(&(text as u16).to_le_bytes());
+        let symtab = b.section(".symtab", elf::SHT_SYMTAB, 0, 0, &sym);
+        b.sections[symtab - 1].sh_link = strtab as u32;
+        b.load(text, text, elf::PF_R | elf::PF_X);
+        let elf = ElfFile::parse(b.build()).unwrap();
+
+        let rows = size_report(&elf, DataSource::Symbols);
+        let vm = |label: &str| rows.iter().find(|r| r.label == label).map_or(0, |r| r.vm_size);
+        assert_eq!(vm("f"), 4);
+        assert_eq!(vm("[section .text]"), 0);
+    }
+}
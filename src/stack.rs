@@ -0,0 +1,630 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
+use crate::callgraph::CallGraph;
+use crate::cfg::Function;
+use crate::disasm::Flow;
+use crate::disasm::Instruction;
+use crate::dwarf;
+use crate::elf;
+use crate::elf::ElfFile;
+
+/// Sections holding a Cortex-M vector table: initial SP, reset, then exception handlers.
+const ARM_VECTOR_SECTIONS: [&str; 4] = [".isr_vector", ".vectors", ".vector_table", ".intvec"];
+/// RX relocatable vector table: one handler per entry.
+const RX_VECTOR_SECTION: &str = ".rvectors";
+
+// Interrupt handler naming conventions (CMSIS, AVR, vendor SDKs)
+const HANDLER_SUFFIXES: [&str; 4] = ["_IRQHandler", "_Handler", "_isr", "_ISR"];
+const HANDLER_PREFIXES: [&str; 2] = ["__vector_", "isr_"];
+const HANDLER_NAMES: [&str; 4] = ["trap_handler", "handle_trap", "irq_handler", "trap_entry"];
+const RESET_HANDLER: &str = "Reset_Handler";
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Source {
+    StackSizes,     // LLVM .stack_sizes
+    Prologue,       // estimated from the stack pointer updates in the code
+    Unknown,        // imported or undecodable
+}
+
+pub fn get_source_name(source: Source) -> &'static str {
+    match source {
+        Source::StackSizes => ".stack_sizes",
+        Source::Prologue => "prologue",
+        Source::Unknown => "unknown",
+    }
+}
+
+pub struct CallSite {
+    pub depth:  u64,    // stack in use by the caller at the call
+    pub callee: usize,
+    pub tail:   bool,   // jump that reuses the caller's return address
+}
+
+pub struct Frame {
+    pub size:       u64,
+    pub source:     Source,
+    pub dynamic:    bool,   // alloca or variable-length arrays
+    pub indirect:   bool,   // calls through a register or memory
+    pub calls:      Vec<CallSite>,
+}
+
+/// Worst-case stack depth of a function and everything it calls.
+#[derive(Clone)]
+pub struct Depth {
+    pub bytes:      u64,
+    pub path:       Vec<usize>,     // deepest call chain, starting at the function
+    pub recursive:  bool,
+    pub dynamic:    bool,
+    pub incomplete: bool,           // indirect calls or functions of unknown frame size
+}
+
+/// How an instruction moves the stack pointer. `fp` is the frame pointer.
+enum SpChange {
+    Add(i64),       // the stack grows by this many bytes
+    SaveFp(i64),    // fp = sp + n
+    FromFp(i64),    // sp = fp + n
+    Dynamic,        // sp moves by a register amount
+}
+
+/// Addresses the relocations against section `index` store, by offset in the section:
+/// symbol value plus addend (SHT_RELA) or plus the value already in place (SHT_REL).
+fn relocated_addresses(elf: &ElfFile, index: usize) -> HashMap<u64, (u64, bool)> {
+    let mut addrs = HashMap::new();
+    for shdr in elf.section_headers.iter() {
+        if (shdr.sh_type != elf::SHT_RELA && shdr.sh_type != elf::SHT_REL) || shdr.sh_info as usize != index {
+            continue;
+        }
+        for reloc in elf.relocations(shdr) {
+            if let Some(sym) = elf.symbols.get(reloc.r_sym as usize) {
+                let rela = shdr.sh_type == elf::SHT_RELA;
+                addrs.insert(reloc.r_offset, (sym.st_value.wrapping_add(reloc.r_addend as u64), rela));
+            }
+        }
+    }
+    addrs
+}
+
+/// .stack_sizes: (function address, ULEB128 frame size) pairs, per function section in
+/// object files and merged in linked ones. In object files the addresses are relocations
+/// against the function or its section, applied here.
+pub fn read_stack_sizes(elf: &ElfFile) -> HashMap<u64, u64> {
+    let mut sizes = HashMap::new();
+    let pointer = if elf.is_64() { 8 } else { 4 };
+    let arm = elf.e_machine == elf::EM_ARM;
+    for (index, shdr) in elf.section_headers.iter().enumerate().filter(|(_, s)| s.name == ".stack_sizes") {
+        let relocated = if elf.e_type == elf::ET_REL { relocated_addresses(elf, index) } else { HashMap::new() };
+        let mut c = dwarf::Cursor::new(elf.section_data(shdr), 0, elf.is_little_endian());
+        while !c.is_empty() {
+            let offset = c.pos as u64;
+            let (addr, size) = match (c.sized(pointer), c.uleb()) {
+                (Some(addr), Some(size)) => (addr, size),
+                _ => break,
+            };
+            let addr = match relocated.get(&offset) {
+                Some(&(target, true)) => target,
+                Some(&(target, false)) => target.wrapping_add(addr),
+                None => addr,
+            };
+            sizes.insert(if arm { addr & !1 } else { addr }, size);
+        }
+    }
+    sizes
+}
+
+/// Parses "#-16", "0x10", "-32" or "1024" and an optional ARM listing comment.
+fn immediate(operand: &str) -> Option<i64> {
+    let operand = operand.split('@').next()?.trim().trim_start_matches('#');
+    let (negative, digits) = match operand.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, operand),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()? as i64,
+        None => digits.parse::<i64>().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Bytes pushed by an ARM register list such as "{r4, r7, lr}" or "{d8-d9}".
+fn register_list_size(operands: &str) -> i64 {
+    let list = match (operands.find('{'), operands.find('}')) {
+        (Some(open), Some(close)) if open < close => &operands[open + 1..close],
+        _ => return 0,
+    };
+    list.split(',').map(|r| {
+        let r = r.trim();
+        let size = if r.starts_with('d') { 8 } else { 4 };
+        let count = match r.split_once('-') {
+            Some((first, last)) => {
+                let n = |s: &str| s.trim_start_matches(|c: char| c.is_ascii_alphabetic()).parse::<i64>().unwrap_or(0);
+                n(last) - n(first) + 1
+            }
+            None => 1,
+        };
+        size * count
+    }).sum()
+}
+
+/// Bytes an x86 push mnemonic stores: "pushw" and "pushfw" a 16-bit word, "pusha" all eight
+/// general registers.
+fn x86_push_size(m: &str, word: i64) -> Option<i64> {
+    match m {
+        "push" | "pushq" | "pushl" | "pushf" | "pushfq" | "pushfd" | "pushfl" => Some(word),
+        "pushw" | "pushfw" => Some(2),
+        "pusha" | "pushad" | "pushal" => Some(8 * word),
+        "pushaw" => Some(16),
+        _ => None,
+    }
+}
+
+fn x86_sp_change(insn: &Instruction, word: i64) -> Option<SpChange> {
+    let m = insn.mnemonic.as_str();
+    let operands: Vec<&str> = insn.operands.split(',').map(|o| o.trim()).collect();
+    let sp = ["rsp", "esp"].contains(&operands[0]);
+    let source = operands.get(1).cloned().unwrap_or("");
+    let fp_source = ["rbp", "ebp"].contains(&source);
+    if let Some(n) = x86_push_size(m, word) {
+        return Some(SpChange::Add(n));
+    }
+    // Each pop undoes the push of the same name: "popfq" for "pushfq", and so on.
+    if let Some(n) = m.strip_prefix("pop").and_then(|rest| x86_push_size(&format!("push{}", rest), word)) {
+        return Some(SpChange::Add(-n));
+    }
+    match m {
+        "sub" if sp => Some(immediate(source).map_or(SpChange::Dynamic, SpChange::Add)),
+        "add" if sp => immediate(source).map(|n| SpChange::Add(-n)),
+        // Realignment can add up to the alignment less the word a call already aligned to.
+        "and" if sp => immediate(source).map(|mask| SpChange::Add(((mask.wrapping_neg() as u64) as i64 - word).max(0))),
+        "mov" if ["rbp", "ebp"].contains(&operands[0]) && ["rsp", "esp"].contains(&source) => Some(SpChange::SaveFp(0)),
+        "mov" if sp && fp_source => Some(SpChange::FromFp(0)),
+        "lea" if sp && (source.contains("[rbp") || source.contains("[ebp")) => {
+            let offset = source.trim_end_matches(']').split(['+', '-']).nth(1).and_then(immediate).unwrap_or(0);
+            Some(SpChange::FromFp(if source.contains('-') { -offset } else { offset }))
+        }
+        "leave" => Some(SpChange::FromFp(word)),
+        "enter" => immediate(operands[0]).map(|n| SpChange::Add(word + n)),
+        _ => None,
+    }
+}
+
+fn aarch64_sp_change(insn: &Instruction) -> Option<SpChange> {
+    let m = insn.mnemonic.as_str();
+    let operands: Vec<&str> = insn.operands.split(", ").map(|o| o.trim()).collect();
+    // Pre-indexed stores "[sp, #-16]!" and post-indexed loads "[sp], #16" move sp.
+    if m.starts_with("st") && insn.operands.contains("[sp, #-") && insn.operands.ends_with('!') {
+        let offset = insn.operands.rsplit("#").next()?.trim_end_matches(['!', ']']);
+        return immediate(offset).map(|n| SpChange::Add(-n));
+    }
+    if m.starts_with("ld") && insn.operands.contains("[sp], #") {
+        return immediate(operands.last()?).map(|n| SpChange::Add(-n));
+    }
+    let shift = if insn.operands.ends_with("lsl #12") { 12 } else { 0 };
+    match (m, operands.as_slice()) {
+        ("sub", ["sp", "sp", n, ..]) => Some(immediate(n).map_or(SpChange::Dynamic, |n| SpChange::Add(n << shift))),
+        ("add", ["sp", "sp", n, ..]) => immediate(n).map(|n| SpChange::Add(-(n << shift))),
+        ("mov", ["x29", "sp"]) => Some(SpChange::SaveFp(0)),
+        ("add", ["x29", "sp", n]) => immediate(n).map(SpChange::SaveFp),
+        ("mov", ["sp", "x29"]) => Some(SpChange::FromFp(0)),
+        ("sub", ["sp", "x29", n]) => immediate(n).map(|n| SpChange::FromFp(-n)),
+        ("add", ["sp", "x29", n]) => immediate(n).map(SpChange::FromFp),
+        _ => None,
+    }
+}
+
+fn riscv_sp_change(insn: &Instruction) -> Option<SpChange> {
+    let operands: Vec<&str> = insn.operands.split(',').map(|o| o.trim()).collect();
+    match (insn.mnemonic.as_str(), operands.as_slice()) {
+        ("addi", ["sp", "sp", n]) => immediate(n).map(|n| SpChange::Add(-n)),
+        ("sub", ["sp", "sp", _]) => Some(SpChange::Dynamic),
+        ("addi", ["s0", "sp", n]) => immediate(n).map(SpChange::SaveFp),
+        ("mv", ["s0", "sp"]) => Some(SpChange::SaveFp(0)),
+        ("addi", ["sp", "s0", n]) => immediate(n).map(SpChange::FromFp),
+        ("mv", ["sp", "s0"]) => Some(SpChange::FromFp(0)),
+        _ => None,
+    }
+}
+
+fn arm_sp_change(insn: &Instruction) -> Option<SpChange> {
+    let m = insn.mnemonic.trim_end_matches(".w");
+    let text = insn.operands.split('@').next().unwrap_or("").trim();
+    let operands: Vec<&str> = text.split(", ").map(|o| o.trim()).collect();
+    let fp = |r: &str| r == "r7" || r == "fp" || r == "r11";
+    match (m, operands.as_slice()) {
+        ("push" | "vpush", _) => Some(SpChange::Add(register_list_size(text))),
+        ("pop" | "vpop", _) => Some(SpChange::Add(-register_list_size(text))),
+        ("stmdb" | "stmfd", ["sp!", ..]) => Some(SpChange::Add(register_list_size(text))),
+        ("ldm" | "ldmia" | "ldmfd", ["sp!", ..]) => Some(SpChange::Add(-register_list_size(text))),
+        ("sub" | "subw", ["sp", n]) | ("sub" | "subw", ["sp", "sp", n]) => {
+            Some(immediate(n).map_or(SpChange::Dynamic, SpChange::Add))
+        }
+        ("add" | "addw", ["sp", n]) | ("add" | "addw", ["sp", "sp", n]) => immediate(n).map(|n| SpChange::Add(-n)),
+        ("mov", [r, "sp"]) if fp(r) => Some(SpChange::SaveFp(0)),
+        ("add", [r, "sp", n]) if fp(r) => immediate(n).map(SpChange::SaveFp),
+        ("mov", ["sp", r]) if fp(r) => Some(SpChange::FromFp(0)),
+        ("sub", ["sp", r, n]) if fp(r) => immediate(n).map(|n| SpChange::FromFp(-n)),
+        _ => None,
+    }
+}
+
+fn sp_change(elf: &ElfFile, insn: &Instruction) -> Option<SpChange> {
+    match elf.e_machine {
+        elf::EM_X86_64 => x86_sp_change(insn, 8),
+        elf::EM_386 => x86_sp_change(insn, 4),
+        elf::EM_AARCH64 => aarch64_sp_change(insn),
+        elf::EM_RISCV => riscv_sp_change(insn),
+        elf::EM_ARM => arm_sp_change(insn),
+        _ => None,
+    }
+}
+
+/// Bytes a call instruction itself pushes: the return address on x86; other machines pass it
+/// in a link register the callee saves in its own frame.
+fn call_size(elf: &ElfFile) -> u64 {
+    match elf.e_machine {
+        elf::EM_X86_64 => 8,
+        elf::EM_386 => 4,
+        _ => 0,
+    }
+}
+
+/// Bytes below rsp (false) or rbp (true) an x86-64 memory operand reaches. Leaf functions
+/// keep their locals in the 128-byte red zone below rsp without moving it.
+fn red_zone_use(insn: &Instruction) -> Option<(bool, i64)> {
+    let open = insn.operands.find('[')?;
+    let close = open + insn.operands[open..].find(']')?;
+    let address = &insn.operands[open + 1..close];
+    let fp = match address.get(..3) {
+        Some("rsp") => false,
+        Some("rbp") => true,
+        _ => return None,
+    };
+    let offset = address.rfind("-0x")?;
+    Some((fp, i64::from_str_radix(&address[offset + 3..], 16).ok()?))
+}
+
+/// Follows the stack pointer through the blocks of a function from its entry. Returns the
+/// frame size, the stack in use at each call and tail jump (by instruction address), and
+/// whether the stack pointer moves by a register amount.
+fn walk(elf: &ElfFile, f: &Function) -> (u64, HashMap<u64, u64>, bool) {
+    let index: HashMap<u64, usize> = f.blocks.iter().enumerate().map(|(i, b)| (b.start, i)).collect();
+    let tail_jumps: HashSet<u64> = f.tail_jumps.iter().map(|(from, _)| *from).collect();
+    let mut seen = HashSet::new();
+    let mut queue: VecDeque<(usize, i64, Option<i64>)> = VecDeque::new();
+    let mut max = 0;
+    let mut calls = HashMap::new();
+    let mut dynamic = false;
+    if let Some(&entry) = index.get(&f.start) {
+        queue.push_back((entry, 0, None));
+        seen.insert(entry);
+    }
+    while let Some((i, mut depth, mut fp)) = queue.pop_front() {
+        let block = &f.blocks[i];
+        for insn in block.insns.iter() {
+            let is_call = matches!(insn.flow, Flow::Call(_) | Flow::IndirectCall);
+            if is_call || tail_jumps.contains(&insn.addr) {
+                calls.insert(insn.addr, depth.max(0) as u64);
+            }
+            match sp_change(elf, insn) {
+                Some(SpChange::Add(n)) => depth += n,
+                Some(SpChange::SaveFp(n)) => fp = Some(depth - n),
+                Some(SpChange::FromFp(n)) => depth = fp.map_or(depth, |fp| fp - n),
+                Some(SpChange::Dynamic) => dynamic = true,
+                None => {}
+            }
+            max = max.max(depth);
+            if elf.e_machine == elf::EM_X86_64 {
+                match red_zone_use(insn) {
+                    Some((false, n)) => max = max.max(depth + n),
+                    Some((true, n)) => max = max.max(fp.map_or(0, |fp| fp + n)),
+                    None => {}
+                }
+            }
+        }
+        for (succ, _) in block.succs.iter() {
+            if let Some(&s) = index.get(succ) {
+                if seen.insert(s) {
+                    queue.push_back((s, depth, fp));
+                }
+            }
+        }
+    }
+    (max.max(0) as u64, calls, dynamic)
+}
+
+/// The frame of every call graph node, from .stack_sizes where it lists the function and
+/// from its code otherwise.
+pub fn frames(elf: &ElfFile, functions: &[Function], graph: &CallGraph) -> Vec<Frame> {
+    let stack_sizes = read_stack_sizes(elf);
+    let nodes: HashMap<u64, usize> = graph.nodes.iter().enumerate().map(|(i, n)| (n.addr, i)).collect();
+    let arm = elf.e_machine == elf::EM_ARM;
+    let mut frames: Vec<Frame> = graph.nodes.iter()
+        .map(|_| Frame{size: 0, source: Source::Unknown, dynamic: false, indirect: false, calls: Vec::new()})
+        .collect();
+    for f in functions.iter() {
+        let node = match nodes.get(&f.start) {
+            Some(&node) => node,
+            None => continue,
+        };
+        let (size, depths, dynamic) = walk(elf, f);
+        let recorded = stack_sizes.get(&f.start).cloned();
+        let frame = &mut frames[node];
+        frame.size = recorded.unwrap_or(size);
+        frame.source = if recorded.is_some() { Source::StackSizes } else { Source::Prologue };
+        frame.dynamic = dynamic;
+        for insn in f.blocks.iter().flat_map(|b| b.insns.iter()) {
+            let depth = recorded.unwrap_or_else(|| depths.get(&insn.addr).cloned().unwrap_or(0));
+            match insn.flow {
+                Flow::Call(t) => match nodes.get(&if arm { t & !1 } else { t }) {
+                    Some(&callee) => frame.calls.push(CallSite{depth, callee, tail: false}),
+                    None => frame.indirect = true,
+                },
+                Flow::IndirectCall => frame.indirect = true,
+                _ => {}
+            }
+        }
+        for &(from, t) in f.tail_jumps.iter() {
+            if let Some(&callee) = nodes.get(&if arm { t & !1 } else { t }) {
+                let depth = if recorded.is_some() { 0 } else { depths.get(&from).cloned().unwrap_or(0) };
+                frame.calls.push(CallSite{depth, callee, tail: true});
+            }
+        }
+    }
+    frames
+}
+
+/// Computes worst-case depths over the call graph, memoized per node. Calls back into a
+/// function already on the chain are recursion and contribute nothing.
+pub struct Analysis<'a> {
+    frames:     &'a [Frame],
+    call_size:  u64,
+    memo:       HashMap<usize, Depth>,
+    active:     HashSet<usize>,
+}
+
+impl<'a> Analysis<'a> {
+    pub fn new(elf: &ElfFile, frames: &'a [Frame]) -> Analysis<'a> {
+        Analysis{frames, call_size: call_size(elf), memo: HashMap::new(), active: HashSet::new()}
+    }
+
+    pub fn depth(&mut self, node: usize) -> Depth {
+        if let Some(depth) = self.memo.get(&node) {
+            return depth.clone();
+        }
+        let frame = &self.frames[node];
+        let mut worst = Depth{bytes: frame.size, path: vec![node], recursive: false, dynamic: frame.dynamic,
+                              incomplete: frame.indirect || frame.source == Source::Unknown};
+        self.active.insert(node);
+        for call in frame.calls.iter() {
+            if self.active.contains(&call.callee) {
+                worst.recursive = true;
+                continue;
+            }
+            let callee = self.depth(call.callee);
+            worst.recursive |= callee.recursive;
+            worst.dynamic |= callee.dynamic;
+            worst.incomplete |= callee.incomplete;
+            let bytes = call.depth + if call.tail { 0 } else { self.call_size } + callee.bytes;
+            if bytes > worst.bytes || (bytes == worst.bytes && worst.path.len() == 1) {
+                worst.bytes = bytes;
+                worst.path = std::iter::once(node).chain(callee.path).collect();
+            }
+        }
+        self.active.remove(&node);
+        self.memo.insert(node, worst.clone());
+        worst
+    }
+}
+
+fn is_handler_name(name: &str) -> bool {
+    name != RESET_HANDLER && (HANDLER_SUFFIXES.iter().any(|s| name.ends_with(s))
+        || HANDLER_PREFIXES.iter().any(|p| name.starts_with(p)) || HANDLER_NAMES.contains(&name))
+}
+
+/// Interrupt entry points: handlers in an ARM or RX vector table, and functions named
+/// like handlers.
+pub fn interrupt_entries(elf: &ElfFile, graph: &CallGraph) -> Vec<usize> {
+    let mut addrs = Vec::new();
+    let tables: Vec<(&elf::SectionHeader, usize)> = match elf.e_machine {
+        elf::EM_ARM => elf.section_headers.iter().filter(|s| ARM_VECTOR_SECTIONS.contains(&s.name.as_str())).map(|s| (s, 2)).collect(),
+        elf::EM_RX => elf.section_by_name(RX_VECTOR_SECTION).map(|s| (s, 0)).into_iter().collect(),
+        _ => Vec::new(),
+    };
+    for (shdr, skip) in tables {
+        let mut c = dwarf::Cursor::new(elf.section_data(shdr), 0, elf.is_little_endian());
+        let mut i = 0;
+        while let Some(addr) = c.u32() {
+            if i >= skip && addr != 0 {
+                addrs.push(addr as u64 & !1);
+            }
+            i += 1;
+        }
+    }
+    let mut entries: Vec<usize> = Vec::new();
+    for (i, node) in graph.nodes.iter().enumerate() {
+        if (addrs.contains(&node.addr) || is_handler_name(&node.name)) && node.name != RESET_HANDLER && !node.import {
+            entries.push(i);
+        }
+    }
+    entries
+}
+
+fn format_depth(depth: &Depth) -> String {
+    let mut notes = Vec::new();
+    if depth.recursive {
+        notes.push("recursion");
+    }
+    if depth.dynamic {
+        notes.push("dynamic allocation");
+    }
+    if depth.incomplete {
+        notes.push("indirect or unknown calls");
+    }
+    if notes.is_empty() {
+        format!("{}", depth.bytes)
+    } else {
+        format!("{}+ ({})", depth.bytes, notes.join(", "))
+    }
+}
+
+pub fn show_frames(graph: &CallGraph, frames: &[Frame]) {
+    println!("  {:<16}  {:>8}  {:<12}  Function", "Address", "Frame", "Source");
+    for (i, frame) in frames.iter().enumerate().filter(|(i, _)| !graph.nodes[*i].import) {
+        let size = if frame.dynamic { format!("{}+", frame.size) } else { frame.size.to_string() };
+        println!("  {:016x}  {:>8}  {:<12}  {}", graph.nodes[i].addr, size, get_source_name(frame.source), graph.nodes[i].name);
+    }
+}
+
+/// Prints the worst case from each root and, with interrupts, the totals when the deepest
+/// one or all of them nest on top of main.
+pub fn show_depths(graph: &CallGraph, analysis: &mut Analysis, main: Option<usize>, roots: &[usize], interrupts: &[usize]) {
+    println!("Worst-case stack depth (bytes):");
+    let all: Vec<(usize, &str)> = main.iter().map(|&m| (m, "main"))
+        .chain(roots.iter().map(|&r| (r, "root")))
+        .chain(interrupts.iter().map(|&r| (r, "interrupt")))
+        .collect();
+    let width = all.iter().map(|(n, _)| graph.nodes[*n].name.len()).max().unwrap_or(0);
+    for (node, kind) in all.iter() {
+        let depth = analysis.depth(*node);
+        let path: Vec<&str> = depth.path.iter().map(|&n| graph.nodes[n].name.as_str()).collect();
+        println!("  {:<9}  {:<width$}  {}", kind, graph.nodes[*node].name, format_depth(&depth));
+        println!("  {:<9}  {:<width$}  {}", "", "", path.join(" -> "));
+    }
+    if let (Some(main), false) = (main, interrupts.is_empty()) {
+        let base = analysis.depth(main).bytes;
+        let handlers: Vec<u64> = interrupts.iter().map(|&n| analysis.depth(n).bytes).collect();
+        println!();
+        println!("main + deepest interrupt:      {}", base + handlers.iter().max().unwrap_or(&0));
+        println!("main + all interrupts nested:  {}", base + handlers.iter().sum::<u64>());
+        println!("(excluding exception frames the hardware pushes on interrupt entry)");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cfg::Block;
+    use crate::disasm::Decoder;
+    use crate::disasm::Syntax;
+
+    /// A header-only little-endian ELF file for `machine`; `entry` 1 selects Thumb on ARM.
+    fn header(machine: u16, is_64: bool, entry: u64) -> ElfFile {
+        let mut bytes = vec![0x7f, b'E', b'L', b'F', if is_64 { 2 } else { 1 }, 1, 1];
+        bytes.resize(if is_64 { 64 } else { 52 }, 0);
+        bytes[16..18].copy_from_slice(&elf::ET_EXEC.to_le_bytes());
+        bytes[18..20].copy_from_slice(&machine.to_le_bytes());
+        bytes[20..24].copy_from_slice(&1u32.to_le_bytes());
+        if is_64 {
+            bytes[24..32].copy_from_slice(&entry.to_le_bytes());
+            bytes[52..54].copy_from_slice(&64u16.to_le_bytes());
+        } else {
+            bytes[24..28].copy_from_slice(&(entry as u32).to_le_bytes());
+            bytes[40..42].copy_from_slice(&52u16.to_le_bytes());
+        }
+        ElfFile::parse(bytes).unwrap()
+    }
+
+    /// A single-block function decoded from `code` at address 0.
+    fn function(elf: &ElfFile, code: &[u8]) -> Function {
+        let mut decoder = Decoder::for_elf(elf, Syntax::Intel).unwrap();
+        let mut insns = Vec::new();
+        let mut pos = 0;
+        while pos < code.len() {
+            let insn = decoder.decode(&code[pos..], pos as u64).unwrap();
+            pos += insn.len;
+            insns.push(insn);
+        }
+        let block = Block{start: 0, end: code.len() as u64, insns, succs: Vec::new()};
+        Function{name: "f".to_string(), start: 0, end: code.len() as u64, blocks: vec![block], tail_jumps: Vec::new()}
+    }
+
+    fn frame_size(elf: &ElfFile, code: &[u8]) -> u64 {
+        walk(elf, &function(elf, code)).0
+    }
+
+    #[test]
+    fn x86_64_frame_pointer_prologue() {
+        // push rbp; mov rbp,rsp; sub rsp,0x20; leave; ret
+        let elf = header(elf::EM_X86_64, true, 0);
+        assert_eq!(frame_size(&elf, &[0x55, 0x48, 0x89, 0xe5, 0x48, 0x83, 0xec, 0x20, 0xc9, 0xc3]), 40);
+    }
+
+    #[test]
+    fn x86_64_popcnt_is_not_a_pop() {
+        // popcnt rax,rdi; push rbx; sub rsp,0x10; add rsp,0x10; pop rbx; ret
+        let elf = header(elf::EM_X86_64, true, 0);
+        let code = [0xf3, 0x48, 0x0f, 0xb8, 0xc7, 0x53, 0x48, 0x83, 0xec, 0x10, 0x48, 0x83, 0xc4, 0x10, 0x5b, 0xc3];
+        assert_eq!(frame_size(&elf, &code), 24);
+    }
+
+    #[test]
+    fn x86_64_pushfq() {
+        // push rbp; pushfq; popfq; pop rbp; ret
+        let elf = header(elf::EM_X86_64, true, 0);
+        assert_eq!(frame_size(&elf, &[0x55, 0x9c, 0x9d, 0x5d, 0xc3]), 16);
+    }
+
+    #[test]
+    fn aarch64_prologue() {
+        // stp x29, x30, [sp, #-32]!; mov x29, sp; sub sp, sp, #16; add sp, sp, #16;
+        // ldp x29, x30, [sp], #32; ret
+        let elf = header(elf::EM_AARCH64, true, 0);
+        let code = [0xfd, 0x7b, 0xbe, 0xa9, 0xfd, 0x03, 0x00, 0x91, 0xff, 0x43, 0x00, 0xd1, 0xff, 0x43, 0x00, 0x91,
+                    0xfd, 0x7b, 0xc2, 0xa8, 0xc0, 0x03, 0x5f, 0xd6];
+        assert_eq!(frame_size(&elf, &code), 48);
+    }
+
+    #[test]
+    fn riscv64_prologue() {
+        // addi sp,sp,-48; sd ra,40(sp); ld ra,40(sp); addi sp,sp,48; ret
+        let elf = header(elf::EM_RISCV, true, 0);
+        let code = [0x13, 0x01, 0x01, 0xfd, 0x23, 0x34, 0x11, 0x02, 0x83, 0x30, 0x81, 0x02, 0x13, 0x01, 0x01, 0x03,
+                    0x67, 0x80, 0x00, 0x00];
+        assert_eq!(frame_size(&elf, &code), 48);
+    }
+
+    #[test]
+    fn thumb_prologue() {
+        // push {r4, r7, lr}; sub sp, #16; add sp, #16; pop {r4, r7, pc}
+        let elf = header(elf::EM_ARM, false, 1);
+        assert_eq!(frame_size(&elf, &[0x90, 0xb5, 0x84, 0xb0, 0x04, 0xb0, 0x90, 0xbd]), 28);
+    }
+
+    fn frame(size: u64, calls: &[usize]) -> Frame {
+        Frame{size, source: Source::Prologue, dynamic: false, indirect: false,
+              calls: calls.iter().map(|&callee| CallSite{depth: size, callee, tail: false}).collect()}
+    }
+
+    fn analysis(frames: &[Frame]) -> Analysis<'_> {
+        Analysis{frames, call_size: 8, memo: HashMap::new(), active: HashSet::new()}
+    }
+
+    #[test]
+    fn deepest_call_chain() {
+        // 0 -> 1 -> 3 and 0 -> 2; 1 + 3 is the deeper path.
+        let frames = [frame(16, &[1, 2]), frame(32, &[3]), frame(64, &[]), frame(48, &[])];
+        let depth = analysis(&frames).depth(0);
+        assert_eq!(depth.bytes, 16 + 8 + 32 + 8 + 48);
+        assert_eq!(depth.path, vec![0, 1, 3]);
+        assert!(!depth.recursive);
+    }
+
+    #[test]
+    fn self_recursion() {
+        let frames = [frame(16, &[0])];
+        let depth = analysis(&frames).depth(0);
+        assert_eq!(depth.bytes, 16);
+        assert!(depth.recursive);
+    }
+
+    #[test]
+    fn mutual_recursion() {
+        // 0 -> 1 -> 2 -> 1
+        let frames = [frame(16, &[1]), frame(32, &[2]), frame(8, &[1])];
+        let depth = analysis(&frames).depth(0);
+        assert_eq!(depth.bytes, 16 + 8 + 32 + 8 + 8);
+        assert_eq!(depth.path, vec![0, 1, 2]);
+        assert!(depth.recursive);
+    }
+}
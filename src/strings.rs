@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use crate::cfg;
+use crate::cfg::Function;
+use crate::disasm::Flow;
+use crate::elf;
+use crate::elf::ElfFile;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Encoding {
+    Ascii,
+    Utf8,
+    Utf16,
+}
+
+pub struct Reference {
+    pub addr:       u64,        // referencing instruction
+    pub function:   String,
+    pub offset:     u64,        // from the start of the function
+}
+
+pub struct FoundString {
+    pub addr:       u64,
+    pub size:       u64,        // bytes, without a terminator
+    pub region:     String,     // section, or "LOAD #<i>" in files without section headers
+    pub encoding:   Encoding,
+    pub text:       String,
+    pub refs:       Vec<Reference>,
+}
+
+/// Characters that make up a string: printable text and tabs.
+fn is_text(c: char) -> bool {
+    c == '\t' || !c.is_control()
+}
+
+/// The UTF-8 text character at the start of `bytes` and its length.
+fn decode_utf8(bytes: &[u8]) -> Option<(char, usize)> {
+    let len = match bytes.first()? {
+        0x00..=0x7f => 1,
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => return None,
+    };
+    let c = std::str::from_utf8(bytes.get(..len)?).ok()?.chars().next()?;
+    if is_text(c) { Some((c, len)) } else { None }
+}
+
+/// ASCII and UTF-8 runs of at least `min` characters.
+fn scan_8bit(bytes: &[u8], base: u64, min: usize, region: &str, out: &mut Vec<FoundString>) {
+    let mut start = 0;
+    let mut text = String::new();
+    let mut i = 0;
+    while i <= bytes.len() {
+        match decode_utf8(&bytes[i..]) {
+            Some((c, len)) => {
+                if text.is_empty() {
+                    start = i;
+                }
+                text.push(c);
+                i += len;
+            }
+            None => {
+                if text.chars().count() >= min {
+                    let encoding = if text.is_ascii() { Encoding::Ascii } else { Encoding::Utf8 };
+                    out.push(FoundString{addr: base + start as u64, size: (i - start) as u64, region: region.to_string(),
+                                         encoding, text: text.clone(), refs: Vec::new()});
+                }
+                text.clear();
+                i += 1;
+            }
+        }
+    }
+}
+
+/// UTF-16 runs of at least `min` characters from the Latin-1 range, at even offsets. Wider
+/// ranges would read pairs of ASCII bytes as CJK text.
+fn scan_utf16(bytes: &[u8], base: u64, min: usize, little_endian: bool, region: &str, out: &mut Vec<FoundString>) {
+    let mut start = 0;
+    let mut text = String::new();
+    for (i, unit) in bytes.chunks(2).enumerate() {
+        let c = match unit {
+            [a, b] if little_endian => *a as u16 | (*b as u16) << 8,
+            [a, b] => (*a as u16) << 8 | *b as u16,
+            _ => 0,
+        };
+        let c = char::from_u32(c as u32).filter(|c| (c.is_ascii() || (*c as u32) >= 0xa0) && (*c as u32) < 0x100 && is_text(*c));
+        match c {
+            Some(c) => {
+                if text.is_empty() {
+                    start = i * 2;
+                }
+                text.push(c);
+            }
+            None => {
+                if text.chars().count() >= min {
+                    out.push(FoundString{addr: base + start as u64, size: (i * 2 - start) as u64, region: region.to_string(),
+                                         encoding: Encoding::Utf16, text: text.clone(), refs: Vec::new()});
+                }
+                text.clear();
+            }
+        }
+    }
+    if text.chars().count() >= min {
+        out.push(FoundString{addr: base + start as u64, size: (bytes.len() / 2 * 2 - start) as u64, region: region.to_string(),
+                             encoding: Encoding::Utf16, text, refs: Vec::new()});
+    }
+}
+
+/// Strings of at least `min` characters in the allocated sections, or in the PT_LOAD
+/// segments when there are no section headers, sorted by address.
+pub fn extract(elf: &ElfFile, min: usize) -> Vec<FoundString> {
+    let mut regions: Vec<(String, u64, &[u8])> = Vec::new();
+    for shdr in elf.section_headers.iter().filter(|s| s.is_alloc() && s.sh_type != elf::SHT_NOBITS) {
+        regions.push((shdr.name.clone(), shdr.sh_addr, elf.section_data(shdr)));
+    }
+    if elf.section_headers.is_empty() {
+        for (i, phdr) in elf.program_headers.iter().enumerate().filter(|(_, p)| p.p_type == elf::PT_LOAD) {
+            regions.push((format!("LOAD #{}", i), phdr.p_vaddr, elf.segment_data(phdr)));
+        }
+    }
+    let mut strings = Vec::new();
+    for (name, addr, data) in regions.iter() {
+        scan_8bit(data, *addr, min, name, &mut strings);
+        scan_utf16(data, *addr, min, elf.is_little_endian(), name, &mut strings);
+    }
+    strings.sort_by_key(|s| s.addr);
+    strings
+}
+
+/// Addresses an instruction may hand to its function: memory operands (with lui/auipc and
+/// PC-relative forms already resolved by the decoder), immediates, and the pointer an ARM
+/// literal pool load fetches. AArch64 builds addresses with adrp + add/ldr, tracked in `pages`.
+fn referenced(elf: &ElfFile, insn: &crate::disasm::Instruction, pages: &mut HashMap<String, u64>) -> Vec<u64> {
+    let mut addrs = Vec::new();
+    // Branch targets are code, not data.
+    if insn.flow != Flow::Next {
+        return addrs;
+    }
+    let operands: Vec<&str> = insn.operands.split(", ").map(|o| o.trim()).collect();
+    if elf.e_machine == elf::EM_AARCH64 {
+        let dest = operands.first().cloned().unwrap_or("").to_string();
+        if insn.mnemonic == "adrp" {
+            if let Some(page) = insn.mem_ref {
+                pages.insert(dest, page);
+            }
+            return addrs;
+        }
+        let offset = cfg::trailing_immediate(&insn.operands.replace(']', "")).unwrap_or(0);
+        let base = operands.get(1).map(|o| o.trim_start_matches('['));
+        match (insn.mnemonic.as_str(), base.and_then(|b| pages.get(b))) {
+            ("add", Some(&page)) | ("ldr", Some(&page)) | ("ldrb", Some(&page)) => addrs.push(page + offset),
+            _ => {}
+        }
+        pages.remove(&dest);
+    }
+    if let Some(addr) = insn.mem_ref {
+        addrs.push(addr);
+        if elf.e_machine == elf::EM_ARM && insn.mnemonic.starts_with("ldr") {
+            if let Some(&[a, b, c, d]) = elf.read_vaddr(addr, 4) {
+                let word = if elf.is_little_endian() { u32::from_le_bytes([a, b, c, d]) } else { u32::from_be_bytes([a, b, c, d]) };
+                addrs.push(word as u64);
+            }
+        }
+    }
+    if let Some(imm) = cfg::trailing_immediate(&insn.operands) {
+        addrs.push(imm);
+    }
+    addrs
+}
+
+/// Fills in the functions whose instructions reference each string (anywhere inside it:
+/// linkers merge strings that are the tail of another).
+pub fn cross_reference(elf: &ElfFile, functions: &[Function], strings: &mut [FoundString]) {
+    let starts: BTreeMap<u64, usize> = strings.iter().enumerate().map(|(i, s)| (s.addr, i)).collect();
+    for f in functions.iter() {
+        let mut pages = HashMap::new();
+        for insn in f.blocks.iter().flat_map(|b| b.insns.iter()) {
+            for addr in referenced(elf, insn, &mut pages) {
+                let index = match starts.range(..=addr).next_back() {
+                    Some((_, &i)) if addr < strings[i].addr + strings[i].size.max(1) => i,
+                    _ => continue,
+                };
+                if !strings[index].refs.iter().any(|r| r.addr == insn.addr) {
+                    strings[index].refs.push(Reference{addr: insn.addr, function: f.name.clone(), offset: insn.addr - f.start});
+                }
+            }
+        }
+    }
+}
+
+pub fn get_encoding_name(encoding: Encoding) -> &'static str {
+    match encoding {
+        Encoding::Ascii => "ascii",
+        Encoding::Utf8 => "utf-8",
+        Encoding::Utf16 => "utf-16",
+    }
+}
+
+pub fn show_strings(strings: &[&FoundString]) {
+    let width = strings.iter().map(|s| s.region.len()).max().unwrap_or(0).max(7);
+    println!("  {:<16}  {:<width$}  {:<8}  String", "Address", "Section", "Encoding");
+    for s in strings.iter() {
+        println!("  {:016x}  {:<width$}  {:<8}  {:?}", s.addr, s.region, get_encoding_name(s.encoding), s.text);
+        if !s.refs.is_empty() {
+            let refs: Vec<String> = s.refs.iter().map(|r| format!("{}+{:#x}", r.function, r.offset)).collect();
+            println!("  {:16}  {:width$}  {:8}  <- {}", "", "", "", refs.join(", "));
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Small little-endian ELF images built in memory for the unit tests.
+
+use crate::elf;
+
+const PAGE: u64 = 0x1000;
+
+pub struct Section {
+    pub name:       &'static str,
+    pub sh_type:    u32,
+    pub sh_flags:   u64,
+    pub sh_addr:    u64,
+    pub data:       Vec<u8>,    // contents, or just the size for SHT_NOBITS
+    pub sh_link:    u32,
+    pub sh_info:    u32,
+    pub sh_entsize: u64,
+}
+
+/// A PT_LOAD segment over sections `first..=last` (indices in the section header table).
+pub struct Load {
+    pub first:  usize,
+    pub last:   usize,
+    pub flags:  u32,
+    pub paddr:  Option<u64>,    // load address when it differs from the first section's address
+}
+
+pub struct Builder {
+    pub is_64:      bool,
+    pub e_type:     u16,
+    pub machine:    u16,
+    pub entry:      u64,
+    pub sections:   Vec<Section>,   // after the null section; .shstrtab is appended by build
+    pub loads:      Vec<Load>,
+}
+
+impl Builder {
+    /// An ELF32 image for ARM and i386, ELF64 otherwise.
+    pub fn new(machine: u16) -> Builder {
+        Builder{is_64: machine != elf::EM_ARM && machine != elf::EM_386, e_type: elf::ET_EXEC, machine, entry: 0,
+                sections: Vec::new(), loads: Vec::new()}
+    }
+
+    /// Adds a section and returns its index in the section header table.
+    pub fn section(&mut self, name: &'static str, sh_type: u32, sh_flags: u64, sh_addr: u64, data: &[u8]) -> usize {
+        self.sections.push(Section{name, sh_type, sh_flags, sh_addr, data: data.to_vec(), sh_link: 0, sh_info: 0,
+                                   sh_entsize: 0});
+        self.sections.len()
+    }
+
+    pub fn load(&mut self, first: usize, last: usize, flags: u32) -> &mut Load {
+        self.loads.push(Load{first, last, flags, paddr: None});
+        self.loads.last_mut().unwrap()
+    }
+
+    /// Lays out the headers, the section contents (allocated ones at file offsets congruent
+    /// to their addresses modulo the page size), .shstrtab and the section header table.
+    pub fn build(&self) -> Vec<u8> {
+        let mut shstrtab = vec![0u8];
+        let mut names = Vec::new();
+        for name in self.sections.iter().map(|s| s.name).chain([".shstrtab"]) {
+            names.push(shstrtab.len() as u32);
+            shstrtab.extend_from_slice(name.as_bytes());
+            shstrtab.push(0);
+        }
+
+        let (ehdr_size, phdr_size, shdr_size, word) = if self.is_64 { (64, 56, 64, 8) } else { (52, 32, 40, 4) };
+        let mut bytes = vec![0u8; ehdr_size + phdr_size * self.loads.len()];
+        let mut offsets = vec![0u64];
+        for s in self.sections.iter() {
+            let pos = bytes.len() as u64;
+            let pos = if s.sh_flags & elf::SHF_ALLOC != 0 {
+                pos + (s.sh_addr.wrapping_sub(pos) % PAGE)
+            } else {
+                (pos + 7) & !7
+            };
+            offsets.push(pos);
+            if s.sh_type != elf::SHT_NOBITS {
+                bytes.resize(pos as usize, 0);
+                bytes.extend_from_slice(&s.data);
+            }
+        }
+        let shstrtab_offset = bytes.len() as u64;
+        bytes.extend_from_slice(&shstrtab);
+        bytes.resize((bytes.len() + 7) & !7, 0);
+        let shoff = bytes.len();
+        bytes.resize(shoff + shdr_size * (self.sections.len() + 2), 0);
+
+        put(&mut bytes, 0, 0x464c457f, 4);
+        bytes[4] = if self.is_64 { 2 } else { 1 };
+        bytes[5] = 1;
+        bytes[6] = 1;
+        put(&mut bytes, 16, self.e_type as u64, 2);
+        put(&mut bytes, 18, self.machine as u64, 2);
+        put(&mut bytes, 20, 1, 4);
+        put(&mut bytes, 24, self.entry, word);
+        put(&mut bytes, 24 + word, if self.loads.is_empty() { 0 } else { ehdr_size as u64 }, word);
+        put(&mut bytes, 24 + 2 * word, shoff as u64, word);
+        let sizes = 28 + 3 * word;
+        put(&mut bytes, sizes, ehdr_size as u64, 2);
+        put(&mut bytes, sizes + 2, phdr_size as u64, 2);
+        put(&mut bytes, sizes + 4, self.loads.len() as u64, 2);
+        put(&mut bytes, sizes + 6, shdr_size as u64, 2);
+        put(&mut bytes, sizes + 8, self.sections.len() as u64 + 2, 2);
+        put(&mut bytes, sizes + 10, self.sections.len() as u64 + 1, 2);
+
+        for (i, load) in self.loads.iter().enumerate() {
+            let first = &self.sections[load.first - 1];
+            let last = &self.sections[load.last - 1];
+            let file_end = (load.first..=load.last)
+                .filter(|&n| self.sections[n - 1].sh_type != elf::SHT_NOBITS)
+                .map(|n| offsets[n] + self.sections[n - 1].data.len() as u64)
+                .max().unwrap_or(offsets[load.first]);
+            let p = ehdr_size + phdr_size * i;
+            // p_flags follows p_type in ELF64 and p_memsz in ELF32.
+            let (flags, fields) = if self.is_64 { (p + 4, p + 8) } else { (p + 24, p + 4) };
+            put(&mut bytes, p, elf::PT_LOAD as u64, 4);
+            put(&mut bytes, flags, load.flags as u64, 4);
+            put(&mut bytes, fields, offsets[load.first], word);
+            put(&mut bytes, fields + word, first.sh_addr, word);
+            put(&mut bytes, fields + 2 * word, load.paddr.unwrap_or(first.sh_addr), word);
+            put(&mut bytes, fields + 3 * word, file_end - offsets[load.first], word);
+            put(&mut bytes, fields + 4 * word, last.sh_addr + last.data.len() as u64 - first.sh_addr, word);
+            put(&mut bytes, p + phdr_size - word, PAGE, word);
+        }
+
+        let shstrtab_section = Section{name: ".shstrtab", sh_type: elf::SHT_STRTAB, sh_flags: 0, sh_addr: 0,
+                                       data: shstrtab, sh_link: 0, sh_info: 0, sh_entsize: 0};
+        offsets.push(shstrtab_offset);
+        for (i, s) in self.sections.iter().chain([&shstrtab_section]).enumerate() {
+            let h = shoff + shdr_size * (i + 1);
+            put(&mut bytes, h, names[i] as u64, 4);
+            put(&mut bytes, h + 4, s.sh_type as u64, 4);
+            put(&mut bytes, h + 8, s.sh_flags, word);
+            put(&mut bytes, h + 8 + word, s.sh_addr, word);
+            put(&mut bytes, h + 8 + 2 * word, offsets[i + 1], word);
+            put(&mut bytes, h + 8 + 3 * word, s.data.len() as u64, word);
+            put(&mut bytes, h + 8 + 4 * word, s.sh_link as u64, 4);
+            put(&mut bytes, h + 12 + 4 * word, s.sh_info as u64, 4);
+            put(&mut bytes, h + 16 + 4 * word, if s.sh_flags & elf::SHF_ALLOC != 0 { 16 } else { 1 }, word);
+            put(&mut bytes, h + 16 + 5 * word, s.sh_entsize, word);
+        }
+        bytes
+    }
+}
+
+/// Writes the low `size` bytes of `value` little-endian at `at`.
+pub fn put(bytes: &mut [u8], at: usize, value: u64, size: usize) {
+    bytes[at..at + size].copy_from_slice(&value.to_le_bytes()[..size]);
+}
+
+/// File offset of program header `index` in an ELF64 image.
+pub fn phdr(bytes: &[u8], index: usize) -> usize {
+    let mut phoff = [0u8; 8];
+    phoff.copy_from_slice(&bytes[32..40]);
+    u64::from_le_bytes(phoff) as usize + 56 * index
+}
+
+/// File offset of section header `index` in an ELF64 image.
+pub fn shdr(bytes: &[u8], index: usize) -> usize {
+    let mut shoff = [0u8; 8];
+    shoff.copy_from_slice(&bytes[40..48]);
+    u64::from_le_bytes(shoff) as usize + 64 * index
+}
@@ -0,0 +1,265 @@
+use crate::elf;
+use crate::elf::ElfFile;
+
+const HIGH_ENTROPY: f64         = 7.2;      // bits/byte; compiled code stays around 6, compressed data above 7.5
+const MIN_ENTROPY_SIZE: usize   = 256;      // smaller regions cannot reach a meaningful entropy
+const SIGNATURE_WINDOW: usize   = 4096;     // "UPX!" headers sit in the first and last page of the file
+
+// Section names left by packers: name, packer
+const PACKER_SECTIONS: [(&str, &str); 26] = [
+  ("UPX0"       , "UPX"         ),
+  ("UPX1"       , "UPX"         ),
+  ("UPX2"       , "UPX"         ),
+  (".UPX0"      , "UPX"         ),
+  (".UPX1"      , "UPX"         ),
+  (".aspack"    , "ASPack"      ),
+  (".adata"     , "ASPack"      ),
+  (".MPRESS1"   , "MPRESS"      ),
+  (".MPRESS2"   , "MPRESS"      ),
+  (".petite"    , "Petite"      ),
+  ("pec1"       , "PECompact"   ),
+  ("pec2"       , "PECompact"   ),
+  ("PEC2"       , "PECompact"   ),
+  ("PEC2TO"     , "PECompact"   ),
+  (".nsp0"      , "NsPack"      ),
+  (".nsp1"      , "NsPack"      ),
+  (".nsp2"      , "NsPack"      ),
+  (".themida"   , "Themida"     ),
+  (".winlice"   , "WinLicense"  ),
+  (".vmp0"      , "VMProtect"   ),
+  (".vmp1"      , "VMProtect"   ),
+  (".vmp2"      , "VMProtect"   ),
+  (".enigma1"   , "Enigma"      ),
+  (".enigma2"   , "Enigma"      ),
+  (".RLPack"    , "RLPack"      ),
+  ("kkrunchy"   , "kkrunchy"    ),
+];
+
+// Strings packer stubs carry: bytes, packer, searched in the first and last page only
+const PACKER_SIGNATURES: [(&[u8], &str, bool); 4] = [
+  (b"UPX!"                                      , "UPX"     , true  ),
+  (b"$Info: This file is packed with the UPX"   , "UPX"     , false ),
+  (b"$Id: UPX "                                 , "UPX"     , false ),
+  (b"aPLib v"                                   , "aPLib"   , false ),
+];
+
+// Finding weights
+const SCORE_SIGNATURE: u32          = 40;
+const SCORE_ENTRY_NOT_EXEC: u32     = 30;
+const SCORE_HIGH_ENTROPY: u32       = 25;
+const SCORE_RWX: u32                = 20;
+const SCORE_ENTRY_OUTSIDE_TEXT: u32 = 15;
+const SCORE_UNPACK_SPACE: u32       = 15;
+const SCORE_LOADER_IMPORTS: u32     = 15;
+const SCORE_NO_SECTIONS: u32        = 10;
+
+/// A section or PT_LOAD segment and the entropy of its file contents.
+pub struct Region {
+    pub name:       String,
+    pub addr:       u64,
+    pub file_size:  u64,
+    pub mem_size:   u64,
+    pub flags:      String,
+    pub entropy:    Option<f64>,    // None without file contents
+}
+
+pub struct Finding {
+    pub score:      u32,
+    pub message:    String,
+}
+
+pub struct Report {
+    pub sections:   Vec<Region>,
+    pub segments:   Vec<Region>,
+    pub entry:      Option<(u64, String)>,      // entry point and where it lies
+    pub findings:   Vec<Finding>,
+}
+
+impl Report {
+    /// Sum of the finding weights, capped at 100.
+    pub fn score(&self) -> u32 {
+        self.findings.iter().map(|f| f.score).sum::<u32>().min(100)
+    }
+}
+
+/// Shannon entropy in bits per byte (0 to 8).
+pub fn entropy(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for b in bytes.iter() {
+        counts[*b as usize] += 1;
+    }
+    let len = bytes.len() as f64;
+    counts.iter().filter(|&&c| c > 0).map(|&c| c as f64 / len * (len / c as f64).log2()).sum()
+}
+
+pub fn get_level_name(score: u32) -> &'static str {
+    match score {
+        0..=19 => "low",
+        20..=49 => "medium",
+        _ => "high",
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Whether a file offset of `elf` lies in a section that holds data rather than code.
+fn in_data_section(elf: &ElfFile, offset: usize) -> bool {
+    elf.section_headers.iter().any(|s| s.sh_type != elf::SHT_NOBITS && !s.is_exec() && s.sh_type != elf::SHT_NULL
+                                   && (s.sh_offset as usize..s.sh_offset.saturating_add(s.sh_size) as usize).contains(&offset))
+}
+
+fn signatures(elf: &ElfFile, file: &[u8], findings: &mut Vec<Finding>) {
+    for (signature, packer, edges) in PACKER_SIGNATURES.iter() {
+        let place = if *edges {
+            let tail = file.len().saturating_sub(SIGNATURE_WINDOW);
+            find(&file[..file.len().min(SIGNATURE_WINDOW)], signature).or(find(&file[tail..], signature).map(|o| tail + o))
+                .map(|offset| format!("file offset {:#x}", offset))
+        } else {
+            // The same strings in a data section are text of the program itself (like the table
+            // above); stubs keep them with their code. Contents are searched in the ELF model so
+            // that PE matches map to an address.
+            let bytes = elf.bytes();
+            let offset = bytes.windows(signature.len()).enumerate()
+                .find(|(i, w)| w == signature && !in_data_section(elf, *i)).map(|(i, _)| i);
+            offset.map(|offset| match elf.program_headers.iter().find(|p| p.p_type == elf::PT_LOAD
+                                           && (p.p_offset..p.p_offset + p.p_filesz).contains(&(offset as u64))) {
+                Some(p) => format!("{:#x}", p.p_vaddr + offset as u64 - p.p_offset),
+                None => format!("file offset {:#x}", offset),
+            })
+        };
+        if let Some(place) = place {
+            findings.push(Finding{score: SCORE_SIGNATURE, message: format!("{} signature \"{}\" at {}", packer,
+                                  String::from_utf8_lossy(signature), place)});
+        }
+    }
+}
+
+/// Triage of `elf`. `file` is the file as stored, which differs from elf.bytes() for PE
+/// files (analyzed through their ELF model) and is searched for packer signatures.
+pub fn analyze(elf: &ElfFile, file: &[u8]) -> Report {
+    let mut findings = Vec::new();
+    let mut sections = Vec::new();
+    // Only the mapped sections of a PE model exist in the file.
+    let is_pe = elf::detect_format(file) == elf::FileFormat::Pe;
+    for shdr in elf.section_headers.iter().skip(1).filter(|s| s.is_alloc() || !is_pe) {
+        let data = if shdr.sh_type == elf::SHT_NOBITS { &[][..] } else { elf.section_data(shdr) };
+        sections.push(Region{name: shdr.name.clone(), addr: shdr.sh_addr, file_size: data.len() as u64, mem_size: shdr.sh_size,
+                             flags: elf::get_section_flags_name(shdr.sh_flags),
+                             entropy: if data.is_empty() { None } else { Some(entropy(data)) }});
+        if shdr.is_exec() && data.len() >= MIN_ENTROPY_SIZE && entropy(data) >= HIGH_ENTROPY {
+            findings.push(Finding{score: SCORE_HIGH_ENTROPY, message: format!("executable section {} has high entropy ({:.2} bits/byte)",
+                                  shdr.name, entropy(data))});
+        }
+        if let Some((_, packer)) = PACKER_SECTIONS.iter().find(|p| p.0 == shdr.name) {
+            findings.push(Finding{score: SCORE_SIGNATURE, message: format!("section {} is left by {}", shdr.name, packer)});
+        }
+    }
+
+    let mut segments = Vec::new();
+    for (i, phdr) in elf.program_headers.iter().enumerate().filter(|(_, p)| p.p_type == elf::PT_LOAD) {
+        let data = elf.segment_data(phdr);
+        let name = format!("LOAD #{}", i);
+        segments.push(Region{name: name.clone(), addr: phdr.p_vaddr, file_size: phdr.p_filesz, mem_size: phdr.p_memsz,
+                             flags: elf::get_segment_flags_name(phdr.p_flags),
+                             entropy: if data.is_empty() { None } else { Some(entropy(data)) }});
+        let exec = phdr.p_flags & elf::PF_X != 0;
+        if phdr.p_flags & (elf::PF_W | elf::PF_X) == elf::PF_W | elf::PF_X {
+            findings.push(Finding{score: SCORE_RWX, message: format!("{} at {:#x} is writable and executable (RWX)", name, phdr.p_vaddr)});
+        }
+        // Per-section entropy already covers segments that have sections.
+        let covered = elf.section_headers.iter().any(|s| s.is_exec() && phdr.contains_vaddr(s.sh_addr));
+        if exec && !covered && data.len() >= MIN_ENTROPY_SIZE && entropy(data) >= HIGH_ENTROPY {
+            findings.push(Finding{score: SCORE_HIGH_ENTROPY, message: format!("executable {} has high entropy ({:.2} bits/byte)",
+                                  name, entropy(data))});
+        }
+        // Stubs decompress into zero-filled memory reserved after (or instead of) the file contents.
+        if exec && phdr.p_memsz > 0x1000 && phdr.p_memsz / 2 > phdr.p_filesz {
+            findings.push(Finding{score: SCORE_UNPACK_SPACE, message: format!("executable {} reserves {} bytes of memory for {} bytes of file",
+                                  name, phdr.p_memsz, phdr.p_filesz)});
+        }
+    }
+    if elf.section_headers.is_empty() && !segments.is_empty() {
+        findings.push(Finding{score: SCORE_NO_SECTIONS, message: "no section headers".to_string()});
+    }
+
+    let entry = entry_point(elf, &mut findings);
+    loader_imports(elf, &mut findings);
+    signatures(elf, file, &mut findings);
+    findings.sort_by_key(|f| std::cmp::Reverse(f.score));
+    Report{sections, segments, entry, findings}
+}
+
+/// Locates the entry point and flags one outside .text or outside executable memory.
+fn entry_point(elf: &ElfFile, findings: &mut Vec<Finding>) -> Option<(u64, String)> {
+    if elf.e_entry == 0 || elf.e_type == elf::ET_REL {
+        return None;
+    }
+    let entry = if elf.e_machine == elf::EM_ARM { elf.e_entry & !1 } else { elf.e_entry };
+    let segment = elf.program_headers.iter().enumerate()
+        .find(|(_, p)| p.p_type == elf::PT_LOAD && p.contains_vaddr(entry));
+    let section = elf.section_for_vaddr(entry);
+    let place = match (section, segment) {
+        (Some(s), _) => s.name.clone(),
+        (None, Some((i, _))) => format!("LOAD #{}", i),
+        (None, None) => "unmapped memory".to_string(),
+    };
+    if segment.is_none_or(|(_, p)| p.p_flags & elf::PF_X == 0) {
+        findings.push(Finding{score: SCORE_ENTRY_NOT_EXEC, message: format!("entry point {:#x} is not in an executable segment", entry)});
+    } else if section.is_some_and(|s| s.name != ".text") {
+        findings.push(Finding{score: SCORE_ENTRY_OUTSIDE_TEXT, message: format!("entry point {:#x} is in {}, not .text", entry, place)});
+    }
+    Some((elf.e_entry, place))
+}
+
+/// A PE import table that is little more than LoadLibrary and GetProcAddress resolves the
+/// real imports at run time.
+fn loader_imports(elf: &ElfFile, findings: &mut Vec<Finding>) {
+    let imports: Vec<&str> = elf.symbols.iter().filter_map(|s| s.name.strip_prefix("__imp_")).collect();
+    let loads = imports.iter().any(|n| n.starts_with("LoadLibrary"));
+    let resolves = imports.contains(&"GetProcAddress");
+    if loads && resolves && imports.len() <= 10 {
+        findings.push(Finding{score: SCORE_LOADER_IMPORTS, message: format!("only {} imports, including LoadLibrary and GetProcAddress",
+                              imports.len())});
+    }
+}
+
+fn show_regions(title: &str, regions: &[Region]) {
+    let width = regions.iter().map(|r| r.name.len()).max().unwrap_or(0).max(7);
+    println!("{}:", title);
+    println!("  {:<width$}  {:<18}  {:>10}  {:>10}  {:<5}  Entropy", "Name", "Address", "FileSiz", "MemSiz", "Flags");
+    for region in regions.iter() {
+        let entropy = region.entropy.map_or("-".to_string(), |e| format!("{:.3}", e));
+        let mark = if region.entropy.is_some_and(|e| e >= HIGH_ENTROPY) { "  high" } else { "" };
+        println!("  {:<width$}  {:#018x}  {:>10}  {:>10}  {:<5}  {:>7}{}", region.name, region.addr, region.file_size,
+                 region.mem_size, region.flags, entropy, mark);
+    }
+    println!();
+}
+
+pub fn show_report(path: &str, file: &[u8], report: &Report) {
+    println!("{}: {:.3} bits/byte over {} bytes", path, entropy(file), file.len());
+    println!();
+    if !report.sections.is_empty() {
+        show_regions("Sections", &report.sections);
+    }
+    show_regions("Segments", &report.segments);
+    match report.entry.as_ref() {
+        Some((addr, place)) => println!("Entry point: {:#x} ({})", addr, place),
+        None => println!("Entry point: none"),
+    }
+    println!();
+    println!("Findings:");
+    for finding in report.findings.iter() {
+        println!("  [{:>3}] {}", finding.score, finding.message);
+    }
+    if report.findings.is_empty() {
+        println!("  none");
+    }
+    println!();
+    println!("Suspicion score: {}/100 ({})", report.score(), get_level_name(report.score()));
+}